@@ -0,0 +1,183 @@
+//! Cost of the real-time capture path: downmixing an interleaved stereo
+//! buffer and resampling it to 16 kHz, as done in every audio callback.
+//!
+//! Before measuring, every case checks that the path stays well under the
+//! duration of the audio it processes, so a regression that would cause
+//! xruns fails the run instead of only showing up as a slower number.
+
+use std::{
+    hint::black_box,
+    time::{Duration, Instant},
+};
+
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use subwin_audio::{
+    mixer::{DownmixMode, mix_stereo_to_mono},
+    resampler::{AudioResampler, StreamingResampler},
+};
+use subwin_bridge::config::ResamplingQuality;
+
+/// Sample rate Whisper expects, which the audio is resampled to.
+const TARGET_RATE: u32 = 16_000;
+
+/// Common sample rates of capture devices.
+const SOURCE_RATES: [u32; 2] = [44_100, 48_000];
+
+/// Block size the resampler is created with, as derived for most devices.
+const RESAMPLER_BLOCK_SIZE: u32 = 1024;
+
+/// Callback sizes in frames of hosts delivering buffers of a steady size.
+const STEADY_BUFFER_FRAMES: [usize; 3] = [256, 480, 1024];
+
+/// Callback sizes in frames of one round of irregular callbacks, as some
+/// hosts deliver them: tiny buffers in between large bursts. A burst may
+/// complete several resampler blocks at once, which is the worst case.
+const JITTERED_BUFFER_FRAMES: [usize; 8] = [13, 480, 97, 4096, 1, 441, 2048, 256];
+
+/// Share of the processed audio's duration the capture path may take.
+const REALTIME_BUDGET_SHARE: f64 = 0.25;
+
+/// Number of rounds the realtime budget is checked over.
+const BUDGET_CHECK_ROUNDS: usize = 200;
+
+/// Downmixer and resampler of a capture stream, with the buffers they're
+/// fed from allocated up front, like in the capture callback.
+struct CapturePath {
+    resampler: StreamingResampler<f32>,
+    mono: Vec<f32>,
+}
+
+impl CapturePath {
+    fn new(source_rate: u32, quality: ResamplingQuality) -> Self {
+        let max_frames = JITTERED_BUFFER_FRAMES
+            .into_iter()
+            .chain(STEADY_BUFFER_FRAMES)
+            .max()
+            .unwrap_or_default();
+        Self {
+            resampler: StreamingResampler::new(
+                source_rate,
+                TARGET_RATE,
+                RESAMPLER_BLOCK_SIZE,
+                quality,
+            )
+            .expect("failed to create a resampler"),
+            mono: vec![0.0; max_frames],
+        }
+    }
+
+    /// Processes one callback's interleaved stereo `buffer`. Returns the
+    /// number of resampled samples.
+    fn process(&mut self, buffer: &[f32]) -> usize {
+        let frames = mix_stereo_to_mono(&mut self.mono, buffer, DownmixMode::Average);
+        let mut resampled = 0;
+        self.resampler
+            .process_callback(&self.mono[..frames], &mut |output| {
+                resampled += black_box(output).len();
+            })
+            .expect("failed to resample a buffer");
+        resampled
+    }
+}
+
+/// Builds an interleaved stereo buffer of `frames` frames, with a different
+/// tone in each channel.
+fn stereo_buffer(frames: usize, source_rate: u32) -> Vec<f32> {
+    (0..frames)
+        .flat_map(|frame| {
+            let time = frame as f32 / source_rate as f32;
+            let left = 0.5 * (std::f32::consts::TAU * 220.0 * time).sin();
+            let right = 0.3 * (std::f32::consts::TAU * 1_250.0 * time).sin();
+            [left, right]
+        })
+        .collect()
+}
+
+/// Returns the duration of the audio in the interleaved stereo `buffers`.
+fn audio_duration(buffers: &[Vec<f32>], source_rate: u32) -> Duration {
+    let frames: usize = buffers.iter().map(|buffer| buffer.len() / 2).sum();
+    Duration::from_secs_f64(frames as f64 / source_rate as f64)
+}
+
+/// Panics if processing a round of `buffers` ever takes more than
+/// [`REALTIME_BUDGET_SHARE`] of the round's audio duration.
+fn check_realtime_budget(source_rate: u32, quality: ResamplingQuality, buffers: &[Vec<f32>]) {
+    let mut path = CapturePath::new(source_rate, quality);
+    let budget = audio_duration(buffers, source_rate).mul_f64(REALTIME_BUDGET_SHARE);
+
+    let mut slowest_round = Duration::ZERO;
+    for _ in 0..BUDGET_CHECK_ROUNDS {
+        let started_at = Instant::now();
+        for buffer in buffers {
+            black_box(path.process(buffer));
+        }
+        slowest_round = slowest_round.max(started_at.elapsed());
+    }
+
+    let buffer_frames: Vec<usize> = buffers.iter().map(|buffer| buffer.len() / 2).collect();
+    assert!(
+        slowest_round <= budget,
+        "{source_rate} Hz, {quality:?}, buffers of {buffer_frames:?} frames: the slowest round took {slowest_round:?}, over the budget of {budget:?}",
+    );
+}
+
+fn steady_buffers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("capture_path/steady");
+    for source_rate in SOURCE_RATES {
+        for frames in STEADY_BUFFER_FRAMES {
+            let buffer = stereo_buffer(frames, source_rate);
+            check_realtime_budget(
+                source_rate,
+                ResamplingQuality::Balanced,
+                std::slice::from_ref(&buffer),
+            );
+
+            let mut path = CapturePath::new(source_rate, ResamplingQuality::Balanced);
+            group.throughput(Throughput::Elements(frames as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{source_rate}hz"), frames),
+                &buffer,
+                |b, buffer| b.iter(|| path.process(buffer)),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn jittered_buffers(c: &mut Criterion) {
+    let mut group = c.benchmark_group("capture_path/jittered");
+    for source_rate in SOURCE_RATES {
+        let buffers: Vec<Vec<f32>> = JITTERED_BUFFER_FRAMES
+            .into_iter()
+            .map(|frames| stereo_buffer(frames, source_rate))
+            .collect();
+        let round_frames: usize = JITTERED_BUFFER_FRAMES.iter().sum();
+
+        for quality in [
+            ResamplingQuality::Fast,
+            ResamplingQuality::Balanced,
+            ResamplingQuality::HighQuality,
+        ] {
+            check_realtime_budget(source_rate, quality, &buffers);
+
+            let mut path = CapturePath::new(source_rate, quality);
+            group.throughput(Throughput::Elements(round_frames as u64));
+            group.bench_with_input(
+                BenchmarkId::new(format!("{source_rate}hz"), format!("{quality:?}")),
+                &buffers,
+                |b, buffers| {
+                    b.iter(|| {
+                        buffers
+                            .iter()
+                            .map(|buffer| path.process(buffer))
+                            .sum::<usize>()
+                    })
+                },
+            );
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, steady_buffers, jittered_buffers);
+criterion_main!(benches);
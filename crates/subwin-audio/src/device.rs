@@ -1,7 +1,7 @@
-use std::str::FromStr;
+use std::{str::FromStr, sync::OnceLock};
 
 use cpal::{
-    Device, Host,
+    Device, Host, Sample,
     traits::{DeviceTrait, HostTrait},
 };
 
@@ -32,6 +32,9 @@ pub enum DeviceError {
     /// You should refer to CPAL's error for more information.
     #[error("failed to parse device id: {0}")]
     ReadDeviceId(#[from] cpal::DeviceIdError),
+    /// Failed to list the stream configurations supported by the device.
+    #[error("failed to read supported device configs: {0}")]
+    SupportedConfigs(#[from] cpal::SupportedStreamConfigsError),
 }
 
 /// Represents parsed input audio device belonging to a specific host.
@@ -43,6 +46,14 @@ pub struct HostInputDevice {
     pub description: String,
 
     device: Device,
+    /// The configuration the device captures with, the default one queried
+    /// once on first use, unless another sample rate is preferred. Backends
+    /// may report a different default on every query, and the stream has to
+    /// be opened with the configuration its buffers were sized for.
+    input_config: OnceLock<cpal::SupportedStreamConfig>,
+    /// Buffer size in frames used instead of the computed one, see
+    /// [`HostInputDevice::override_buffer_size`].
+    buffer_size_override: Option<u32>,
 }
 
 impl std::fmt::Display for HostInputDevice {
@@ -52,41 +63,172 @@ impl std::fmt::Display for HostInputDevice {
 }
 
 impl HostInputDevice {
-    /// Returns the default input sample rate and channel count for this device.
+    /// Returns the input stream configuration the device captures with: its
+    /// default one, or the one chosen by
+    /// [`HostInputDevice::prefer_sample_rate`].
     ///
-    /// This method queries the device’s default input stream configuration and
-    /// returns the sample rate and number of channels selected by the audio
-    /// backend as its preferred input settings.
+    /// The default configuration is queried from the backend only once, and
+    /// the same one is returned afterwards, so every method of this device
+    /// agrees on it. A failed query isn't remembered, and is retried on the
+    /// next call.
+    pub fn input_config(&self) -> Result<&cpal::SupportedStreamConfig, DeviceError> {
+        if let Some(config) = self.input_config.get() {
+            return Ok(config);
+        }
+        let config = self.device.default_input_config()?;
+        Ok(self.input_config.get_or_init(|| config))
+    }
+
+    /// Makes the device capture at `sample_rate` instead of its default
+    /// rate, e.g. at the rate the audio is needed at, so it doesn't have to
+    /// be resampled. Returns `false` and keeps the default configuration if
+    /// the device doesn't support the rate with its default channel count.
+    /// The default sample format is kept if possible.
+    pub fn prefer_sample_rate(
+        &mut self,
+        sample_rate: cpal::SampleRate,
+    ) -> Result<bool, DeviceError> {
+        let default_config = self.input_config()?.clone();
+        if default_config.sample_rate() == sample_rate {
+            return Ok(true);
+        }
+
+        let preferred_config = self
+            .device
+            .supported_input_configs()?
+            .filter(|config| {
+                config.channels() == default_config.channels()
+                    && (config.min_sample_rate()..=config.max_sample_rate()).contains(&sample_rate)
+            })
+            .min_by_key(|config| config.sample_format() != default_config.sample_format());
+        Ok(match preferred_config {
+            Some(config) => {
+                self.input_config = OnceLock::from(config.with_sample_rate(sample_rate));
+                true
+            }
+            None => false,
+        })
+    }
+
+    /// Makes the device capture in buffers of `frames` frames, instead of the
+    /// size computed by [`HostInputDevice::target_buffer_size`], for devices
+    /// not honoring the computed size. Returns `false` and keeps the
+    /// computed size if the device reports a range of supported buffer sizes
+    /// not containing `frames`. Sizes of devices not reporting a range can't
+    /// be checked, and are accepted.
+    ///
+    /// Should be called after [`HostInputDevice::prefer_sample_rate`], as the
+    /// supported range depends on the configuration.
+    pub fn override_buffer_size(&mut self, frames: u32) -> Result<bool, DeviceError> {
+        let is_supported = frames > 0
+            && match self.input_config()?.buffer_size() {
+                cpal::SupportedBufferSize::Range { min, max } => (*min..=*max).contains(&frames),
+                cpal::SupportedBufferSize::Unknown => true,
+            };
+        if is_supported {
+            self.buffer_size_override = Some(frames);
+        }
+        Ok(is_supported)
+    }
+
+    /// Returns the input sample rate and channel count for this device.
+    ///
+    /// This method returns the sample rate and number of channels of
+    /// [`HostInputDevice::input_config`], i.e. the ones selected by the audio
+    /// backend as its preferred input settings, unless another sample rate
+    /// has been preferred.
     pub fn sample_rate_and_channels(&self) -> Result<(cpal::SampleRate, u16), DeviceError> {
-        let default_input_config = self.device.default_input_config()?;
-        Ok((
-            default_input_config.sample_rate(),
-            default_input_config.channels(),
-        ))
+        let input_config = self.input_config()?;
+        Ok((input_config.sample_rate(), input_config.channels()))
     }
 
     /// Returns a preferred input buffer size adjusted for the target sample rate.
     ///
-    /// This method queries the device’s default input stream configuration and
+    /// This method reads the device’s input stream configuration and
     /// computes a buffer size that is compatible with both the device’s native
-    /// sample rate and the requested `target_rate`.
+    /// sample rate and the requested `target_rate`, unless the size has been
+    /// overridden with [`HostInputDevice::override_buffer_size`].
     pub fn target_buffer_size(&self, target_rate: u32) -> Result<u32, DeviceError> {
-        let default_input_config = self.device.default_input_config()?;
-        let device_buffer_size = match default_input_config.buffer_size() {
+        if let Some(frames) = self.buffer_size_override {
+            return Ok(frames);
+        }
+        let input_config = self.input_config()?;
+        let device_buffer_size = match input_config.buffer_size() {
             cpal::SupportedBufferSize::Range { max, .. } => *max,
             cpal::SupportedBufferSize::Unknown => super::FIXED_FRAME_COUNT,
         };
 
-        // calculate the target buffer size with accordance to the sample rate
-        // ratio, since rubato wants a buffer size that is denominated to the
-        // target sample rate
-        let original_sample_rate = default_input_config.sample_rate();
-        let rate_denominator = crate::gcd(original_sample_rate, target_rate);
-        Ok(crate::find_nearest_to(
+        Ok(crate::buffer_size_for_rates(
             device_buffer_size,
-            original_sample_rate / rate_denominator,
+            input_config.sample_rate(),
+            target_rate,
         ))
     }
+
+    /// Returns whether the device can capture samples in `format` at its
+    /// input sample rate and channel count.
+    pub fn supports_input_format(&self, format: cpal::SampleFormat) -> Result<bool, DeviceError> {
+        let (sample_rate, channels) = self.sample_rate_and_channels()?;
+        Ok(self.device.supported_input_configs()?.any(|config| {
+            config.sample_format() == format
+                && config.channels() == channels
+                && (config.min_sample_rate()..=config.max_sample_rate()).contains(&sample_rate)
+        }))
+    }
+
+    /// Picks the format to capture samples in with
+    /// [`open_f32_input_stream`]: `preferred` if the device supports it, or
+    /// the device's default format otherwise. Formats that can't be
+    /// converted (see [`is_convertible_format`]) are captured as `f32`.
+    pub fn choose_input_format(
+        &self,
+        preferred: Option<cpal::SampleFormat>,
+    ) -> Result<cpal::SampleFormat, DeviceError> {
+        if let Some(preferred) = preferred
+            && self.supports_input_format(preferred)?
+        {
+            return Ok(preferred);
+        }
+
+        let default_format = self.input_config()?.sample_format();
+        Ok(if is_convertible_format(default_format) {
+            default_format
+        } else {
+            cpal::SampleFormat::F32
+        })
+    }
+
+    /// Returns the stream configuration input streams of this device are
+    /// opened with: [`HostInputDevice::input_config`], with a fixed buffer
+    /// size derived from [`HostInputDevice::target_buffer_size`].
+    pub fn input_stream_config(&self, target_rate: u32) -> Result<cpal::StreamConfig, DeviceError> {
+        let mut config = self.input_config()?.config();
+        config.buffer_size = cpal::BufferSize::Fixed(self.target_buffer_size(target_rate)?);
+        Ok(config)
+    }
+}
+
+/// Returns whether samples in `format` can be captured and converted to
+/// `f32` by [`open_f32_input_stream`].
+pub fn is_convertible_format(format: cpal::SampleFormat) -> bool {
+    matches!(
+        format,
+        cpal::SampleFormat::I8
+            | cpal::SampleFormat::I16
+            | cpal::SampleFormat::I32
+            | cpal::SampleFormat::U8
+            | cpal::SampleFormat::U16
+            | cpal::SampleFormat::U32
+            | cpal::SampleFormat::F32
+            | cpal::SampleFormat::F64
+    )
+}
+
+impl HostInputDevice {
+    /// Consumes this wrapper, returning the underlying CPAL device.
+    pub fn into_device(self) -> Device {
+        self.device
+    }
 }
 
 impl From<Device> for HostInputDevice {
@@ -98,6 +240,8 @@ impl From<Device> for HostInputDevice {
                 .expect("failed to obtain device's information")
                 .to_string(),
             device,
+            input_config: OnceLock::new(),
+            buffer_size_override: None,
         }
     }
 }
@@ -146,19 +290,122 @@ pub fn open_cpal_input_stream<T>(
 where
     T: cpal::SizedSample + cpal::Sample,
 {
-    let mut default_input_config: cpal::StreamConfig =
-        input_device.device.default_input_config()?.into();
-    default_input_config.buffer_size =
-        cpal::BufferSize::Fixed(input_device.target_buffer_size(target_rate)?);
-
     Ok(input_device.device.build_input_stream(
-        &default_input_config,
+        &input_device.input_stream_config(target_rate)?,
         move |data: &[T], _| callback(data),
         error_callback,
         None,
     )?)
 }
 
+/// Opens an input stream capturing samples of type `T`, and delivers them to
+/// `callback` converted to `f32`.
+fn open_converting_input_stream<T>(
+    input_device: &HostInputDevice,
+    target_rate: u32,
+    mut callback: impl FnMut(&[f32]) + Send + 'static,
+    error_callback: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, DeviceError>
+where
+    T: cpal::SizedSample + cpal::Sample,
+    f32: cpal::FromSample<T>,
+{
+    let (_, channels) = input_device.sample_rate_and_channels()?;
+    let buffer_len = input_device.target_buffer_size(target_rate)? as usize * channels as usize;
+    // the buffer size is fixed, so the conversion buffer doesn't have to
+    // grow on the audio thread
+    let mut converted = Vec::with_capacity(buffer_len);
+    open_cpal_input_stream(
+        input_device,
+        target_rate,
+        move |data: &[T]| {
+            converted.clear();
+            converted.extend(data.iter().map(|sample| sample.to_sample::<f32>()));
+            callback(&converted);
+        },
+        error_callback,
+    )
+}
+
+/// Opens an input stream capturing samples in `format`, and delivers them to
+/// `callback` converted to `f32`, see [`HostInputDevice::choose_input_format`].
+///
+/// Capturing the device's native format, e.g. `i16`, saves the driver a
+/// conversion, while `f32` samples are passed through as is. Formats that
+/// can't be converted (see [`is_convertible_format`]) are captured as `f32`.
+/// Otherwise, this behaves like [`open_cpal_input_stream`], including its
+/// threading requirements.
+pub fn open_f32_input_stream(
+    input_device: &HostInputDevice,
+    target_rate: u32,
+    format: cpal::SampleFormat,
+    callback: impl FnMut(&[f32]) + Send + 'static,
+    error_callback: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<cpal::Stream, DeviceError> {
+    match format {
+        cpal::SampleFormat::I8 => {
+            open_converting_input_stream::<i8>(input_device, target_rate, callback, error_callback)
+        }
+        cpal::SampleFormat::I16 => {
+            open_converting_input_stream::<i16>(input_device, target_rate, callback, error_callback)
+        }
+        cpal::SampleFormat::I32 => {
+            open_converting_input_stream::<i32>(input_device, target_rate, callback, error_callback)
+        }
+        cpal::SampleFormat::U8 => {
+            open_converting_input_stream::<u8>(input_device, target_rate, callback, error_callback)
+        }
+        cpal::SampleFormat::U16 => {
+            open_converting_input_stream::<u16>(input_device, target_rate, callback, error_callback)
+        }
+        cpal::SampleFormat::U32 => {
+            open_converting_input_stream::<u32>(input_device, target_rate, callback, error_callback)
+        }
+        cpal::SampleFormat::F64 => {
+            open_converting_input_stream::<f64>(input_device, target_rate, callback, error_callback)
+        }
+        _ => open_cpal_input_stream(input_device, target_rate, callback, error_callback),
+    }
+}
+
+/// Opens an input stream in the device's native sample format, and delivers
+/// every buffer to `callback` converted to `f32` and downmixed to mono, by
+/// averaging the channels of each frame.
+///
+/// Callers don't have to know which sample type the device produces, unlike
+/// with [`open_cpal_input_stream`]. Returns the stream along with the
+/// configuration it has been opened with, whose sample rate the mono samples
+/// are in. Otherwise, this behaves like [`open_cpal_input_stream`], including
+/// its threading requirements.
+pub fn open_best_input_stream(
+    input_device: &HostInputDevice,
+    target_rate: u32,
+    mut callback: impl FnMut(&[f32]) + Send + 'static,
+    error_callback: impl FnMut(cpal::StreamError) + Send + 'static,
+) -> Result<(cpal::Stream, cpal::StreamConfig), DeviceError> {
+    let config = input_device.input_stream_config(target_rate)?;
+    let format = input_device.choose_input_format(None)?;
+    let channels = config.channels.max(1) as usize;
+
+    // sized for the fixed buffer, so it doesn't grow on the audio thread
+    let mut mono = Vec::with_capacity(input_device.target_buffer_size(target_rate)? as usize);
+    let stream = open_f32_input_stream(
+        input_device,
+        target_rate,
+        format,
+        move |data: &[f32]| {
+            mono.clear();
+            mono.extend(
+                data.chunks_exact(channels)
+                    .map(|frame| frame.iter().sum::<f32>() / channels as f32),
+            );
+            callback(&mono);
+        },
+        error_callback,
+    )?;
+    Ok((stream, config))
+}
+
 /// Retrieves a specific audio device by its unique identifier within a given
 /// host.
 ///
@@ -168,3 +415,72 @@ pub fn get_device_by_id(host: &Host, device_id: String) -> Result<Option<Device>
     let device_id = cpal::DeviceId::from_str(&device_id)?;
     Ok(host.device_by_id(&device_id))
 }
+
+/// Finds the input device whose description is exactly `description`.
+///
+/// Meant as a fallback for when a remembered [`cpal::DeviceId`] no longer
+/// resolves, since some platforms don't keep identifiers stable across
+/// reboots. Returns `None` when no device or several devices match, as
+/// picking one of identically named devices would be a guess.
+pub fn find_input_device_by_description(
+    host: &Host,
+    description: &str,
+) -> Result<Option<HostInputDevice>, DeviceError> {
+    Ok(find_unique_by_description(
+        list_host_input_devices(host)?,
+        description,
+        |device| &device.description,
+    ))
+}
+
+/// Returns the only one of `devices` whose description, as told by
+/// `describe`, is exactly `description`.
+fn find_unique_by_description<D>(
+    devices: impl IntoIterator<Item = D>,
+    description: &str,
+    describe: impl Fn(&D) -> &str,
+) -> Option<D> {
+    let mut matching = devices
+        .into_iter()
+        .filter(|device| describe(device) == description);
+
+    match (matching.next(), matching.next()) {
+        (Some(device), None) => Some(device),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Devices as `(identifier, description)` pairs.
+    const DEVICES: [(&str, &str); 4] = [
+        ("alsa:hw:0", "Built-in Microphone"),
+        ("alsa:hw:1", "USB Headset"),
+        ("alsa:hw:2", "USB Microphone"),
+        ("alsa:hw:3", "USB Microphone"),
+    ];
+
+    fn find(description: &str) -> Option<&'static str> {
+        find_unique_by_description(DEVICES, description, |(_, description)| description)
+            .map(|(id, _)| id)
+    }
+
+    #[test]
+    fn device_is_found_by_its_exact_description() {
+        assert_eq!(find("USB Headset"), Some("alsa:hw:1"));
+        assert_eq!(find("usb headset"), None);
+    }
+
+    #[test]
+    fn missing_device_is_not_found() {
+        assert_eq!(find("Bluetooth Headset"), None);
+        assert_eq!(find(""), None);
+    }
+
+    #[test]
+    fn identically_described_devices_are_not_guessed_between() {
+        assert_eq!(find("USB Microphone"), None);
+    }
+}
@@ -0,0 +1,301 @@
+//! Gain stage applied to mono samples before they reach the transcriber.
+//!
+//! The stage is meant to run on a worker thread once per buffer, not inside
+//! the real-time audio callback. Amplified samples are either hard-clamped to
+//! full scale or, with a [`SoftLimiter`], bent smoothly below a ceiling.
+
+/// Maximum amplification automatic gain may apply, in dB.
+const AGC_MAX_GAIN_DB: f32 = 24.0;
+
+/// Time constant for lowering the gain when the signal gets louder, in
+/// seconds. Short, so sudden loud speech doesn't clip for long.
+const AGC_ATTACK_SECONDS: f32 = 0.05;
+
+/// Time constant for raising the gain when the signal gets quieter, in
+/// seconds. Long, so the gain doesn't pump between words.
+const AGC_RELEASE_SECONDS: f32 = 1.0;
+
+/// Buffers quieter than this (before automatic gain), in dBFS, are treated as
+/// silence: automatic gain is neither adapted to nor applied to them, so
+/// background noise isn't lifted into something that looks like speech.
+/// Matches the transcriber's silence gate.
+const NOISE_GATE_DB: f32 = -60.0;
+
+/// Fraction of the limiter's ceiling, above which samples start being
+/// compressed. Samples below it pass through unchanged.
+const LIMITER_KNEE_RATIO: f32 = 0.8;
+
+/// Converts a level in decibels to a linear amplitude factor.
+pub fn db_to_linear(db: f32) -> f32 {
+    10f32.powf(db / 20.0)
+}
+
+/// State of the automatic gain control.
+#[derive(Clone)]
+struct AutoGain {
+    /// Current gain, as a linear factor.
+    gain: f32,
+    /// RMS level the gain aims for, as a linear amplitude.
+    target: f32,
+    /// Sample rate of the processed audio, used to scale the time constants
+    /// to the buffer length.
+    sample_rate: u32,
+}
+
+impl AutoGain {
+    /// Adapts the gain to a buffer with the given RMS level and returns the
+    /// new gain.
+    fn adapt(&mut self, rms: f32, frames: usize) -> f32 {
+        let desired_gain = (self.target / rms).min(db_to_linear(AGC_MAX_GAIN_DB));
+        let time_constant = if desired_gain < self.gain {
+            AGC_ATTACK_SECONDS
+        } else {
+            AGC_RELEASE_SECONDS
+        };
+
+        let buffer_seconds = frames as f32 / self.sample_rate as f32;
+        let smoothing = (-buffer_seconds / time_constant).exp();
+        self.gain = desired_gain + (self.gain - desired_gain) * smoothing;
+        self.gain
+    }
+}
+
+/// Soft limiter keeping samples below a ceiling without the harsh edges of
+/// hard clipping.
+///
+/// Samples quieter than the knee pass through unchanged; louder ones are
+/// compressed with a `tanh` curve approaching the ceiling asymptotically.
+#[derive(Clone, Copy, Debug)]
+pub struct SoftLimiter {
+    /// Level samples never exceed, as a linear amplitude.
+    ceiling: f32,
+    /// Level compression starts at, as a linear amplitude.
+    knee: f32,
+}
+
+impl SoftLimiter {
+    /// Creates a limiter with the ceiling at `ceiling_db` dBFS. The ceiling
+    /// is capped at full scale.
+    pub fn new(ceiling_db: f32) -> Self {
+        let ceiling = db_to_linear(ceiling_db).min(1.0);
+        Self {
+            ceiling,
+            knee: ceiling * LIMITER_KNEE_RATIO,
+        }
+    }
+
+    /// Limits a single sample.
+    fn limit(&self, sample: f32) -> f32 {
+        let magnitude = sample.abs();
+        if magnitude <= self.knee {
+            return sample;
+        }
+
+        let headroom = self.ceiling - self.knee;
+        let limited = self.knee + headroom * ((magnitude - self.knee) / headroom).tanh();
+        limited.copysign(sample)
+    }
+
+    /// Limits `samples` in place. Returns how many of them exceeded the
+    /// ceiling before limiting.
+    pub fn process(&self, samples: &mut [f32]) -> usize {
+        let mut clipped = 0;
+        for sample in samples.iter_mut() {
+            if sample.abs() > self.ceiling {
+                clipped += 1;
+            }
+            *sample = self.limit(*sample);
+        }
+        clipped
+    }
+}
+
+/// Applies manual gain and, optionally, automatic gain control to mono
+/// samples.
+#[derive(Clone)]
+pub struct GainStage {
+    /// Manual gain, as a linear factor.
+    manual_gain: f32,
+    /// Automatic gain control, if enabled.
+    auto_gain: Option<AutoGain>,
+    /// Soft limiter replacing the hard clamp, if enabled.
+    limiter: Option<SoftLimiter>,
+}
+
+impl GainStage {
+    /// Creates a gain stage applying `gain_db` of manual gain, followed by
+    /// automatic gain control aiming for `agc_target_db` dBFS (RMS), if set.
+    /// The target is capped at full scale.
+    pub fn new(gain_db: f32, agc_target_db: Option<f32>, sample_rate: u32) -> Self {
+        Self {
+            manual_gain: db_to_linear(gain_db),
+            auto_gain: agc_target_db.map(|target_db| AutoGain {
+                gain: 1.0,
+                target: db_to_linear(target_db).min(1.0),
+                sample_rate,
+            }),
+            limiter: None,
+        }
+    }
+
+    /// Replaces the hard clamp to full scale with a [`SoftLimiter`] at
+    /// `ceiling_db` dBFS, or restores the clamp if `None`.
+    pub fn set_limiter(&mut self, ceiling_db: Option<f32>) {
+        self.limiter = ceiling_db.map(SoftLimiter::new);
+    }
+
+    /// Returns whether processing would leave samples unchanged.
+    pub fn is_bypassed(&self) -> bool {
+        self.manual_gain == 1.0 && self.auto_gain.is_none() && self.limiter.is_none()
+    }
+
+    /// Applies the gain to `samples` in place, then limits the result to the
+    /// limiter's ceiling or clamps it to `[-1.0, 1.0]`. Returns how many
+    /// samples exceeded the ceiling (or full scale) after amplification.
+    pub fn process(&mut self, samples: &mut [f32]) -> usize {
+        if self.is_bypassed() || samples.is_empty() {
+            return 0;
+        }
+
+        let mut start_gain = self.manual_gain;
+        let mut end_gain = self.manual_gain;
+        if let Some(auto_gain) = self.auto_gain.as_mut() {
+            let sum_of_squares: f32 = samples.iter().map(|sample| sample * sample).sum();
+            let rms = (sum_of_squares / samples.len() as f32).sqrt() * self.manual_gain;
+
+            if rms > db_to_linear(NOISE_GATE_DB) {
+                start_gain *= auto_gain.gain;
+                end_gain *= auto_gain.adapt(rms, samples.len());
+            }
+        }
+
+        // ramp the gain across the buffer to avoid audible steps
+        let step = (end_gain - start_gain) / samples.len() as f32;
+        for (index, sample) in samples.iter_mut().enumerate() {
+            *sample *= start_gain + step * index as f32;
+        }
+
+        match self.limiter {
+            Some(limiter) => limiter.process(samples),
+            None => {
+                let mut clipped = 0;
+                for sample in samples.iter_mut() {
+                    if sample.abs() > 1.0 {
+                        clipped += 1;
+                        *sample = sample.clamp(-1.0, 1.0);
+                    }
+                }
+                clipped
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16_000;
+
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// A 440 Hz tone of `amplitude`, lasting a tenth of a second.
+    fn tone(amplitude: f32) -> Vec<f32> {
+        (0..SAMPLE_RATE / 10)
+            .map(|index| {
+                let phase = index as f32 * 440.0 / SAMPLE_RATE as f32;
+                amplitude * (phase * std::f32::consts::TAU).sin()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn disabled_stage_leaves_samples_unchanged() {
+        let mut stage = GainStage::new(0.0, None, SAMPLE_RATE);
+        assert!(stage.is_bypassed());
+
+        let mut samples = vec![0.25, -1.5, 2.0];
+        assert_eq!(stage.process(&mut samples), 0);
+        assert_eq!(samples, [0.25, -1.5, 2.0]);
+    }
+
+    #[test]
+    fn manual_gain_is_clamped_to_full_scale() {
+        // +6 dB doubles the amplitude
+        let mut stage = GainStage::new(20.0 * 2f32.log10(), None, SAMPLE_RATE);
+        assert!(!stage.is_bypassed());
+
+        let mut samples = vec![0.25, -0.4, 0.7, -0.9];
+        assert_eq!(stage.process(&mut samples), 2);
+        for (sample, expected) in samples.iter().zip([0.5, -0.8, 1.0, -1.0]) {
+            assert!((sample - expected).abs() < 1e-5, "{samples:?}");
+        }
+    }
+
+    #[test]
+    fn limiter_bends_samples_below_the_ceiling() {
+        let limiter = SoftLimiter::new(-6.0);
+        let ceiling = db_to_linear(-6.0);
+        let knee = ceiling * LIMITER_KNEE_RATIO;
+
+        let mut samples = vec![knee * 0.5, -knee, knee + 0.01, ceiling, 0.9, -4.0];
+        assert_eq!(limiter.process(&mut samples), 2);
+        // samples below the knee pass through
+        assert_eq!(samples[..2], [knee * 0.5, -knee]);
+        // louder ones are compressed, keeping their order and sign
+        assert!(samples[2] > knee && samples[2] < knee + 0.01);
+        assert!(samples[2] < samples[3] && samples[3] < samples[4]);
+        assert!(samples[4] < ceiling);
+        assert!(samples[5] < 0.0 && samples[5] >= -ceiling);
+    }
+
+    #[test]
+    fn limiter_replaces_the_clamp() {
+        let mut stage = GainStage::new(12.0, None, SAMPLE_RATE);
+        stage.set_limiter(Some(-1.0));
+
+        let mut samples = tone(0.5);
+        assert!(stage.process(&mut samples) > 0);
+        assert!(
+            samples
+                .iter()
+                .all(|sample| sample.abs() < db_to_linear(-1.0))
+        );
+    }
+
+    #[test]
+    fn automatic_gain_lifts_quiet_speech_up_to_its_maximum() {
+        let mut stage = GainStage::new(0.0, Some(-20.0), SAMPLE_RATE);
+        let target = db_to_linear(-20.0);
+
+        // a tone 12 dB below the target is lifted to it over a few seconds
+        let mut samples = Vec::new();
+        for _ in 0..50 {
+            samples = tone(target * db_to_linear(-12.0) * 2f32.sqrt());
+            stage.process(&mut samples);
+        }
+        assert!((rms(&samples) / target - 1.0).abs() < 0.05);
+
+        // a tone 30 dB below it is only lifted by the maximum gain
+        let mut stage = GainStage::new(0.0, Some(-20.0), SAMPLE_RATE);
+        let input_rms = target * db_to_linear(-30.0);
+        for _ in 0..100 {
+            samples = tone(input_rms * 2f32.sqrt());
+            stage.process(&mut samples);
+        }
+        let gain_db = 20.0 * (rms(&samples) / input_rms).log10();
+        assert!((gain_db - AGC_MAX_GAIN_DB).abs() < 0.1, "{gain_db}");
+    }
+
+    #[test]
+    fn automatic_gain_ignores_silence() {
+        let mut stage = GainStage::new(0.0, Some(-20.0), SAMPLE_RATE);
+        let mut samples = tone(db_to_linear(NOISE_GATE_DB - 10.0));
+        let original = samples.clone();
+
+        stage.process(&mut samples);
+        assert_eq!(samples, original);
+    }
+}
@@ -4,14 +4,18 @@
 //! helpers that are oriented toward real-time input capture and mono
 //! processing. It focuses on:
 //! - Enumerating input devices and building input streams with `cpal`.
-//! - Converting interleaved stereo frames to mono samples.
+//! - Converting interleaved stereo frames to mono samples, or splitting out
+//!   single channels.
 //! - Resampling mono audio streams with FFT-based resamplers.
+//! - Applying manual and automatic gain to mono samples, with an optional
+//!   soft limiter.
 //!
 //! # Real-time constraints
 //! Audio callbacks run on a real-time thread. Avoid allocations, locks, and
 //! blocking I/O inside callbacks whenever possible.
 
 pub mod device;
+pub mod gain;
 pub mod mixer;
 pub mod resampler;
 
@@ -34,6 +38,22 @@ pub(crate) fn gcd(mut a: u32, mut b: u32) -> u32 {
     a
 }
 
+/// Rounds `device_buffer_size` to a buffer size compatible with resampling
+/// from `original_rate` to `target_rate`.
+///
+/// rubato wants a buffer size that is a multiple of the input rate's share
+/// of the rates' GCD, e.g. 441 frames from 44.1 kHz to 16 kHz. Rates dividing
+/// the target one, like 8 kHz telephony audio, accept any buffer size. The
+/// result is never smaller than one such multiple, even for tiny buffers.
+pub(crate) fn buffer_size_for_rates(
+    device_buffer_size: u32,
+    original_rate: u32,
+    target_rate: u32,
+) -> u32 {
+    let denominator = original_rate / gcd(original_rate, target_rate);
+    find_nearest_to(device_buffer_size, denominator).max(denominator)
+}
+
 /// Rounds `base` to the nearest multiple of `denominator`.
 ///
 /// This function finds the closest integer to `base` that is evenly divisible
@@ -47,3 +67,29 @@ pub(crate) fn find_nearest_to(base: u32, denominator: u32) -> u32 {
         base - remainder + denominator
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn narrowband_rates_keep_the_device_buffer_size() {
+        for buffer_size in [1, 160, 256, 480, 1024, 4096] {
+            assert_eq!(
+                buffer_size_for_rates(buffer_size, 8_000, 16_000),
+                buffer_size
+            );
+        }
+    }
+
+    #[test]
+    fn buffer_size_is_a_multiple_of_the_rate_denominator() {
+        for (original_rate, denominator) in [(44_100, 441), (48_000, 3), (22_050, 441)] {
+            for buffer_size in [1, 256, 480, 1024, 4096] {
+                let size = buffer_size_for_rates(buffer_size, original_rate, 16_000);
+                assert!(size >= denominator);
+                assert_eq!(size % denominator, 0);
+            }
+        }
+    }
+}
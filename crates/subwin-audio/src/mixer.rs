@@ -1,7 +1,35 @@
+/// How channels are combined when mixing down to mono.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DownmixMode {
+    /// Averages the channels, scaling their sum by `1 / channels`. Keeps the
+    /// level of content that's identical on every channel, e.g. a voice
+    /// panned to the center, but uncorrelated channels end up 3 dB quieter
+    /// for stereo. Default value.
+    #[default]
+    Average,
+    /// Scales the sum by `1 / sqrt(channels)`, keeping the level (RMS) of
+    /// uncorrelated channels, e.g. a voice recorded off-center or by a
+    /// microphone per channel. Content identical on every channel gets 3 dB
+    /// louder for stereo, and may exceed full scale.
+    PowerPreserving,
+}
+
+impl DownmixMode {
+    /// Returns the factor the sum of `channels` channels is scaled by.
+    pub fn scale(&self, channels: usize) -> f32 {
+        let channels = channels.max(1) as f32;
+        match self {
+            DownmixMode::Average => 1.0 / channels,
+            DownmixMode::PowerPreserving => 1.0 / channels.sqrt(),
+        }
+    }
+}
+
 /// Mixes interleaved stereo audio samples down to mono.
 ///
-/// This function converts a stereo audio buffer into mono by averaging the
-/// left and right channels for each frame: `mono = (left + right) * 0.5`.
+/// This function converts a stereo audio buffer into mono by summing the
+/// left and right channels for each frame and scaling the sum according to
+/// `mode`: `mono = (left + right) * 0.5` when averaging.
 ///
 /// The input slice must contain interleaved stereo samples in the form
 /// `[L0, R0, L1, R1, ...]`. The resulting mono samples are written into
@@ -9,7 +37,11 @@
 ///
 /// # Returns
 /// Returns the number of mono frames written to `samples_accumulator`.
-pub fn mix_stereo_to_mono<T>(samples_accumulator: &mut [T], samples_frame_data: &[T]) -> usize
+pub fn mix_stereo_to_mono<T>(
+    samples_accumulator: &mut [T],
+    samples_frame_data: &[T],
+    mode: DownmixMode,
+) -> usize
 where
     T: Copy
         + num_traits::identities::Zero
@@ -18,11 +50,102 @@ where
         + std::ops::Mul<Output = T>,
 {
     let frames = samples_frame_data.len() / 2;
-    let half = T::from_f32(0.5).expect("failed to obtain a half for the type");
+    let scale =
+        T::from_f32(mode.scale(2)).expect("failed to obtain the downmix scale for the type");
     for i in 0..frames {
         let left_channel_sample = samples_frame_data[i * 2];
         let right_channel_sample = samples_frame_data[(i * 2) + 1];
-        samples_accumulator[i] = (left_channel_sample + right_channel_sample) * half;
+        samples_accumulator[i] = (left_channel_sample + right_channel_sample) * scale;
+    }
+    frames
+}
+
+/// Extracts a single channel from interleaved audio samples.
+///
+/// The input slice must contain interleaved frames of `channels` samples,
+/// e.g. `[L0, R0, L1, R1, ...]` for stereo. The samples of `channel` are
+/// written into `samples_accumulator`, which must fit every frame.
+///
+/// # Returns
+/// Returns the number of frames written to `samples_accumulator`.
+pub fn extract_channel<T: Copy>(
+    samples_accumulator: &mut [T],
+    samples_frame_data: &[T],
+    channels: usize,
+    channel: usize,
+) -> usize {
+    let frames = samples_frame_data.len() / channels;
+    for i in 0..frames {
+        samples_accumulator[i] = samples_frame_data[(i * channels) + channel];
     }
     frames
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns a second of a sine at `frequency`, sampled at 48 kHz.
+    fn sine(frequency: f32) -> Vec<f32> {
+        (0..48_000)
+            .map(|frame| 0.5 * (std::f32::consts::TAU * frequency * frame as f32 / 48_000.0).sin())
+            .collect()
+    }
+
+    /// Returns the root mean square of `samples`.
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    /// Mixes the `left` and `right` channels down with `mode`, returning the
+    /// level of the mix relative to the level of a channel, in dB.
+    fn downmix_gain_db(left: &[f32], right: &[f32], mode: DownmixMode) -> f32 {
+        let interleaved: Vec<f32> = left
+            .iter()
+            .zip(right)
+            .flat_map(|(left, right)| [*left, *right])
+            .collect();
+        let mut mono = vec![0.0f32; left.len()];
+        assert_eq!(
+            mix_stereo_to_mono(&mut mono, &interleaved, mode),
+            left.len()
+        );
+        20.0 * (rms(&mono) / rms(left)).log10()
+    }
+
+    #[test]
+    fn correlated_channels_keep_their_level_when_averaged() {
+        let voice = sine(440.0);
+        let gain_db = downmix_gain_db(&voice, &voice, DownmixMode::Average);
+        assert!(gain_db.abs() < 0.01, "{gain_db} dB");
+    }
+
+    #[test]
+    fn correlated_channels_get_louder_when_preserving_power() {
+        let voice = sine(440.0);
+        let gain_db = downmix_gain_db(&voice, &voice, DownmixMode::PowerPreserving);
+        assert!((gain_db - 3.01).abs() < 0.01, "{gain_db} dB");
+    }
+
+    #[test]
+    fn uncorrelated_channels_get_quieter_when_averaged() {
+        // whole periods of different frequencies are orthogonal
+        let gain_db = downmix_gain_db(&sine(440.0), &sine(1_000.0), DownmixMode::Average);
+        assert!((gain_db + 3.01).abs() < 0.01, "{gain_db} dB");
+    }
+
+    #[test]
+    fn uncorrelated_channels_keep_their_level_when_preserving_power() {
+        let gain_db = downmix_gain_db(&sine(440.0), &sine(1_000.0), DownmixMode::PowerPreserving);
+        assert!(gain_db.abs() < 0.01, "{gain_db} dB");
+    }
+
+    #[test]
+    fn scale_follows_the_channel_count() {
+        assert_eq!(DownmixMode::Average.scale(4), 0.25);
+        assert_eq!(DownmixMode::PowerPreserving.scale(4), 0.5);
+        // a device reporting no channels is treated as mono
+        assert_eq!(DownmixMode::Average.scale(0), 1.0);
+        assert_eq!(DownmixMode::PowerPreserving.scale(0), 1.0);
+    }
+}
@@ -1,4 +1,25 @@
-use rubato::{FftFixedInOut, Resampler, ResamplerConstructionError};
+use rubato::{FftFixedInOut, Resampler};
+use subwin_bridge::config::ResamplingQuality;
+
+pub use rubato::ResamplerConstructionError;
+
+/// Returns the FFT resampler's input chunk size for `block_size` at
+/// `quality`.
+///
+/// The FFT resampler's anti-aliasing filter gets sharper with the size of
+/// its FFT: [`ResamplingQuality::Fast`] halves the block size,
+/// [`ResamplingQuality::HighQuality`] quadruples it. Larger FFTs take more
+/// CPU time per sample and hold back the input until a whole block is
+/// collected, adding latency; smaller ones let more aliasing through, which
+/// speech recognition hardly notices.
+fn fft_chunk_size(quality: ResamplingQuality, block_size: u32) -> usize {
+    let block_size = block_size as usize;
+    match quality {
+        ResamplingQuality::Fast => (block_size / 2).max(1),
+        ResamplingQuality::Balanced => block_size,
+        ResamplingQuality::HighQuality => block_size * 4,
+    }
+}
 
 /// Errors that can occur during audio resampling.
 ///
@@ -60,6 +81,55 @@ pub trait AudioResampler<T: rubato::Sample>: Send {
         input: &[T],
         callback: &mut dyn FnMut(&[T]),
     ) -> Result<usize, ResamplerError>;
+
+    /// Clear all buffered input and the resampling engine's internal state.
+    ///
+    /// Call this at discontinuities of the input stream (e.g. after a pause or
+    /// skipped audio), so stale samples don't bleed across the boundary.
+    /// Afterwards, the resampler behaves as if it had been freshly
+    /// constructed. Does not allocate.
+    fn reset(&mut self);
+
+    /// Returns the number of output samples the next
+    /// [`AudioResampler::process_callback`] call would emit for `input_len`
+    /// input samples, given the currently buffered input.
+    ///
+    /// Meant for sizing downstream buffers up front; the count is exact as
+    /// long as the call succeeds. Does not allocate.
+    fn output_frames_for(&self, input_len: usize) -> usize;
+
+    /// Returns the delay of the resampled audio relative to the input, in
+    /// output samples, caused by the resampling filter. Input waiting for a
+    /// full block is not included.
+    fn output_delay(&self) -> usize;
+
+    /// Sets whether the first [`AudioResampler::output_delay`] output
+    /// samples after construction or a [`AudioResampler::reset`] are
+    /// discarded. Enabled by default.
+    ///
+    /// These samples precede the first input sample and only hold the
+    /// filter's transient, so discarding them keeps the output aligned with
+    /// the input, and spares speech recognition a garbled start. Disable it
+    /// where the output has to stay in step with the filter's blocks.
+    fn set_skip_output_delay(&mut self, skip: bool);
+}
+
+/// Passes `output` to `callback` without the samples still left to skip of
+/// the resampler's delay, counting down `delay_left`. Returns the number of
+/// samples passed.
+fn emit_skipping_delay<T>(
+    output: &[T],
+    delay_left: &mut usize,
+    callback: &mut dyn FnMut(&[T]),
+) -> usize {
+    let skipped = (*delay_left).min(output.len());
+    *delay_left -= skipped;
+    let output = &output[skipped..];
+    // don't call callback if nothing was written
+    if !output.is_empty() {
+        callback(output);
+    }
+    output.len()
 }
 
 /// Fixed-block-size FFT-based resampler.
@@ -74,15 +144,21 @@ pub struct FixedBlockResampler<T: rubato::Sample> {
     input_buffer: Vec<T>,
     output_buffer: Vec<T>,
     resampler: FftFixedInOut<T>,
+    /// Whether the filter's delay is skipped after a start or a reset.
+    skip_output_delay: bool,
+    /// Output samples still to be skipped of the filter's delay.
+    delay_left: usize,
 }
 
 impl<T: rubato::Sample> FixedBlockResampler<T> {
     /// Creates a new fixed-block-size FFT-based resampler for mono audio.
     ///
-    /// The resampler expects exactly `block_size` input samples on every call
+    /// The resampler expects a fixed number of input samples on every call
     /// to [`AudioResampler::process_callback`] and will return an error if the
     /// input size differs. The number of output samples produced per call is
-    /// constant.
+    /// constant. The block is `block_size` samples, scaled by `quality` (see
+    /// [`ResamplingQuality`]) and rounded up to what the rates allow; see
+    /// [`FixedBlockResampler::input_block_size`].
     ///
     /// This function performs internal memory allocations and should be called
     /// during initialization, not from a real-time audio thread.
@@ -94,11 +170,12 @@ impl<T: rubato::Sample> FixedBlockResampler<T> {
         original_rate: u32,
         target_rate: u32,
         block_size: u32,
+        quality: ResamplingQuality,
     ) -> Result<Self, ResamplerConstructionError> {
         let resampler = FftFixedInOut::new(
             original_rate as usize,
             target_rate as usize,
-            block_size as usize,
+            fft_chunk_size(quality, block_size),
             1, // we're using mono
         )?;
 
@@ -108,11 +185,20 @@ impl<T: rubato::Sample> FixedBlockResampler<T> {
         Ok(Self {
             input_buffer: raw_input_buffer[0].clone(),
             output_buffer: raw_output_buffer[0].clone(),
+            skip_output_delay: true,
+            delay_left: resampler.output_delay(),
             resampler,
         })
     }
 }
 
+impl<T: rubato::Sample> FixedBlockResampler<T> {
+    /// Returns the number of input samples expected on every call.
+    pub fn input_block_size(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+}
+
 impl<T: rubato::Sample> AudioResampler<T> for FixedBlockResampler<T> {
     fn process_callback(
         &mut self,
@@ -138,11 +224,40 @@ impl<T: rubato::Sample> AudioResampler<T> for FixedBlockResampler<T> {
             self.resampler
                 .process_into_buffer(input_buffer, output_buffer, None)?;
 
-        // don't call callback if nothing was written
-        if output_written > 0 {
-            callback(&self.output_buffer[..output_written]);
+        Ok(emit_skipping_delay(
+            &self.output_buffer[..output_written],
+            &mut self.delay_left,
+            callback,
+        ))
+    }
+
+    fn reset(&mut self) {
+        self.resampler.reset();
+        self.set_skip_output_delay(self.skip_output_delay);
+    }
+
+    fn output_frames_for(&self, input_len: usize) -> usize {
+        // blocks of any other size are rejected
+        if input_len == self.resampler.input_frames_next() {
+            self.resampler
+                .output_frames_next()
+                .saturating_sub(self.delay_left)
+        } else {
+            0
         }
-        Ok(output_written)
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn set_skip_output_delay(&mut self, skip: bool) {
+        self.skip_output_delay = skip;
+        self.delay_left = if skip {
+            self.resampler.output_delay()
+        } else {
+            0
+        };
     }
 }
 
@@ -160,6 +275,10 @@ pub struct StreamingResampler<T: rubato::Sample> {
 
     input_buffer: Vec<T>,
     output_buffer: Vec<T>,
+    /// Whether the filter's delay is skipped after a start or a reset.
+    skip_output_delay: bool,
+    /// Output samples still to be skipped of the filter's delay.
+    delay_left: usize,
 }
 
 impl<T: rubato::Sample> StreamingResampler<T> {
@@ -169,9 +288,10 @@ impl<T: rubato::Sample> StreamingResampler<T> {
     /// number of input samples per processing call. Any number of input
     /// samples may be provided, including zero or partial frames.
     ///
-    /// The `block_size` parameter controls the internal FFT processing size
-    /// and therefore affects latency and performance, but it does not impose
-    /// any constraints on the public API.
+    /// The `block_size` parameter, scaled by `quality` (see
+    /// [`ResamplingQuality`]), controls the internal FFT processing size and
+    /// therefore affects latency and performance, but it does not impose any
+    /// constraints on the public API.
     ///
     /// This function performs internal memory allocations and should be called
     /// during initialization, not from a real-time audio thread.
@@ -183,11 +303,12 @@ impl<T: rubato::Sample> StreamingResampler<T> {
         original_rate: u32,
         target_rate: u32,
         block_size: u32,
+        quality: ResamplingQuality,
     ) -> Result<Self, ResamplerConstructionError> {
         let resampler = FftFixedInOut::new(
             original_rate as usize,
             target_rate as usize,
-            block_size as usize,
+            fft_chunk_size(quality, block_size),
             1, // we're using mono
         )?;
 
@@ -199,11 +320,25 @@ impl<T: rubato::Sample> StreamingResampler<T> {
             frames_queue: std::collections::VecDeque::new(),
             input_buffer: raw_input_buffer[0].clone(),
             output_buffer: raw_output_buffer[0].clone(),
+            skip_output_delay: true,
+            delay_left: resampler.output_delay(),
             resampler,
         })
     }
 }
 
+impl<T: rubato::Sample> StreamingResampler<T> {
+    /// Returns the number of input samples queued until the next full block.
+    pub fn queued_samples(&self) -> usize {
+        self.frames_queue.len()
+    }
+
+    /// Returns the number of input samples processed at once.
+    pub fn input_block_size(&self) -> usize {
+        self.resampler.input_frames_next()
+    }
+}
+
 impl<T: rubato::Sample> AudioResampler<T> for StreamingResampler<T> {
     fn process_callback(
         &mut self,
@@ -237,13 +372,299 @@ impl<T: rubato::Sample> AudioResampler<T> for StreamingResampler<T> {
                 self.resampler
                     .process_into_buffer(input_buffer, output_buffer, None)?;
 
-            // don't call callback if nothing was written
-            if output_written > 0 {
-                callback(&self.output_buffer[..output_written]);
-                total_written += output_written;
-            }
+            total_written += emit_skipping_delay(
+                &self.output_buffer[..output_written],
+                &mut self.delay_left,
+                callback,
+            );
         }
 
         Ok(total_written)
     }
+
+    fn reset(&mut self) {
+        self.frames_queue.clear();
+        self.resampler.reset();
+        self.set_skip_output_delay(self.skip_output_delay);
+    }
+
+    fn output_frames_for(&self, input_len: usize) -> usize {
+        // every full block of queued input is processed, the rest stays queued
+        let blocks = (self.frames_queue.len() + input_len) / self.resampler.input_frames_next();
+        (blocks * self.resampler.output_frames_next()).saturating_sub(self.delay_left)
+    }
+
+    fn output_delay(&self) -> usize {
+        self.resampler.output_delay()
+    }
+
+    fn set_skip_output_delay(&mut self, skip: bool) {
+        self.skip_output_delay = skip;
+        self.delay_left = if skip {
+            self.resampler.output_delay()
+        } else {
+            0
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Resamples all of `input` in one go.
+    fn resample_all(resampler: &mut dyn AudioResampler<f32>, input: &[f32]) -> Vec<f32> {
+        let mut output = Vec::new();
+        resampler
+            .process_callback(input, &mut |samples| output.extend_from_slice(samples))
+            .expect("failed to resample");
+        output
+    }
+
+    /// Returns the index of the sample with the largest magnitude.
+    fn peak_index(samples: &[f32]) -> usize {
+        samples
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs()))
+            .map(|(index, _)| index)
+            .expect("no samples")
+    }
+
+    #[test]
+    fn impulse_stays_aligned_after_skipping_the_delay() {
+        let mut input = vec![0.0f32; 48_000];
+        input[3_000] = 1.0;
+
+        let mut resampler =
+            StreamingResampler::<f32>::new(48_000, 16_000, 1024, ResamplingQuality::Balanced)
+                .expect("failed to create a resampler");
+        let output = resample_all(&mut resampler, &input);
+        assert!(peak_index(&output).abs_diff(1_000) <= 1);
+
+        // the delay is skipped again after a reset
+        resampler.reset();
+        let output = resample_all(&mut resampler, &input);
+        assert!(peak_index(&output).abs_diff(1_000) <= 1);
+    }
+
+    #[test]
+    fn impulse_is_delayed_without_skipping() {
+        let mut input = vec![0.0f32; 48_000];
+        input[3_000] = 1.0;
+
+        let mut resampler =
+            StreamingResampler::<f32>::new(48_000, 16_000, 1024, ResamplingQuality::Balanced)
+                .expect("failed to create a resampler");
+        resampler.set_skip_output_delay(false);
+        let delay = resampler.output_delay();
+        let output = resample_all(&mut resampler, &input);
+        assert!(peak_index(&output).abs_diff(1_000 + delay) <= 1);
+    }
+
+    #[test]
+    fn output_frames_exclude_the_skipped_delay() {
+        let mut resampler =
+            FixedBlockResampler::<f32>::new(48_000, 16_000, 1024, ResamplingQuality::Balanced)
+                .expect("failed to create a resampler");
+        let block = vec![0.0f32; resampler.input_block_size()];
+        for _ in 0..4 {
+            let expected = resampler.output_frames_for(block.len());
+            let written = resampler
+                .process_callback(&block, &mut |_| {})
+                .expect("failed to resample");
+            assert_eq!(written, expected);
+        }
+    }
+
+    /// Returns the root mean square of `samples`.
+    fn rms(samples: &[f32]) -> f32 {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    }
+
+    #[test]
+    fn narrowband_impulse_is_upsampled_in_place() {
+        let mut input = vec![0.0f32; 8_000];
+        input[1_000] = 1.0;
+
+        let mut resampler =
+            StreamingResampler::<f32>::new(8_000, 16_000, 1024, ResamplingQuality::Balanced)
+                .expect("failed to create a resampler");
+        let output = resample_all(&mut resampler, &input);
+        assert!(peak_index(&output).abs_diff(2_000) <= 1);
+    }
+
+    #[test]
+    fn narrowband_tone_keeps_its_level() {
+        // quiet telephony speech level
+        let input: Vec<f32> = (0..16_000)
+            .map(|frame| 0.05 * (std::f32::consts::TAU * 440.0 * frame as f32 / 8_000.0).sin())
+            .collect();
+
+        for block_size in [160, 256, 1024] {
+            let mut resampler = StreamingResampler::<f32>::new(
+                8_000,
+                16_000,
+                block_size,
+                ResamplingQuality::Balanced,
+            )
+            .expect("failed to create a resampler");
+            let output = resample_all(&mut resampler, &input);
+            // the edges are smeared by the filter
+            let steady = &output[4_000..output.len() - 4_000];
+            assert!((rms(steady) / rms(&input) - 1.0).abs() < 0.05);
+        }
+    }
+
+    /// Returns a second of a 440 Hz tone with a slow fade, so every block of
+    /// it differs, at `rate`.
+    fn tone(rate: u32) -> Vec<f32> {
+        (0..rate)
+            .map(|frame| {
+                let time = frame as f32 / rate as f32;
+                0.5 * (1.0 - time) * (std::f32::consts::TAU * 440.0 * time).sin()
+            })
+            .collect()
+    }
+
+    /// Resamples `input` in chunks of `chunk_len`, like device buffers.
+    fn resample_in_chunks(
+        resampler: &mut dyn AudioResampler<f32>,
+        input: &[f32],
+        chunk_len: usize,
+    ) -> Vec<f32> {
+        let mut output = Vec::new();
+        for chunk in input.chunks(chunk_len) {
+            resampler
+                .process_callback(chunk, &mut |samples| output.extend_from_slice(samples))
+                .expect("failed to resample");
+        }
+        output
+    }
+
+    #[test]
+    fn streaming_resampler_after_reset_matches_a_fresh_one() {
+        let input = tone(48_000);
+        for skip_output_delay in [true, false] {
+            let new_resampler = || {
+                let mut resampler = StreamingResampler::<f32>::new(
+                    48_000,
+                    16_000,
+                    1024,
+                    ResamplingQuality::Balanced,
+                )
+                .expect("failed to create a resampler");
+                resampler.set_skip_output_delay(skip_output_delay);
+                resampler
+            };
+
+            let mut fresh = new_resampler();
+            let expected = resample_in_chunks(&mut fresh, &input, 480);
+
+            // leaves a partial block queued and the filter's state dirty
+            let mut reused = new_resampler();
+            resample_in_chunks(&mut reused, &input[..5_000], 480);
+            assert!(reused.queued_samples() > 0);
+            reused.reset();
+            assert_eq!(reused.queued_samples(), 0);
+            assert_eq!(resample_in_chunks(&mut reused, &input, 480), expected);
+        }
+    }
+
+    #[test]
+    fn fixed_block_resampler_after_reset_matches_a_fresh_one() {
+        let mut fresh =
+            FixedBlockResampler::<f32>::new(44_100, 16_000, 1024, ResamplingQuality::Balanced)
+                .expect("failed to create a resampler");
+        let block_size = fresh.input_block_size();
+        let input = tone(44_100);
+        let input = &input[..input.len() / block_size * block_size];
+        let expected = resample_in_chunks(&mut fresh, input, block_size);
+
+        let mut reused =
+            FixedBlockResampler::<f32>::new(44_100, 16_000, 1024, ResamplingQuality::Balanced)
+                .expect("failed to create a resampler");
+        resample_in_chunks(&mut reused, &input[..block_size * 3], block_size);
+        reused.reset();
+        assert_eq!(resample_in_chunks(&mut reused, input, block_size), expected);
+    }
+
+    /// Input rates commonly delivered by capture devices.
+    const COMMON_RATES: [u32; 10] = [
+        8_000, 11_025, 16_000, 22_050, 32_000, 44_100, 48_000, 88_200, 96_000, 192_000,
+    ];
+
+    #[test]
+    fn streaming_output_frames_match_the_written_ones() {
+        for original_rate in COMMON_RATES {
+            let input = tone(original_rate);
+            for skip_output_delay in [true, false] {
+                let mut resampler = StreamingResampler::<f32>::new(
+                    original_rate,
+                    16_000,
+                    1024,
+                    ResamplingQuality::Balanced,
+                )
+                .expect("failed to create a resampler");
+                resampler.set_skip_output_delay(skip_output_delay);
+
+                // uneven chunks, so the queue holds every possible remainder
+                let mut position = 0;
+                for chunk_len in [1, 160, 441, 480, 1_000, 4_096].into_iter().cycle() {
+                    if position >= input.len() {
+                        break;
+                    }
+                    let chunk = &input[position..(position + chunk_len).min(input.len())];
+                    position += chunk.len();
+
+                    let expected = resampler.output_frames_for(chunk.len());
+                    let mut emitted = 0;
+                    let written = resampler
+                        .process_callback(chunk, &mut |samples| emitted += samples.len())
+                        .expect("failed to resample");
+                    assert_eq!(written, expected, "{original_rate} Hz");
+                    assert_eq!(emitted, written, "{original_rate} Hz");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_quality_resamples_common_rates() {
+        let qualities = [
+            ResamplingQuality::Fast,
+            ResamplingQuality::Balanced,
+            ResamplingQuality::HighQuality,
+        ];
+        for quality in qualities {
+            for original_rate in COMMON_RATES {
+                for block_size in [1, 160, 480, 1024] {
+                    let context = format!("{quality:?}, {original_rate} Hz, {block_size} frames");
+
+                    let mut fixed =
+                        FixedBlockResampler::<f32>::new(original_rate, 16_000, block_size, quality)
+                            .unwrap_or_else(|err| panic!("{context}: {err}"));
+                    let block = vec![0.0f32; fixed.input_block_size()];
+                    fixed
+                        .process_callback(&block, &mut |_| {})
+                        .unwrap_or_else(|err| panic!("{context}: {err}"));
+
+                    let mut streaming =
+                        StreamingResampler::<f32>::new(original_rate, 16_000, block_size, quality)
+                            .unwrap_or_else(|err| panic!("{context}: {err}"));
+                    let output = resample_all(&mut streaming, &tone(original_rate));
+                    // a second of input, short of the filter's delay and the
+                    // partial block still queued
+                    let block_output =
+                        (streaming.input_block_size() * 16_000).div_ceil(original_rate as usize);
+                    let missing = 16_000 - output.len();
+                    assert!(
+                        missing <= streaming.output_delay() + block_output,
+                        "{context}: {} samples",
+                        output.len(),
+                    );
+                }
+            }
+        }
+    }
 }
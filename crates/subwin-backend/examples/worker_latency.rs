@@ -0,0 +1,81 @@
+//! Measures how late a thread wakes up from short sleeps while every core is
+//! kept busy, at the default priority of the blocking pool's threads and at
+//! the priority of the dedicated transcription worker (see
+//! `dedicated_worker_thread` in the config).
+//!
+//! Run with `cargo run --release -p subwin-backend --example worker_latency`.
+
+use std::{
+    hint::black_box,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use thread_priority::ThreadPriority;
+
+/// Sleep between wake-ups, like the worker waiting for the next buffer.
+const SLEEP: Duration = Duration::from_millis(5);
+
+/// Number of wake-ups measured at each priority.
+const WAKE_UPS: usize = 400;
+
+fn main() {
+    let stop = Arc::new(AtomicBool::new(false));
+    let load_threads = thread::available_parallelism().map_or(4, |cores| cores.get()) * 2;
+    let load = (0..load_threads)
+        .map(|_| {
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut counter = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    counter = black_box(counter.wrapping_add(1));
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+
+    println!("Waking up every {SLEEP:?} next to {load_threads} busy threads");
+    report("default priority", measure(false));
+    report("maximum priority", measure(true));
+
+    stop.store(true, Ordering::Relaxed);
+    for thread in load {
+        let _ = thread.join();
+    }
+}
+
+/// Returns how late each wake-up of a new thread has been, optionally with
+/// the thread's priority raised first.
+fn measure(raise_priority: bool) -> Vec<Duration> {
+    thread::spawn(move || {
+        if raise_priority
+            && let Err(err) = thread_priority::set_current_thread_priority(ThreadPriority::Max)
+        {
+            eprintln!("Failed to raise the priority, measuring the default one: {err:?}");
+        }
+        (0..WAKE_UPS)
+            .map(|_| {
+                let deadline = Instant::now() + SLEEP;
+                thread::sleep(SLEEP);
+                Instant::now().saturating_duration_since(deadline)
+            })
+            .collect()
+    })
+    .join()
+    .expect("measuring thread has panicked")
+}
+
+fn report(label: &str, mut lateness: Vec<Duration>) {
+    lateness.sort();
+    let percentile = |percent: usize| lateness[(lateness.len() - 1) * percent / 100];
+    println!(
+        "{label}: median {:.2?}, p99 {:.2?}, max {:.2?}",
+        percentile(50),
+        percentile(99),
+        percentile(100),
+    );
+}
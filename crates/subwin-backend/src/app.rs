@@ -3,10 +3,19 @@
 //! The context contains the shared state and provides helpers for sending
 //! responses and notifications back to the frontend bridge.
 
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 
-use subwin_bridge::{MessageFromBackend, MessageToBackend};
-use tokio::sync::mpsc::{Receiver, Sender};
+use subwin_bridge::{
+    MessageFromBackend, MessageToBackend,
+    stats::{InputLevel, PipelineStats},
+};
+use tokio::{
+    sync::{
+        mpsc::{Receiver, Sender},
+        watch,
+    },
+    task::JoinSet,
+};
 
 use crate::services;
 use crate::state::SharedState;
@@ -17,17 +26,89 @@ pub(crate) struct AppContext {
     pub state: SharedState,
     /// Outbound channel to the frontend bridge.
     pub tx: Sender<MessageFromBackend>,
+    /// Outbound channel for high-frequency updates, which are dropped rather
+    /// than waited on when the frontend falls behind.
+    updates_tx: Sender<MessageFromBackend>,
+    /// Outbound channel for input levels, holding only the latest one.
+    levels_tx: watch::Sender<InputLevel>,
+    /// Background tasks spawned by services, aborted on shutdown.
+    tasks: Mutex<JoinSet<()>>,
+    /// The latest pipeline stats, kept for diagnostics.
+    last_pipeline_stats: Mutex<Option<PipelineStats>>,
 }
 
 impl AppContext {
-    /// Read and dispatch messages from the frontend bridge until it closes.
-    pub async fn consume_bridge_messages(self: &Arc<Self>, mut rx: Receiver<MessageToBackend>) {
+    /// Creates a new application context with no tracked background tasks.
+    pub fn new(
+        state: SharedState,
+        tx: Sender<MessageFromBackend>,
+        updates_tx: Sender<MessageFromBackend>,
+        levels_tx: watch::Sender<InputLevel>,
+    ) -> Self {
+        Self {
+            state,
+            tx,
+            updates_tx,
+            levels_tx,
+            tasks: Mutex::new(JoinSet::new()),
+            last_pipeline_stats: Mutex::new(None),
+        }
+    }
+
+    /// Read and dispatch messages from the frontend bridge until it closes or
+    /// a shutdown is requested.
+    pub async fn consume_bridge_messages(self: &Arc<Self>, rx: &mut Receiver<MessageToBackend>) {
         while let Some(message) = rx.recv().await {
             log::debug!("Got a frontend message: {message:?}");
+            if matches!(message, MessageToBackend::ShutdownRequest) {
+                break;
+            }
             self.dispatch_message(message).await;
         }
     }
 
+    /// Spawn a background task tracked by the context, so that it can be
+    /// aborted when the backend shuts down. The returned handle aborts the
+    /// task earlier.
+    pub fn spawn<F>(&self, future: F) -> tokio::task::AbortHandle
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let mut tasks = self.tasks.lock().expect("failed to lock background tasks");
+        // reap finished tasks, so the set doesn't grow over a long session
+        while tasks.try_join_next().is_some() {}
+        tasks.spawn(future)
+    }
+
+    /// Remembers `stats` as the latest pipeline stats.
+    pub fn set_last_pipeline_stats(&self, stats: PipelineStats) {
+        *self
+            .last_pipeline_stats
+            .lock()
+            .expect("failed to lock pipeline stats") = Some(stats);
+    }
+
+    /// Returns the latest pipeline stats, if any session has reported them.
+    pub fn last_pipeline_stats(&self) -> Option<PipelineStats> {
+        self.last_pipeline_stats
+            .lock()
+            .expect("failed to lock pipeline stats")
+            .clone()
+    }
+
+    /// Stop the active transcription session and abort all in-flight
+    /// background tasks, waiting for them to wind down.
+    pub async fn shutdown(self: &Arc<Self>) {
+        services::transcription_service::stop_active_session(self).await;
+
+        let mut tasks = {
+            let mut tasks = self.tasks.lock().expect("failed to lock background tasks");
+            std::mem::take(&mut *tasks)
+        };
+        log::info!("Aborting {} background task(-s)", tasks.len());
+        tasks.shutdown().await;
+    }
+
     /// Dispatches the received message from frontend down to individual
     /// service handlers.
     async fn dispatch_message(self: &Arc<Self>, message: MessageToBackend) {
@@ -35,19 +116,79 @@ impl AppContext {
             MessageToBackend::ConfigurationRequest => {
                 services::config_service::handle_config_request(self.clone()).await;
             }
+            MessageToBackend::UpdateConfigRequest(config) => {
+                services::config_service::handle_update_config_request(self.clone(), *config).await;
+            }
             MessageToBackend::DownloadModelRequest(model) => {
                 services::model_service::handle_download_model_request(self.clone(), model).await;
             }
+            MessageToBackend::DownloadedModelsRequest => {
+                services::model_service::handle_downloaded_models_request(self.clone()).await;
+            }
+            MessageToBackend::SetActiveModel(model) => {
+                services::model_service::handle_set_active_model_request(self.clone(), model).await;
+            }
             MessageToBackend::AudioDevicesListRequest => {
                 services::audio_service::handle_audio_devices_list_request(self.clone()).await;
             }
             MessageToBackend::SelectAudioDevice(id) => {
                 services::audio_service::handle_audio_device_selection(self.clone(), id).await;
             }
+            MessageToBackend::SelectAudioDeviceBy(selector) => {
+                services::audio_service::handle_audio_device_selector(self.clone(), selector).await;
+            }
             MessageToBackend::StartTranscriptionRequest => {
                 services::transcription_service::handle_start_transcription_request(self.clone())
                     .await;
             }
+            MessageToBackend::TestMicrophone {
+                device_id,
+                duration_ms,
+                playback,
+            } => {
+                services::audio_service::handle_test_microphone_request(
+                    self.clone(),
+                    device_id,
+                    duration_ms,
+                    playback,
+                )
+                .await;
+            }
+            MessageToBackend::StopTranscriptionRequest => {
+                services::transcription_service::handle_stop_transcription_request(self.clone())
+                    .await;
+            }
+            MessageToBackend::ResumeCaptureRequest => {
+                services::transcription_service::handle_resume_capture_request(self.clone()).await;
+            }
+            MessageToBackend::PushToTalk(is_held) => {
+                services::transcription_service::handle_push_to_talk(self.clone(), is_held).await;
+            }
+            MessageToBackend::StatusRequest => {
+                services::transcription_service::handle_status_request(self.clone()).await;
+            }
+            MessageToBackend::LatencyEstimateRequest => {
+                services::transcription_service::handle_latency_estimate_request(self.clone())
+                    .await;
+            }
+            MessageToBackend::BenchmarkModel => {
+                services::model_service::handle_benchmark_model_request(self.clone()).await;
+            }
+            MessageToBackend::ExportDiagnostics => {
+                services::diagnostics_service::handle_export_diagnostics_request(self.clone())
+                    .await;
+            }
+            MessageToBackend::ValidateConfig => {
+                services::config_service::handle_validate_config_request(self.clone()).await;
+            }
+            MessageToBackend::ExportConfigTo(path) => {
+                services::config_service::handle_export_config_request(self.clone(), path).await;
+            }
+            MessageToBackend::ImportConfigFrom(path) => {
+                services::config_service::handle_import_config_request(self.clone(), path).await;
+            }
+            // handled by `consume_bridge_messages`, never dispatched
+            MessageToBackend::ShutdownRequest => {}
         }
     }
 
@@ -66,6 +207,21 @@ impl AppContext {
             .expect("failed to blocking send message to frontend");
     }
 
+    /// Send a high-frequency update (see [`MessageFromBackend::is_update`])
+    /// to the frontend bridge without waiting. Returns whether it has been
+    /// queued; it's dropped if the frontend is behind or gone.
+    pub fn send_update(&self, message: MessageFromBackend) -> bool {
+        debug_assert!(message.is_update(), "{message:?} is not an update");
+        self.updates_tx.try_send(message).is_ok()
+    }
+
+    /// Publish the latest input level, overwriting the previous one if the
+    /// frontend hasn't read it yet. Never waits, so it's safe to call for
+    /// every processed buffer.
+    pub fn publish_level(&self, level: InputLevel) {
+        self.levels_tx.send_replace(level);
+    }
+
     /// Send a notification message to the frontend bridge.
     pub async fn send_notification(
         &self,
@@ -80,4 +236,19 @@ impl AppContext {
         ))
         .await;
     }
+
+    /// Send a notification message synchronously (blocking) to the frontend
+    /// bridge.
+    pub fn send_notification_blocking(
+        &self,
+        notification_type: subwin_bridge::notification::NotificationType,
+        content: impl Into<String>,
+    ) {
+        self.send_blocking(MessageFromBackend::NotificationMessage(
+            subwin_bridge::notification::NotificationMessage {
+                notification_type,
+                message: content.into(),
+            },
+        ));
+    }
 }
@@ -1,13 +1,18 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use directories::ProjectDirs;
-use subwin_bridge::config::Config;
+use subwin_bridge::config::{CONFIG_VERSION, Config};
 use tokio::{
     fs::{OpenOptions, create_dir_all, read_to_string},
     io::AsyncWriteExt,
 };
 
-// TODO: add migrations for config files.
+/// Migrations of the raw config file, the one at index `n` upgrading a file
+/// of version `n` to version `n + 1` (see [`CONFIG_VERSION`]).
+const MIGRATIONS: [fn(&mut toml::Table); CONFIG_VERSION as usize] = [
+    // version 1 only starts stamping the version, the layout is the same
+    |_| {},
+];
 
 /// Errors that can occur while loading or resolving application configuration.
 #[derive(Debug, thiserror::Error)]
@@ -39,16 +44,17 @@ fn build_project_dirs() -> Result<(PathBuf, PathBuf), ConfigError> {
 }
 
 /// Loads the application configuration from disk. Returns the loaded config,
-/// as well as path to the cache directory.
-pub async fn load_config() -> Result<(Config, PathBuf), ConfigError> {
+/// the path to the cache directory, and the messages to notify the user with
+/// about the values reset while loading (see [`validate_config`]).
+pub async fn load_config() -> Result<(Config, PathBuf, Vec<String>), ConfigError> {
     let (config_dir, cache_dir) = build_project_dirs()?;
 
     let config_path = config_dir.join("config.toml");
     log::info!("Loading configuration from {config_path:?}");
     if config_path.exists() {
         let contents = read_to_string(config_path).await?;
-        let config: Config = toml::from_str(&contents)?;
-        return Ok((config, cache_dir));
+        let (config, messages) = parse_config(&contents)?;
+        return Ok((config, cache_dir, messages));
     }
 
     let config = Config::default();
@@ -65,7 +71,65 @@ pub async fn load_config() -> Result<(Config, PathBuf), ConfigError> {
     file.write_all(contents.as_bytes()).await?;
     file.sync_all().await?;
 
-    Ok((config, cache_dir))
+    Ok((config, cache_dir, Vec::new()))
+}
+
+/// Parses the contents of a config file, as saved by this or another version
+/// of the application. Files of older versions are migrated first (see
+/// [`MIGRATIONS`]). Fields missing from them get their default values and
+/// fields unknown to this version are ignored. Returns the config and the
+/// messages to notify the user with about the values reset while parsing.
+fn parse_config(contents: &str) -> Result<(Config, Vec<String>), ConfigError> {
+    let mut table: toml::Table = toml::from_str(contents)?;
+    let mut messages = migrate_config(&mut table);
+    let mut config: Config = toml::Value::Table(table).try_into()?;
+    config.config_version = CONFIG_VERSION;
+    messages.extend(validate_config(&mut config));
+    Ok((config, messages))
+}
+
+/// Upgrades the raw config `table` to the current [`CONFIG_VERSION`]. Returns
+/// the messages to notify the user with, e.g. about a file of a newer
+/// version, whose unknown fields are ignored.
+fn migrate_config(table: &mut toml::Table) -> Vec<String> {
+    let version = table
+        .get("config_version")
+        .and_then(toml::Value::as_integer)
+        .unwrap_or(0);
+    let Ok(version) = u32::try_from(version) else {
+        log::warn!("Ignoring invalid config version {version}");
+        return Vec::new();
+    };
+    if version > CONFIG_VERSION {
+        log::warn!("Config version {version} is newer than the supported {CONFIG_VERSION}");
+        return vec![
+            "Настройки сохранены более новой версией приложения, часть из них может быть пропущена."
+                .to_string(),
+        ];
+    }
+
+    for (from_version, migrate) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        log::info!("Migrating the config from version {from_version}");
+        migrate(table);
+    }
+    Vec::new()
+}
+
+/// Checks loaded or updated values that can't be validated by
+/// deserialization alone, resetting invalid ones to their defaults. Returns
+/// the messages to notify the user with about the reset values.
+pub fn validate_config(config: &mut Config) -> Vec<String> {
+    let mut messages = Vec::new();
+    if let Some(mirror_url) = &config.model_mirror_url
+        && let Err(err) = url::Url::parse(mirror_url)
+    {
+        log::warn!("Ignoring invalid model mirror URL {mirror_url:?}: {err}");
+        messages.push(format!(
+            "Адрес зеркала моделей «{mirror_url}» некорректен ({err}), модели будут скачиваться с HuggingFace."
+        ));
+        config.model_mirror_url = None;
+    }
+    messages
 }
 
 /// Saves the current configuration to disk. This function serializes the
@@ -92,3 +156,78 @@ pub async fn save_config(config: &Config) -> Result<(), ConfigError> {
 
     Ok(())
 }
+
+/// Writes `config` into the file at `path`, in the format of the config file,
+/// so it can be shared or backed up.
+pub async fn export_config(config: &Config, path: &Path) -> Result<(), ConfigError> {
+    let contents = toml::to_string_pretty(config)?;
+    tokio::fs::write(path, contents).await?;
+    Ok(())
+}
+
+/// Reads a config exported with [`export_config`] from the file at `path`,
+/// as [`parse_config`] does.
+pub async fn import_config(path: &Path) -> Result<(Config, Vec<String>), ConfigError> {
+    let contents = read_to_string(path).await?;
+    parse_config(&contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the file `config` is saved as, to compare configs by.
+    fn saved(config: &Config) -> String {
+        toml::to_string_pretty(config).expect("config should serialize")
+    }
+
+    #[tokio::test]
+    async fn exported_config_is_imported_unchanged() {
+        let mut config = Config::default();
+        config.captions_config.window_width = 1234.0;
+        config.language = Some("de".to_string());
+        config.model_mirror_url = Some("https://mirror.example/models".to_string());
+
+        let path = std::env::temp_dir().join(format!(
+            "subwin-config-roundtrip-{}.toml",
+            std::process::id()
+        ));
+        export_config(&config, &path)
+            .await
+            .expect("export should succeed");
+        let imported = import_config(&path).await;
+        let _ = tokio::fs::remove_file(&path).await;
+
+        let (imported, messages) = imported.expect("import should succeed");
+        assert!(messages.is_empty());
+        assert_eq!(saved(&imported), saved(&config));
+    }
+
+    #[test]
+    fn unversioned_config_is_migrated() {
+        let (config, messages) =
+            parse_config("show_caption_window = false\n").expect("config should parse");
+        assert!(messages.is_empty());
+        assert_eq!(config.config_version, CONFIG_VERSION);
+        assert!(!config.show_caption_window);
+    }
+
+    #[test]
+    fn newer_config_is_loaded_with_a_warning() {
+        let contents = format!(
+            "config_version = {}\nshow_caption_window = false\nunknown_field = 1\n",
+            CONFIG_VERSION + 1
+        );
+        let (config, messages) = parse_config(&contents).expect("config should parse");
+        assert_eq!(messages.len(), 1);
+        assert!(!config.show_caption_window);
+    }
+
+    #[test]
+    fn invalid_values_are_reported() {
+        let (config, messages) =
+            parse_config("model_mirror_url = \"not a url\"\n").expect("config should parse");
+        assert_eq!(config.model_mirror_url, None);
+        assert_eq!(messages.len(), 1);
+    }
+}
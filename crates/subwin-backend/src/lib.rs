@@ -5,8 +5,14 @@
 
 mod app;
 mod config;
+mod log_recorder;
+mod monitor;
+mod realtime;
 mod runtime;
 mod services;
+mod sinks;
 mod state;
+mod stats;
 
-pub use crate::runtime::run;
+pub use crate::log_recorder::install_log_recorder;
+pub use crate::runtime::{BackendHandle, HeadlessOptions, run, run_headless};
@@ -0,0 +1,72 @@
+//! In-memory record of the most recent log lines.
+//!
+//! The application's logger is wrapped into a [`RecordingLogger`], which
+//! forwards every record to it and keeps the latest lines, so they can be
+//! attached to a diagnostics export (see
+//! [`subwin_bridge::MessageToBackend::ExportDiagnostics`]).
+
+use std::{collections::VecDeque, sync::Mutex};
+
+/// Number of log lines kept for diagnostics.
+const RECENT_LOG_LINES: usize = 500;
+
+/// The most recent log lines, the oldest first.
+static RECENT_LINES: Mutex<VecDeque<String>> = Mutex::new(VecDeque::new());
+
+/// Logger forwarding records to the wrapped one, while remembering the
+/// latest [`RECENT_LOG_LINES`] of them.
+struct RecordingLogger<L: log::Log> {
+    inner: L,
+}
+
+impl<L: log::Log> log::Log for RecordingLogger<L> {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        self.inner.enabled(metadata)
+    }
+
+    fn log(&self, record: &log::Record) {
+        if !self.inner.enabled(record.metadata()) {
+            return;
+        }
+        self.inner.log(record);
+
+        let line = format!(
+            "{:<5} [{}] {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        let mut lines = RECENT_LINES
+            .lock()
+            .expect("failed to lock recent log lines");
+        if lines.len() == RECENT_LOG_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    fn flush(&self) {
+        self.inner.flush();
+    }
+}
+
+/// Installs `logger` as the global logger, recording the lines it logs up to
+/// `max_level`. Fails if a logger has already been installed.
+pub fn install_log_recorder(
+    logger: impl log::Log + 'static,
+    max_level: log::LevelFilter,
+) -> Result<(), log::SetLoggerError> {
+    log::set_boxed_logger(Box::new(RecordingLogger { inner: logger }))?;
+    log::set_max_level(max_level);
+    Ok(())
+}
+
+/// Returns the most recent log lines, the oldest first.
+pub fn recent_log_lines() -> Vec<String> {
+    RECENT_LINES
+        .lock()
+        .expect("failed to lock recent log lines")
+        .iter()
+        .cloned()
+        .collect()
+}
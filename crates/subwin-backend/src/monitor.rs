@@ -0,0 +1,116 @@
+//! Optional audio passthrough monitor.
+//!
+//! The monitor plays the resampled mono audio, that is fed to the transcriber,
+//! back through an output device, so users can hear what is being captured.
+//! The transcription worker tees its samples into a [`MonitorFeed`], which
+//! converts them to the output device's rate and pushes them into a ring
+//! buffer drained by the output stream.
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use ringbuf_blocking::{
+    BlockingHeapRb,
+    traits::{Consumer, Producer, Split},
+};
+use subwin_audio::resampler::{AudioResampler, ResamplerConstructionError, StreamingResampler};
+use subwin_bridge::config::ResamplingQuality;
+
+/// Internal processing block size of the monitor resampler, in frames.
+const MONITOR_BLOCK_SIZE: u32 = 320;
+
+/// Errors that can occur while opening the monitoring output stream.
+#[derive(Debug, thiserror::Error)]
+pub enum MonitorError {
+    /// Neither the configured nor the default output device is available.
+    #[error("no output device is available for monitoring")]
+    NoOutputDevice,
+    /// Failed to look up the configured output device.
+    #[error("failed to find monitoring device: {0}")]
+    Device(#[from] subwin_audio::device::DeviceError),
+    /// Failed to obtain the output device's default configuration.
+    #[error("failed to read output device config: {0}")]
+    OutputConfig(#[from] cpal::DefaultStreamConfigError),
+    /// The audio backend rejected the output stream.
+    #[error("failed to build output stream: {0}")]
+    BuildStream(#[from] cpal::BuildStreamError),
+    /// The output stream has been built, but could not be started.
+    #[error("failed to play output stream: {0}")]
+    PlayStream(#[from] cpal::PlayStreamError),
+    /// The resampler for the output device's rate could not be created.
+    #[error("failed to create monitor resampler: {0}")]
+    Resampler(#[from] ResamplerConstructionError),
+}
+
+/// Worker-side end of the monitor: converts transcription samples to the
+/// output device's rate and queues them for playback.
+pub struct MonitorFeed<P: Producer<Item = f32>> {
+    resampler: StreamingResampler<f32>,
+    producer: P,
+}
+
+impl<P: Producer<Item = f32>> MonitorFeed<P> {
+    /// Queue mono samples for playback. Samples that don't fit into the
+    /// playback buffer are dropped, so the worker never waits on the output.
+    pub fn push(&mut self, samples: &[f32]) {
+        let producer = &mut self.producer;
+        let mut push_resampled = |resampled: &[f32]| {
+            producer.push_slice(resampled);
+        };
+
+        if let Err(err) = self
+            .resampler
+            .process_callback(samples, &mut push_resampled)
+        {
+            log::error!("Monitor resampler caught an error: {err:?}");
+        }
+    }
+}
+
+/// Opens an output stream on the device with `device_id` (or the host's
+/// default output device) that plays back mono audio sampled at
+/// `source_rate`. Returns the playing stream and the feed to write into.
+pub fn open_monitor(
+    host: &cpal::Host,
+    device_id: Option<&str>,
+    source_rate: u32,
+) -> Result<(cpal::Stream, MonitorFeed<impl Producer<Item = f32> + Send>), MonitorError> {
+    let device = match device_id {
+        Some(device_id) => subwin_audio::device::get_device_by_id(host, device_id.to_string())?,
+        None => host.default_output_device(),
+    }
+    .ok_or(MonitorError::NoOutputDevice)?;
+
+    let output_config: cpal::StreamConfig = device.default_output_config()?.into();
+    let output_rate = output_config.sample_rate;
+    let output_channels = output_config.channels as usize;
+
+    // keep up to half a second of audio queued for playback
+    let playback_buffer = BlockingHeapRb::<f32>::new((output_rate / 2) as usize);
+    let (producer, mut consumer) = playback_buffer.split();
+
+    let stream = device.build_output_stream(
+        &output_config,
+        move |data: &mut [f32], _| {
+            // duplicate the mono signal into every output channel, filling
+            // with silence when the worker hasn't caught up yet
+            for frame in data.chunks_mut(output_channels) {
+                frame.fill(consumer.try_pop().unwrap_or(0.0));
+            }
+        },
+        |error| log::error!("An error occured while playing the monitor stream: {error}"),
+        None,
+    )?;
+    stream.play()?;
+
+    let feed = MonitorFeed {
+        // monitoring is only meant for checking the device, so it keeps the
+        // default quality instead of following the configured one
+        resampler: StreamingResampler::new(
+            source_rate,
+            output_rate,
+            MONITOR_BLOCK_SIZE,
+            ResamplingQuality::Balanced,
+        )?,
+        producer,
+    };
+    Ok((stream, feed))
+}
@@ -0,0 +1,216 @@
+//! Error reporting from realtime audio callbacks.
+//!
+//! Audio callbacks run on the audio thread, where blocking, awaiting or
+//! talking to the frontend is off-limits. Instead, callbacks hand their
+//! problems to a [`RealtimeErrorReporter`], which queues them without
+//! blocking. An async forwarder task on the backend runtime drains the queue,
+//! logs every error and turns them into user notifications.
+//!
+//! Errors caused by the device changing its configuration mid-session, e.g.
+//! its sample rate, can't be fixed inside the callback, so the forwarder
+//! restarts the transcription session with the device's fresh settings. A
+//! stream failing like this right after starting is not restarted, as fresh
+//! settings won't fix it, and the session is stopped instead.
+
+use std::{collections::HashMap, mem::Discriminant, sync::Arc, time::Duration};
+
+use subwin_audio::resampler::ResamplerError;
+use subwin_bridge::notification::NotificationType;
+use tokio::{sync::mpsc, time::Instant};
+
+use crate::{services::AppContextHandle, stats::PipelineCounters};
+
+/// Number of errors queued for the forwarder; errors beyond it are dropped.
+const REALTIME_ERROR_CAPACITY: usize = 32;
+
+/// Minimum interval between two notifications about the same kind of error,
+/// so a problem repeating on every callback doesn't flood the user.
+const NOTIFICATION_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Number of mismatched buffers in a row after which the device is
+/// considered to have changed its configuration.
+const RESTART_AFTER_UNEXPECTED_BUFFERS: u32 = 20;
+
+/// Minimum time a stream has to run before a configuration change restarts
+/// it. A stream failing right away won't be fixed by restarting it, so it
+/// stops the session instead, which prevents restart loops.
+const MIN_STREAM_UPTIME: Duration = Duration::from_secs(5);
+
+/// Problems detected inside realtime audio callbacks.
+#[derive(Debug, thiserror::Error)]
+pub enum RealtimeError {
+    /// The device delivered a buffer of a different size than requested, so
+    /// it has been skipped. `in_a_row` counts the mismatched buffers since
+    /// the last one of the expected size.
+    #[error("unexpected buffer of {received} samples, expected {expected}")]
+    UnexpectedBufferSize {
+        received: usize,
+        expected: usize,
+        in_a_row: u32,
+    },
+    /// The captured audio could not be resampled.
+    #[error("resampler caught an error: {0}")]
+    Resampler(#[from] ResamplerError),
+    /// The transcription buffer was full, so captured samples were dropped.
+    #[error("{dropped} samples dropped, the transcription buffer is full")]
+    Overflow { dropped: usize },
+    /// The audio backend reported a stream error.
+    #[error("stream error: {0}")]
+    Stream(#[from] cpal::StreamError),
+}
+
+impl RealtimeError {
+    /// Returns whether the error means the stream has to be rebuilt.
+    fn requires_restart(&self) -> bool {
+        match self {
+            RealtimeError::Stream(cpal::StreamError::StreamInvalidated) => true,
+            RealtimeError::UnexpectedBufferSize { in_a_row, .. } => {
+                *in_a_row >= RESTART_AFTER_UNEXPECTED_BUFFERS
+            }
+            _ => false,
+        }
+    }
+
+    /// Returns the notification shown to the user for this error.
+    fn notification(&self) -> (NotificationType, String) {
+        match self {
+            RealtimeError::UnexpectedBufferSize { .. } => (
+                NotificationType::Warning,
+                "Устройство передаёт звук блоками неожиданного размера, часть звука пропускается."
+                    .to_string(),
+            ),
+            RealtimeError::Resampler(err) => (
+                NotificationType::Error,
+                format!("Не удалось преобразовать частоту дискретизации звука: {err}"),
+            ),
+            RealtimeError::Overflow { .. } => (
+                NotificationType::Warning,
+                "Распознавание не успевает за звуком, часть звука пропущена.".to_string(),
+            ),
+            RealtimeError::Stream(err) => (
+                NotificationType::Error,
+                format!("Ошибка входного аудиопотока: {err}"),
+            ),
+        }
+    }
+}
+
+/// Audio thread end of the error channel. Cheap to clone, so both the data
+/// and the error callbacks of a stream can own one.
+#[derive(Debug, Clone)]
+pub struct RealtimeErrorReporter {
+    tx: mpsc::Sender<RealtimeError>,
+}
+
+impl RealtimeErrorReporter {
+    /// Queues `error` for the forwarder without blocking. The error is
+    /// dropped if the queue is full or the forwarder is gone.
+    pub fn report(&self, error: impl Into<RealtimeError>) {
+        let _ = self.tx.try_send(error.into());
+    }
+}
+
+/// Restarts the active transcription session, so its stream is rebuilt with
+/// the device's current configuration.
+async fn restart_session(context: AppContextHandle) {
+    // errors queued before a stop request must not start a new session
+    if context.state.read().await.active_session.is_none() {
+        return;
+    }
+
+    log::warn!("Audio device configuration has changed, restarting the transcription session");
+    context
+        .send_notification(
+            NotificationType::Info,
+            "Настройки аудиоустройства изменились, перезапускаем захват звука.",
+        )
+        .await;
+    crate::services::transcription_service::handle_start_transcription_request(context).await;
+}
+
+/// Stops the active transcription session, whose stream has failed too soon
+/// after starting for a restart to help (see [`MIN_STREAM_UPTIME`]).
+async fn stop_failed_session(context: AppContextHandle) {
+    if context.state.read().await.active_session.is_none() {
+        return;
+    }
+
+    log::error!("Audio stream has failed right after starting, stopping the transcription session");
+    context
+        .send_notification(
+            NotificationType::Error,
+            "Аудиоустройство не работает с текущими настройками, распознавание остановлено.",
+        )
+        .await;
+    crate::services::transcription_service::handle_stop_transcription_request(context).await;
+}
+
+/// Spawns the forwarder task that turns realtime errors into notifications,
+/// restarting the session if the device's configuration has changed. The
+/// task ends once every reporter has been dropped, i.e. together with the
+/// streams owning them. The latest error is recorded in `counters`.
+pub fn spawn_realtime_error_forwarder(
+    context: &AppContextHandle,
+    counters: Arc<PipelineCounters>,
+) -> RealtimeErrorReporter {
+    let (tx, mut rx) = mpsc::channel(REALTIME_ERROR_CAPACITY);
+
+    let tasks = context.clone();
+    let context = context.clone();
+    tasks.spawn(async move {
+        let started_at = Instant::now();
+        let mut last_notified: HashMap<Discriminant<RealtimeError>, Instant> = HashMap::new();
+        while let Some(error) = rx.recv().await {
+            log::error!("Realtime audio callback has reported an error: {error}");
+            counters.set_last_error(&error);
+
+            if error.requires_restart() {
+                // both replace the stream owning this forwarder's reporters,
+                // so there is nothing left to forward afterwards
+                if started_at.elapsed() >= MIN_STREAM_UPTIME {
+                    restart_session(context).await;
+                } else {
+                    stop_failed_session(context).await;
+                }
+                return;
+            }
+
+            let kind = std::mem::discriminant(&error);
+            let now = Instant::now();
+            if last_notified
+                .get(&kind)
+                .is_some_and(|notified_at| now - *notified_at < NOTIFICATION_INTERVAL)
+            {
+                continue;
+            }
+            last_notified.insert(kind, now);
+
+            let (notification_type, message) = error.notification();
+            context.send_notification(notification_type, message).await;
+        }
+    });
+
+    RealtimeErrorReporter { tx }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unexpected_buffer(in_a_row: u32) -> RealtimeError {
+        RealtimeError::UnexpectedBufferSize {
+            received: 256,
+            expected: 512,
+            in_a_row,
+        }
+    }
+
+    #[test]
+    fn only_mismatched_buffers_in_a_row_require_a_restart() {
+        assert!(!unexpected_buffer(1).requires_restart());
+        assert!(!unexpected_buffer(RESTART_AFTER_UNEXPECTED_BUFFERS - 1).requires_restart());
+        assert!(unexpected_buffer(RESTART_AFTER_UNEXPECTED_BUFFERS).requires_restart());
+        assert!(RealtimeError::Stream(cpal::StreamError::StreamInvalidated).requires_restart());
+        assert!(!RealtimeError::Overflow { dropped: 128 }.requires_restart());
+    }
+}
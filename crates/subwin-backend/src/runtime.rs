@@ -2,54 +2,391 @@
 //!
 //! This module wires together configuration, shared state, and the message
 //! dispatch loop that listens to frontend bridge requests.
+//!
+//! # Recovery from panics
+//! The backend is supervised: if a message handler panics, the backend is
+//! torn down and set up again with the same bridge channels, so the frontend
+//! keeps working. The running session is stopped, the frontend is notified
+//! and told that transcription has stopped, and the state is set up from the
+//! saved config again. Messages the frontend has sent in the meantime are
+//! handled by the new backend. Panics of background tasks and of the
+//! transcription worker don't reach the supervisor, they only end the task.
+//! If the backend keeps panicking right after starting, it gives up and shuts
+//! down.
+
+use std::{
+    panic::AssertUnwindSafe,
+    path::PathBuf,
+    sync::{Arc, OnceLock},
+    thread,
+    time::{Duration, Instant},
+};
 
-use std::{sync::Arc, thread};
+use futures_util::FutureExt;
 
-use subwin_bridge::{MessageFromBackend, MessageToBackend};
+use subwin_bridge::{
+    MessageFromBackend, MessageToBackend,
+    audio::DeviceSelector,
+    config::Config,
+    notification::{NotificationMessage, NotificationType},
+    stats::InputLevel,
+};
 use tokio::sync::{
     RwLock,
     mpsc::{Receiver, Sender},
+    watch,
 };
 
 use crate::app::AppContext;
-use crate::state::State;
+use crate::services::{
+    AppContextHandle,
+    audio_service::{resolve_device_selector, resolve_selected_device},
+    config_service, transcription_service,
+};
+use crate::state::{SessionPhase, SharedState, State};
+
+/// Time given to the runtime's remaining tasks to finish once the backend has
+/// shut down.
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Number of times in a row the backend is set up again after panicking
+/// within [`RAPID_PANIC_WINDOW`] of its start, before giving up.
+const MAX_RAPID_RESTARTS: u32 = 3;
+
+/// Time after the backend's start, within which a panic counts as a rapid
+/// one (see [`MAX_RAPID_RESTARTS`]).
+const RAPID_PANIC_WINDOW: Duration = Duration::from_secs(60);
+
+/// Options for running the backend without the GUI (see [`run_headless`]).
+///
+/// Overrides are applied to the loaded config for this run only and are not
+/// persisted.
+#[derive(Debug, Clone, Default)]
+pub struct HeadlessOptions {
+    /// Input device to capture instead of the configured one.
+    pub device: Option<DeviceSelector>,
+    /// Path to the model to use instead of the configured one.
+    pub model_path: Option<PathBuf>,
+}
+
+/// Handle of the backend thread, returned by [`run`] and [`run_headless`].
+pub struct BackendHandle {
+    thread: thread::JoinHandle<()>,
+    /// The backend's state, set once it has started up.
+    state: Arc<OnceLock<SharedState>>,
+}
+
+impl BackendHandle {
+    /// Returns the config the backend runs with: the loaded one, with the
+    /// remembered device resolved and the headless overrides applied.
+    /// Returns `None` until the backend has started up.
+    ///
+    /// Blocks while the config is being updated. Must not be called from
+    /// within an async runtime; frontends request it with
+    /// [`MessageToBackend::ConfigurationRequest`] instead.
+    pub fn current_config(&self) -> Option<Config> {
+        self.state
+            .get()
+            .map(|state| state.blocking_read().config.clone())
+    }
 
-/// Initialize backend state and start processing frontend messages.
-async fn setup_backend(rx: Receiver<MessageToBackend>, tx: Sender<MessageFromBackend>) {
-    let (config, cache_path) = crate::config::load_config()
+    /// Waits for the backend to stop: for the active stream and in-flight
+    /// tasks to wind down. Fails if the backend thread has panicked.
+    pub fn join(self) -> thread::Result<()> {
+        self.thread.join()
+    }
+}
+
+/// Initialize backend state and start processing frontend messages. In
+/// headless mode, transcription starts right away and runs until Ctrl+C, and
+/// `SIGUSR1` resumes capture once the idle device has been released.
+///
+/// The created context is stored into `active_context`, so it can be shut
+/// down if the backend panics. If `shared_state` has been set by a previous
+/// setup, its state is replaced instead.
+async fn setup_backend(
+    rx: &mut Receiver<MessageToBackend>,
+    tx: Sender<MessageFromBackend>,
+    updates_tx: Sender<MessageFromBackend>,
+    levels_tx: watch::Sender<InputLevel>,
+    headless: Option<HeadlessOptions>,
+    shared_state: &OnceLock<SharedState>,
+    active_context: &mut Option<AppContextHandle>,
+) {
+    let (mut config, cache_path, config_messages) = crate::config::load_config()
         .await
         .expect("failed to load config");
 
     let request_client = reqwest::Client::new();
     let active_host = Arc::new(cpal::default_host()); // using default host for now
-    let active_audio_device = match config.audio_device_config.selected_device_id {
-        Some(ref device_id) => {
-            subwin_audio::device::get_device_by_id(&active_host, device_id.to_string())
-                .expect("failed to get active audio device")
+
+    let is_headless = headless.is_some();
+    if let Some(options) = headless {
+        if let Some(selector) = options.device {
+            // an unresolved device must not fall back to the configured one
+            config.audio_device_config.selected_device_id =
+                match resolve_device_selector(&active_host, &selector) {
+                    Ok(device_id) => Some(device_id),
+                    Err(err) => {
+                        log::error!("Failed to select the audio device: {err}");
+                        None
+                    }
+                };
+            config.audio_device_config.selected_device_description = None;
         }
-        None => None,
-    };
+        if let Some(model_path) = options.model_path {
+            config.active_model_path = Some(model_path);
+        }
+    }
+    let (active_audio_device, is_device_id_updated) =
+        resolve_selected_device(&active_host, &mut config.audio_device_config);
 
-    let state = Arc::new(RwLock::new(State {
+    let state = State {
         config,
         cache_path,
         request_client,
         active_host,
         active_audio_device: Arc::new(active_audio_device),
-        active_stream: None,
-    }));
+        active_session: None,
+        session_phase: SessionPhase::default(),
+        is_benchmarking: false,
+        is_headless,
+    };
+    let state = match shared_state.get() {
+        Some(shared_state) => {
+            *shared_state.write().await = state;
+            shared_state.clone()
+        }
+        None => shared_state
+            .get_or_init(|| Arc::new(RwLock::new(state)))
+            .clone(),
+    };
+
+    let context = Arc::new(AppContext::new(state, tx, updates_tx, levels_tx));
+    *active_context = Some(context.clone());
+    if is_device_id_updated && !is_headless {
+        let config = context.state.read().await.config.clone();
+        config_service::persist_config(&context, &config).await;
+    }
+    if is_device_id_updated {
+        context
+            .send_notification(
+                NotificationType::Info,
+                "Идентификатор выбранного источника звука изменился, устройство найдено по названию.",
+            )
+            .await;
+    }
+    for message in config_messages {
+        context
+            .send_notification(NotificationType::Warning, message)
+            .await;
+    }
+
+    if is_headless {
+        transcription_service::handle_start_transcription_request(context.clone()).await;
+        if context.state.read().await.active_session.is_none() {
+            log::error!("Failed to start transcription, shutting down the backend...");
+            context.shutdown().await;
+            return;
+        }
+        #[cfg(unix)]
+        spawn_resume_signal_listener(&context);
 
-    let context = Arc::new(AppContext { state, tx });
-    context.consume_bridge_messages(rx).await;
+        tokio::select! {
+            _ = context.consume_bridge_messages(rx) => {}
+            _ = tokio::signal::ctrl_c() => log::info!("Interrupted, stopping transcription..."),
+        }
+    } else {
+        if context.state.read().await.config.auto_start_transcription {
+            log::info!("Starting transcription automatically on launch");
+            // the captions window is laid out according to the config, so
+            // it has to reach the frontend before the session starts
+            config_service::handle_config_request(context.clone()).await;
+            transcription_service::handle_start_transcription_request(context.clone()).await;
+        }
+        context.consume_bridge_messages(rx).await;
+    }
+
+    log::info!("Frontend bridge closed, shutting down the backend...");
+    context.shutdown().await;
 }
 
-/// Spawn the backend runtime and begin processing bridge messages.
-pub fn run(rx: Receiver<MessageToBackend>, tx: Sender<MessageFromBackend>) {
-    thread::spawn(move || {
+/// Spawns a task that captures from the released input device again (see
+/// [`Config::idle_release_minutes`]) whenever the process receives
+/// `SIGUSR1`, as there's no window to request it from in headless mode.
+#[cfg(unix)]
+fn spawn_resume_signal_listener(context: &AppContextHandle) {
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut signals = match signal(SignalKind::user_defined1()) {
+        Ok(signals) => signals,
+        Err(err) => {
+            log::warn!("Failed to listen for SIGUSR1, released capture can't be resumed: {err}");
+            return;
+        }
+    };
+    let tasks = context.clone();
+    let context = context.clone();
+    tasks.spawn(async move {
+        while signals.recv().await.is_some() {
+            log::info!("Received SIGUSR1, resuming capture");
+            transcription_service::handle_resume_capture_request(context.clone()).await;
+        }
+    });
+}
+
+/// Returns the message of a caught panic's `payload`.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Sends an error notification through `tx`, while there's no context to
+/// send it with. A closed bridge is ignored.
+async fn send_error_notification(tx: &Sender<MessageFromBackend>, message: &str) {
+    let _ = tx
+        .send(MessageFromBackend::NotificationMessage(
+            NotificationMessage {
+                notification_type: NotificationType::Error,
+                message: message.to_string(),
+            },
+        ))
+        .await;
+}
+
+/// Runs the backend, setting it up again whenever it panics (see the
+/// [module docs](self)), until it shuts down or keeps panicking.
+async fn supervise_backend(
+    mut rx: Receiver<MessageToBackend>,
+    tx: Sender<MessageFromBackend>,
+    updates_tx: Sender<MessageFromBackend>,
+    levels_tx: watch::Sender<InputLevel>,
+    headless: Option<HeadlessOptions>,
+    shared_state: Arc<OnceLock<SharedState>>,
+) {
+    let mut rapid_restarts = 0;
+    loop {
+        let started_at = Instant::now();
+        let mut active_context = None;
+        let result = AssertUnwindSafe(setup_backend(
+            &mut rx,
+            tx.clone(),
+            updates_tx.clone(),
+            levels_tx.clone(),
+            headless.clone(),
+            &shared_state,
+            &mut active_context,
+        ))
+        .catch_unwind()
+        .await;
+        let Err(payload) = result else {
+            return;
+        };
+        log::error!("Backend has panicked: {}", panic_message(&*payload));
+
+        // the panicked handler may have left the session running
+        if let Some(context) = active_context
+            && AssertUnwindSafe(context.shutdown())
+                .catch_unwind()
+                .await
+                .is_err()
+        {
+            log::error!("Failed to shut down the panicked backend cleanly");
+        }
+
+        rapid_restarts = if started_at.elapsed() < RAPID_PANIC_WINDOW {
+            rapid_restarts + 1
+        } else {
+            1
+        };
+        if rapid_restarts > MAX_RAPID_RESTARTS {
+            log::error!("Backend keeps panicking, giving up after {MAX_RAPID_RESTARTS} restarts");
+            send_error_notification(
+                &tx,
+                "Внутренняя ошибка повторяется, распознавание недоступно. Перезапустите приложение.",
+            )
+            .await;
+            return;
+        }
+
+        log::info!("Restarting the backend...");
+        send_error_notification(
+            &tx,
+            "Произошла внутренняя ошибка, распознавание остановлено. Запустите его снова.",
+        )
+        .await;
+        let _ = tx
+            .send(MessageFromBackend::TranscriptionStoppedResponse)
+            .await;
+    }
+}
+
+/// Spawn the backend runtime on a dedicated thread.
+fn spawn_runtime(
+    rx: Receiver<MessageToBackend>,
+    tx: Sender<MessageFromBackend>,
+    updates_tx: Sender<MessageFromBackend>,
+    levels_tx: watch::Sender<InputLevel>,
+    headless: Option<HeadlessOptions>,
+) -> BackendHandle {
+    let state = Arc::new(OnceLock::new());
+    let shared_state = state.clone();
+    let thread = thread::spawn(move || {
         let runtime = tokio::runtime::Builder::new_multi_thread()
             .enable_all()
             .build()
             .expect("failed to build tokio runtime");
-        runtime.block_on(async { setup_backend(rx, tx).await });
+        runtime.block_on(supervise_backend(
+            rx,
+            tx,
+            updates_tx,
+            levels_tx,
+            headless,
+            shared_state,
+        ));
+        runtime.shutdown_timeout(RUNTIME_SHUTDOWN_TIMEOUT);
     });
+    BackendHandle { thread, state }
+}
+
+/// Spawn the backend runtime and begin processing bridge messages.
+///
+/// The backend runs until the frontend sends
+/// [`MessageToBackend::ShutdownRequest`] or drops its sender. The returned
+/// handle can be joined to wait for the active stream and in-flight tasks to
+/// be stopped, or queried for the effective config. High-frequency updates
+/// are sent through `updates_tx` (see [`MessageFromBackend::is_update`]),
+/// while input levels are published through `levels_tx`.
+pub fn run(
+    rx: Receiver<MessageToBackend>,
+    tx: Sender<MessageFromBackend>,
+    updates_tx: Sender<MessageFromBackend>,
+    levels_tx: watch::Sender<InputLevel>,
+) -> BackendHandle {
+    spawn_runtime(rx, tx, updates_tx, levels_tx, None)
+}
+
+/// Spawn the backend runtime without a frontend: transcription starts
+/// immediately and finalized captions are printed to the standard output
+/// until Ctrl+C is pressed or a shutdown is requested through `rx`. On Unix,
+/// sending `SIGUSR1` to the process captures from the input device again
+/// after it has been released (see [`Config::idle_release_minutes`]).
+///
+/// Messages sent through `tx` still have to be drained by the caller, while
+/// updates sent through `updates_tx` and levels published through
+/// `levels_tx` may be left unread. The returned handle completes once the
+/// final captions have been flushed.
+pub fn run_headless(
+    rx: Receiver<MessageToBackend>,
+    tx: Sender<MessageFromBackend>,
+    updates_tx: Sender<MessageFromBackend>,
+    levels_tx: watch::Sender<InputLevel>,
+    options: HeadlessOptions,
+) -> BackendHandle {
+    spawn_runtime(rx, tx, updates_tx, levels_tx, Some(options))
 }
@@ -1,4 +1,309 @@
-use subwin_bridge::audio::InputDevice;
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use cpal::traits::{DeviceTrait, StreamTrait};
+use ringbuf_blocking::{
+    BlockingHeapRb,
+    traits::{Consumer, Producer, Split},
+};
+use subwin_audio::device::{DeviceError, HostInputDevice};
+use subwin_bridge::{
+    audio::{
+        ActiveDeviceInfo, AudioCaptureError, DeviceSelector, InputDevice, MicTestResult,
+        SYSTEM_DEFAULT_DEVICE_ID,
+    },
+    config::AudioDeviceConfig,
+    notification::NotificationType,
+};
+
+/// Sample magnitude from which the input is considered clipping.
+const CLIPPING_THRESHOLD: f32 = 0.999;
+
+/// Length of the chunks the recording is played back in.
+const PLAYBACK_CHUNK: Duration = Duration::from_millis(100);
+
+/// Interval between checks whether the system's default input device has
+/// changed, while a session follows it.
+const DEFAULT_DEVICE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Longest recording of a microphone test. The recording is kept in memory
+/// for the playback, so longer tests are refused.
+const MAX_MIC_TEST_DURATION: Duration = Duration::from_secs(30);
+
+/// Fragments of audio backend error descriptions, in lowercase, reported
+/// when another application holds the device: ALSA's `EBUSY` and WASAPI's
+/// `AUDCLNT_E_DEVICE_IN_USE`.
+const DEVICE_BUSY_MARKERS: [&str; 4] = ["busy", "in use", "device_in_use", "0x8889000a"];
+
+/// Fragments of audio backend error descriptions, in lowercase, reported
+/// when the system doesn't allow recording: ALSA's `EACCES`, `E_ACCESSDENIED`
+/// of WASAPI and the microphone privacy settings on macOS.
+const PERMISSION_DENIED_MARKERS: [&str; 5] = [
+    "permission",
+    "access denied",
+    "access is denied",
+    "0x80070005",
+    "not authorized",
+];
+
+/// Errors that can occur while testing a microphone.
+#[derive(Debug, thiserror::Error)]
+enum MicTestError {
+    /// The device to test is not available.
+    #[error("device {0} was not found")]
+    DeviceNotFound(String),
+    /// Failed to open the device's input stream.
+    #[error("{0}")]
+    Device(#[from] DeviceError),
+    /// The input stream has been built, but could not be started.
+    #[error("failed to start recording: {0}")]
+    PlayStream(#[from] cpal::PlayStreamError),
+}
+
+/// Errors that can occur while resolving a [`DeviceSelector`].
+#[derive(Debug, thiserror::Error)]
+pub enum DeviceSelectorError {
+    /// There is no device at the selected position.
+    #[error("no input device at index {index}, {count} device(-s) available")]
+    IndexOutOfRange {
+        /// The selected position.
+        index: usize,
+        /// Number of input devices of the host.
+        count: usize,
+    },
+    /// No device description contains the selected name.
+    #[error("no input device matches {0:?}")]
+    NoMatch(String),
+    /// Several device descriptions contain the selected name.
+    #[error("several input devices match {name:?}: {}", candidates.join(", "))]
+    Ambiguous {
+        /// The selected name.
+        name: String,
+        /// Descriptions of the matching devices.
+        candidates: Vec<String>,
+    },
+    /// Failed to list the host's input devices.
+    #[error("{0}")]
+    Device(#[from] DeviceError),
+}
+
+/// Resolves `selector` to the identifier of a device of `host`. Identifiers
+/// are returned as is, without checking that they exist.
+pub fn resolve_device_selector(
+    host: &cpal::Host,
+    selector: &DeviceSelector,
+) -> Result<String, DeviceSelectorError> {
+    match selector {
+        DeviceSelector::Id(id) => Ok(id.clone()),
+        DeviceSelector::Index(index) => {
+            let devices = subwin_audio::device::list_host_input_devices(host)?;
+            devices
+                .get(*index)
+                .map(|device| device.id.to_string())
+                .ok_or(DeviceSelectorError::IndexOutOfRange {
+                    index: *index,
+                    count: devices.len(),
+                })
+        }
+        DeviceSelector::Name(name) => {
+            let needle = name.to_lowercase();
+            let mut matching: Vec<HostInputDevice> =
+                subwin_audio::device::list_host_input_devices(host)?
+                    .into_iter()
+                    .filter(|device| device.description.to_lowercase().contains(&needle))
+                    .collect();
+            match matching.len() {
+                0 => Err(DeviceSelectorError::NoMatch(name.clone())),
+                1 => Ok(matching.remove(0).id.to_string()),
+                _ => Err(DeviceSelectorError::Ambiguous {
+                    name: name.clone(),
+                    candidates: matching
+                        .into_iter()
+                        .map(|device| device.description)
+                        .collect(),
+                }),
+            }
+        }
+    }
+}
+
+/// Finds the input device with `device_id`, resolving
+/// [`SYSTEM_DEFAULT_DEVICE_ID`] to the host's current default input device.
+fn find_input_device(
+    host: &cpal::Host,
+    device_id: &str,
+) -> Result<Option<cpal::Device>, DeviceError> {
+    if device_id == SYSTEM_DEFAULT_DEVICE_ID {
+        return Ok(host.default_input_device());
+    }
+    subwin_audio::device::get_device_by_id(host, device_id.to_string())
+}
+
+/// Converts a linear amplitude to dBFS, flooring silence at -100 dBFS.
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    20.0 * amplitude.max(1e-5).log10()
+}
+
+/// Records `duration` of audio from `device`, downmixed to mono. Returns the
+/// samples and their sample rate. Blocks for the whole duration.
+fn record_mono(
+    device: &HostInputDevice,
+    duration: Duration,
+) -> Result<(Vec<f32>, u32), MicTestError> {
+    let (sample_rate, _) = device.sample_rate_and_channels()?;
+    let capacity = (sample_rate as u128 * duration.as_millis() / 1000) as usize;
+    let (mut producer, mut consumer) = BlockingHeapRb::<f32>::new(capacity.max(1)).split();
+
+    let (stream, _) = subwin_audio::device::open_best_input_stream(
+        device,
+        sample_rate,
+        move |data: &[f32]| {
+            // the buffer only fits the requested duration, later samples are
+            // dropped
+            producer.push_slice(data);
+        },
+        |error| log::error!("An error occured while recording the microphone test: {error}"),
+    )?;
+    stream.play()?;
+    std::thread::sleep(duration);
+    drop(stream);
+
+    let mut samples = vec![0.0; capacity];
+    let len = consumer.pop_slice(&mut samples);
+    samples.truncate(len);
+    Ok((samples, sample_rate))
+}
+
+/// Plays `samples` back through the monitoring output device. Blocks until
+/// the playback ends.
+fn play_back(host: &cpal::Host, monitor_device_id: Option<&str>, samples: &[f32], rate: u32) {
+    let (stream, mut feed) = match crate::monitor::open_monitor(host, monitor_device_id, rate) {
+        Ok(monitor) => monitor,
+        Err(err) => {
+            log::error!("Failed to play the microphone test back: {err}");
+            return;
+        }
+    };
+
+    // the playback buffer only holds half a second, so feed it in real time
+    let chunk_size = (rate as u128 * PLAYBACK_CHUNK.as_millis() / 1000) as usize;
+    for chunk in samples.chunks(chunk_size.max(1)) {
+        feed.push(chunk);
+        std::thread::sleep(PLAYBACK_CHUNK);
+    }
+    // let the queued tail play out
+    std::thread::sleep(PLAYBACK_CHUNK * 3);
+    drop(stream);
+}
+
+/// Records audio from the device with `device_id`, measures its levels, and
+/// plays the recording back if `playback` is set. Blocks until done.
+fn run_microphone_test(
+    host: &cpal::Host,
+    device_id: &str,
+    duration: Duration,
+    playback: bool,
+    monitor_device_id: Option<&str>,
+) -> Result<MicTestResult, MicTestError> {
+    let device = find_input_device(host, device_id)?
+        .ok_or_else(|| MicTestError::DeviceNotFound(device_id.to_string()))?;
+    let (samples, sample_rate) = record_mono(&HostInputDevice::from(device), duration)?;
+
+    let peak = samples
+        .iter()
+        .fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+    let rms = if samples.is_empty() {
+        0.0
+    } else {
+        (samples.iter().map(|sample| sample * sample).sum::<f32>() / samples.len() as f32).sqrt()
+    };
+
+    if playback {
+        play_back(host, monitor_device_id, &samples, sample_rate);
+    }
+
+    Ok(MicTestResult {
+        peak_db: amplitude_to_db(peak),
+        rms_db: amplitude_to_db(rms),
+        clipping: peak >= CLIPPING_THRESHOLD,
+    })
+}
+
+/// Looks up the remembered input device of `device_config`.
+///
+/// When the stored identifier doesn't resolve anymore, the device is looked
+/// up by its stored description instead, and the identifier in
+/// `device_config` is replaced with the current one. A device that can't be
+/// found either way falls back to the host's default input device, keeping
+/// the stored selection. Returns the device and whether the identifier has
+/// been replaced.
+pub fn resolve_selected_device(
+    host: &cpal::Host,
+    device_config: &mut AudioDeviceConfig,
+) -> (Option<cpal::Device>, bool) {
+    resolve_device(
+        device_config,
+        |device_id| match subwin_audio::device::get_device_by_id(host, device_id.to_string()) {
+            Ok(device) => device,
+            Err(err) => {
+                log::warn!("Stored audio device id {device_id} is invalid: {err}");
+                None
+            }
+        },
+        |description| match subwin_audio::device::find_input_device_by_description(
+            host,
+            description,
+        ) {
+            Ok(device) => device.map(|device| (device.id.to_string(), device.into_device())),
+            Err(err) => {
+                log::error!("Failed to list audio devices: {err}");
+                None
+            }
+        },
+        || host.default_input_device(),
+    )
+}
+
+/// Resolves the device selected by `device_config` as described at
+/// [`resolve_selected_device`], using the given lookups of the host: by
+/// identifier, by description returning the identifier too, and of the
+/// default device.
+fn resolve_device<D>(
+    device_config: &mut AudioDeviceConfig,
+    find_by_id: impl FnOnce(&str) -> Option<D>,
+    find_by_description: impl FnOnce(&str) -> Option<(String, D)>,
+    default_device: impl FnOnce() -> Option<D>,
+) -> (Option<D>, bool) {
+    let Some(device_id) = device_config.selected_device_id.clone() else {
+        return (None, false);
+    };
+    if device_id == SYSTEM_DEFAULT_DEVICE_ID {
+        return (default_device(), false);
+    }
+
+    if let Some(device) = find_by_id(&device_id) {
+        return (Some(device), false);
+    }
+
+    let description = device_config.selected_device_description.as_deref();
+    if let Some((new_id, device)) = description.and_then(find_by_description) {
+        log::info!(
+            "Audio device {device_id} was not found, using {new_id} matched by its description"
+        );
+        device_config.selected_device_id = Some(new_id);
+        return (Some(device), true);
+    }
+
+    log::warn!(
+        "Could not find the stored audio device {device_id} ({description:?}), using the default device"
+    );
+    (default_device(), false)
+}
 
 /// Handles an incoming audio devices list request (see
 /// [`subwin_bridge::MessageToBackend::AudioDevicesListRequest`]).
@@ -10,13 +315,27 @@ pub async fn handle_audio_devices_list_request(context: super::AppContextHandle)
 
     let devices = subwin_audio::device::list_host_input_devices(&host)
         .expect("failed to obtain host's input devices");
-    let response_devices: Vec<InputDevice> = devices
-        .iter()
-        .map(|device| InputDevice {
+    let selected_device_id = config.audio_device_config.selected_device_id.as_deref();
+    let default_description = match host.default_input_device() {
+        Some(device) => match device.description() {
+            Ok(description) => format!("По умолчанию ({description})"),
+            Err(_) => "По умолчанию".to_string(),
+        },
+        None => "По умолчанию (нет устройства)".to_string(),
+    };
+
+    // following the system default is offered first
+    let system_default = InputDevice {
+        id: SYSTEM_DEFAULT_DEVICE_ID.to_string(),
+        description: default_description,
+        selected: selected_device_id == Some(SYSTEM_DEFAULT_DEVICE_ID),
+    };
+    let response_devices: Vec<InputDevice> = std::iter::once(system_default)
+        .chain(devices.iter().map(|device| InputDevice {
             id: device.id.to_string(),
             description: device.description.clone(),
-            selected: config.audio_device_config.selected_device_id == Some(device.id.to_string()),
-        })
+            selected: selected_device_id == Some(device.id.to_string().as_str()),
+        }))
         .collect();
 
     context
@@ -24,6 +343,48 @@ pub async fn handle_audio_devices_list_request(context: super::AppContextHandle)
             response_devices,
         ))
         .await;
+    send_active_device_info(&context).await;
+}
+
+/// Builds the [`ActiveDeviceInfo`] of `device`, capturing at `sample_rate`
+/// with `channels`.
+pub fn active_device_info(
+    device: &HostInputDevice,
+    sample_rate: cpal::SampleRate,
+    channels: u16,
+) -> ActiveDeviceInfo {
+    ActiveDeviceInfo {
+        description: device.description.clone(),
+        sample_rate,
+        channels,
+        is_resampled: sample_rate != super::transcription_service::TARGET_RATE,
+    }
+}
+
+/// Sends the native format of the active input device to the frontend (see
+/// [`subwin_bridge::MessageFromBackend::ActiveDeviceInfo`]).
+async fn send_active_device_info(context: &super::AppContextHandle) {
+    let (active_device, audio_device_config) = {
+        let state = context.state.read().await;
+        (
+            state.active_audio_device.clone(),
+            state.config.audio_device_config.clone(),
+        )
+    };
+    let info = active_device.as_ref().as_ref().and_then(|device| {
+        let mut device = HostInputDevice::from(device.clone());
+        super::transcription_service::prefer_target_rate(&mut device, &audio_device_config);
+        match device.sample_rate_and_channels() {
+            Ok((sample_rate, channels)) => Some(active_device_info(&device, sample_rate, channels)),
+            Err(err) => {
+                log::warn!("Failed to query the active input device {device}: {err}");
+                None
+            }
+        }
+    });
+    context
+        .send(subwin_bridge::MessageFromBackend::ActiveDeviceInfo(info))
+        .await;
 }
 
 /// Handles an audio device selection request and persists it to config.
@@ -33,19 +394,435 @@ pub async fn handle_audio_device_selection(context: super::AppContextHandle, id:
         state.active_host.clone()
     };
 
-    let audio_device = subwin_audio::device::get_device_by_id(&active_host, id.clone())
-        .expect("failed to get target device id");
+    let audio_device =
+        find_input_device(&active_host, &id).expect("failed to get target device id");
 
     match audio_device {
         Some(device) => {
             let mut state = context.state.write().await;
+            // the default device is looked up anew, never by description
+            state.config.audio_device_config.selected_device_description =
+                if id == SYSTEM_DEFAULT_DEVICE_ID {
+                    None
+                } else {
+                    device
+                        .description()
+                        .ok()
+                        .map(|description| description.to_string())
+                };
             state.active_audio_device = std::sync::Arc::new(Some(device));
             state.config.audio_device_config.selected_device_id = Some(id);
             // persist the updated selection so it is remembered across runs
-            crate::config::save_config(&state.config)
-                .await
-                .expect("failed to update selected device id");
+            super::config_service::persist_config(&context, &state.config).await;
+            drop(state);
+            send_active_device_info(&context).await;
         }
         None => log::error!("Could not find the target device at {}", id),
     }
 }
+
+/// Handles a request to select an audio device by a selector (see
+/// [`subwin_bridge::MessageToBackend::SelectAudioDeviceBy`]).
+pub async fn handle_audio_device_selector(
+    context: super::AppContextHandle,
+    selector: DeviceSelector,
+) {
+    let active_host = {
+        let state = context.state.read().await;
+        state.active_host.clone()
+    };
+
+    let resolved =
+        tokio::task::spawn_blocking(move || resolve_device_selector(&active_host, &selector))
+            .await
+            .expect("device selector resolution has panicked");
+    match resolved {
+        Ok(id) => handle_audio_device_selection(context, id).await,
+        Err(err) => {
+            log::error!("Failed to select an audio device: {err}");
+            let message = match err {
+                DeviceSelectorError::Ambiguous { name, candidates } => format!(
+                    "Названию «{name}» соответствует несколько устройств: {}. Уточните название или выберите устройство по номеру.",
+                    candidates.join(", "),
+                ),
+                err => format!("Не удалось выбрать устройство: {err}"),
+            };
+            context
+                .send_notification(NotificationType::Error, message)
+                .await;
+        }
+    }
+}
+
+/// Handles a microphone test request (see
+/// [`subwin_bridge::MessageToBackend::TestMicrophone`]). The test runs in the
+/// background, so other requests are served meanwhile.
+pub async fn handle_test_microphone_request(
+    context: super::AppContextHandle,
+    device_id: String,
+    duration_ms: u32,
+    playback: bool,
+) {
+    let duration = Duration::from_millis(duration_ms as u64);
+    if duration > MAX_MIC_TEST_DURATION {
+        log::warn!("Refused a microphone test of {duration_ms} ms, exceeding the limit");
+        context
+            .send_notification(
+                NotificationType::Error,
+                format!(
+                    "Проверка микрофона не может длиться дольше {} секунд.",
+                    MAX_MIC_TEST_DURATION.as_secs()
+                ),
+            )
+            .await;
+        return;
+    }
+
+    let (host, monitor_device_id) = {
+        let state = context.state.read().await;
+        (
+            state.active_host.clone(),
+            state.config.audio_device_config.monitor_device_id.clone(),
+        )
+    };
+
+    context
+        .send_notification(
+            NotificationType::Info,
+            "Идёт запись, скажите что-нибудь в микрофон...",
+        )
+        .await;
+
+    let tasks = context.clone();
+    tasks.spawn(async move {
+        let test = tokio::task::spawn_blocking(move || {
+            run_microphone_test(
+                &host,
+                &device_id,
+                duration,
+                playback,
+                monitor_device_id.as_deref(),
+            )
+        });
+
+        match test.await.expect("microphone test has panicked") {
+            Ok(result) => {
+                context
+                    .send(subwin_bridge::MessageFromBackend::MicTestResult(result))
+                    .await;
+            }
+            Err(err) => {
+                log::error!("Microphone test has failed: {err}");
+                context
+                    .send_notification(
+                        NotificationType::Error,
+                        format!("Не удалось проверить микрофон: {err}"),
+                    )
+                    .await;
+            }
+        }
+    });
+}
+
+/// Looks up the system's default input device again, if the selection
+/// follows it, so a session starts on the device that is the default now.
+pub async fn refresh_default_device(context: &super::AppContextHandle) {
+    let mut state = context.state.write().await;
+    if state
+        .config
+        .audio_device_config
+        .selected_device_id
+        .as_deref()
+        != Some(SYSTEM_DEFAULT_DEVICE_ID)
+    {
+        return;
+    }
+
+    let device = state.active_host.default_input_device();
+    if let Some(description) = device.as_ref().and_then(|device| device.description().ok()) {
+        log::info!("Following the system default input device: {description}");
+    }
+    state.active_audio_device = Arc::new(device);
+}
+
+/// Returns the identifier of the host's current default input device.
+fn default_input_device_id(host: &cpal::Host) -> Option<cpal::DeviceId> {
+    host.default_input_device()
+        .and_then(|device| device.id().ok())
+}
+
+/// Spawns a task that restarts the session once the system's default input
+/// device changes, or stops it once there's no default device anymore. The
+/// task ends when `stop_signal` of the session is set.
+pub fn spawn_default_device_watcher(
+    context: &super::AppContextHandle,
+    stop_signal: Arc<AtomicBool>,
+    host: Arc<cpal::Host>,
+) {
+    let tasks = context.clone();
+    let context = context.clone();
+    tasks.spawn(async move {
+        let initial_id = default_input_device_id(&host);
+        loop {
+            tokio::time::sleep(DEFAULT_DEVICE_POLL_INTERVAL).await;
+            if stop_signal.load(Ordering::Relaxed) {
+                return;
+            }
+            let current_id = default_input_device_id(&host);
+            if current_id == initial_id {
+                continue;
+            }
+            if current_id.is_none() {
+                log::warn!("System default input device is gone, stopping the session");
+                context
+                    .send_notification(
+                        NotificationType::Warning,
+                        "Системное устройство ввода отключено, распознавание остановлено. Подключите устройство или выберите другое.",
+                    )
+                    .await;
+                super::transcription_service::handle_stop_transcription_request(context).await;
+                return;
+            }
+
+            log::info!("System default input device has changed, restarting the session");
+            context
+                .send_notification(
+                    NotificationType::Info,
+                    "Системное устройство ввода изменилось, перезапускаем захват звука.",
+                )
+                .await;
+            // the restart looks the new default device up
+            super::transcription_service::handle_start_transcription_request(context).await;
+            return;
+        }
+    });
+}
+
+/// Tells the reason of a backend-specific failure by its description. cpal
+/// doesn't distinguish a busy device or a denied permission, so the messages
+/// of the platform APIs are matched.
+fn classify_backend_error(err: &cpal::BackendSpecificError) -> AudioCaptureError {
+    let description = err.description.to_lowercase();
+    if DEVICE_BUSY_MARKERS
+        .iter()
+        .any(|marker| description.contains(marker))
+    {
+        AudioCaptureError::DeviceBusy
+    } else if PERMISSION_DENIED_MARKERS
+        .iter()
+        .any(|marker| description.contains(marker))
+    {
+        AudioCaptureError::PermissionDenied
+    } else {
+        AudioCaptureError::Backend(err.description.clone())
+    }
+}
+
+/// Maps a failure to query or open the input device onto the reason shown
+/// to the user.
+pub fn classify_device_error(err: &DeviceError) -> AudioCaptureError {
+    match err {
+        DeviceError::BuildStream(cpal::BuildStreamError::DeviceNotAvailable)
+        | DeviceError::BuildStreamConfig(cpal::DefaultStreamConfigError::DeviceNotAvailable)
+        | DeviceError::SupportedConfigs(cpal::SupportedStreamConfigsError::DeviceNotAvailable) => {
+            AudioCaptureError::DeviceNotAvailable
+        }
+        DeviceError::BuildStream(cpal::BuildStreamError::StreamConfigNotSupported)
+        | DeviceError::BuildStreamConfig(cpal::DefaultStreamConfigError::StreamTypeNotSupported) => {
+            AudioCaptureError::UnsupportedConfig
+        }
+        DeviceError::BuildStream(cpal::BuildStreamError::BackendSpecific { err })
+        | DeviceError::BuildStreamConfig(cpal::DefaultStreamConfigError::BackendSpecific { err })
+        | DeviceError::SupportedConfigs(cpal::SupportedStreamConfigsError::BackendSpecific {
+            err,
+        })
+        | DeviceError::ReadDevices(cpal::DevicesError::BackendSpecific { err }) => {
+            classify_backend_error(err)
+        }
+        err => AudioCaptureError::Backend(err.to_string()),
+    }
+}
+
+/// Maps a failure to start the input stream onto the reason shown to the
+/// user.
+pub fn classify_play_error(err: &cpal::PlayStreamError) -> AudioCaptureError {
+    match err {
+        cpal::PlayStreamError::DeviceNotAvailable => AudioCaptureError::DeviceNotAvailable,
+        cpal::PlayStreamError::BackendSpecific { err } => classify_backend_error(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Input devices of a fake host as `(identifier, description)` pairs, the
+    /// first one being the default.
+    const DEVICES: [(&str, &str); 3] = [
+        ("hw:0", "Built-in Microphone"),
+        ("hw:1", "USB Headset"),
+        ("hw:2", "USB Headset"),
+    ];
+
+    fn device_config(device_id: &str, description: Option<&str>) -> AudioDeviceConfig {
+        AudioDeviceConfig {
+            selected_device_id: Some(device_id.to_string()),
+            selected_device_description: description.map(str::to_string),
+            ..AudioDeviceConfig::default()
+        }
+    }
+
+    /// Resolves the device selected by `device_config` among [`DEVICES`].
+    fn resolve(device_config: &mut AudioDeviceConfig) -> (Option<&'static str>, bool) {
+        resolve_device(
+            device_config,
+            |device_id| {
+                DEVICES
+                    .iter()
+                    .find(|(id, _)| *id == device_id)
+                    .map(|(id, _)| *id)
+            },
+            |description| {
+                let mut matching = DEVICES.iter().filter(|(_, other)| *other == description);
+                match (matching.next(), matching.next()) {
+                    (Some((id, _)), None) => Some((id.to_string(), *id)),
+                    _ => None,
+                }
+            },
+            || Some(DEVICES[0].0),
+        )
+    }
+
+    #[test]
+    fn stored_device_is_found_by_its_identifier() {
+        let mut config = device_config("hw:1", Some("USB Headset"));
+        assert_eq!(resolve(&mut config), (Some("hw:1"), false));
+        assert_eq!(config.selected_device_id.as_deref(), Some("hw:1"));
+    }
+
+    #[test]
+    fn moved_device_is_found_by_its_description() {
+        let mut config = device_config("hw:9", Some("Built-in Microphone"));
+        assert_eq!(resolve(&mut config), (Some("hw:0"), true));
+        assert_eq!(config.selected_device_id.as_deref(), Some("hw:0"));
+    }
+
+    #[test]
+    fn missing_device_falls_back_to_the_default() {
+        for mut config in [
+            device_config("hw:9", None),
+            device_config("hw:9", Some("Bluetooth Headset")),
+        ] {
+            assert_eq!(resolve(&mut config), (Some("hw:0"), false));
+            // the device is looked up again on the next start
+            assert_eq!(config.selected_device_id.as_deref(), Some("hw:9"));
+        }
+    }
+
+    #[test]
+    fn identically_described_devices_fall_back_to_the_default() {
+        let mut config = device_config("hw:9", Some("USB Headset"));
+        assert_eq!(resolve(&mut config), (Some("hw:0"), false));
+        assert_eq!(config.selected_device_id.as_deref(), Some("hw:9"));
+    }
+
+    #[test]
+    fn system_default_device_is_followed() {
+        let mut config = device_config(SYSTEM_DEFAULT_DEVICE_ID, Some("USB Headset"));
+        assert_eq!(resolve(&mut config), (Some("hw:0"), false));
+
+        let mut config = AudioDeviceConfig::default();
+        assert_eq!(resolve(&mut config), (None, false));
+    }
+
+    fn backend_error(description: &str) -> cpal::BackendSpecificError {
+        cpal::BackendSpecificError {
+            description: description.to_string(),
+        }
+    }
+
+    #[test]
+    fn backend_errors_are_classified_by_their_description() {
+        let cases = [
+            ("Device or resource busy", AudioCaptureError::DeviceBusy),
+            (
+                "The device is in use by another app",
+                AudioCaptureError::DeviceBusy,
+            ),
+            ("AUDCLNT_E_DEVICE_IN_USE", AudioCaptureError::DeviceBusy),
+            ("HRESULT 0x8889000A", AudioCaptureError::DeviceBusy),
+            ("Permission denied", AudioCaptureError::PermissionDenied),
+            (
+                "Access is denied. (0x80070005)",
+                AudioCaptureError::PermissionDenied,
+            ),
+            (
+                "Recording is not authorized",
+                AudioCaptureError::PermissionDenied,
+            ),
+            (
+                "Unknown error -22",
+                AudioCaptureError::Backend("Unknown error -22".to_string()),
+            ),
+        ];
+        for (description, expected) in cases {
+            assert_eq!(
+                classify_backend_error(&backend_error(description)),
+                expected,
+                "{description}"
+            );
+        }
+    }
+
+    #[test]
+    fn device_errors_are_classified_by_their_kind() {
+        let cases = [
+            (
+                DeviceError::BuildStream(cpal::BuildStreamError::DeviceNotAvailable),
+                AudioCaptureError::DeviceNotAvailable,
+            ),
+            (
+                DeviceError::BuildStreamConfig(cpal::DefaultStreamConfigError::DeviceNotAvailable),
+                AudioCaptureError::DeviceNotAvailable,
+            ),
+            (
+                DeviceError::SupportedConfigs(
+                    cpal::SupportedStreamConfigsError::DeviceNotAvailable,
+                ),
+                AudioCaptureError::DeviceNotAvailable,
+            ),
+            (
+                DeviceError::BuildStream(cpal::BuildStreamError::StreamConfigNotSupported),
+                AudioCaptureError::UnsupportedConfig,
+            ),
+            (
+                DeviceError::BuildStreamConfig(
+                    cpal::DefaultStreamConfigError::StreamTypeNotSupported,
+                ),
+                AudioCaptureError::UnsupportedConfig,
+            ),
+            (
+                DeviceError::BuildStream(cpal::BuildStreamError::BackendSpecific {
+                    err: backend_error("Device or resource busy"),
+                }),
+                AudioCaptureError::DeviceBusy,
+            ),
+            (
+                DeviceError::ReadDevices(cpal::DevicesError::BackendSpecific {
+                    err: backend_error("Permission denied"),
+                }),
+                AudioCaptureError::PermissionDenied,
+            ),
+        ];
+        for (err, expected) in cases {
+            assert_eq!(classify_device_error(&err), expected, "{err}");
+        }
+
+        // anything else is described by the error itself
+        let err = DeviceError::BuildStream(cpal::BuildStreamError::InvalidArgument);
+        assert_eq!(
+            classify_device_error(&err),
+            AudioCaptureError::Backend(err.to_string())
+        );
+    }
+}
@@ -1,3 +1,56 @@
+use std::{ops::RangeInclusive, path::PathBuf, sync::Arc};
+
+use subwin_audio::device::HostInputDevice;
+use subwin_bridge::{
+    config::{Config, ConfigIssue, ConfigIssueKind},
+    notification::NotificationType,
+    whisper_model::WhisperModel,
+};
+use subwin_speech::whisper::WhisperTranscriber;
+
+/// Smallest width and height of the captions window, in pixels, which still
+/// fits a line of caption text.
+const MIN_CAPTIONS_WINDOW_SIZE: f32 = 20.0;
+
+/// Largest width and height of the captions window, in pixels.
+const MAX_CAPTIONS_WINDOW_SIZE: f32 = 16_384.0;
+
+/// Range of the overridden window length, in milliseconds. Shorter windows
+/// hold too little speech to decode, and Whisper decodes 30 s at most.
+const CONTEXT_LENGTH_RANGE_MS: RangeInclusive<u32> = 1_000..=30_000;
+
+/// Range of the overridden interval between transcription runs, in
+/// milliseconds.
+const REPEAT_RUN_RANGE_MS: RangeInclusive<u32> = 100..=10_000;
+
+/// Range of the overridden buffer sizes, in frames.
+const BUFFER_SIZE_RANGE: RangeInclusive<u32> = 16..=16_384;
+
+/// Saves `config` to disk and reports the outcome to the frontend: a
+/// [`subwin_bridge::MessageFromBackend::ConfigSaved`] once it's written, or
+/// an error notification, so a disk error doesn't take the backend down.
+/// Returns whether the config has been saved.
+pub async fn persist_config(context: &super::AppContextHandle, config: &Config) -> bool {
+    match crate::config::save_config(config).await {
+        Ok(()) => {
+            context
+                .send(subwin_bridge::MessageFromBackend::ConfigSaved)
+                .await;
+            true
+        }
+        Err(err) => {
+            log::error!("Failed to save the config: {err}");
+            context
+                .send_notification(
+                    NotificationType::Error,
+                    format!("Не удалось сохранить настройки: {err}"),
+                )
+                .await;
+            false
+        }
+    }
+}
+
 /// Handles an incoming configuration request (see
 /// [`subwin_bridge::MessageToBackend::ConfigurationRequest`]).
 pub async fn handle_config_request(context: super::AppContextHandle) {
@@ -7,7 +60,279 @@ pub async fn handle_config_request(context: super::AppContextHandle) {
     };
     context
         .send(subwin_bridge::MessageFromBackend::ConfigurationResponse(
-            config,
+            Box::new(config),
         ))
         .await;
 }
+
+/// Handles an incoming configuration update request (see
+/// [`subwin_bridge::MessageToBackend::UpdateConfigRequest`]).
+pub async fn handle_update_config_request(context: super::AppContextHandle, mut config: Config) {
+    let messages = {
+        let mut state = context.state.write().await;
+        keep_backend_managed_fields(&mut config, &state.config);
+        let messages = crate::config::validate_config(&mut config);
+        state.config = config;
+        persist_config(&context, &state.config).await;
+        messages
+    };
+    for message in messages {
+        context
+            .send_notification(NotificationType::Warning, message)
+            .await;
+    }
+
+    // echo the authoritative config back to the frontend
+    handle_config_request(context).await;
+}
+
+/// Copies the fields changed by dedicated requests from the `current` config
+/// into the `updated` one. The frontend sends the whole config it has seen,
+/// which may predate e.g. a model download or a device switch finishing in
+/// the meantime, and would revert them otherwise.
+fn keep_backend_managed_fields(updated: &mut Config, current: &Config) {
+    updated.active_model_path = current.active_model_path.clone();
+    updated.recent_models = current.recent_models.clone();
+
+    let device_config = &mut updated.audio_device_config;
+    device_config.selected_host_id = current.audio_device_config.selected_host_id.clone();
+    device_config.selected_device_id = current.audio_device_config.selected_device_id.clone();
+    device_config.selected_device_description = current
+        .audio_device_config
+        .selected_device_description
+        .clone();
+}
+
+/// Handles a request to export the config into a file (see
+/// [`subwin_bridge::MessageToBackend::ExportConfigTo`]).
+pub async fn handle_export_config_request(context: super::AppContextHandle, path: PathBuf) {
+    let config = context.state.read().await.config.clone();
+    match crate::config::export_config(&config, &path).await {
+        Ok(()) => {
+            log::info!("Exported the config to {path:?}");
+            context
+                .send_notification(
+                    NotificationType::Success,
+                    format!("Настройки сохранены в {}", path.display()),
+                )
+                .await;
+        }
+        Err(err) => {
+            log::error!("Failed to export the config to {path:?}: {err}");
+            context
+                .send_notification(
+                    NotificationType::Error,
+                    format!("Не удалось экспортировать настройки: {err}"),
+                )
+                .await;
+        }
+    }
+}
+
+/// Handles a request to import the config from a file (see
+/// [`subwin_bridge::MessageToBackend::ImportConfigFrom`]). A file that
+/// can't be read or parsed leaves the current config untouched. The active
+/// model and the selected devices are kept, as the ones of the file may not
+/// exist on this machine.
+pub async fn handle_import_config_request(context: super::AppContextHandle, path: PathBuf) {
+    let (mut config, messages) = match crate::config::import_config(&path).await {
+        Ok(imported) => imported,
+        Err(err) => {
+            log::error!("Failed to import the config from {path:?}: {err}");
+            context
+                .send_notification(
+                    NotificationType::Error,
+                    format!("Не удалось импортировать настройки: {err}"),
+                )
+                .await;
+            return;
+        }
+    };
+
+    log::info!("Imported the config from {path:?}");
+    let is_saved = {
+        let mut state = context.state.write().await;
+        keep_backend_managed_fields(&mut config, &state.config);
+        state.config = config;
+        persist_config(&context, &state.config).await
+    };
+    handle_config_request(context.clone()).await;
+    if is_saved {
+        context
+            .send_notification(NotificationType::Success, "Настройки импортированы.")
+            .await;
+    }
+    for message in messages {
+        context
+            .send_notification(NotificationType::Warning, message)
+            .await;
+    }
+    handle_validate_config_request(context).await;
+}
+
+/// Checks `config` and the selected `active_device` for the problems a
+/// transcription start would fail on or run degraded with. The model is only
+/// checked to exist and have the expected size, as hashing it takes too long
+/// to do on every settings change. The device is queried on the blocking
+/// pool, as the audio backend may take a while to answer.
+pub async fn find_config_issues(
+    config: &Config,
+    active_device: Arc<Option<cpal::Device>>,
+) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let mut push_issue = |kind: ConfigIssueKind, message: String| {
+        issues.push(ConfigIssue { kind, message });
+    };
+
+    let active_model = config
+        .active_model_path
+        .as_deref()
+        .and_then(WhisperModel::from_path);
+    match config.active_model_path.as_deref() {
+        None => push_issue(
+            ConfigIssueKind::NoActiveModel,
+            "Сначала скачайте модель для распознания речи.".to_string(),
+        ),
+        Some(path) => match tokio::fs::metadata(path).await {
+            Err(_) => push_issue(
+                ConfigIssueKind::ModelMissing(path.to_path_buf()),
+                "Файл модели распознавания речи не найден. Скачайте модель заново.".to_string(),
+            ),
+            Ok(metadata) => {
+                if let Some(model) = &active_model {
+                    let expected = model.approx_size_bytes() as f64;
+                    let deviation = (metadata.len() as f64 - expected).abs() / expected;
+                    if deviation > super::diagnostics_service::MODEL_SIZE_TOLERANCE {
+                        push_issue(
+                            ConfigIssueKind::ModelSizeMismatch(path.to_path_buf()),
+                            format!(
+                                "Размер файла модели «{}» отличается от ожидаемого, файл может быть повреждён. Если распознавание не запустится, скачайте модель заново.",
+                                model.display_name(),
+                            ),
+                        );
+                    }
+                }
+            }
+        },
+    }
+
+    if let Some(model) = &active_model
+        && !model.supports_language(config.language.as_deref())
+    {
+        push_issue(
+            ConfigIssueKind::LanguageNotSupported(model.clone()),
+            format!(
+                "Модель «{}» распознаёт только английскую речь. Выберите многоязычную модель или английский язык.",
+                model.display_name(),
+            ),
+        );
+    }
+    if let Some(code) = config.language.as_deref()
+        && WhisperTranscriber::resolve_language(code).is_none()
+    {
+        push_issue(
+            ConfigIssueKind::UnknownLanguage(code.to_string()),
+            format!("Язык «{code}» не поддерживается, язык речи будет определён автоматически."),
+        );
+    }
+
+    let device_probe = tokio::task::spawn_blocking(move || {
+        let device = HostInputDevice::from((*active_device).clone()?);
+        let result = device.sample_rate_and_channels();
+        Some((device, result))
+    })
+    .await;
+    match device_probe {
+        Ok(None) => push_issue(
+            ConfigIssueKind::NoInputDevice,
+            "Выберите вводное устройство для захвата звука.".to_string(),
+        ),
+        Ok(Some((_, Ok(_)))) => {}
+        Ok(Some((device, Err(err)))) => {
+            log::warn!("Input device {device} is not usable: {err}");
+            push_issue(
+                ConfigIssueKind::InputDeviceUnavailable,
+                format!(
+                    "Устройство «{}» не отвечает. Переподключите его или выберите другое.",
+                    device.description,
+                ),
+            );
+        }
+        Err(err) => {
+            log::warn!("Querying the input device has failed: {err}");
+            push_issue(
+                ConfigIssueKind::InputDeviceUnavailable,
+                "Выбранное устройство не отвечает. Переподключите его или выберите другое."
+                    .to_string(),
+            );
+        }
+    }
+
+    let captions_config = &config.captions_config;
+    let window_size_range = MIN_CAPTIONS_WINDOW_SIZE..=MAX_CAPTIONS_WINDOW_SIZE;
+    if !window_size_range.contains(&captions_config.window_width)
+        || !window_size_range.contains(&captions_config.window_height)
+    {
+        push_issue(
+            ConfigIssueKind::CaptionsWindowSize,
+            format!(
+                "Размер окна субтитров {}×{} недопустим: ширина и высота должны быть от {MIN_CAPTIONS_WINDOW_SIZE} до {MAX_CAPTIONS_WINDOW_SIZE} пикселей.",
+                captions_config.window_width, captions_config.window_height,
+            ),
+        );
+    }
+
+    let params_overrides = &config.params_overrides;
+    if let Some(context_length_ms) = params_overrides.context_length_ms
+        && !CONTEXT_LENGTH_RANGE_MS.contains(&context_length_ms)
+    {
+        push_issue(
+            ConfigIssueKind::ParamsOverride,
+            format!(
+                "Длина окна распознавания {context_length_ms} мс недопустима: она должна быть от {} до {} мс.",
+                CONTEXT_LENGTH_RANGE_MS.start(),
+                CONTEXT_LENGTH_RANGE_MS.end(),
+            ),
+        );
+    }
+    if let Some(repeat_run_ms) = params_overrides.repeat_run_ms
+        && !REPEAT_RUN_RANGE_MS.contains(&repeat_run_ms)
+    {
+        push_issue(
+            ConfigIssueKind::ParamsOverride,
+            format!(
+                "Интервал распознавания {repeat_run_ms} мс недопустим: он должен быть от {} до {} мс.",
+                REPEAT_RUN_RANGE_MS.start(),
+                REPEAT_RUN_RANGE_MS.end(),
+            ),
+        );
+    }
+
+    for (device_id, &frames) in &config.audio_device_config.buffer_size_overrides {
+        if !BUFFER_SIZE_RANGE.contains(&frames) {
+            push_issue(
+                ConfigIssueKind::BufferSizeOverride(device_id.clone()),
+                format!(
+                    "Размер буфера {frames} для устройства «{device_id}» недопустим: он должен быть от {} до {} кадров.",
+                    BUFFER_SIZE_RANGE.start(),
+                    BUFFER_SIZE_RANGE.end(),
+                ),
+            );
+        }
+    }
+
+    issues
+}
+
+/// Handles an incoming config validation request (see
+/// [`subwin_bridge::MessageToBackend::ValidateConfig`]).
+pub async fn handle_validate_config_request(context: super::AppContextHandle) {
+    let (config, active_device) = {
+        let state = context.state.read().await;
+        (state.config.clone(), state.active_audio_device.clone())
+    };
+    let issues = find_config_issues(&config, active_device).await;
+    context
+        .send(subwin_bridge::MessageFromBackend::ConfigValidation(issues))
+        .await;
+}
@@ -0,0 +1,198 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use cpal::traits::{DeviceTrait, HostTrait};
+use subwin_bridge::{notification::NotificationType, whisper_model::WhisperModel};
+
+/// Name of the directory in the cache, where diagnostics are exported to.
+const DIAGNOSTICS_DIRECTORY: &str = "diagnostics";
+
+/// Relative deviation of a model file's size from the expected one, above
+/// which the file is reported as suspicious.
+pub(crate) const MODEL_SIZE_TOLERANCE: f64 = 0.1;
+
+/// Replaces the user's home directory in `text` with `~`, so exported
+/// diagnostics don't reveal the user name.
+fn redact_home(text: &str) -> String {
+    match directories::BaseDirs::new() {
+        Some(dirs) => {
+            let home = dirs.home_dir().display().to_string();
+            if home.is_empty() {
+                text.to_string()
+            } else {
+                text.replace(&home, "~")
+            }
+        }
+        None => text.to_string(),
+    }
+}
+
+/// Describes the audio hosts and the input devices of `host` along with the
+/// configurations they support.
+fn describe_audio_devices(host: &cpal::Host) -> String {
+    let mut report = String::new();
+    let available_hosts = cpal::available_hosts()
+        .iter()
+        .map(|host_id| host_id.name())
+        .collect::<Vec<_>>();
+    let _ = writeln!(report, "Available hosts: {}", available_hosts.join(", "));
+    let _ = writeln!(report, "Active host: {}", host.id().name());
+
+    let default_device = host
+        .default_input_device()
+        .and_then(|device| device.description().ok())
+        .map(|description| description.to_string());
+    let _ = writeln!(report, "Default input device: {default_device:?}");
+
+    let devices = match host.input_devices() {
+        Ok(devices) => devices,
+        Err(err) => {
+            let _ = writeln!(report, "Failed to list input devices: {err}");
+            return report;
+        }
+    };
+    for device in devices {
+        let _ = writeln!(report);
+        match device.description() {
+            Ok(description) => {
+                let _ = writeln!(report, "Device: {description}");
+            }
+            Err(err) => {
+                let _ = writeln!(report, "Device: <unknown: {err}>");
+            }
+        }
+        if let Ok(id) = device.id() {
+            let _ = writeln!(report, "  id: {id}");
+        }
+        match device.default_input_config() {
+            Ok(config) => {
+                let _ = writeln!(report, "  default config: {config:?}");
+            }
+            Err(err) => {
+                let _ = writeln!(report, "  default config: <{err}>");
+            }
+        }
+        match device.supported_input_configs() {
+            Ok(configs) => {
+                for config in configs {
+                    let _ = writeln!(report, "  supported: {config:?}");
+                }
+            }
+            Err(err) => {
+                let _ = writeln!(report, "  supported configs: <{err}>");
+            }
+        }
+    }
+    report
+}
+
+/// Describes the model at `model_path` and checks that its file looks
+/// complete.
+async fn describe_model(model_path: Option<&Path>) -> String {
+    let Some(model_path) = model_path else {
+        return "No active model.\n".to_string();
+    };
+
+    let mut report = String::new();
+    let _ = writeln!(report, "Active model path: {}", model_path.display());
+    let model = WhisperModel::from_path(model_path);
+    let _ = writeln!(report, "Known model: {model:?}");
+
+    match tokio::fs::metadata(model_path).await {
+        Ok(metadata) => {
+            let _ = writeln!(report, "File size: {} bytes", metadata.len());
+            if let Some(model) = model {
+                let expected = model.approx_size_bytes() as f64;
+                let deviation = (metadata.len() as f64 - expected).abs() / expected;
+                let verdict = if deviation <= MODEL_SIZE_TOLERANCE {
+                    "ok"
+                } else {
+                    "size differs from the expected one, the file may be damaged"
+                };
+                let _ = writeln!(
+                    report,
+                    "Expected size: ~{} bytes, verification: {verdict}",
+                    model.approx_size_bytes()
+                );
+            }
+        }
+        Err(err) => {
+            let _ = writeln!(report, "File is not accessible: {err}");
+        }
+    }
+    report
+}
+
+/// Writes the diagnostics bundle into a new directory in the cache. Returns
+/// the directory's path.
+async fn export_diagnostics(context: &super::AppContextHandle) -> std::io::Result<PathBuf> {
+    let (config, cache_path, host, is_running) = {
+        let state = context.state.read().await;
+        (
+            state.config.clone(),
+            state.cache_path.clone(),
+            state.active_host.clone(),
+            state.active_session.is_some(),
+        )
+    };
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let directory = cache_path
+        .join(DIAGNOSTICS_DIRECTORY)
+        .join(format!("subwin-diagnostics-{timestamp}"));
+    tokio::fs::create_dir_all(&directory).await?;
+
+    let model = describe_model(config.active_model_path.as_deref()).await;
+    let config = toml::to_string_pretty(&config).unwrap_or_else(|err| format!("<{err}>"));
+    let devices = tokio::task::spawn_blocking(move || describe_audio_devices(&host))
+        .await
+        .unwrap_or_else(|err| format!("Failed to describe audio devices: {err}\n"));
+    let stats = match context.last_pipeline_stats() {
+        Some(stats) => format!("Session running: {is_running}\n{stats:#?}\n"),
+        None => format!("Session running: {is_running}\nNo pipeline stats collected yet.\n"),
+    };
+    let mut log = crate::log_recorder::recent_log_lines().join("\n");
+    log.push('\n');
+
+    let files = [
+        ("config.toml", config),
+        ("audio_devices.txt", devices),
+        ("model.txt", model),
+        ("pipeline_stats.txt", stats),
+        ("log.txt", log),
+    ];
+    for (name, contents) in files {
+        tokio::fs::write(directory.join(name), redact_home(&contents)).await?;
+    }
+    Ok(directory)
+}
+
+/// Handles a diagnostics export request (see
+/// [`subwin_bridge::MessageToBackend::ExportDiagnostics`]).
+pub async fn handle_export_diagnostics_request(context: super::AppContextHandle) {
+    match export_diagnostics(&context).await {
+        Ok(directory) => {
+            log::info!("Exported diagnostics to {directory:?}");
+            context
+                .send(subwin_bridge::MessageFromBackend::DiagnosticsExported(
+                    directory,
+                ))
+                .await;
+        }
+        Err(err) => {
+            log::error!("Failed to export diagnostics: {err}");
+            context
+                .send_notification(
+                    NotificationType::Error,
+                    format!("Не удалось сохранить диагностику: {err}"),
+                )
+                .await;
+        }
+    }
+}
@@ -6,6 +6,7 @@
 
 pub mod audio_service;
 pub mod config_service;
+pub mod diagnostics_service;
 pub mod model_service;
 pub mod transcription_service;
 
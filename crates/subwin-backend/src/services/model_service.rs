@@ -1,50 +1,192 @@
-use std::str::FromStr;
+use std::{path::Path, str::FromStr};
 
 use futures_util::StreamExt;
 use reqwest::Url;
+use sha2::{Digest, Sha256};
 use subwin_bridge::whisper_model::WhisperModel;
-use tokio::io::AsyncWriteExt;
+use subwin_speech::{Transcriber, whisper::WhisperTranscriber};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 
-/// Base path for the HuggingFace that will trigger a download for a Whisper
-/// model.
-const BASE_DOWNLOAD_PATH: &str = "https://huggingface.co/ggerganov/whisper.cpp/resolve/main/";
+use crate::{
+    services::transcription_service::{
+        TARGET_RATE, language_for, sampling_strategy_for, temperature_options_for,
+    },
+    state::SessionPhase,
+};
+
+/// Address of HuggingFace, model files are downloaded from by default.
+const HUGGINGFACE_URL: &str = "https://huggingface.co";
+
+/// Extension of a model file that is still being downloaded (or whose
+/// download has been interrupted).
+const PARTIAL_DOWNLOAD_EXTENSION: &str = "bin.part";
+
+/// Minimum interval between two download progress updates, so a fast
+/// download doesn't flood the bridge with them.
+const PROGRESS_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Size of the chunks a downloaded model file is read in while verifying it.
+const VERIFICATION_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Free disk space required on top of a model's size before downloading it,
+/// as the known sizes are approximate, in bytes.
+const DOWNLOAD_SPACE_MARGIN: u64 = 64 * 1024 * 1024;
+
+/// Number of bytes in a mebibyte, the unit disk space is reported in.
+const MEBIBYTE: u64 = 1024 * 1024;
+
+/// Number of measured decodes of a model benchmark.
+const BENCHMARK_RUNS: u32 = 5;
+
+/// Fundamental frequency of the synthetic voice a benchmark decodes, in Hz.
+const BENCHMARK_PITCH_HZ: f32 = 140.0;
+
+/// Rate the synthetic voice pulses at, like syllables of speech, in Hz.
+const BENCHMARK_SYLLABLE_RATE_HZ: f32 = 2.0;
+
+/// Errors that can occur while benchmarking a model.
+#[derive(Debug, thiserror::Error)]
+enum BenchmarkError {
+    /// The model couldn't be loaded.
+    #[error("failed to load the model: {0}")]
+    Load(String),
+    /// A decode has failed.
+    #[error("failed to transcribe: {0}")]
+    Decode(String),
+    /// The transcriber has skipped the clip instead of decoding it.
+    #[error("the clip has not been decoded")]
+    NotDecoded,
+}
 
 /// Builds the download URL for the given Whisper model.
 ///
 /// This function maps a [`WhisperModel`] variant to its corresponding model
-/// file name and constructs the full download URL using the predefined base
-/// download path.
+/// file name and constructs the full download URL using `mirror_url` as the
+/// base, falling back to the model's repository on HuggingFace.
 ///
 /// # Returns
 /// - The model file name.
 /// - The full URL from which the model can be downloaded.
-fn build_download_url(model: &WhisperModel) -> (&str, Url) {
-    let model_file_name = match model {
-        WhisperModel::TinyQuantized8 => "ggml-tiny-q8_0.bin",
-        WhisperModel::TinyQuantized5 => "ggml-tiny-q5_1.bin",
-        WhisperModel::Tiny => "ggml-tiny.bin",
-        WhisperModel::SmallQuantized8 => "ggml-small-q8_0.bin",
-        WhisperModel::SmallQuantized5 => "ggml-small-q5_1.bin",
-        WhisperModel::Small => "ggml-small.bin",
-        WhisperModel::BaseQuantized8 => "ggml-base-q8_0.bin",
-        WhisperModel::BaseQuantized5 => "ggml-base-q5_1.bin",
-        WhisperModel::Base => "ggml-base.bin",
-        WhisperModel::MediumQuantized8 => "ggml-medium-q8_0.bin",
-        WhisperModel::MediumQuantized5 => "ggml-medium-q5_0.bin",
-        WhisperModel::Medium => "ggml-medium.bin",
-        WhisperModel::LargeTurboQuantized8 => "ggml-large-v3-turbo-q8_0.bin",
-        WhisperModel::LargeTurboQuantized5 => "ggml-large-v3-turbo-q5_0.bin",
-        WhisperModel::LargeTurbo => "ggml-large-v3-turbo.bin",
-        WhisperModel::LargeQuantized5 => "ggml-large-v3-q5_0.bin",
-        WhisperModel::Large => "ggml-large-v3.bin",
+///
+/// Fails if `mirror_url` isn't a valid URL, e.g. in a config edited by hand.
+fn build_download_url(
+    model: &WhisperModel,
+    mirror_url: Option<&str>,
+) -> Result<(&'static str, Url), url::ParseError> {
+    let model_file_name = model.file_name();
+
+    let base_url = match mirror_url {
+        // without a trailing slash, `join` would replace the last path segment
+        Some(mirror_url) if !mirror_url.ends_with('/') => Url::from_str(&format!("{mirror_url}/")),
+        Some(mirror_url) => Url::from_str(mirror_url),
+        None => Url::from_str(&format!(
+            "{HUGGINGFACE_URL}/{}/resolve/main/",
+            model.repository()
+        )),
+    }?;
+    let model_url = base_url.join(model_file_name)?;
+
+    Ok((model_file_name, model_url))
+}
+
+/// Checks that the cache directory at `cache_path` can hold `model`, creating
+/// it if needed. Returns the message to notify the user with, if it can't.
+/// Failing to query the free space doesn't prevent the download.
+async fn check_download_space(cache_path: &Path, model: &WhisperModel) -> Result<(), String> {
+    if let Err(err) = tokio::fs::create_dir_all(cache_path).await {
+        log::error!("Failed to create the cache directory {cache_path:?}: {err}");
+        return Err(format!(
+            "Не удалось создать папку для моделей {}: {err}",
+            cache_path.display()
+        ));
+    }
+
+    let directory = cache_path.to_path_buf();
+    let available_space =
+        match tokio::task::spawn_blocking(move || fs4::available_space(directory)).await {
+            Ok(available_space) => available_space,
+            Err(err) => {
+                log::warn!("Free disk space lookup in {cache_path:?} has panicked: {err}");
+                return Ok(());
+            }
+        };
+    let required_space = model.approx_size_bytes() + DOWNLOAD_SPACE_MARGIN;
+    match available_space {
+        Ok(available_space) if available_space < required_space => {
+            log::error!(
+                "Not enough disk space for model {model:?} in {cache_path:?}: {available_space} bytes available, {required_space} required"
+            );
+            Err(format!(
+                "Недостаточно места на диске для модели «{}»: нужно ~{} МиБ, свободно {} МиБ.",
+                model.display_name(),
+                required_space.div_ceil(MEBIBYTE),
+                available_space / MEBIBYTE,
+            ))
+        }
+        Ok(_) => Ok(()),
+        Err(err) => {
+            log::warn!("Failed to query the free disk space in {cache_path:?}: {err}");
+            Ok(())
+        }
+    }
+}
+
+/// Returns the SHA-256 digest of a downloaded file as advertised by the
+/// server, if any. HuggingFace reports it as the ETag of files stored in LFS.
+fn expected_sha256(headers: &reqwest::header::HeaderMap) -> Option<String> {
+    ["x-linked-etag", reqwest::header::ETAG.as_str()]
+        .into_iter()
+        .filter_map(|name| headers.get(name)?.to_str().ok())
+        .map(|etag| {
+            etag.trim_start_matches("W/")
+                .trim_matches('"')
+                .to_ascii_lowercase()
+        })
+        .find(|etag| etag.len() == 64 && etag.bytes().all(|byte| byte.is_ascii_hexdigit()))
+}
+
+/// Hashes the model file at `path`, reporting the progress to the frontend.
+/// Returns the file's SHA-256 digest as a lowercase hex string.
+async fn hash_model_file(
+    context: &super::AppContextHandle,
+    path: &Path,
+) -> std::io::Result<String> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let total_bytes = file.metadata().await?.len();
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; VERIFICATION_CHUNK_SIZE];
+    let mut processed_bytes = 0u64;
+    let mut last_progress_at: Option<tokio::time::Instant> = None;
+
+    let send_progress = async |processed_bytes: u64| {
+        context
+            .send(
+                subwin_bridge::MessageFromBackend::ModelVerificationProgress {
+                    processed_bytes,
+                    total_bytes,
+                },
+            )
+            .await;
     };
 
-    let model_url = Url::from_str(BASE_DOWNLOAD_PATH)
-        .expect("failed to build a base HuggingFace URL")
-        .join(model_file_name)
-        .expect("failed to append model's file name");
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+        processed_bytes += read as u64;
+
+        if last_progress_at.is_some_and(|sent_at| sent_at.elapsed() < PROGRESS_INTERVAL) {
+            continue;
+        }
+        last_progress_at = Some(tokio::time::Instant::now());
+        send_progress(processed_bytes).await;
+    }
+    // the final update is never throttled, so the frontend sees the end
+    send_progress(processed_bytes).await;
 
-    (model_file_name, model_url)
+    Ok(format!("{:x}", hasher.finalize()))
 }
 
 /// Handles an incoming model download request (see
@@ -53,56 +195,123 @@ pub async fn handle_download_model_request(
     context: super::AppContextHandle,
     model: subwin_bridge::whisper_model::WhisperModel,
 ) {
-    let (request_client, cache_path) = {
+    let (request_client, cache_path, mirror_url) = {
         let state = context.state.read().await;
-        (state.request_client.clone(), state.cache_path.clone())
+        (
+            state.request_client.clone(),
+            state.cache_path.clone(),
+            state.config.model_mirror_url.clone(),
+        )
     };
 
-    let (model_file_name, model_download_url) = build_download_url(&model);
+    let (model_file_name, model_download_url) =
+        match build_download_url(&model, mirror_url.as_deref()) {
+            Ok(download) => download,
+            Err(err) => {
+                log::error!(
+                    "Failed to build the download URL of {model:?} ({mirror_url:?}): {err}"
+                );
+                context
+                    .send_notification(
+                        subwin_bridge::notification::NotificationType::Error,
+                        format!("Не удалось скачать модель: некорректный адрес зеркала ({err})."),
+                    )
+                    .await;
+                return;
+            }
+        };
     let save_path = cache_path.join(model_file_name);
+    // the model is written under a temporary name and only renamed once it's
+    // complete, so an interrupted download is never mistaken for a model
+    let partial_path = save_path.with_extension(PARTIAL_DOWNLOAD_EXTENSION);
     log::info!("Downloading model {model:?} from {model_download_url}, saving to {save_path:?}");
 
-    // ensure the cache directory exists before creating the model file
-    if let Some(parent) = save_path.parent() {
-        tokio::fs::create_dir_all(parent)
-            .await
-            .expect("failed to create cache directory");
+    // a multi-gigabyte download shouldn't fail midway for a lack of space
+    if let Err(message) = check_download_space(&cache_path, &model).await {
+        context
+            .send_notification(
+                subwin_bridge::notification::NotificationType::Error,
+                message,
+            )
+            .await;
+        return;
     }
 
-    let mut output_file = tokio::fs::File::options()
+    let output_file = tokio::fs::File::options()
         .write(true)
         .create(true)
         .truncate(true)
-        .open(save_path.clone())
-        .await
-        .expect("failed to create model file");
+        .open(partial_path.clone())
+        .await;
+    let mut output_file = match output_file {
+        Ok(output_file) => output_file,
+        Err(err) => {
+            log::error!("Failed to create model file {partial_path:?}: {err}");
+            context
+                .send_notification(
+                    subwin_bridge::notification::NotificationType::Error,
+                    format!("Не удалось создать файл модели: {err}"),
+                )
+                .await;
+            return;
+        }
+    };
 
     let request = request_client
         .get(model_download_url)
         .build()
         .expect("failed to build model download request");
 
-    tokio::spawn(async move {
+    let tasks = context.clone();
+    tasks.spawn(async move {
         match request_client.execute(request).await {
+            Ok(response) if response.status() == reqwest::StatusCode::NOT_FOUND => {
+                log::error!(
+                    "Model file {model_file_name} was not found at {}",
+                    response.url()
+                );
+                drop(output_file);
+                let _ = tokio::fs::remove_file(&partial_path).await;
+                let source = match mirror_url {
+                    Some(_) => "Зеркало моделей",
+                    None => "Сервер моделей",
+                };
+                context
+                    .send_notification(
+                        subwin_bridge::notification::NotificationType::Error,
+                        format!("{source} не содержит файл {model_file_name}."),
+                    )
+                    .await;
+            }
+            // error pages must not be saved as the model
+            Ok(response) if !response.status().is_success() => {
+                let status = response.status();
+                log::error!(
+                    "Failed to download model file {model_file_name} from {}: {status}",
+                    response.url()
+                );
+                drop(output_file);
+                let _ = tokio::fs::remove_file(&partial_path).await;
+                context
+                    .send_notification(
+                        subwin_bridge::notification::NotificationType::Error,
+                        format!("Не удалось скачать модель, сервер ответил с ошибкой: {status}."),
+                    )
+                    .await;
+            }
             Ok(response) => {
                 let start = tokio::time::Instant::now();
                 let total_bytes = response.content_length().unwrap_or(0);
+                let expected_sha256 = expected_sha256(response.headers());
                 let mut downloaded_bytes = 0u64;
+                let mut last_progress_at: Option<tokio::time::Instant> = None;
 
-                let mut body = response.bytes_stream();
-                while let Some(chunk) = body.next().await {
-                    let current_chunk = chunk.expect("failed to get current file chunk");
-                    output_file
-                        .write_all(&current_chunk)
-                        .await
-                        .expect("failed to write current file chunk");
-                    downloaded_bytes += current_chunk.len() as u64;
-
+                // notify frontend about current state
+                let send_progress = async |downloaded_bytes: u64| {
                     let elapsed_secs = start.elapsed().as_secs_f64();
                     let speed = downloaded_bytes as f64 / elapsed_secs;
-                    let remaining_time = (total_bytes - downloaded_bytes) as f64 / speed;
-
-                    // notify frontend about current state
+                    let remaining_time =
+                        total_bytes.saturating_sub(downloaded_bytes) as f64 / speed;
                     context
                         .send(subwin_bridge::MessageFromBackend::DownloadProgressUpdate {
                             speed,
@@ -111,21 +320,132 @@ pub async fn handle_download_model_request(
                             remaining_time,
                         })
                         .await;
+                };
+
+                let mut body = response.bytes_stream();
+                while let Some(chunk) = body.next().await {
+                    let current_chunk = match chunk {
+                        Ok(current_chunk) => current_chunk,
+                        // e.g. the connection has dropped
+                        Err(err) => {
+                            log::error!("Failed to download model file {model_file_name}: {err}");
+                            drop(output_file);
+                            let _ = tokio::fs::remove_file(&partial_path).await;
+                            context
+                                .send_notification(
+                                    subwin_bridge::notification::NotificationType::Error,
+                                    format!(
+                                        "Не удалось скачать модель: {}",
+                                        err.without_url()
+                                    ),
+                                )
+                                .await;
+                            return;
+                        }
+                    };
+                    // e.g. the disk has been filled by something else meanwhile
+                    if let Err(err) = output_file.write_all(&current_chunk).await {
+                        log::error!("Failed to write model file {partial_path:?}: {err}");
+                        drop(output_file);
+                        let _ = tokio::fs::remove_file(&partial_path).await;
+                        context
+                            .send_notification(
+                                subwin_bridge::notification::NotificationType::Error,
+                                format!("Не удалось сохранить модель: {err}"),
+                            )
+                            .await;
+                        return;
+                    }
+                    downloaded_bytes += current_chunk.len() as u64;
+
+                    if last_progress_at.is_some_and(|sent_at| sent_at.elapsed() < PROGRESS_INTERVAL)
+                    {
+                        continue;
+                    }
+                    last_progress_at = Some(tokio::time::Instant::now());
+                    send_progress(downloaded_bytes).await;
+                }
+                // the final update is never throttled, so the download ends at 100%
+                send_progress(downloaded_bytes).await;
+
+                let synced = output_file.sync_all().await;
+                drop(output_file);
+                if let Err(err) = synced {
+                    log::error!("Failed to flush model file {partial_path:?}: {err}");
+                    let _ = tokio::fs::remove_file(&partial_path).await;
+                    context
+                        .send_notification(
+                            subwin_bridge::notification::NotificationType::Error,
+                            format!("Не удалось сохранить модель: {err}"),
+                        )
+                        .await;
+                    return;
+                }
+
+                // hashing a multi-gigabyte file takes a while, so it reports
+                // its own progress
+                match expected_sha256 {
+                    Some(expected_sha256) => {
+                        let verification = hash_model_file(&context, &partial_path).await;
+                        let error_message = match verification {
+                            Ok(sha256) if sha256 == expected_sha256 => {
+                                log::info!("Model file {model_file_name} passed verification");
+                                None
+                            }
+                            Ok(sha256) => {
+                                log::error!(
+                                    "Model file {model_file_name} is damaged: expected SHA-256 {expected_sha256}, got {sha256}"
+                                );
+                                Some("Скачанная модель повреждена, скачайте её заново.".to_string())
+                            }
+                            Err(err) => {
+                                log::error!("Failed to verify model file {model_file_name}: {err}");
+                                Some(format!("Не удалось проверить скачанную модель: {err}"))
+                            }
+                        };
+                        if let Some(error_message) = error_message {
+                            let _ = tokio::fs::remove_file(&partial_path).await;
+                            context
+                                .send_notification(
+                                    subwin_bridge::notification::NotificationType::Error,
+                                    error_message,
+                                )
+                                .await;
+                            return;
+                        }
+                    }
+                    None => log::warn!(
+                        "The server didn't report a checksum of {model_file_name}, skipping verification"
+                    ),
+                }
+
+                if let Err(err) = tokio::fs::rename(&partial_path, &save_path).await {
+                    log::error!("Failed to move model file {partial_path:?} to {save_path:?}: {err}");
+                    let _ = tokio::fs::remove_file(&partial_path).await;
+                    context
+                        .send_notification(
+                            subwin_bridge::notification::NotificationType::Error,
+                            format!("Не удалось сохранить модель: {err}"),
+                        )
+                        .await;
+                    return;
                 }
 
                 // update config with new path and persist the authoritative config
                 {
                     let mut state = context.state.write().await;
                     state.config.active_model_path = Some(save_path);
-                    crate::config::save_config(&state.config)
-                        .await
-                        .expect("failed to update active model path");
+                    state.config.remember_model(model);
+                    super::config_service::persist_config(&context, &state.config).await;
                 }
 
                 // notify frontend about config changes
-                super::config_service::handle_config_request(context).await;
+                super::config_service::handle_config_request(context.clone()).await;
+                handle_downloaded_models_request(context).await;
             }
             Err(e) => {
+                drop(output_file);
+                let _ = tokio::fs::remove_file(&partial_path).await;
                 context
                     .send_notification(
                         subwin_bridge::notification::NotificationType::Error,
@@ -136,3 +456,330 @@ pub async fn handle_download_model_request(
         }
     });
 }
+
+/// Handles a request for the downloaded models (see
+/// [`subwin_bridge::MessageToBackend::DownloadedModelsRequest`]).
+pub async fn handle_downloaded_models_request(context: super::AppContextHandle) {
+    let cache_path = context.state.read().await.cache_path.clone();
+    let lookup = tokio::task::spawn_blocking(move || {
+        WhisperModel::ALL
+            .into_iter()
+            .filter(|model| model.is_downloaded(&cache_path))
+            .collect::<Vec<_>>()
+    })
+    .await;
+    let downloaded_models = match lookup {
+        Ok(downloaded_models) => downloaded_models,
+        Err(err) => {
+            log::error!("Looking up the downloaded models has failed: {err}");
+            context
+                .send_notification(
+                    subwin_bridge::notification::NotificationType::Error,
+                    "Не удалось найти скачанные модели.",
+                )
+                .await;
+            return;
+        }
+    };
+
+    log::info!("Found downloaded models: {downloaded_models:?}");
+    context
+        .send(subwin_bridge::MessageFromBackend::DownloadedModelsResponse(
+            downloaded_models,
+        ))
+        .await;
+}
+
+/// Spawns a switch of the active model to the largest downloaded one that is
+/// smaller, because the active model can't keep up with real time (see
+/// [`subwin_bridge::config::Config::adaptive_model`]). The running session is
+/// restarted with the smaller model.
+pub fn spawn_model_fallback(context: &super::AppContextHandle) {
+    let tasks = context.clone();
+    let context = context.clone();
+    tasks.spawn(async move {
+        let (active_model, cache_path, language) = {
+            let state = context.state.read().await;
+            if state.active_session.is_none() {
+                return;
+            }
+            (
+                state
+                    .config
+                    .active_model_path
+                    .as_deref()
+                    .and_then(WhisperModel::from_path),
+                state.cache_path.clone(),
+                state.config.language.clone(),
+            )
+        };
+        let Some(active_model) = active_model else {
+            log::warn!("The active model is a custom one, there is nothing to fall back to");
+            return;
+        };
+
+        let candidates = active_model.smaller_models();
+        let lookup = tokio::task::spawn_blocking(move || {
+            candidates.into_iter().find(|model| {
+                model.is_downloaded(&cache_path) && model.supports_language(language.as_deref())
+            })
+        })
+        .await;
+        let fallback_model = match lookup {
+            Ok(fallback_model) => fallback_model,
+            Err(err) => {
+                log::error!("Looking up a smaller model has failed: {err}");
+                return;
+            }
+        };
+
+        let Some(fallback_model) = fallback_model else {
+            log::warn!("No smaller model than {active_model:?} has been downloaded");
+            context
+                .send_notification(
+                    subwin_bridge::notification::NotificationType::Warning,
+                    format!(
+                        "Модель «{}» не успевает за речью, а модели поменьше не скачаны. Скачайте модель поменьше, чтобы субтитры не отставали.",
+                        active_model.display_name(),
+                    ),
+                )
+                .await;
+            return;
+        };
+
+        log::info!("Falling back from model {active_model:?} to {fallback_model:?}");
+        context
+            .send_notification(
+                subwin_bridge::notification::NotificationType::Info,
+                format!(
+                    "Модель «{}» не успевает за речью, переключаемся на «{}».",
+                    active_model.display_name(),
+                    fallback_model.display_name(),
+                ),
+            )
+            .await;
+        handle_set_active_model_request(context, fallback_model).await;
+    });
+}
+
+/// Handles a request to switch the active model (see
+/// [`subwin_bridge::MessageToBackend::SetActiveModel`]). The model must have
+/// been downloaded before.
+pub async fn handle_set_active_model_request(
+    context: super::AppContextHandle,
+    model: subwin_bridge::whisper_model::WhisperModel,
+) {
+    let is_running = {
+        let mut state = context.state.write().await;
+        let model_path = state.cache_path.join(model.file_name());
+        if !tokio::fs::try_exists(&model_path).await.unwrap_or(false) {
+            log::error!("Model {model:?} is not downloaded, expected it at {model_path:?}");
+            drop(state);
+            context
+                .send_notification(
+                    subwin_bridge::notification::NotificationType::Error,
+                    format!("Модель {} не загружена.", model.file_name()),
+                )
+                .await;
+            return;
+        }
+
+        if !model.supports_language(state.config.language.as_deref()) {
+            log::error!(
+                "Model {model:?} is English-only, but the language is set to {:?}",
+                state.config.language
+            );
+            drop(state);
+            context
+                .send_notification(
+                    subwin_bridge::notification::NotificationType::Error,
+                    format!(
+                        "Модель «{}» распознаёт только английскую речь, а выбран другой язык.",
+                        model.display_name(),
+                    ),
+                )
+                .await;
+            return;
+        }
+
+        log::info!("Switching active model to {model:?} at {model_path:?}");
+        state.config.active_model_path = Some(model_path);
+        state.config.remember_model(model);
+        super::config_service::persist_config(&context, &state.config).await;
+        state.active_session.is_some()
+    };
+
+    // notify frontend about config changes
+    super::config_service::handle_config_request(context.clone()).await;
+
+    // starting a session replaces the running one, picking up the new model
+    if is_running {
+        super::transcription_service::handle_start_transcription_request(context).await;
+    }
+}
+
+/// Builds `duration_ms` of speech-like audio at `sample_rate`: a voice with a
+/// few harmonics, pulsing like syllables. The encoder takes as long for any
+/// audio, but silence isn't decoded at all.
+fn synthetic_speech(duration_ms: u32, sample_rate: u32) -> Vec<f32> {
+    let sample_count = subwin_speech::milliseconds_to_samples(duration_ms, sample_rate);
+    (0..sample_count)
+        .map(|index| {
+            let time = index as f32 / sample_rate as f32;
+            let voice: f32 = (1..=4)
+                .map(|harmonic| {
+                    let frequency = BENCHMARK_PITCH_HZ * harmonic as f32;
+                    (std::f32::consts::TAU * frequency * time).sin() / harmonic as f32
+                })
+                .sum();
+            let envelope = (std::f32::consts::PI * BENCHMARK_SYLLABLE_RATE_HZ * time)
+                .sin()
+                .abs();
+            0.2 * envelope * voice
+        })
+        .collect()
+}
+
+/// Loads the model at `model_path` like a session does, on the GPU or the
+/// CPU as configured, and decodes a synthetic clip as long as the context
+/// window [`BENCHMARK_RUNS`] times. A first, unmeasured decode warms the
+/// model up.
+fn run_model_benchmark(
+    config: &subwin_bridge::config::Config,
+    model_path: &Path,
+) -> Result<subwin_bridge::stats::ModelBenchmark, BenchmarkError> {
+    let params_profile = config.active_params_profile();
+    let mut transcriber = WhisperTranscriber::new(
+        TARGET_RATE,
+        &model_path.to_string_lossy(),
+        WhisperTranscriber::build_context_params(config.use_gpu),
+        params_profile.context_length_ms,
+        params_profile.repeat_run_ms,
+    )
+    .map_err(|err| BenchmarkError::Load(err.to_string()))?;
+    transcriber.set_suppressed_tokens(config.suppressed_tokens.clone());
+    let params = WhisperTranscriber::build_request_params(
+        sampling_strategy_for(params_profile.strategy),
+        temperature_options_for(&config.temperature),
+        config.suppress_non_speech,
+        config.single_segment,
+        config.max_tokens,
+        language_for(config, WhisperModel::from_path(model_path)),
+    );
+
+    let clip = synthetic_speech(params_profile.context_length_ms, TARGET_RATE);
+    let mut decodes_ms = Vec::with_capacity(BENCHMARK_RUNS as usize);
+    let mut real_time_factors = 0.0;
+    for run in 0..=BENCHMARK_RUNS {
+        // the window is replaced by the clip, so every decode is the same
+        transcriber.accept_samples(&clip);
+        let (_, metrics) = transcriber
+            .try_transcribe(params.clone())
+            .map_err(|err| BenchmarkError::Decode(err.to_string()))?;
+        let metrics = metrics.ok_or(BenchmarkError::NotDecoded)?;
+        log::debug!(
+            "Benchmark decode {run} has taken {} ms, RTF {:.2}",
+            metrics.inference_ms,
+            metrics.real_time_factor
+        );
+        if run > 0 {
+            decodes_ms.push(metrics.inference_ms as f64);
+            real_time_factors += metrics.real_time_factor;
+        }
+    }
+
+    Ok(subwin_bridge::stats::ModelBenchmark {
+        runs: BENCHMARK_RUNS,
+        window_ms: params_profile.context_length_ms,
+        average_decode_ms: decodes_ms.iter().sum::<f64>() / BENCHMARK_RUNS as f64,
+        max_decode_ms: decodes_ms.iter().copied().fold(0.0, f64::max),
+        real_time_factor: real_time_factors / BENCHMARK_RUNS as f64,
+    })
+}
+
+/// Handles a model benchmark request (see
+/// [`subwin_bridge::MessageToBackend::BenchmarkModel`]). The benchmark runs
+/// in the background, so other requests are served meanwhile.
+pub async fn handle_benchmark_model_request(context: super::AppContextHandle) {
+    // a session would slow the benchmark down, and be slowed down by it, so
+    // none is started until the benchmark ends
+    let benchmark = {
+        let mut state = context.state.write().await;
+        match state.config.active_model_path.clone() {
+            _ if state.is_benchmarking => Err("Скорость модели уже проверяется."),
+            _ if state.session_phase != SessionPhase::Idle => {
+                Err("Выключите субтитры, чтобы проверить скорость модели.")
+            }
+            Some(model_path) => {
+                state.is_benchmarking = true;
+                Ok((state.config.clone(), model_path))
+            }
+            None => Err("Модель не выбрана."),
+        }
+    };
+    let (config, model_path) = match benchmark {
+        Ok(benchmark) => benchmark,
+        Err(problem) => {
+            context
+                .send_notification(
+                    subwin_bridge::notification::NotificationType::Warning,
+                    problem,
+                )
+                .await;
+            context
+                .send(subwin_bridge::MessageFromBackend::ModelBenchmarkResult(
+                    None,
+                ))
+                .await;
+            return;
+        }
+    };
+
+    context
+        .send_notification(
+            subwin_bridge::notification::NotificationType::Info,
+            "Идёт проверка скорости модели...",
+        )
+        .await;
+
+    let tasks = context.clone();
+    tasks.spawn(async move {
+        log::info!("Benchmarking the model at {model_path:?}");
+        let benchmark =
+            tokio::task::spawn_blocking(move || run_model_benchmark(&config, &model_path));
+
+        // the frontend waits for a result either way
+        let result = match benchmark.await {
+            Ok(Ok(result)) => {
+                log::info!("Model benchmark result: {result:?}");
+                Some(result)
+            }
+            Ok(Err(err)) => {
+                log::error!("Model benchmark has failed: {err}");
+                context
+                    .send_notification(
+                        subwin_bridge::notification::NotificationType::Error,
+                        format!("Не удалось проверить скорость модели: {err}"),
+                    )
+                    .await;
+                None
+            }
+            Err(err) => {
+                log::error!("Model benchmark has panicked: {err}");
+                context
+                    .send_notification(
+                        subwin_bridge::notification::NotificationType::Error,
+                        "Не удалось проверить скорость модели.",
+                    )
+                    .await;
+                None
+            }
+        };
+        context.state.write().await.is_benchmarking = false;
+        context
+            .send(subwin_bridge::MessageFromBackend::ModelBenchmarkResult(
+                result,
+            ))
+            .await;
+    });
+}
@@ -1,24 +1,91 @@
-use std::path::PathBuf;
+use std::{
+    ops::Range,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 
-use cpal::traits::StreamTrait;
+use cpal::traits::{DeviceTrait, StreamTrait};
 use ringbuf_blocking::{
     BlockingHeapRb,
-    traits::{Consumer, Producer, Split},
+    traits::{Consumer, Observer, Producer, Split},
 };
 use subwin_audio::{
-    device::HostInputDevice,
-    resampler::{AudioResampler, StreamingResampler},
+    device::{DeviceError, HostInputDevice},
+    gain::GainStage,
+    mixer::DownmixMode,
+    resampler::{AudioResampler, ResamplerConstructionError, StreamingResampler},
+};
+use subwin_bridge::{
+    audio::{AudioCaptureError, InputDevice, SYSTEM_DEFAULT_DEVICE_ID},
+    config::{
+        self, AudioDeviceConfig, Config, ConfigIssue, ConfigIssueKind, DecodeWindowAnchor,
+        ResamplingQuality, SampleFormatPreference, StabilizerConfig, TemperatureConfig,
+        TextNormalizationConfig,
+    },
+    notification::NotificationType,
+    stats::{InputLevel, LatencyEstimate, PipelineStats},
+    whisper_model::{DecodingStrategy, ParamsProfile, WhisperModel},
 };
-use subwin_bridge::notification::NotificationType;
 use subwin_speech::{
-    CaptionSegment, Transcriber, stabilizer::CaptionsStabilizer, whisper::WhisperTranscriber,
+    CaptionSegment, Transcriber, milliseconds_to_samples, normalize,
+    replacements::WordReplacer,
+    stabilizer::CaptionsStabilizer,
+    whisper::{SamplingStrategy, TemperatureOptions, WhisperTranscriber, WindowAnchor},
+};
+use thread_priority::ThreadPriority;
+
+use crate::{
+    monitor::{self, MonitorFeed},
+    realtime::{self, RealtimeError, RealtimeErrorReporter},
+    sinks::{self, CaptionSink, CaptionUpdate},
+    state::{SessionPhase, StreamOpener, TranscriptionSession, WorkerHandle},
+    stats::{PipelineCounters, STATS_INTERVAL_MILLISECONDS},
 };
 
 /// Target sample rate for the transcription pipeline.
-const TARGET_RATE: u32 = 16_000;
+pub(crate) const TARGET_RATE: u32 = 16_000;
+
+/// Number of failed inferences in a row, after which the transcriber is
+/// considered broken and a recovery is attempted.
+const MAX_CONSECUTIVE_DECODE_FAILURES: u32 = 5;
+
+/// Weight of the latest inference in the smoothed decode latency used to
+/// adapt the stabilizer's tail.
+const LATENCY_SMOOTHING: f64 = 0.2;
+
+/// Duration the decoding has to stay slower than real time for, before the
+/// model is switched to a smaller one (see
+/// [`subwin_bridge::config::Config::adaptive_model`]). Long enough to not
+/// react to the slow first inferences or a short load spike.
+const SUSTAINED_LAG: Duration = Duration::from_secs(15);
+
+/// Real-time factor guessed per billion parameters of a model, when there is
+/// no measured one. A rough figure for decoding on a CPU; GPUs are faster.
+const GUESSED_REAL_TIME_FACTOR_PER_BILLION_PARAMETERS: f64 = 1.0;
 
-/// History window length for the captions stabilizer, in milliseconds.
-const STABILIZER_WINDOW_MILLISECONDS: i64 = 1500;
+/// Real-time factor guessed for custom models of an unknown size.
+const GUESSED_CUSTOM_MODEL_REAL_TIME_FACTOR: f64 = 0.5;
+
+/// Number of channels transcribed separately when channels are split (see
+/// [`subwin_bridge::config::Config::split_channels`]).
+const SPLIT_CHANNELS: usize = 2;
+
+/// Audio the ring buffers hold on top of a single callback's output, so the
+/// worker may fall this far behind, e.g. during a slow decode, before
+/// captured audio is dropped. In milliseconds.
+const RING_BUFFER_HEADROOM_MS: u32 = 3_000;
+
+/// Confidence below which a segment is marked as uncertain in the captions
+/// (see [`subwin_bridge::config::CaptionsConfig::mark_uncertain_text`]).
+const UNCERTAIN_SEGMENT_CONFIDENCE: f32 = 0.5;
+
+/// Identifier of the next transcription session. Kept across backend
+/// restarts, so the frontend never mistakes a new session for a finished one.
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
 
 /// Aggregates inputs required to build a transcription session.
 struct TranscriptionInputs {
@@ -26,6 +93,133 @@ struct TranscriptionInputs {
     active_model_path: PathBuf,
     /// Selected audio device converted to a host-aware wrapper.
     active_device: HostInputDevice,
+    /// Transcription parameters for the active model.
+    params_profile: ParamsProfile,
+    /// Gain applied to the captured audio before transcription.
+    gain: GainStage,
+    /// Silence duration that ends an utterance, in milliseconds.
+    silence_finalize_ms: u32,
+    /// Silence duration that clears the captions, in milliseconds.
+    clear_captions_after_silence_ms: Option<u32>,
+    /// Minimum duration the latest caption is shown before it's cleared.
+    min_caption_display: Duration,
+    /// Number of finalized segments kept for display.
+    max_history_segments: usize,
+    /// Number of characters the displayed captions are truncated to, if
+    /// they're limited.
+    max_caption_chars: Option<usize>,
+    /// Sampling temperature and fallback settings.
+    temperature: TemperatureConfig,
+    /// Whether to suppress non-speech tokens while decoding.
+    suppress_non_speech: bool,
+    /// Token strings removed from the recognized text.
+    suppressed_tokens: Vec<String>,
+    /// Whether the decoder produces a single segment per run.
+    single_segment: bool,
+    /// Maximum number of tokens per segment, `0` is unlimited.
+    max_tokens: u32,
+    /// Whether the finalized captions are the prompt of the next decode.
+    carry_prompt: bool,
+    /// Settings of the stabilizer's revisable tail.
+    stabilizer: StabilizerConfig,
+    /// User dictionary applied to the recognized text.
+    replacer: WordReplacer,
+    /// Cleanup of the recognized text.
+    text_normalization: TextNormalizationConfig,
+    /// Language of the speech; `None` detects it automatically.
+    language: Option<&'static str>,
+    /// Minimum length of audio a decode runs on, in milliseconds.
+    min_transcription_ms: u32,
+    /// Time limit of a single decode, if any.
+    decode_timeout: Option<Duration>,
+    /// Anchoring of the decode window.
+    window_anchor: WindowAnchor,
+    /// Whether the first channels are transcribed separately.
+    split_channels: bool,
+    /// Whether to fall back to a smaller model when decoding lags behind.
+    adaptive_model: bool,
+    /// Silence after which the input device is released, in minutes.
+    idle_release_minutes: Option<u32>,
+    /// Whether the worker runs on a dedicated thread.
+    dedicated_worker_thread: bool,
+    /// Whether the model is loaded on the GPU.
+    use_gpu: bool,
+    /// Audio transcribed after the push-to-talk key is released, in
+    /// milliseconds; `None` transcribes continuously.
+    push_to_talk_release_tail_ms: Option<u32>,
+}
+
+/// Settings and outputs the transcription worker takes ownership of.
+struct WorkerSetup {
+    /// Identifier of the session, sent once the model is loaded.
+    session_id: u64,
+    /// Number of samples read from the ring buffer per iteration.
+    target_buffer_size: u32,
+    /// Path to the Whisper model to load.
+    active_model_path: PathBuf,
+    /// Transcription parameters for the model.
+    params_profile: ParamsProfile,
+    /// Gain applied to every buffer before transcription.
+    gain: GainStage,
+    /// Silence duration that ends an utterance, in milliseconds; `0` disables
+    /// utterance detection.
+    silence_finalize_ms: u32,
+    /// Silence duration that clears the captions, in milliseconds; `None`
+    /// keeps them shown.
+    clear_captions_after_silence_ms: Option<u32>,
+    /// Minimum duration the latest caption is shown before it's cleared.
+    min_caption_display: Duration,
+    /// Number of finalized segments kept for display.
+    max_history_segments: usize,
+    /// Number of characters the displayed captions are truncated to, if
+    /// they're limited.
+    max_caption_chars: Option<usize>,
+    /// Sampling temperature and fallback settings.
+    temperature: TemperatureConfig,
+    /// Whether to suppress non-speech tokens while decoding.
+    suppress_non_speech: bool,
+    /// Token strings removed from the recognized text.
+    suppressed_tokens: Vec<String>,
+    /// Whether the decoder produces a single segment per run.
+    single_segment: bool,
+    /// Maximum number of tokens per segment, `0` is unlimited.
+    max_tokens: u32,
+    /// Whether the finalized captions are the prompt of the next decode.
+    carry_prompt: bool,
+    /// Settings of the stabilizer's revisable tail.
+    stabilizer: StabilizerConfig,
+    /// User dictionary applied to the recognized text.
+    replacer: WordReplacer,
+    /// Cleanup of the recognized text.
+    text_normalization: TextNormalizationConfig,
+    /// Language of the speech; `None` detects it automatically.
+    language: Option<&'static str>,
+    /// Minimum length of audio a decode runs on, in milliseconds.
+    min_transcription_ms: u32,
+    /// Time limit of a single decode, if any.
+    decode_timeout: Option<Duration>,
+    /// Anchoring of the decode window.
+    window_anchor: WindowAnchor,
+    /// Whether to fall back to a smaller model when decoding lags behind.
+    adaptive_model: bool,
+    /// Silence after which the input device is released, in minutes.
+    idle_release_minutes: Option<u32>,
+    /// Whether the worker runs on a dedicated thread.
+    dedicated_worker_thread: bool,
+    /// Whether the model is loaded on the GPU.
+    use_gpu: bool,
+    /// Flag set while the push-to-talk key is held; `None` transcribes
+    /// continuously.
+    push_to_talk: Option<Arc<AtomicBool>>,
+    /// Audio transcribed after the push-to-talk key is released, in
+    /// milliseconds.
+    push_to_talk_release_tail_ms: u32,
+    /// Sample format the audio is captured in, reported in the stats.
+    sample_format: cpal::SampleFormat,
+    /// Outputs caption changes are emitted to.
+    sinks: Vec<Box<dyn CaptionSink>>,
+    /// Counters of the session, reported along the worker's own stats.
+    counters: Arc<PipelineCounters>,
 }
 
 /// Represents derived settings for the active audio device.
@@ -36,6 +230,10 @@ struct AudioDeviceSettings {
     channels: u16,
     /// The target buffer size for capture, expressed in frames.
     target_buffer_size: u32,
+    /// Sample format the audio is captured in.
+    sample_format: cpal::SampleFormat,
+    /// How the channels are mixed down to mono.
+    downmix: DownmixMode,
 }
 
 /// Holds mutable state for the audio callback (resampling and mixing).
@@ -44,39 +242,82 @@ struct ResampleCallbackState {
     channels: u16,
     /// Target chunk size (in mono samples) before forwarding to the transcoder.
     target_buffer_size: u32,
-    /// Streaming resampler instance handling rate conversion.
-    resampler: StreamingResampler<f32>,
+    /// Whether the audio is captured at the target rate already, and passed
+    /// through without resampling.
+    is_passthrough: bool,
+    /// Whether the first channels are resampled separately instead of being
+    /// mixed down (see [`subwin_bridge::config::Config::split_channels`]).
+    split_channels: bool,
+    /// How the channels are mixed down, unless they're split.
+    downmix: DownmixMode,
+    /// Streaming resampler instances handling rate conversion, one per
+    /// output: a single one for the downmix, or one per split channel.
+    resamplers: Vec<StreamingResampler<f32>>,
     /// Accumulator for a downmixed mono f32 samples across callbacks.
     samples_accumulator: Vec<f32>,
+    /// Number of buffers of an unexpected size received in a row.
+    unexpected_buffers: u32,
+    /// Channel to report problems through, as logging is not realtime-safe.
+    errors: RealtimeErrorReporter,
+    /// Counters of the session's pipeline stats.
+    counters: Arc<PipelineCounters>,
 }
 
 impl ResampleCallbackState {
     /// Create a new resampling callback state with pre-allocated buffers.
+    /// Fails if the device's rate can't be resampled to `target_rate`.
     fn new(
-        sample_rate: cpal::SampleRate,
+        device_settings: &AudioDeviceSettings,
         target_rate: u32,
-        target_buffer_size: u32,
-        channels: u16,
-    ) -> Self {
-        Self {
-            channels,
-            target_buffer_size,
-            resampler: StreamingResampler::<f32>::new(sample_rate, target_rate, target_buffer_size)
-                .expect("failed to create a resampler"),
-            samples_accumulator: Vec::with_capacity(target_buffer_size as usize),
-        }
+        resampler_quality: ResamplingQuality,
+        split_channels: bool,
+        errors: RealtimeErrorReporter,
+        counters: Arc<PipelineCounters>,
+    ) -> Result<Self, ResamplerConstructionError> {
+        let outputs = if split_channels { SPLIT_CHANNELS } else { 1 };
+        let resamplers = (0..outputs)
+            .map(|_| {
+                StreamingResampler::<f32>::new(
+                    device_settings.sample_rate,
+                    target_rate,
+                    device_settings.target_buffer_size,
+                    resampler_quality,
+                )
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self {
+            channels: device_settings.channels,
+            target_buffer_size: device_settings.target_buffer_size,
+            is_passthrough: device_settings.sample_rate == target_rate,
+            split_channels,
+            downmix: device_settings.downmix,
+            resamplers,
+            samples_accumulator: Vec::with_capacity(device_settings.target_buffer_size as usize),
+            unexpected_buffers: 0,
+            errors,
+            counters,
+        })
     }
 
-    /// Convert interleaved input to mono and resample it into the ring buffer.
-    fn process_input<P: Producer<Item = f32>>(&mut self, data: &[f32], producer: &mut P) {
+    /// Convert interleaved input to mono and resample it into the ring
+    /// buffer, or resample every split channel into its own ring buffer in
+    /// `producers`.
+    fn process_input<P: Producer<Item = f32>>(&mut self, data: &[f32], producers: &mut [P]) {
         let expected_samples = self.target_buffer_size as usize * self.channels as usize;
         if data.len() != expected_samples {
-            log::error!(
-                "Received an unexpected buffer from CPAL with the size of {} samples. Should be switching to a StreamingResampler?",
-                data.len(),
-            );
+            self.unexpected_buffers = self.unexpected_buffers.saturating_add(1);
+            self.errors.report(RealtimeError::UnexpectedBufferSize {
+                received: data.len(),
+                expected: expected_samples,
+                in_a_row: self.unexpected_buffers,
+            });
+            // the skipped buffer leaves a gap, don't resample across it
+            for resampler in self.resamplers.iter_mut() {
+                resampler.reset();
+            }
             return;
         }
+        self.unexpected_buffers = 0;
 
         let received_frames = data.len() / self.channels as usize;
         if received_frames > self.samples_accumulator.len() {
@@ -88,28 +329,164 @@ impl ResampleCallbackState {
         }
 
         self.samples_accumulator.resize(received_frames, 0.0);
-        subwin_audio::mixer::mix_stereo_to_mono(
-            &mut self.samples_accumulator[..received_frames],
-            data,
-        );
+        for (channel, (resampler, producer)) in self
+            .resamplers
+            .iter_mut()
+            .zip(producers.iter_mut())
+            .enumerate()
+        {
+            if self.split_channels {
+                subwin_audio::mixer::extract_channel(
+                    &mut self.samples_accumulator[..received_frames],
+                    data,
+                    self.channels as usize,
+                    channel,
+                );
+            } else {
+                subwin_audio::mixer::mix_stereo_to_mono(
+                    &mut self.samples_accumulator[..received_frames],
+                    data,
+                    self.downmix,
+                );
+            }
 
-        // push the resampled data and notify the worker
-        let mut resampled_callback = |written_data: &[f32]| {
-            producer.push_slice(written_data);
-        };
+            // push the resampled data and notify the worker
+            let errors = &self.errors;
+            let counters = &self.counters;
+            let mut resampled_callback = |written_data: &[f32]| {
+                let pushed = producer.push_slice(written_data);
+                if pushed < written_data.len() {
+                    counters.add_dropped(written_data.len() - pushed);
+                    errors.report(RealtimeError::Overflow {
+                        dropped: written_data.len() - pushed,
+                    });
+                }
+            };
+
+            if self.is_passthrough {
+                resampled_callback(&self.samples_accumulator[..received_frames]);
+            } else if let Err(err) = resampler.process_callback(
+                &self.samples_accumulator[..received_frames],
+                &mut resampled_callback,
+            ) {
+                self.errors.report(err);
+            }
+        }
+        self.counters.set_resampler_queue(
+            self.resamplers
+                .iter()
+                .map(|resampler| resampler.queued_samples())
+                .sum(),
+        );
+    }
+}
 
-        if let Err(err) = self.resampler.process_callback(
-            &self.samples_accumulator[..received_frames],
-            &mut resampled_callback,
+/// Returns the capacity of a ring buffer fed by a stream capturing
+/// `target_buffer_size` frames per callback at `sample_rate`: the most a
+/// single callback can push, plus [`RING_BUFFER_HEADROOM_MS`] of audio.
+fn ring_buffer_capacity(
+    sample_rate: u32,
+    target_buffer_size: u32,
+    resampler_quality: ResamplingQuality,
+) -> usize {
+    let callback_frames = target_buffer_size as usize;
+    let max_callback_output = if sample_rate == TARGET_RATE {
+        callback_frames
+    } else {
+        match StreamingResampler::<f32>::new(
+            sample_rate,
+            TARGET_RATE,
+            target_buffer_size,
+            resampler_quality,
         ) {
-            log::error!(
-                "Resampler caught an error: {err:?}, received_frames={received_frames}, target_buffer_size={target_buffer_size}",
-                target_buffer_size = self.target_buffer_size,
-            );
+            Ok(mut resampler) => {
+                // the filter's delay is only skipped once, at the start
+                resampler.set_skip_output_delay(false);
+                // up to a block short of input stays queued between callbacks
+                resampler.output_frames_for(resampler.input_block_size() - 1 + callback_frames)
+            }
+            // the stream can't be opened with this resampler either
+            Err(_) => callback_frames,
+        }
+    };
+    max_callback_output + milliseconds_to_samples(RING_BUFFER_HEADROOM_MS, TARGET_RATE)
+}
+
+/// Derives the stabilizer's tail from the smoothed decode latency. Text is
+/// revised on the next run at the earliest, which starts once the current
+/// one is done, so the tail is lengthened by two inferences' worth.
+fn adaptive_tail_ms(config: &StabilizerConfig, smoothed_inference_ms: f64) -> i64 {
+    let tail_ms = config.tail_ms as f64 + 2.0 * smoothed_inference_ms;
+    let min_tail_ms = config.min_tail_ms.min(config.max_tail_ms) as f64;
+    tail_ms.clamp(min_tail_ms, config.max_tail_ms as f64) as i64
+}
+
+/// Per-channel state of the transcription worker. Mixed audio is transcribed
+/// by a single pipeline, while split channels get a pipeline each.
+struct ChannelPipeline<C> {
+    /// Speaker the captions are tagged with; `None` for mixed audio.
+    speaker: Option<u16>,
+    /// Ring buffer the channel's resampled audio is read from.
+    consumer: C,
+    /// Gain applied to every buffer before transcription.
+    gain: GainStage,
+    transcriber: WhisperTranscriber,
+    stabilizer: CaptionsStabilizer,
+    /// Decode latency smoothed over the recent inferences, in milliseconds.
+    smoothed_inference_ms: Option<f64>,
+    total_samples_seen: i64,
+    /// Finalized segments kept for display.
+    history_segments: Vec<CaptionSegment>,
+    /// Segments that may still change, as displayed.
+    active_segments: Vec<CaptionSegment>,
+    /// Segments that may still change, as recognized, before the word
+    /// replacements and normalization, to finalize them from.
+    raw_active_segments: Vec<CaptionSegment>,
+    /// Recovery of the transcriber from failing decodes.
+    recovery: DecodeRecovery,
+    /// Number of silent samples in a row.
+    silent_samples: usize,
+}
+
+impl<C> ChannelPipeline<C> {
+    /// Ends the current utterance: its segments, which may still change, are
+    /// finalized at once, and the decode window is cleared, so the next
+    /// utterance doesn't repeat it. Returns the finalized segments, as
+    /// recognized.
+    fn finalize(&mut self) -> Vec<CaptionSegment> {
+        self.active_segments.clear();
+        let utterance = std::mem::take(&mut self.raw_active_segments);
+        self.transcriber.clear_window();
+        if utterance.is_empty() {
+            return Vec::new();
+        }
+        self.stabilizer.flush(utterance).history
+    }
+
+    /// Passes the finalized segments kept for display to the transcriber, as
+    /// the prompt of its next decodes (see
+    /// [`subwin_bridge::config::Config::carry_prompt`]).
+    fn carry_prompt(&mut self) {
+        let text = segments_to_text(&self.history_segments);
+        if let Err(err) = self.transcriber.set_prompt(&text) {
+            log::warn!("Failed to set the decode prompt: {err}");
         }
     }
 }
 
+/// Applies the user dictionary to the text of `segments`.
+fn replace_words<'a>(
+    replacer: &WordReplacer,
+    segments: impl Iterator<Item = &'a mut CaptionSegment>,
+) {
+    if replacer.is_empty() {
+        return;
+    }
+    for segment in segments {
+        segment.text = replacer.apply(&segment.text);
+    }
+}
+
 /// Join a list of caption segments into a single string with spaces.
 fn segments_to_text(segments: &[CaptionSegment]) -> String {
     let mut parts = Vec::with_capacity(segments.len());
@@ -122,220 +499,2156 @@ fn segments_to_text(segments: &[CaptionSegment]) -> String {
     parts.join(" ")
 }
 
+/// Display text of the captions, with the segments the model is unsure
+/// about marked in it.
+#[derive(Debug, Default)]
+struct CaptionText {
+    text: String,
+    /// Byte ranges of `text` recognized with a confidence below
+    /// [`UNCERTAIN_SEGMENT_CONFIDENCE`], in order.
+    uncertain_ranges: Vec<Range<usize>>,
+}
+
+impl CaptionText {
+    /// Appends the trimmed text of `segments`, separated by single spaces.
+    fn push_segments(&mut self, segments: &[CaptionSegment]) {
+        for segment in segments {
+            let text = segment.text.trim();
+            if text.is_empty() {
+                continue;
+            }
+            if !self.text.is_empty() {
+                self.text.push(' ');
+            }
+            let start = self.text.len();
+            self.text.push_str(text);
+            if segment
+                .confidence
+                .is_some_and(|confidence| confidence < UNCERTAIN_SEGMENT_CONFIDENCE)
+            {
+                self.uncertain_ranges.push(start..self.text.len());
+            }
+        }
+    }
+
+    /// Appends `other`, moving its uncertain ranges along.
+    fn append(&mut self, other: CaptionText) {
+        let offset = self.text.len();
+        self.text.push_str(&other.text);
+        self.uncertain_ranges.extend(
+            other
+                .uncertain_ranges
+                .into_iter()
+                .map(|range| range.start + offset..range.end + offset),
+        );
+    }
+}
+
 /// Merge history and active caption segments into the latest display string.
-fn compose_caption_text(history: &[CaptionSegment], active: &[CaptionSegment]) -> String {
-    let history_text = segments_to_text(history);
-    let active_text = segments_to_text(active);
-
-    if history_text.is_empty() {
-        active_text
-    } else if active_text.is_empty() {
-        history_text
-    } else {
-        format!("{history_text} {active_text}")
+fn compose_caption_text(history: &[CaptionSegment], active: &[CaptionSegment]) -> CaptionText {
+    let mut caption = CaptionText::default();
+    caption.push_segments(history);
+    caption.push_segments(active);
+    caption
+}
+
+/// Keeps the last words of `caption` that fit into `max_chars` characters,
+/// including the leading ellipsis marking the truncation.
+fn truncate_caption_start(caption: CaptionText, max_chars: usize) -> CaptionText {
+    let text = &caption.text;
+    let char_count = text.chars().count();
+    if char_count <= max_chars {
+        return caption;
+    }
+
+    let kept_chars = max_chars.saturating_sub(1);
+    let (kept_from, _) = text
+        .char_indices()
+        .nth(char_count - kept_chars)
+        .unwrap_or((text.len(), ' '));
+    let kept = &text[kept_from..];
+    // the first kept word is dropped if it's been cut, unless it's the only one
+    let kept = match kept.split_once(char::is_whitespace) {
+        Some((_, rest)) if !text[..kept_from].ends_with(char::is_whitespace) => rest,
+        _ => kept,
+    };
+    let kept = kept.trim_start();
+
+    // the kept text is the end of the caption
+    let kept_from = text.len() - kept.len();
+    let mut truncated = CaptionText {
+        text: "…".to_string(),
+        uncertain_ranges: Vec::new(),
+    };
+    truncated.append(CaptionText {
+        text: kept.to_string(),
+        uncertain_ranges: caption
+            .uncertain_ranges
+            .iter()
+            .filter(|range| range.end > kept_from)
+            .map(|range| range.start.max(kept_from) - kept_from..range.end - kept_from)
+            .collect(),
+    });
+    truncated
+}
+
+/// Merge the captions of every pipeline into the latest display string. Split
+/// channels get a line each, labeled with their speaker. Each line's text is
+/// truncated to `max_caption_chars`, keeping its latest words; transcripts
+/// are written from the segments, so they keep the full text.
+fn compose_pipelines_text<C>(
+    pipelines: &[ChannelPipeline<C>],
+    max_caption_chars: Option<usize>,
+) -> CaptionText {
+    let mut caption = CaptionText::default();
+    for pipeline in pipelines {
+        let mut line = compose_caption_text(&pipeline.history_segments, &pipeline.active_segments);
+        if line.text.is_empty() {
+            continue;
+        }
+        if let Some(max_chars) = max_caption_chars {
+            line = truncate_caption_start(line, max_chars);
+        }
+        if !caption.text.is_empty() {
+            caption.text.push('\n');
+        }
+        if let Some(speaker) = pipeline.speaker {
+            caption.text.push_str(&sinks::speaker_label(speaker));
+            caption.text.push_str(": ");
+        }
+        caption.append(line);
+    }
+    caption
+}
+
+/// Returns the downmix mode matching the configured `mode`.
+fn downmix_mode_for(mode: config::DownmixMode) -> DownmixMode {
+    match mode {
+        config::DownmixMode::Average => DownmixMode::Average,
+        config::DownmixMode::PowerPreserving => DownmixMode::PowerPreserving,
+    }
+}
+
+/// Returns the decode window anchoring set in `config`.
+fn window_anchor_for(config: &Config) -> WindowAnchor {
+    match config.window_anchor {
+        DecodeWindowAnchor::Trailing => WindowAnchor::Trailing,
+        // a single segment ends with the window, so it would never be shown
+        DecodeWindowAnchor::Centered if config.single_segment => WindowAnchor::Trailing,
+        DecodeWindowAnchor::Centered => WindowAnchor::Centered {
+            lookahead_ms: config.window_lookahead_ms,
+        },
+    }
+}
+
+/// Clears the active model, whose file at `model_path` has gone missing, e.g.
+/// with the cache cleaned up, so the frontend asks for a model again instead
+/// of failing on every start.
+async fn forget_missing_model(context: &super::AppContextHandle, model_path: &std::path::Path) {
+    {
+        let mut state = context.state.write().await;
+        // the model may have been switched meanwhile
+        if state.config.active_model_path.as_deref() != Some(model_path) {
+            return;
+        }
+        state.config.active_model_path = None;
+        super::config_service::persist_config(context, &state.config).await;
     }
+
+    super::config_service::handle_config_request(context.clone()).await;
+    super::model_service::handle_downloaded_models_request(context.clone()).await;
 }
 
 /// Validate config/device state and resolve the inputs needed for transcription.
 async fn load_transcription_inputs(
     context: &super::AppContextHandle,
 ) -> Option<TranscriptionInputs> {
-    let (config, active_device) = {
+    let (config, active_device, is_headless) = {
         let state = context.state.read().await;
-        (state.config.clone(), state.active_audio_device.clone())
+        (
+            state.config.clone(),
+            state.active_audio_device.clone(),
+            state.is_headless,
+        )
     };
 
-    let active_model_path = match config.active_model_path {
-        Some(path) => path,
-        None => {
+    let issues = super::config_service::find_config_issues(&config, active_device.clone()).await;
+    if !issues.is_empty() {
+        for issue in &issues {
+            log::warn!("Config issue: {:?}", issue.kind);
+            let notification_type = if issue.is_blocking() {
+                NotificationType::Error
+            } else {
+                NotificationType::Warning
+            };
             context
-                .send_notification(
-                    NotificationType::Error,
-                    "Сначала скачайте модель для распознания речи.",
-                )
+                .send_notification(notification_type, issue.message.clone())
                 .await;
-            return None;
         }
-    };
-
-    if !active_model_path.exists() {
+        let missing_model = issues.iter().find_map(|issue| match &issue.kind {
+            ConfigIssueKind::ModelMissing(path) => Some(path.clone()),
+            _ => None,
+        });
+        if let Some(missing_model) = missing_model {
+            log::error!("Active model is missing at {missing_model:?}, forgetting it");
+            forget_missing_model(context, &missing_model).await;
+        }
+        let is_blocked = issues.iter().any(ConfigIssue::is_blocking);
         context
-            .send_notification(
-                NotificationType::Error,
-                "Скачанная модель распознавания речи повреждена.",
-            )
+            .send(subwin_bridge::MessageFromBackend::ConfigValidation(issues))
             .await;
-        // TODO: update config to remove the path
-        return None;
-    }
-
-    let active_device = match active_device.as_ref() {
-        Some(device) => HostInputDevice::from(device.clone()),
-        None => {
-            context
-                .send_notification(
-                    NotificationType::Error,
-                    "Выберите вводное устройство для захвата звука.",
-                )
-                .await;
+        if is_blocked {
             return None;
         }
+    }
+
+    // both are present, otherwise validation would have failed
+    let (Some(active_model_path), Some(active_device)) =
+        (config.active_model_path.clone(), (*active_device).clone())
+    else {
+        return None;
     };
+    let active_device = HostInputDevice::from(active_device);
+
+    let language = language_for(&config, WhisperModel::from_path(&active_model_path));
+
+    if config.window_anchor == DecodeWindowAnchor::Centered && config.single_segment {
+        log::warn!("Centered decode window is ignored when decoding a single segment");
+    }
+    let window_anchor = window_anchor_for(&config);
 
     Some(TranscriptionInputs {
+        params_profile: config.active_params_profile(),
+        gain: {
+            let mut gain = GainStage::new(
+                config.input_gain_db,
+                config.enable_agc.then_some(config.agc_target_db),
+                TARGET_RATE,
+            );
+            gain.set_limiter(config.limiter_ceiling_db);
+            gain
+        },
+        silence_finalize_ms: config.silence_finalize_ms,
+        clear_captions_after_silence_ms: config
+            .clear_captions_after_silence_ms
+            .filter(|silence_ms| *silence_ms > 0),
+        min_caption_display: Duration::from_millis(config.min_caption_display_ms as u64),
+        max_history_segments: config.captions_config.max_history_segments as usize,
+        max_caption_chars: Some(config.captions_config.max_caption_chars as usize)
+            .filter(|max_chars| *max_chars > 0),
+        temperature: config.temperature.clone(),
+        suppress_non_speech: config.suppress_non_speech,
+        suppressed_tokens: config.suppressed_tokens.clone(),
+        single_segment: config.single_segment,
+        max_tokens: config.max_tokens,
+        carry_prompt: config.carry_prompt,
+        stabilizer: config.stabilizer.clone(),
+        replacer: WordReplacer::new(&config.word_replacements),
+        text_normalization: config.text_normalization.clone(),
+        language,
+        min_transcription_ms: config.min_transcription_ms,
+        decode_timeout: config
+            .decode_timeout_ms
+            .filter(|timeout_ms| *timeout_ms > 0)
+            .map(|timeout_ms| Duration::from_millis(timeout_ms as u64)),
+        window_anchor,
+        split_channels: config.split_channels,
+        adaptive_model: config.adaptive_model,
+        idle_release_minutes: config.idle_release_minutes.filter(|minutes| *minutes > 0),
+        dedicated_worker_thread: config.dedicated_worker_thread,
+        use_gpu: config.use_gpu,
+        // without the GUI, there is no key to hold
+        push_to_talk_release_tail_ms: (config.push_to_talk.enabled && !is_headless)
+            .then_some(config.push_to_talk.release_tail_ms),
         active_model_path,
         active_device,
     })
 }
 
+/// Returns the language passed to Whisper for `config` and `active_model`;
+/// `None` detects it from the audio. Unknown languages are reported by the
+/// validation, and decoded as detected.
+pub(crate) fn language_for(
+    config: &Config,
+    active_model: Option<WhisperModel>,
+) -> Option<&'static str> {
+    match config.language.as_deref() {
+        Some(code) => WhisperTranscriber::resolve_language(code),
+        // English-only models don't detect the language
+        None if active_model.is_some_and(|model| !model.is_multilingual()) => Some("en"),
+        None => None,
+    }
+}
+
+/// Returns Whisper's sampling strategy for the configured `strategy`.
+pub(crate) fn sampling_strategy_for(strategy: DecodingStrategy) -> SamplingStrategy {
+    match strategy {
+        DecodingStrategy::Greedy => SamplingStrategy::Greedy { best_of: 1 },
+        DecodingStrategy::BeamSearch { beam_size } => SamplingStrategy::BeamSearch {
+            beam_size: beam_size as i32,
+            patience: -1.0,
+        },
+    }
+}
+
+/// Returns Whisper's temperature options for the configured `temperature`.
+pub(crate) fn temperature_options_for(temperature: &TemperatureConfig) -> TemperatureOptions {
+    TemperatureOptions {
+        temperature: temperature.temperature,
+        temperature_inc: temperature.temperature_inc,
+        entropy_threshold: temperature.entropy_threshold,
+        logprob_threshold: temperature.logprob_threshold,
+    }
+}
+
+/// Makes `device` capture at [`TARGET_RATE`] directly, if that's enabled in
+/// `audio_device_config` and the device supports it. Returns whether it
+/// does; otherwise, the device captures at its default rate.
+pub(crate) fn prefer_target_rate(
+    device: &mut HostInputDevice,
+    audio_device_config: &AudioDeviceConfig,
+) -> bool {
+    if !audio_device_config.capture_at_target_rate {
+        return false;
+    }
+    match device.prefer_sample_rate(TARGET_RATE) {
+        Ok(is_supported) => is_supported,
+        Err(err) => {
+            log::warn!("Failed to query the sample rates supported by {device}: {err}");
+            false
+        }
+    }
+}
+
+/// Makes `device` capture in buffers of the size overridden for it in
+/// `audio_device_config`, if there is one the device supports.
+fn apply_buffer_size_override(
+    device: &mut HostInputDevice,
+    audio_device_config: &AudioDeviceConfig,
+) {
+    let Some(&frames) = audio_device_config
+        .buffer_size_overrides
+        .get(&device.id.to_string())
+    else {
+        return;
+    };
+    match device.override_buffer_size(frames) {
+        Ok(true) => log::info!("Using the overridden buffer size of {frames} frames for {device}"),
+        Ok(false) => log::warn!(
+            "Ignoring the buffer size override of {frames} frames for {device}, as the device doesn't support it"
+        ),
+        Err(err) => log::warn!("Failed to query the buffer sizes supported by {device}: {err}"),
+    }
+}
+
 /// Read the device's preferred sample rate and buffer size settings.
-fn derive_audio_device_settings(active_device: &HostInputDevice) -> AudioDeviceSettings {
-    let (sample_rate, channels) = active_device
-        .sample_rate_and_channels()
-        .expect("failed to get device's original sample rate and channels");
+/// The sample rate is the target one, if the device supports it and
+/// `capture_at_target_rate` is enabled. The buffer size is the one
+/// overridden for the device, if any. The sample format is chosen
+/// according to `format_preference`.
+fn derive_audio_device_settings(
+    active_device: &mut HostInputDevice,
+    audio_device_config: &AudioDeviceConfig,
+) -> Result<AudioDeviceSettings, DeviceError> {
+    if prefer_target_rate(active_device, audio_device_config) {
+        log::info!("Capturing at {TARGET_RATE} Hz directly, without resampling");
+    }
+    apply_buffer_size_override(active_device, audio_device_config);
+    let (sample_rate, channels) = active_device.sample_rate_and_channels()?;
+    let target_buffer_size = active_device.target_buffer_size(TARGET_RATE)?;
+    // narrowband audio, e.g. of telephony devices, is upsampled, but it
+    // carries nothing above half its rate and tends to be quiet
+    if sample_rate < TARGET_RATE {
+        log::info!(
+            "Capturing narrowband audio at {sample_rate} Hz, upsampling it to {TARGET_RATE} Hz; enable the AGC if the captions miss quiet speech"
+        );
+    }
 
-    let target_buffer_size = active_device
-        .target_buffer_size(TARGET_RATE)
-        .expect("failed to get target buffer size for the device");
+    let preferred_format = match audio_device_config.preferred_sample_format {
+        SampleFormatPreference::Auto => None,
+        SampleFormatPreference::F32 => Some(cpal::SampleFormat::F32),
+        SampleFormatPreference::I16 => Some(cpal::SampleFormat::I16),
+    };
+    let sample_format = active_device.choose_input_format(preferred_format)?;
+    if preferred_format.is_some_and(|preferred| preferred != sample_format) {
+        log::warn!(
+            "The device doesn't support {preferred_format:?} samples, using {sample_format}"
+        );
+    }
 
-    AudioDeviceSettings {
+    Ok(AudioDeviceSettings {
         sample_rate,
         channels,
         target_buffer_size,
-    }
+        sample_format,
+        downmix: downmix_mode_for(audio_device_config.downmix),
+    })
 }
 
-/// Spawn a blocking transcription loop that consumes resampled audio frames.
-fn spawn_transcription_worker(
-    context: super::AppContextHandle,
-    target_buffer_size: u32,
-    active_model_path: PathBuf,
-    mut consumer: impl Consumer<Item = f32> + Send + 'static,
-) {
-    tokio::task::spawn_blocking(move || {
-        let mut transcriber = WhisperTranscriber::new(
-            TARGET_RATE,
-            active_model_path
-                .to_str()
-                .expect("failed to decode active transcription model's path"),
-            WhisperTranscriber::build_context_params(),
+/// Opens the audio passthrough monitor, if it's enabled in the config. Failing
+/// to open it is not fatal: the user is notified and transcription goes on.
+async fn open_monitor_if_enabled(
+    context: &super::AppContextHandle,
+) -> Option<(
+    cpal::Stream,
+    MonitorFeed<impl Producer<Item = f32> + Send + 'static>,
+)> {
+    let (enable_monitor, monitor_device_id, active_host) = {
+        let state = context.state.read().await;
+        (
+            state.config.enable_monitor,
+            state.config.audio_device_config.monitor_device_id.clone(),
+            state.active_host.clone(),
         )
-        .expect("failed to create a new Whisper transcriber");
+    };
 
-        let params = WhisperTranscriber::build_request_params();
-        let mut samples_buffer = vec![0.0f32; target_buffer_size as usize];
-        let mut stabilizer = CaptionsStabilizer::new(STABILIZER_WINDOW_MILLISECONDS);
+    if !enable_monitor {
+        return None;
+    }
 
-        let mut total_samples_seen: i64 = 0;
-        let mut history_segments: Vec<CaptionSegment> = Vec::new();
-        let mut active_segments: Vec<CaptionSegment> = Vec::new();
-        let mut last_sent_text = String::new();
+    match monitor::open_monitor(&active_host, monitor_device_id.as_deref(), TARGET_RATE) {
+        Ok(monitor) => {
+            context
+                .send_notification(
+                    NotificationType::Warning,
+                    "Прослушивание включено. Если захватывается микрофон, используйте наушники, чтобы избежать обратной связи.",
+                )
+                .await;
+            Some(monitor)
+        }
+        Err(err) => {
+            log::error!("Failed to open the audio monitor: {err}");
+            context
+                .send_notification(
+                    NotificationType::Warning,
+                    format!("Не удалось включить прослушивание: {err}"),
+                )
+                .await;
+            None
+        }
+    }
+}
 
-        loop {
-            let len = consumer.pop_slice(&mut samples_buffer);
-            if len == 0 {
-                continue;
-            }
+/// What to do about a failed decode (see [`DecodeRecovery::on_failure`]).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RecoveryAction {
+    /// Keep decoding, the failure may be transient.
+    Retry,
+    /// Reload the model, on the GPU or on the CPU.
+    Reload { attempt: u32, use_gpu: bool },
+    /// Stop transcribing, recovering hasn't helped.
+    GiveUp,
+}
 
-            total_samples_seen += len as i64;
-            transcriber.accept_samples(&samples_buffer[..len]);
+/// Tracks the failing decodes of a transcriber and the attempts to recover
+/// it. Once [`MAX_CONSECUTIVE_DECODE_FAILURES`] decodes fail in a row, the
+/// first attempt rebuilds the transcriber as is, the second one reloads the
+/// model on the CPU, in case the GPU is out of memory or otherwise unusable.
+/// A successful decode starts the attempts over, while the model stays on
+/// the CPU once it has been moved there.
+#[derive(Debug, Default)]
+struct DecodeRecovery {
+    /// Number of decodes failed in a row.
+    consecutive_failures: u32,
+    /// Number of recovery attempts since the last successful decode.
+    attempts: u32,
+    /// Whether the model has been moved to the CPU, or was loaded there
+    /// in the first place.
+    is_on_cpu: bool,
+}
 
-            let (segments, duration) = transcriber.try_transcribe(params.clone());
+impl DecodeRecovery {
+    fn on_success(&mut self) {
+        self.consecutive_failures = 0;
+        self.attempts = 0;
+    }
 
-            let now_milliseconds = total_samples_seen * 1000 / TARGET_RATE as i64;
-            let update = stabilizer.push(now_milliseconds, segments);
+    fn on_failure(&mut self) -> RecoveryAction {
+        self.consecutive_failures += 1;
+        if self.consecutive_failures < MAX_CONSECUTIVE_DECODE_FAILURES {
+            return RecoveryAction::Retry;
+        }
 
-            if update.active.is_empty() && update.history.is_empty() {
-                continue;
+        self.consecutive_failures = 0;
+        self.attempts += 1;
+        match self.attempts {
+            1 => RecoveryAction::Reload {
+                attempt: 1,
+                use_gpu: !self.is_on_cpu,
+            },
+            2 => {
+                self.is_on_cpu = true;
+                RecoveryAction::Reload {
+                    attempt: 2,
+                    use_gpu: false,
+                }
             }
+            _ => RecoveryAction::GiveUp,
+        }
+    }
+}
 
-            history_segments.extend(update.history);
-            active_segments = update.active;
+/// Carries out `action` for a transcriber whose inference keeps failing (see
+/// [`DecodeRecovery`]).
+///
+/// Returns `false` if the transcriber could not be recovered and transcription
+/// should stop.
+fn recover_transcriber(
+    context: &super::AppContextHandle,
+    transcriber: &mut WhisperTranscriber,
+    action: RecoveryAction,
+) -> bool {
+    let (attempt, use_gpu) = match action {
+        RecoveryAction::Retry => return true,
+        RecoveryAction::Reload { attempt, use_gpu } => (attempt, use_gpu),
+        RecoveryAction::GiveUp => {
+            log::error!("Transcriber keeps failing after recovering it");
+            context.send_notification_blocking(
+                NotificationType::Error,
+                "Распознавание речи остановлено из-за повторяющихся ошибок.",
+            );
+            return false;
+        }
+    };
+    let message = if use_gpu {
+        "Распознавание речи даёт сбои, перезапускаем модель..."
+    } else if attempt == 1 {
+        "Распознавание речи даёт сбои, перезапускаем модель на процессоре..."
+    } else {
+        "Распознавание речи снова даёт сбои, переключаемся на процессор..."
+    };
 
-            let caption_text = compose_caption_text(&history_segments, &active_segments);
-            if caption_text.is_empty() || caption_text == last_sent_text {
-                continue;
-            }
+    log::warn!("Recovering the transcriber (attempt {attempt}, GPU: {use_gpu})");
+    context.send_notification_blocking(NotificationType::Warning, message);
 
-            last_sent_text = caption_text.clone();
-            context.send_blocking(
-                subwin_bridge::MessageFromBackend::TranscriptionStateUpdate {
-                    time_taken: duration,
-                    new_segment_text: caption_text,
-                },
+    match transcriber.reload(WhisperTranscriber::build_context_params(use_gpu)) {
+        Ok(()) => true,
+        Err(err) => {
+            log::error!("Failed to reload the transcriber: {err}");
+            context.send_notification_blocking(
+                NotificationType::Error,
+                format!("Не удалось перезапустить модель: {err}"),
             );
+            false
         }
-    });
+    }
 }
 
-/// Build a CPAL input stream that feeds resampled mono samples into the ring buffer.
-fn build_audio_stream(
-    active_device: &HostInputDevice,
-    device_settings: &AudioDeviceSettings,
-    mut producer: impl Producer<Item = f32> + Send + 'static,
-) -> cpal::Stream {
-    let mut callback_state = ResampleCallbackState::new(
-        device_settings.sample_rate,
-        TARGET_RATE,
-        device_settings.target_buffer_size,
-        device_settings.channels,
-    );
+/// Spawn a blocking transcription loop that consumes resampled audio frames,
+/// on the blocking pool or on a dedicated thread.
+///
+/// The loop runs until `stop_signal` is set. Every consumer is transcribed by
+/// its own pipeline; more than one means the channels are split, and their
+/// captions are tagged with the speaker of the channel. Every buffer passes
+/// through the gain stage first; when `monitor` is provided, the first
+/// channel is also queued for playback. Caption changes are emitted to every
+/// sink of `worker_setup`.
+fn spawn_transcription_worker<C: Consumer<Item = f32> + Send + 'static>(
+    context: super::AppContextHandle,
+    worker_setup: WorkerSetup,
+    stop_signal: Arc<AtomicBool>,
+    consumers: Vec<C>,
+    mut monitor: Option<MonitorFeed<impl Producer<Item = f32> + Send + 'static>>,
+) -> WorkerHandle {
+    let WorkerSetup {
+        session_id,
+        target_buffer_size,
+        active_model_path,
+        params_profile,
+        gain,
+        silence_finalize_ms,
+        clear_captions_after_silence_ms,
+        min_caption_display,
+        max_history_segments,
+        max_caption_chars,
+        temperature,
+        suppress_non_speech,
+        suppressed_tokens,
+        single_segment,
+        max_tokens,
+        carry_prompt,
+        stabilizer: stabilizer_config,
+        replacer,
+        text_normalization,
+        language,
+        min_transcription_ms,
+        decode_timeout,
+        window_anchor,
+        adaptive_model,
+        idle_release_minutes,
+        dedicated_worker_thread,
+        use_gpu,
+        push_to_talk,
+        push_to_talk_release_tail_ms,
+        sample_format,
+        mut sinks,
+        counters,
+    } = worker_setup;
+    let silence_finalize_samples = milliseconds_to_samples(silence_finalize_ms, TARGET_RATE);
+    // every channel's samples are counted, so the interval is kept in time
+    let stats_interval_samples =
+        milliseconds_to_samples(STATS_INTERVAL_MILLISECONDS, TARGET_RATE) * consumers.len();
+    let clear_captions_samples = clear_captions_after_silence_ms
+        .map(|silence_ms| milliseconds_to_samples(silence_ms, TARGET_RATE));
+    let idle_release_samples = idle_release_minutes
+        .map(|minutes| milliseconds_to_samples(minutes.saturating_mul(60_000), TARGET_RATE));
+    let release_tail_samples = milliseconds_to_samples(push_to_talk_release_tail_ms, TARGET_RATE);
 
-    subwin_audio::device::open_cpal_input_stream(
-        active_device,
-        TARGET_RATE,
-        move |data: &[f32]| {
-            callback_state.process_input(data, &mut producer);
-        },
-        |error| {
-            log::error!("An error occured while processing the input stream data: {error}");
-        },
-    )
-    .expect("failed to open an input stream for the device")
-}
+    let run = move || {
+        let is_split = consumers.len() > 1;
+        // the model is loaded once per channel, so channels decode
+        // independently of each other
+        let transcribers = (0..consumers.len())
+            .map(|_| {
+                WhisperTranscriber::new(
+                    TARGET_RATE,
+                    &active_model_path.to_string_lossy(),
+                    WhisperTranscriber::build_context_params(use_gpu),
+                    params_profile.context_length_ms,
+                    params_profile.repeat_run_ms,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>();
+        let transcribers = match transcribers {
+            Ok(transcribers) => transcribers,
+            Err(err) => {
+                log::error!("Failed to load the model at {active_model_path:?}: {err}");
+                context.send_notification_blocking(
+                    NotificationType::Error,
+                    format!("Не удалось загрузить модель: {err}"),
+                );
+                end_failed_session(&context, &stop_signal);
+                return;
+            }
+        };
 
-/// Handles an incoming transcription start request.
-pub async fn handle_start_transcription_request(context: super::AppContextHandle) {
-    let inputs = match load_transcription_inputs(&context).await {
-        Some(inputs) => inputs,
-        None => return,
-    };
+        let mut pipelines: Vec<ChannelPipeline<C>> = consumers
+            .into_iter()
+            .zip(transcribers)
+            .enumerate()
+            .map(|(channel, (consumer, mut transcriber))| {
+                transcriber.set_suppressed_tokens(suppressed_tokens.clone());
+                transcriber.set_min_transcription_ms(min_transcription_ms);
+                transcriber.set_window_anchor(window_anchor);
+                transcriber.set_decode_timeout(decode_timeout);
 
-    let TranscriptionInputs {
-        active_model_path,
-        active_device,
-    } = inputs;
+                ChannelPipeline {
+                    speaker: is_split.then_some(channel as u16),
+                    consumer,
+                    gain: gain.clone(),
+                    transcriber,
+                    stabilizer: CaptionsStabilizer::new(stabilizer_config.tail_ms as i64),
+                    smoothed_inference_ms: None,
+                    total_samples_seen: 0,
+                    history_segments: Vec::new(),
+                    active_segments: Vec::new(),
+                    raw_active_segments: Vec::new(),
+                    recovery: DecodeRecovery {
+                        is_on_cpu: !use_gpu,
+                        ..DecodeRecovery::default()
+                    },
+                    silent_samples: 0,
+                }
+            })
+            .collect();
 
-    log::info!("Active device is: {active_device}, active model: {active_model_path:?}");
+        // loading the model can't be interrupted, but a start cancelled
+        // meanwhile ends here, without producing any captions
+        if stop_signal.load(Ordering::Relaxed) {
+            log::info!("Transcription start has been cancelled while loading the model");
+            return;
+        }
+        context.send_blocking(
+            subwin_bridge::MessageFromBackend::TranscriptionStartedResponse { session_id },
+        );
 
-    let device_settings = derive_audio_device_settings(&active_device);
-    log::info!(
-        "The target device's original sample rate is {} Hz and it has {} channel(-s). Target buffer size is {}.",
-        device_settings.sample_rate,
-        device_settings.channels,
-        device_settings.target_buffer_size,
-    );
+        let params = WhisperTranscriber::build_request_params(
+            sampling_strategy_for(params_profile.strategy),
+            temperature_options_for(&temperature),
+            suppress_non_speech,
+            single_segment,
+            max_tokens,
+            language,
+        );
+        let mut samples_buffer = vec![0.0f32; target_buffer_size as usize];
 
-    let inner_buffer = BlockingHeapRb::<f32>::new((TARGET_RATE * 3) as usize);
-    let (producer, consumer) = inner_buffer.split();
+        let mut last_inference_ms = 0u128;
+        let mut last_real_time_factor = 0.0f64;
+        let mut stats_samples = 0usize;
+        let mut stats_sum_of_squares = 0.0f64;
+        let mut lagging_since: Option<std::time::Instant> = None;
+        let mut is_fallback_requested = false;
+        let mut timed_out_decodes = 0u64;
+        let mut clipped_samples = 0u64;
+        // silence heard on every channel, counted on the first one
+        let mut idle_samples = 0usize;
+        // when the captions have last changed, `None` while they're cleared
+        let mut last_caption_at: Option<std::time::Instant> = None;
+        // audio still transcribed after the push-to-talk key's release
+        let mut release_tail_left = 0usize;
+        // whether audio has been transcribed since the last push-to-talk
+        // utterance has been finalized
+        let mut is_utterance_open = false;
 
-    spawn_transcription_worker(
-        context.clone(),
-        device_settings.target_buffer_size,
-        active_model_path,
-        consumer,
-    );
+        'worker: while !stop_signal.load(Ordering::Relaxed) {
+            for sink in sinks.iter_mut() {
+                sink.poll();
+            }
+            if let Some(clear_captions_samples) = clear_captions_samples
+                && idle_samples >= clear_captions_samples
+                && last_caption_at
+                    .is_some_and(|changed_at| changed_at.elapsed() >= min_caption_display)
+                && pipelines
+                    .iter()
+                    .all(|pipeline| pipeline.active_segments.is_empty())
+            {
+                last_caption_at = None;
+                for pipeline in pipelines.iter_mut() {
+                    pipeline.history_segments.clear();
+                }
+                for sink in sinks.iter_mut() {
+                    sink.clear();
+                }
+            }
 
-    let audio_stream = build_audio_stream(&active_device, &device_settings, producer);
-    audio_stream.play().expect("failed to play audio stream");
+            let is_talking = match &push_to_talk {
+                None => true,
+                Some(is_held) if is_held.load(Ordering::Relaxed) => {
+                    release_tail_left = release_tail_samples;
+                    true
+                }
+                Some(_) => release_tail_left > 0,
+            };
+            if is_talking {
+                is_utterance_open = true;
+            } else if is_utterance_open {
+                // the key has been released and the tail transcribed: the
+                // utterance is complete, and the next one starts afresh
+                is_utterance_open = false;
+                let mut finalized = Vec::new();
+                for pipeline in pipelines.iter_mut() {
+                    let mut segments = pipeline.finalize();
+                    replace_words(&replacer, segments.iter_mut());
+                    if text_normalization.enabled {
+                        normalize::normalize_segments(
+                            pipeline
+                                .history_segments
+                                .last()
+                                .map(|segment| segment.text.as_str()),
+                            segments.iter_mut(),
+                            text_normalization.capitalize_sentences,
+                        );
+                    }
+                    pipeline.history_segments.extend(segments.iter().cloned());
+                    let excess_segments = pipeline
+                        .history_segments
+                        .len()
+                        .saturating_sub(max_history_segments);
+                    pipeline.history_segments.drain(..excess_segments);
+                    if carry_prompt && !segments.is_empty() {
+                        pipeline.carry_prompt();
+                    }
+                    finalized.extend(segments);
+                }
+                if !finalized.is_empty() {
+                    let caption = compose_pipelines_text(&pipelines, max_caption_chars);
+                    let caption_update = CaptionUpdate {
+                        finalized: &finalized,
+                        active: &[],
+                        text: &caption.text,
+                        uncertain_ranges: &caption.uncertain_ranges,
+                        inference_ms: last_inference_ms,
+                        window_ms: None,
+                    };
+                    for sink in sinks.iter_mut() {
+                        sink.emit(&caption_update);
+                    }
+                    last_caption_at = Some(std::time::Instant::now());
+                }
+            }
 
-    {
-        let mut state = context.state.write().await;
-        state.active_stream = Some(audio_stream);
-    }
+            for index in 0..pipelines.len() {
+                let pipeline = &mut pipelines[index];
+                let len = pipeline.consumer.pop_slice(&mut samples_buffer);
+                if len == 0 {
+                    continue;
+                }
 
-    log::info!("Started playing the stream...");
-    context
-        .send(subwin_bridge::MessageFromBackend::TranscriptionStartedResponse)
+                pipeline.total_samples_seen += len as i64;
+                clipped_samples += pipeline.gain.process(&mut samples_buffer[..len]) as u64;
+                context.publish_level(InputLevel::measure(&samples_buffer[..len]));
+                if subwin_speech::is_silence(&samples_buffer[..len]) {
+                    pipeline.silent_samples += len;
+                    if index == 0 {
+                        idle_samples += len;
+                    }
+                } else {
+                    pipeline.silent_samples = 0;
+                    idle_samples = 0;
+                }
+                if let Some(idle_release_samples) = idle_release_samples
+                    && idle_samples >= idle_release_samples
+                {
+                    // counting starts over once capture resumes
+                    idle_samples = 0;
+                    spawn_device_release(&context, stop_signal.clone());
+                }
+                if is_talking {
+                    pipeline.transcriber.accept_samples(&samples_buffer[..len]);
+                    if index == 0 {
+                        release_tail_left = release_tail_left.saturating_sub(len);
+                    }
+                }
+                if index == 0
+                    && let Some(monitor) = monitor.as_mut()
+                {
+                    monitor.push(&samples_buffer[..len]);
+                }
+
+                stats_samples += len;
+                stats_sum_of_squares += samples_buffer[..len]
+                    .iter()
+                    .map(|sample| (*sample as f64).powi(2))
+                    .sum::<f64>();
+                if stats_samples >= stats_interval_samples {
+                    let rms = (stats_sum_of_squares / stats_samples as f64).sqrt() as f32;
+                    let (resampler_queue_samples, dropped_samples, last_error) =
+                        counters.snapshot();
+                    let stats = PipelineStats {
+                        input_level_db: 20.0 * rms.max(1e-5).log10(),
+                        resampler_queue_samples,
+                        ring_buffer_samples: pipelines
+                            .iter()
+                            .map(|pipeline| pipeline.consumer.occupied_len())
+                            .sum(),
+                        ring_buffer_capacity: pipelines
+                            .iter()
+                            .map(|pipeline| pipeline.consumer.capacity().get())
+                            .sum(),
+                        real_time_factor: last_real_time_factor,
+                        dropped_samples,
+                        timed_out_decodes,
+                        clipped_samples,
+                        last_error,
+                        sample_format: sample_format.to_string(),
+                    };
+                    context.set_last_pipeline_stats(stats.clone());
+                    context.send_update(subwin_bridge::MessageFromBackend::PipelineStats(stats));
+                    stats_samples = 0;
+                    stats_sum_of_squares = 0.0;
+                }
+
+                // audio heard without the push-to-talk key held is dropped
+                if !is_talking {
+                    continue;
+                }
+
+                let pipeline = &mut pipelines[index];
+                let (mut segments, metrics) =
+                    match pipeline.transcriber.try_transcribe(params.clone()) {
+                        Ok(result) => {
+                            pipeline.recovery.on_success();
+                            result
+                        }
+                        Err(err) => {
+                            log::error!(
+                                "Failed to transcribe audio ({} time(-s) in a row): {err}",
+                                pipeline.recovery.consecutive_failures + 1,
+                            );
+                            let action = pipeline.recovery.on_failure();
+                            counters.set_last_error(format!("decode failed: {err}"));
+                            if !recover_transcriber(&context, &mut pipeline.transcriber, action) {
+                                end_failed_session(&context, &stop_signal);
+                                break 'worker;
+                            }
+                            continue;
+                        }
+                    };
+                let window_ms = pipeline.transcriber.window_bounds_ms();
+                if let Some(metrics) = metrics {
+                    if metrics.timed_out {
+                        timed_out_decodes += 1;
+                        log::warn!(
+                            "Decode has been aborted after {} ms, exceeding the decode timeout",
+                            metrics.inference_ms,
+                        );
+                        counters.set_last_error("decode timed out");
+                    }
+                    last_inference_ms = metrics.inference_ms;
+                    last_real_time_factor = metrics.real_time_factor;
+                    if stabilizer_config.adaptive {
+                        let inference_ms = metrics.inference_ms as f64;
+                        let smoothed = pipeline
+                            .smoothed_inference_ms
+                            .map_or(inference_ms, |smoothed| {
+                                smoothed + LATENCY_SMOOTHING * (inference_ms - smoothed)
+                            });
+                        pipeline.smoothed_inference_ms = Some(smoothed);
+                        pipeline
+                            .stabilizer
+                            .set_tail_ms(adaptive_tail_ms(&stabilizer_config, smoothed));
+                    }
+                    if adaptive_model && !is_fallback_requested {
+                        if metrics.real_time_factor > 1.0 {
+                            let since = *lagging_since.get_or_insert_with(std::time::Instant::now);
+                            if since.elapsed() >= SUSTAINED_LAG {
+                                log::warn!(
+                                    "Decoding has been slower than real time for {SUSTAINED_LAG:?}, falling back to a smaller model"
+                                );
+                                is_fallback_requested = true;
+                                super::model_service::spawn_model_fallback(&context);
+                            }
+                        } else {
+                            lagging_since = None;
+                        }
+                    }
+                    context.send_update(subwin_bridge::MessageFromBackend::TranscriptionMetrics {
+                        inference_ms: metrics.inference_ms,
+                        samples_processed: metrics.samples_processed,
+                        real_time_factor: metrics.real_time_factor,
+                    });
+                }
+
+                for segment in segments.iter_mut() {
+                    segment.speaker = pipeline.speaker;
+                }
+
+                let now_milliseconds = pipeline.total_samples_seen * 1000 / TARGET_RATE as i64;
+                let mut update = pipeline.stabilizer.push(now_milliseconds, segments);
+
+                // a sustained pause ends the utterance: finalize its segments
+                // now, rather than waiting for them to age out of the
+                // stabilizer window
+                if silence_finalize_ms > 0 && pipeline.silent_samples >= silence_finalize_samples {
+                    let utterance = if update.active.is_empty() {
+                        pipeline.active_segments.clear();
+                        std::mem::take(&mut pipeline.raw_active_segments)
+                    } else {
+                        std::mem::take(&mut update.active)
+                    };
+                    if !utterance.is_empty() {
+                        update
+                            .history
+                            .extend(pipeline.stabilizer.flush(utterance).history);
+                        // a single segment always spans the whole window, so
+                        // the finalized speech would be decoded into the next
+                        // one too
+                        if single_segment {
+                            pipeline.transcriber.clear_window();
+                        }
+                    }
+                }
+
+                if update.active.is_empty() && update.history.is_empty() {
+                    continue;
+                }
+                pipeline.raw_active_segments = update.active.clone();
+
+                // replaced in the stabilizer's output rather than in its
+                // input, so it compares the model's own revisions, and fixed
+                // once per update, so the overlay and every sink agree
+                replace_words(
+                    &replacer,
+                    update.history.iter_mut().chain(update.active.iter_mut()),
+                );
+
+                // normalized after the stabilizer, so sentence starts are
+                // told by the finalized text preceding the update
+                if text_normalization.enabled {
+                    normalize::normalize_segments(
+                        pipeline
+                            .history_segments
+                            .last()
+                            .map(|segment| segment.text.as_str()),
+                        update.history.iter_mut().chain(update.active.iter_mut()),
+                        text_normalization.capitalize_sentences,
+                    );
+                }
+
+                // only the tail of the history is displayed; the full
+                // transcript is up to the sinks, which get every finalized
+                // segment once
+                pipeline
+                    .history_segments
+                    .extend(update.history.iter().cloned());
+                let excess_segments = pipeline
+                    .history_segments
+                    .len()
+                    .saturating_sub(max_history_segments);
+                pipeline.history_segments.drain(..excess_segments);
+                if carry_prompt && !update.history.is_empty() {
+                    pipeline.carry_prompt();
+                }
+                pipeline.active_segments = update.active;
+
+                let caption = compose_pipelines_text(&pipelines, max_caption_chars);
+                let active_segments: Vec<CaptionSegment> = pipelines
+                    .iter()
+                    .flat_map(|pipeline| pipeline.active_segments.iter().cloned())
+                    .collect();
+                let caption_update = CaptionUpdate {
+                    finalized: &update.history,
+                    active: &active_segments,
+                    text: &caption.text,
+                    uncertain_ranges: &caption.uncertain_ranges,
+                    inference_ms: last_inference_ms,
+                    window_ms: Some(window_ms),
+                };
+                for sink in sinks.iter_mut() {
+                    sink.emit(&caption_update);
+                }
+                last_caption_at = Some(std::time::Instant::now());
+            }
+        }
+
+        for sink in sinks.iter_mut() {
+            sink.finish();
+        }
+        context.publish_level(InputLevel::SILENT);
+        log::info!("Transcription worker has been stopped.");
+    };
+
+    if !dedicated_worker_thread {
+        return WorkerHandle::Pooled(tokio::task::spawn_blocking(run));
+    }
+
+    // the worker spawns tasks, e.g. to release the device
+    let runtime = tokio::runtime::Handle::current();
+    let (finished_tx, finished_rx) = tokio::sync::oneshot::channel();
+    // the loop is only handed over once the thread is up, so it's still
+    // around to run on the pool if the thread can't be spawned
+    let (run_tx, run_rx) = std::sync::mpsc::sync_channel(1);
+    let thread = std::thread::Builder::new()
+        .name("transcription-worker".to_string())
+        .spawn(move || {
+            let Ok(run) = run_rx.recv() else {
+                return;
+            };
+            let _runtime = runtime.enter();
+            if let Err(err) = thread_priority::set_current_thread_priority(ThreadPriority::Max) {
+                log::warn!("Failed to raise the transcription worker's priority: {err:?}");
+            }
+            run();
+            let _ = finished_tx.send(());
+        });
+    match thread {
+        Ok(thread) => {
+            let _ = run_tx.send(run);
+            WorkerHandle::Dedicated {
+                thread,
+                finished: finished_rx,
+            }
+        }
+        Err(err) => {
+            log::warn!(
+                "Failed to spawn the transcription worker thread, running on the blocking pool instead: {err}"
+            );
+            WorkerHandle::Pooled(tokio::task::spawn_blocking(run))
+        }
+    }
+}
+
+/// Producers of the session's ring buffers, lent to an input stream. They are
+/// returned once the stream's callback is dropped, so the stream can be
+/// opened again after the device has been released.
+struct LentProducers<P> {
+    producers: Vec<P>,
+    owner: Arc<Mutex<Vec<P>>>,
+}
+
+impl<P> LentProducers<P> {
+    /// Takes the producers from `owner` until the lease is dropped. Fails if
+    /// they are still lent to another stream, which would be left without
+    /// anywhere to write to.
+    fn lend(owner: &Arc<Mutex<Vec<P>>>) -> Result<Self, AudioCaptureError> {
+        let producers = std::mem::take(&mut *owner.lock().expect("failed to lock producers"));
+        if producers.is_empty() {
+            log::error!("The ring buffer producers haven't been returned by the previous stream");
+            return Err(AudioCaptureError::Backend(
+                "the previous audio stream hasn't been closed".to_string(),
+            ));
+        }
+        Ok(Self {
+            producers,
+            owner: owner.clone(),
+        })
+    }
+}
+
+impl<P> Drop for LentProducers<P> {
+    fn drop(&mut self) {
+        if let Ok(mut owner) = self.owner.lock() {
+            *owner = std::mem::take(&mut self.producers);
+        }
+    }
+}
+
+/// Build a CPAL input stream that feeds resampled mono samples into the ring
+/// buffer, or every split channel into its own one of `producers`.
+fn build_audio_stream(
+    active_device: &HostInputDevice,
+    device_settings: &AudioDeviceSettings,
+    resampler_quality: ResamplingQuality,
+    split_channels: bool,
+    mut producers: LentProducers<impl Producer<Item = f32> + Send + 'static>,
+    errors: RealtimeErrorReporter,
+    counters: Arc<PipelineCounters>,
+) -> Result<cpal::Stream, AudioCaptureError> {
+    let mut callback_state = ResampleCallbackState::new(
+        device_settings,
+        TARGET_RATE,
+        resampler_quality,
+        split_channels,
+        errors.clone(),
+        counters,
+    )
+    .map_err(|err| {
+        log::error!(
+            "Failed to create a resampler from {} Hz to {TARGET_RATE} Hz: {err}",
+            device_settings.sample_rate,
+        );
+        AudioCaptureError::UnsupportedConfig
+    })?;
+
+    subwin_audio::device::open_f32_input_stream(
+        active_device,
+        TARGET_RATE,
+        device_settings.sample_format,
+        move |data: &[f32]| {
+            callback_state.process_input(data, &mut producers.producers);
+        },
+        move |error| errors.report(error),
+    )
+    .map_err(|err| {
+        log::error!("Failed to open an input stream for the device: {err}");
+        super::audio_service::classify_device_error(&err)
+    })
+}
+
+/// Resets the session phase set to [`SessionPhase::Starting`] or
+/// [`SessionPhase::Stopping`] once dropped, unless it's been disarmed: when
+/// starting or stopping a session panics halfway through, later requests
+/// would be rejected forever otherwise.
+struct TransitionPhaseGuard {
+    /// Context whose phase is reset; `None` once disarmed.
+    context: Option<super::AppContextHandle>,
+    /// Phase of the guarded transition, which is left alone once it has
+    /// changed meanwhile.
+    transition: SessionPhase,
+}
+
+impl TransitionPhaseGuard {
+    fn new(context: &super::AppContextHandle, transition: SessionPhase) -> Self {
+        Self {
+            context: Some(context.clone()),
+            transition,
+        }
+    }
+
+    /// Keeps the phase as it is, now that the transition has ended normally.
+    fn disarm(mut self) {
+        self.context = None;
+    }
+}
+
+impl Drop for TransitionPhaseGuard {
+    fn drop(&mut self) {
+        let Some(context) = self.context.take() else {
+            return;
+        };
+        let transition = std::mem::discriminant(&self.transition);
+        log::error!(
+            "Transcription session has failed abnormally while {:?}",
+            self.transition
+        );
+        // the lock can't be awaited here, nor is it certain to be free
+        let tasks = context.clone();
+        tasks.spawn(async move {
+            {
+                let mut state = context.state.write().await;
+                if std::mem::discriminant(&state.session_phase) != transition {
+                    return;
+                }
+                if state.active_session.is_some() {
+                    state.session_phase = SessionPhase::Running;
+                    return;
+                }
+                state.session_phase = SessionPhase::Idle;
+            }
+            context
+                .send(subwin_bridge::MessageFromBackend::TranscriptionStoppedResponse)
+                .await;
+        });
+    }
+}
+
+/// Handles an incoming transcription start request. A running session is
+/// restarted, while a request arriving as a session is being started or
+/// stopped is rejected with a notification (see [`SessionPhase`]).
+pub async fn handle_start_transcription_request(context: super::AppContextHandle) {
+    let rejection = {
+        let mut state = context.state.write().await;
+        match state.session_phase {
+            _ if state.is_benchmarking => {
+                log::warn!("Rejected a transcription start request, a model benchmark is running");
+                Some("Идёт проверка скорости модели, запустите распознавание после неё.")
+            }
+            SessionPhase::Idle | SessionPhase::Running => {
+                state.session_phase = SessionPhase::Starting {
+                    is_stop_queued: false,
+                };
+                None
+            }
+            phase => {
+                log::warn!("Rejected a transcription start request, the session is {phase:?}");
+                Some(match phase {
+                    SessionPhase::Stopping => {
+                        "Распознавание ещё останавливается, запустите его чуть позже."
+                    }
+                    _ => "Распознавание уже запускается.",
+                })
+            }
+        }
+    };
+    if let Some(message) = rejection {
+        context
+            .send_notification(NotificationType::Info, message)
+            .await;
+        return;
+    }
+
+    let phase_guard = TransitionPhaseGuard::new(
+        &context,
+        SessionPhase::Starting {
+            is_stop_queued: false,
+        },
+    );
+    start_transcription(context.clone()).await;
+
+    let is_stop_queued = {
+        let mut state = context.state.write().await;
+        let is_stop_queued = matches!(
+            state.session_phase,
+            SessionPhase::Starting {
+                is_stop_queued: true
+            }
+        );
+        state.session_phase = if state.active_session.is_some() {
+            SessionPhase::Running
+        } else {
+            SessionPhase::Idle
+        };
+        is_stop_queued
+    };
+    phase_guard.disarm();
+    if is_stop_queued {
+        log::info!("Carrying out the stop request queued while starting");
+        handle_stop_transcription_request(context).await;
+    }
+}
+
+/// Starts a transcription session, replacing the active one. Failures are
+/// reported to the frontend, leaving no session behind.
+async fn start_transcription(context: super::AppContextHandle) {
+    super::audio_service::refresh_default_device(&context).await;
+    let inputs = match load_transcription_inputs(&context).await {
+        Some(inputs) => inputs,
+        None => return,
+    };
+
+    let TranscriptionInputs {
+        active_model_path,
+        mut active_device,
+        params_profile,
+        gain,
+        silence_finalize_ms,
+        clear_captions_after_silence_ms,
+        min_caption_display,
+        max_history_segments,
+        max_caption_chars,
+        temperature,
+        suppress_non_speech,
+        suppressed_tokens,
+        single_segment,
+        max_tokens,
+        carry_prompt,
+        stabilizer,
+        replacer,
+        text_normalization,
+        language,
+        min_transcription_ms,
+        decode_timeout,
+        window_anchor,
+        split_channels: split_requested,
+        adaptive_model,
+        idle_release_minutes,
+        dedicated_worker_thread,
+        use_gpu,
+        push_to_talk_release_tail_ms,
+    } = inputs;
+
+    log::info!("Active device is: {active_device}, active model: {active_model_path:?}");
+    log::info!("Transcription parameters: {params_profile:?}");
+    // loading a large model takes a while, the frontend shows it's underway
+    context
+        .send(subwin_bridge::MessageFromBackend::TranscriptionStarting)
+        .await;
+
+    // never leave a previous worker running without a stream feeding it
+    stop_active_session(&context).await;
+
+    let audio_device_config = context
+        .state
+        .read()
+        .await
+        .config
+        .audio_device_config
+        .clone();
+    let resampler_quality = audio_device_config.resampling_quality;
+    let device_settings =
+        match derive_audio_device_settings(&mut active_device, &audio_device_config) {
+            Ok(device_settings) => device_settings,
+            Err(err) => {
+                log::error!("Failed to query the input device: {err}");
+                report_start_failure(&context, super::audio_service::classify_device_error(&err))
+                    .await;
+                return;
+            }
+        };
+    log::info!(
+        "The target device's original sample rate is {} Hz and it has {} channel(-s). Target buffer size is {}, capturing {} samples.",
+        device_settings.sample_rate,
+        device_settings.channels,
+        device_settings.target_buffer_size,
+        device_settings.sample_format,
+    );
+    context
+        .send(subwin_bridge::MessageFromBackend::ActiveDeviceInfo(Some(
+            super::audio_service::active_device_info(
+                &active_device,
+                device_settings.sample_rate,
+                device_settings.channels,
+            ),
+        )))
         .await;
+
+    let split_channels = split_requested && device_settings.channels as usize >= SPLIT_CHANNELS;
+    if split_requested && !split_channels {
+        log::warn!("The device has a single channel, transcribing it without splitting");
+        context
+            .send_notification(
+                NotificationType::Warning,
+                "У устройства только один канал, раздельное распознавание каналов отключено.",
+            )
+            .await;
+    }
+
+    let outputs = if split_channels { SPLIT_CHANNELS } else { 1 };
+    let capacity = ring_buffer_capacity(
+        device_settings.sample_rate,
+        device_settings.target_buffer_size,
+        resampler_quality,
+    );
+    let (producers, consumers): (Vec<_>, Vec<_>) = (0..outputs)
+        .map(|_| BlockingHeapRb::<f32>::new(capacity).split())
+        .unzip();
+
+    let counters = Arc::new(PipelineCounters::default());
+    let (target_buffer_size, sample_format) = (
+        device_settings.target_buffer_size,
+        device_settings.sample_format,
+    );
+    let realtime_errors = realtime::spawn_realtime_error_forwarder(&context, counters.clone());
+    let producers = Arc::new(Mutex::new(producers));
+    let stream_counters = counters.clone();
+    let mut open_stream: StreamOpener = Box::new(move || {
+        build_audio_stream(
+            &active_device,
+            &device_settings,
+            resampler_quality,
+            split_channels,
+            LentProducers::lend(&producers)?,
+            realtime_errors.clone(),
+            stream_counters.clone(),
+        )
+    });
+    // the stream is opened before the worker is spawned, so a device that
+    // can't be opened doesn't leave a worker behind
+    let audio_stream = match open_stream() {
+        Ok(audio_stream) => audio_stream,
+        Err(err) => {
+            report_start_failure(&context, err).await;
+            return;
+        }
+    };
+    if let Err(err) = audio_stream.play() {
+        log::error!("Failed to play the audio stream: {err}");
+        report_start_failure(&context, super::audio_service::classify_play_error(&err)).await;
+        return;
+    }
+    // the records of the caption sinks are timed from here
+    let started_at = std::time::Instant::now();
+
+    let (monitor_stream, monitor_feed) = open_monitor_if_enabled(&context).await.unzip();
+    let push_to_talk = push_to_talk_release_tail_ms.map(|_| Arc::new(AtomicBool::new(false)));
+    let session_id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+    let worker_setup = WorkerSetup {
+        session_id,
+        target_buffer_size,
+        active_model_path,
+        params_profile,
+        gain,
+        silence_finalize_ms,
+        clear_captions_after_silence_ms,
+        min_caption_display,
+        max_history_segments,
+        max_caption_chars,
+        temperature,
+        suppress_non_speech,
+        suppressed_tokens,
+        single_segment,
+        max_tokens,
+        carry_prompt,
+        stabilizer,
+        replacer,
+        text_normalization,
+        language,
+        min_transcription_ms,
+        decode_timeout,
+        window_anchor,
+        adaptive_model,
+        idle_release_minutes,
+        dedicated_worker_thread,
+        use_gpu,
+        push_to_talk: push_to_talk.clone(),
+        push_to_talk_release_tail_ms: push_to_talk_release_tail_ms.unwrap_or_default(),
+        sample_format,
+        sinks: sinks::open_caption_sinks(&context, session_id, started_at).await,
+        counters,
+    };
+
+    let stop_signal = Arc::new(AtomicBool::new(false));
+    let worker = spawn_transcription_worker(
+        context.clone(),
+        worker_setup,
+        stop_signal.clone(),
+        consumers,
+        monitor_feed,
+    );
+
+    let (host, follows_default, max_session_minutes) = {
+        let state = context.state.read().await;
+        (
+            state.active_host.clone(),
+            state
+                .config
+                .audio_device_config
+                .selected_device_id
+                .as_deref()
+                == Some(SYSTEM_DEFAULT_DEVICE_ID),
+            state.config.max_session_minutes,
+        )
+    };
+    if follows_default {
+        super::audio_service::spawn_default_device_watcher(&context, stop_signal.clone(), host);
+    }
+
+    let session_timer = max_session_minutes
+        .filter(|minutes| *minutes > 0)
+        .map(|minutes| {
+            let limit = Duration::from_secs(minutes as u64 * 60);
+            spawn_session_timer(&context, stop_signal.clone(), limit)
+        });
+
+    let session = TranscriptionSession {
+        stream: Some(audio_stream),
+        open_stream,
+        monitor_stream,
+        stop_signal,
+        worker,
+        started_at,
+        session_timer,
+        push_to_talk,
+    };
+    let failed_session = {
+        let mut state = context.state.write().await;
+        // the worker may have failed already, e.g. to load the model, before
+        // there was a session for it to end (see `end_failed_session`)
+        if session.stop_signal.load(Ordering::Relaxed) {
+            Some(session)
+        } else {
+            state.active_session = Some(session);
+            None
+        }
+    };
+    if let Some(session) = failed_session {
+        tear_down_session(session).await;
+        context
+            .send(subwin_bridge::MessageFromBackend::TranscriptionStoppedResponse)
+            .await;
+        return;
+    }
+
+    // the worker reports the start once the model is loaded
+    log::info!("Started playing the stream...");
+}
+
+/// Handles a press, with `is_held`, or a release of the push-to-talk key
+/// (see [`subwin_bridge::MessageToBackend::PushToTalk`]). A press captures
+/// from the input device again if the session has released it.
+pub async fn handle_push_to_talk(context: super::AppContextHandle, is_held: bool) {
+    let is_released = {
+        let state = context.state.read().await;
+        let Some(session) = state.active_session.as_ref() else {
+            return;
+        };
+        let Some(push_to_talk) = session.push_to_talk.as_ref() else {
+            return;
+        };
+        push_to_talk.store(is_held, Ordering::Relaxed);
+        session.stream.is_none()
+    };
+    if is_held && is_released {
+        handle_resume_capture_request(context).await;
+    }
+}
+
+/// Reports a capture failure that has prevented the session from starting,
+/// so the frontend leaves the starting state.
+async fn report_start_failure(context: &super::AppContextHandle, error: AudioCaptureError) {
+    context
+        .send(subwin_bridge::MessageFromBackend::AudioError(error))
+        .await;
+    context
+        .send(subwin_bridge::MessageFromBackend::TranscriptionStoppedResponse)
+        .await;
+}
+
+/// Spawns a timer that stops the session with `stop_signal` once `limit`
+/// has passed. Aborting the returned handle cancels the timer.
+fn spawn_session_timer(
+    context: &super::AppContextHandle,
+    stop_signal: Arc<AtomicBool>,
+    limit: Duration,
+) -> tokio::task::AbortHandle {
+    let tasks = context.clone();
+    let context = context.clone();
+    tasks.spawn(async move {
+        tokio::time::sleep(limit).await;
+        {
+            let mut state = context.state.write().await;
+            match state.active_session.as_mut() {
+                // the timer is detached from the session, so stopping it
+                // doesn't abort the timer halfway through
+                Some(session) if Arc::ptr_eq(&session.stop_signal, &stop_signal) => {
+                    session.session_timer = None;
+                }
+                // the session has been replaced or stopped meanwhile
+                _ => return,
+            }
+        }
+
+        log::info!("Maximum session duration of {limit:?} reached, stopping transcription");
+        context
+            .send_notification(
+                NotificationType::Info,
+                format!(
+                    "Распознавание остановлено: прошло {} мин.",
+                    limit.as_secs() / 60
+                ),
+            )
+            .await;
+        handle_stop_transcription_request(context).await;
+    })
+}
+
+/// Spawns a task closing the audio stream of the session with `stop_signal`,
+/// so the device is free for other applications while nobody speaks. The
+/// stream is opened again on request or once the push-to-talk key is
+/// pressed (see [`handle_resume_capture_request`]).
+fn spawn_device_release(context: &super::AppContextHandle, stop_signal: Arc<AtomicBool>) {
+    let tasks = context.clone();
+    let context = context.clone();
+    tasks.spawn(async move {
+        {
+            let mut state = context.state.write().await;
+            let Some(session) = state
+                .active_session
+                .as_mut()
+                .filter(|session| Arc::ptr_eq(&session.stop_signal, &stop_signal))
+            else {
+                return;
+            };
+            let Some(stream) = session.stream.take() else {
+                return;
+            };
+            if let Err(err) = stream.pause() {
+                log::warn!("Failed to pause the audio stream before releasing: {err}");
+            }
+        }
+
+        log::info!("No speech has been heard for a while, released the input device");
+        context
+            .send(subwin_bridge::MessageFromBackend::CaptureReleased(true))
+            .await;
+        context
+            .send_notification(
+                NotificationType::Info,
+                "Речь долго не звучала, устройство освобождено для других приложений. Нажмите «Продолжить», чтобы возобновить захват.",
+            )
+            .await;
+    });
+}
+
+/// Handles a request to capture from the released device again (see
+/// [`subwin_bridge::MessageToBackend::ResumeCaptureRequest`]), also made by
+/// a press of the push-to-talk key. If the device can't be opened, the
+/// session is stopped.
+pub async fn handle_resume_capture_request(context: super::AppContextHandle) {
+    let error = {
+        let mut state = context.state.write().await;
+        let Some(session) = state.active_session.as_mut() else {
+            return;
+        };
+        if session.stream.is_some() {
+            return;
+        }
+
+        match (session.open_stream)() {
+            Ok(stream) => match stream.play() {
+                Ok(()) => {
+                    session.stream = Some(stream);
+                    None
+                }
+                Err(err) => Some(super::audio_service::classify_play_error(&err)),
+            },
+            Err(err) => Some(err),
+        }
+    };
+
+    match error {
+        None => {
+            log::info!("Captured from the input device again");
+            context
+                .send(subwin_bridge::MessageFromBackend::CaptureReleased(false))
+                .await;
+            context
+                .send_notification(NotificationType::Info, "Захват звука возобновлён.")
+                .await;
+        }
+        Some(err) => {
+            log::error!("Failed to capture from the input device again: {err}");
+            context
+                .send(subwin_bridge::MessageFromBackend::AudioError(err))
+                .await;
+            handle_stop_transcription_request(context).await;
+        }
+    }
+}
+
+/// Handles an incoming transcription stop request. A request arriving as a
+/// session is being started is carried out once the start ends, while one
+/// arriving during another stop is ignored (see [`SessionPhase`]).
+pub async fn handle_stop_transcription_request(context: super::AppContextHandle) {
+    let is_starting = {
+        let mut state = context.state.write().await;
+        match state.session_phase {
+            SessionPhase::Starting { .. } => {
+                state.session_phase = SessionPhase::Starting {
+                    is_stop_queued: true,
+                };
+                true
+            }
+            SessionPhase::Stopping => {
+                log::info!("Ignored a transcription stop request, the session is already stopping");
+                return;
+            }
+            SessionPhase::Idle | SessionPhase::Running => {
+                state.session_phase = SessionPhase::Stopping;
+                false
+            }
+        }
+    };
+    if is_starting {
+        log::info!("Queued a transcription stop request until the session starts");
+        context
+            .send_notification(
+                NotificationType::Info,
+                "Распознавание остановится, как только запустится.",
+            )
+            .await;
+        return;
+    }
+
+    let phase_guard = TransitionPhaseGuard::new(&context, SessionPhase::Stopping);
+    stop_active_session(&context).await;
+    context.state.write().await.session_phase = SessionPhase::Idle;
+    phase_guard.disarm();
+
+    log::info!("Stopped the transcription session.");
+    context
+        .send(subwin_bridge::MessageFromBackend::TranscriptionStoppedResponse)
+        .await;
+}
+
+/// Ends the session with `stop_signal` from its worker, which has failed and
+/// is about to return: the worker's loop is left, and a task tears the
+/// session down and tells the frontend it has stopped. A session that has been
+/// replaced or stopped meanwhile is left alone.
+fn end_failed_session(context: &super::AppContextHandle, stop_signal: &Arc<AtomicBool>) {
+    stop_signal.store(true, Ordering::Relaxed);
+
+    let tasks = context.clone();
+    let context = context.clone();
+    let stop_signal = stop_signal.clone();
+    tasks.spawn(async move {
+        let session = {
+            let mut state = context.state.write().await;
+            let is_failed_session = state
+                .active_session
+                .as_ref()
+                .is_some_and(|session| Arc::ptr_eq(&session.stop_signal, &stop_signal));
+            if !is_failed_session {
+                return;
+            }
+            // a start or stop underway sets the phase once it's done
+            if state.session_phase == SessionPhase::Running {
+                state.session_phase = SessionPhase::Idle;
+            }
+            state.active_session.take()
+        };
+
+        if let Some(session) = session {
+            tear_down_session(session).await;
+        }
+        log::info!("Stopped the failed transcription session.");
+        context
+            .send(subwin_bridge::MessageFromBackend::TranscriptionStoppedResponse)
+            .await;
+    });
+}
+
+/// Stops the active transcription session, if any (see
+/// [`tear_down_session`]).
+pub async fn stop_active_session(context: &super::AppContextHandle) {
+    let session = {
+        let mut state = context.state.write().await;
+        state.active_session.take()
+    };
+
+    if let Some(session) = session {
+        tear_down_session(session).await;
+    }
+}
+
+/// Pauses and drops the audio stream of `session`, then signals its worker
+/// and waits for it to leave its loop.
+async fn tear_down_session(session: TranscriptionSession) {
+    if let Some(session_timer) = session.session_timer {
+        session_timer.abort();
+    }
+
+    if let Some(stream) = session.stream
+        && let Err(err) = stream.pause()
+    {
+        log::warn!("Failed to pause the audio stream before stopping: {err}");
+    }
+    drop(session.monitor_stream);
+
+    session.stop_signal.store(true, Ordering::Relaxed);
+    if let Err(err) = session.worker.join().await {
+        log::error!("Transcription worker has finished abnormally: {err}");
+    }
+}
+
+/// Estimates the delay between speech and its captions with `config` on
+/// `device`. The decode time is derived from `measured_real_time_factor`, if
+/// any, or guessed from the model's size. Returns `None` if the device can't
+/// be queried.
+fn estimate_latency(
+    config: &Config,
+    device: &HostInputDevice,
+    measured_real_time_factor: Option<f64>,
+) -> Option<LatencyEstimate> {
+    let (sample_rate, _) = device.sample_rate_and_channels().ok()?;
+    let target_buffer_size = device.target_buffer_size(TARGET_RATE).ok()?;
+    let resampler = StreamingResampler::<f32>::new(
+        sample_rate,
+        TARGET_RATE,
+        target_buffer_size,
+        config.audio_device_config.resampling_quality,
+    )
+    .ok()?;
+    // input waits for a whole block, when it's longer than a capture buffer
+    let block_wait_samples = resampler
+        .input_block_size()
+        .saturating_sub(target_buffer_size as usize);
+    // audio captured at the target rate isn't resampled at all
+    let resampler_ms = if sample_rate == TARGET_RATE {
+        0.0
+    } else {
+        resampler.output_delay() as f64 * 1000.0 / TARGET_RATE as f64
+            + block_wait_samples as f64 * 1000.0 / sample_rate as f64
+    };
+    let params_profile = config.active_params_profile();
+
+    let real_time_factor = measured_real_time_factor.unwrap_or_else(|| {
+        match config
+            .active_model_path
+            .as_deref()
+            .and_then(WhisperModel::from_path)
+        {
+            Some(model) => {
+                model.parameter_count() as f64 / 1e9
+                    * GUESSED_REAL_TIME_FACTOR_PER_BILLION_PARAMETERS
+            }
+            None => GUESSED_CUSTOM_MODEL_REAL_TIME_FACTOR,
+        }
+    });
+    // the transcriber caps the lookahead the same way
+    let lookahead_ms = match window_anchor_for(config) {
+        WindowAnchor::Trailing => 0,
+        WindowAnchor::Centered { lookahead_ms } => {
+            lookahead_ms.min(params_profile.context_length_ms / 2)
+        }
+    };
+
+    Some(LatencyEstimate {
+        capture_buffer_ms: target_buffer_size as f64 * 1000.0 / sample_rate as f64,
+        resampler_ms,
+        decode_wait_ms: params_profile.repeat_run_ms as f64 / 2.0,
+        decode_ms: real_time_factor * params_profile.context_length_ms as f64,
+        is_decode_measured: measured_real_time_factor.is_some(),
+        lookahead_ms: lookahead_ms as f64,
+    })
+}
+
+/// Handles a latency estimate request (see
+/// [`subwin_bridge::MessageToBackend::LatencyEstimateRequest`]).
+pub async fn handle_latency_estimate_request(context: super::AppContextHandle) {
+    let (config, active_device) = {
+        let state = context.state.read().await;
+        (state.config.clone(), state.active_audio_device.clone())
+    };
+    let measured_real_time_factor = context
+        .last_pipeline_stats()
+        .map(|stats| stats.real_time_factor)
+        .filter(|real_time_factor| *real_time_factor > 0.0);
+
+    // querying the device's configs blocks on the audio host
+    let estimate = tokio::task::spawn_blocking(move || {
+        let mut device = HostInputDevice::from((*active_device).clone()?);
+        prefer_target_rate(&mut device, &config.audio_device_config);
+        apply_buffer_size_override(&mut device, &config.audio_device_config);
+        estimate_latency(&config, &device, measured_real_time_factor)
+    })
+    .await
+    .unwrap_or_else(|err| {
+        log::error!("Latency estimate has panicked: {err}");
+        None
+    });
+    context
+        .send(subwin_bridge::MessageFromBackend::LatencyEstimateResponse(
+            estimate,
+        ))
+        .await;
+}
+
+/// Handles an incoming status request (see
+/// [`subwin_bridge::MessageToBackend::StatusRequest`]).
+pub async fn handle_status_request(context: super::AppContextHandle) {
+    let response = {
+        let state = context.state.read().await;
+        let active_device = state
+            .active_audio_device
+            .as_ref()
+            .as_ref()
+            .map(|device| InputDevice {
+                id: state
+                    .config
+                    .audio_device_config
+                    .selected_device_id
+                    .clone()
+                    .unwrap_or_default(),
+                description: device
+                    .description()
+                    .map(|description| description.to_string())
+                    .unwrap_or_default(),
+                selected: true,
+            });
+
+        subwin_bridge::MessageFromBackend::StatusResponse {
+            running: state.active_session.is_some(),
+            active_model: state.config.active_model_path.clone(),
+            active_device,
+            uptime: state
+                .active_session
+                .as_ref()
+                .map(|session| session.started_at.elapsed()),
+        }
+    };
+    context.send(response).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(text: &str) -> CaptionSegment {
+        CaptionSegment {
+            start_milliseconds: 0,
+            end_milliseconds: 0,
+            text: text.to_string(),
+            speaker: None,
+            confidence: None,
+        }
+    }
+
+    #[test]
+    fn segments_to_text_trims_and_joins_with_single_spaces() {
+        let segments = [segment("  hello "), segment("world  ")];
+        assert_eq!(segments_to_text(&segments), "hello world");
+    }
+
+    #[test]
+    fn segments_to_text_drops_whitespace_only_segments() {
+        let segments = [
+            segment(" "),
+            segment("hello"),
+            segment("\t\n"),
+            segment("world"),
+        ];
+        assert_eq!(segments_to_text(&segments), "hello world");
+        assert_eq!(segments_to_text(&[segment("  ")]), "");
+        assert_eq!(segments_to_text(&[]), "");
+    }
+
+    #[test]
+    fn compose_caption_text_of_nothing_is_empty() {
+        assert_eq!(compose_caption_text(&[], &[]).text, "");
+        assert_eq!(
+            compose_caption_text(&[segment(" ")], &[segment("")]).text,
+            ""
+        );
+    }
+
+    #[test]
+    fn compose_caption_text_with_active_only() {
+        assert_eq!(
+            compose_caption_text(&[], &[segment(" active ")]).text,
+            "active"
+        );
+    }
+
+    #[test]
+    fn compose_caption_text_with_history_only() {
+        assert_eq!(
+            compose_caption_text(&[segment(" history ")], &[]).text,
+            "history"
+        );
+    }
+
+    #[test]
+    fn compose_caption_text_joins_history_and_active() {
+        let history = [segment("first "), segment(" second")];
+        let active = [segment(" "), segment(" third ")];
+        assert_eq!(
+            compose_caption_text(&history, &active).text,
+            "first second third"
+        );
+    }
+
+    fn uncertain_segment(text: &str) -> CaptionSegment {
+        CaptionSegment {
+            confidence: Some(0.1),
+            ..segment(text)
+        }
+    }
+
+    #[test]
+    fn compose_caption_text_marks_uncertain_segments() {
+        let history = [segment("sure"), uncertain_segment(" maybe ")];
+        let active = [segment("sure"), uncertain_segment("перец")];
+        let caption = compose_caption_text(&history, &active);
+        assert_eq!(caption.text, "sure maybe sure перец");
+        let uncertain: Vec<&str> = caption
+            .uncertain_ranges
+            .iter()
+            .map(|range| &caption.text[range.clone()])
+            .collect();
+        assert_eq!(uncertain, ["maybe", "перец"]);
+    }
+
+    #[test]
+    fn truncate_caption_start_moves_uncertain_ranges() {
+        let caption = compose_caption_text(
+            &[uncertain_segment("first words"), segment("second")],
+            &[uncertain_segment("third")],
+        );
+        let truncated = truncate_caption_start(caption, 19);
+        assert_eq!(truncated.text, "…words second third");
+        let uncertain: Vec<&str> = truncated
+            .uncertain_ranges
+            .iter()
+            .map(|range| &truncated.text[range.clone()])
+            .collect();
+        assert_eq!(uncertain, ["words", "third"]);
+    }
+
+    /// Fails decodes until the next recovery action other than retrying.
+    fn fail_until_action(recovery: &mut DecodeRecovery) -> RecoveryAction {
+        for _ in 1..MAX_CONSECUTIVE_DECODE_FAILURES {
+            assert_eq!(recovery.on_failure(), RecoveryAction::Retry);
+        }
+        recovery.on_failure()
+    }
+
+    #[test]
+    fn recovery_escalates_to_the_cpu_and_gives_up() {
+        let mut recovery = DecodeRecovery::default();
+        assert_eq!(
+            fail_until_action(&mut recovery),
+            RecoveryAction::Reload {
+                attempt: 1,
+                use_gpu: true
+            }
+        );
+        assert_eq!(
+            fail_until_action(&mut recovery),
+            RecoveryAction::Reload {
+                attempt: 2,
+                use_gpu: false
+            }
+        );
+        assert_eq!(fail_until_action(&mut recovery), RecoveryAction::GiveUp);
+    }
+
+    #[test]
+    fn successful_decode_starts_the_recovery_over() {
+        let mut recovery = DecodeRecovery::default();
+        fail_until_action(&mut recovery);
+        recovery.on_success();
+        assert_eq!(
+            fail_until_action(&mut recovery),
+            RecoveryAction::Reload {
+                attempt: 1,
+                use_gpu: true
+            }
+        );
+
+        // a success in between failures keeps them from adding up
+        recovery.on_success();
+        for _ in 0..3 {
+            for _ in 1..MAX_CONSECUTIVE_DECODE_FAILURES {
+                assert_eq!(recovery.on_failure(), RecoveryAction::Retry);
+            }
+            recovery.on_success();
+        }
+    }
+
+    #[test]
+    fn model_stays_on_the_cpu_after_recovering() {
+        let mut recovery = DecodeRecovery::default();
+        fail_until_action(&mut recovery);
+        fail_until_action(&mut recovery);
+        recovery.on_success();
+
+        assert_eq!(
+            fail_until_action(&mut recovery),
+            RecoveryAction::Reload {
+                attempt: 1,
+                use_gpu: false
+            }
+        );
+        recovery.on_success();
+        assert_eq!(
+            fail_until_action(&mut recovery),
+            RecoveryAction::Reload {
+                attempt: 1,
+                use_gpu: false
+            }
+        );
+        assert_eq!(
+            fail_until_action(&mut recovery),
+            RecoveryAction::Reload {
+                attempt: 2,
+                use_gpu: false
+            }
+        );
+        assert_eq!(fail_until_action(&mut recovery), RecoveryAction::GiveUp);
+    }
+
+    #[test]
+    fn producers_are_lent_once_at_a_time() {
+        let owner = Arc::new(Mutex::new(vec![1, 2]));
+        let lease = LentProducers::lend(&owner).unwrap();
+        assert!(LentProducers::lend(&owner).is_err());
+
+        drop(lease);
+        assert_eq!(*owner.lock().unwrap(), vec![1, 2]);
+        assert!(LentProducers::lend(&owner).is_ok());
+    }
+
+    #[test]
+    fn ring_buffer_fits_a_callback_on_top_of_the_headroom() {
+        let headroom = milliseconds_to_samples(RING_BUFFER_HEADROOM_MS, TARGET_RATE);
+        assert_eq!(
+            ring_buffer_capacity(TARGET_RATE, 512, ResamplingQuality::Balanced),
+            headroom + 512
+        );
+
+        for quality in [
+            ResamplingQuality::Fast,
+            ResamplingQuality::Balanced,
+            ResamplingQuality::HighQuality,
+        ] {
+            let mut resampler = StreamingResampler::<f32>::new(48_000, TARGET_RATE, 480, quality)
+                .expect("failed to build a resampler");
+            resampler.set_skip_output_delay(false);
+            let capacity = ring_buffer_capacity(48_000, 480, quality);
+            // the largest callback comes with a nearly full queue
+            let mut largest_callback = 0;
+            for _ in 0..32 {
+                let written = resampler
+                    .process_callback(&[0.0; 480], &mut |_| {})
+                    .expect("failed to resample");
+                largest_callback = largest_callback.max(written);
+            }
+            assert!(largest_callback > 0);
+            assert!(capacity >= headroom + largest_callback, "{quality:?}");
+        }
+    }
+
+    #[test]
+    fn adaptive_tail_is_lengthened_by_two_inferences() {
+        let config = StabilizerConfig {
+            tail_ms: 1500,
+            adaptive: true,
+            min_tail_ms: 1000,
+            max_tail_ms: 5000,
+        };
+        assert_eq!(adaptive_tail_ms(&config, 0.0), 1500);
+        assert_eq!(adaptive_tail_ms(&config, 400.0), 2300);
+        assert_eq!(adaptive_tail_ms(&config, 1749.6), 4999);
+    }
+
+    #[test]
+    fn adaptive_tail_is_clamped_to_its_bounds() {
+        let config = StabilizerConfig {
+            tail_ms: 500,
+            adaptive: true,
+            min_tail_ms: 1000,
+            max_tail_ms: 3000,
+        };
+        assert_eq!(adaptive_tail_ms(&config, 100.0), 1000);
+        assert_eq!(adaptive_tail_ms(&config, 1250.0), 3000);
+        assert_eq!(adaptive_tail_ms(&config, 10_000.0), 3000);
+
+        // a lower bound above the upper one gives way to the upper one
+        let inverted = StabilizerConfig {
+            min_tail_ms: 4000,
+            ..config
+        };
+        assert_eq!(adaptive_tail_ms(&inverted, 0.0), 3000);
+    }
 }
@@ -0,0 +1,156 @@
+use std::{
+    ops::Range,
+    time::{Duration, Instant},
+};
+
+use subwin_bridge::transcript::TranscriptLine;
+use subwin_speech::CaptionSegment;
+
+use super::{CaptionSink, CaptionUpdate};
+
+/// Converts `segments` into transcript lines, skipping empty ones.
+fn transcript_lines(segments: &[CaptionSegment]) -> Vec<TranscriptLine> {
+    segments
+        .iter()
+        .map(|segment| TranscriptLine {
+            start_ms: segment.start_milliseconds,
+            end_ms: segment.end_milliseconds,
+            text: super::labeled_text(segment),
+        })
+        .filter(|line| !line.text.is_empty())
+        .collect()
+}
+
+/// Sends the caption text to the frontend bridge, skipping updates that
+/// don't change it. Revisions arriving sooner than the minimum update
+/// interval after the last sent one are held back, and only the latest of
+/// them is sent once the interval passes. Updates with finalized segments,
+/// as well as the last held back one when the session ends, are sent right
+/// away, and so is an empty text once the captions are cleared. Updates the
+/// frontend isn't ready for are retried later.
+///
+/// Finalized segments are also sent as lines of the transcript, never
+/// dropped, followed by the last active ones when the session ends.
+pub struct BridgeSink {
+    context: crate::services::AppContextHandle,
+    /// Identifier of the session the updates come from.
+    session_id: u64,
+    min_update_interval: Duration,
+    last_sent_text: String,
+    last_sent_at: Option<Instant>,
+    /// Latest update not sent yet.
+    pending: Option<PendingUpdate>,
+    /// Active segments of the latest update, finalized when the session
+    /// ends.
+    last_active: Vec<CaptionSegment>,
+}
+
+/// Caption text held back by [`BridgeSink`].
+struct PendingUpdate {
+    text: String,
+    uncertain_ranges: Vec<Range<usize>>,
+    inference_ms: u128,
+}
+
+impl BridgeSink {
+    pub fn new(
+        context: crate::services::AppContextHandle,
+        session_id: u64,
+        min_update_interval: Duration,
+    ) -> Self {
+        Self {
+            context,
+            session_id,
+            min_update_interval,
+            last_sent_text: String::new(),
+            last_sent_at: None,
+            pending: None,
+            last_active: Vec::new(),
+        }
+    }
+
+    /// Sends `segments` as lines of the transcript, if there are any.
+    fn send_transcript_lines(&self, segments: &[CaptionSegment]) {
+        let lines = transcript_lines(segments);
+        if !lines.is_empty() {
+            self.context
+                .send_blocking(subwin_bridge::MessageFromBackend::TranscriptLines(lines));
+        }
+    }
+
+    /// Returns whether the minimum update interval has passed since the
+    /// last sent update.
+    fn is_update_due(&self) -> bool {
+        self.last_sent_at
+            .is_none_or(|sent_at| sent_at.elapsed() >= self.min_update_interval)
+    }
+
+    /// Sends the pending update, keeping it pending if the frontend isn't
+    /// ready for it.
+    fn flush(&mut self) {
+        let Some(update) = self.pending.take() else {
+            return;
+        };
+
+        let is_sent = self.context.send_update(
+            subwin_bridge::MessageFromBackend::TranscriptionStateUpdate {
+                session_id: self.session_id,
+                time_taken: update.inference_ms,
+                new_segment_text: update.text.clone(),
+                uncertain_ranges: update.uncertain_ranges.clone(),
+            },
+        );
+        if is_sent {
+            self.last_sent_text = update.text;
+            self.last_sent_at = Some(Instant::now());
+        } else {
+            self.pending = Some(update);
+        }
+    }
+}
+
+impl CaptionSink for BridgeSink {
+    fn emit(&mut self, update: &CaptionUpdate) {
+        self.send_transcript_lines(update.finalized);
+        self.last_active = update.active.to_vec();
+
+        if update.text.is_empty() {
+            return;
+        }
+        if update.text == self.last_sent_text {
+            // a held back revision has been reverted
+            self.pending = None;
+            return;
+        }
+
+        self.pending = Some(PendingUpdate {
+            text: update.text.to_string(),
+            uncertain_ranges: update.uncertain_ranges.to_vec(),
+            inference_ms: update.inference_ms,
+        });
+        if !update.finalized.is_empty() || self.is_update_due() {
+            self.flush();
+        }
+    }
+
+    fn poll(&mut self) {
+        if self.pending.is_some() && self.is_update_due() {
+            self.flush();
+        }
+    }
+
+    fn clear(&mut self) {
+        self.pending = Some(PendingUpdate {
+            text: String::new(),
+            uncertain_ranges: Vec::new(),
+            inference_ms: 0,
+        });
+        self.flush();
+    }
+
+    fn finish(&mut self) {
+        self.flush();
+        let last_active = std::mem::take(&mut self.last_active);
+        self.send_transcript_lines(&last_active);
+    }
+}
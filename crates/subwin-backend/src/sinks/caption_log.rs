@@ -0,0 +1,180 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+    time::Instant,
+};
+
+use serde::Serialize;
+use subwin_speech::CaptionSegment;
+
+use super::{CaptionSink, CaptionUpdate};
+
+/// A segment, as it's logged.
+#[derive(Serialize)]
+pub(super) struct SegmentRecord<'a> {
+    start_ms: i64,
+    end_ms: i64,
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    speaker: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<f32>,
+}
+
+impl<'a> From<&'a CaptionSegment> for SegmentRecord<'a> {
+    fn from(segment: &'a CaptionSegment) -> Self {
+        Self {
+            start_ms: segment.start_milliseconds,
+            end_ms: segment.end_milliseconds,
+            text: &segment.text,
+            speaker: segment.speaker,
+            confidence: segment.confidence,
+        }
+    }
+}
+
+/// A line of the log, also sent by [`super::CaptionSocketSink`]. `at_ms` is
+/// the time since the session has started.
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub(super) enum Record<'a> {
+    Update {
+        at_ms: u128,
+        window_ms: Option<(i64, i64)>,
+        inference_ms: u128,
+        finalized: Vec<SegmentRecord<'a>>,
+        active: Vec<SegmentRecord<'a>>,
+        text: &'a str,
+    },
+    Clear {
+        at_ms: u128,
+    },
+    Finish {
+        at_ms: u128,
+    },
+}
+
+/// Logs every caption change of a session as a line of JSON, so sessions
+/// can be replayed offline, e.g. to tune the stabilizer.
+pub struct CaptionLogSink {
+    writer: BufWriter<File>,
+    started_at: Instant,
+    /// Whether a write has failed, so a failing log is only reported once.
+    is_failing: bool,
+}
+
+impl<'a> Record<'a> {
+    /// Builds the record of `update`, made `at_ms` after the session's start.
+    pub(super) fn update(at_ms: u128, update: &CaptionUpdate<'a>) -> Self {
+        Record::Update {
+            at_ms,
+            window_ms: update.window_ms,
+            inference_ms: update.inference_ms,
+            finalized: update.finalized.iter().map(SegmentRecord::from).collect(),
+            active: update.active.iter().map(SegmentRecord::from).collect(),
+            text: update.text,
+        }
+    }
+}
+
+impl CaptionLogSink {
+    /// Creates the log at `path`, replacing an existing file, for the
+    /// session that has started at `started_at`.
+    pub fn create(path: &Path, started_at: Instant) -> std::io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            started_at,
+            is_failing: false,
+        })
+    }
+
+    fn elapsed_ms(&self) -> u128 {
+        self.started_at.elapsed().as_millis()
+    }
+
+    fn write(&mut self, record: &Record) {
+        let result = serde_json::to_writer(&mut self.writer, record)
+            .map_err(std::io::Error::from)
+            .and_then(|()| self.writer.write_all(b"\n"));
+        if let Err(err) = result {
+            if !self.is_failing {
+                log::warn!("Failed to write to the caption log: {err}");
+            }
+            self.is_failing = true;
+        }
+    }
+}
+
+impl CaptionSink for CaptionLogSink {
+    fn emit(&mut self, update: &CaptionUpdate) {
+        let record = Record::update(self.elapsed_ms(), update);
+        self.write(&record);
+    }
+
+    fn clear(&mut self) {
+        let record = Record::Clear {
+            at_ms: self.elapsed_ms(),
+        };
+        self.write(&record);
+    }
+
+    fn finish(&mut self) {
+        let record = Record::Finish {
+            at_ms: self.elapsed_ms(),
+        };
+        self.write(&record);
+        if let Err(err) = self.writer.flush() {
+            log::warn!("Failed to flush the caption log: {err}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_are_serialized_as_json_lines() {
+        let finalized = [CaptionSegment {
+            start_milliseconds: 1_200,
+            end_milliseconds: 2_400,
+            text: "Привет".to_string(),
+            speaker: None,
+            confidence: Some(0.5),
+        }];
+        let active = [CaptionSegment {
+            start_milliseconds: 2_400,
+            end_milliseconds: 3_000,
+            text: " мир".to_string(),
+            speaker: Some(1),
+            confidence: None,
+        }];
+        let update = CaptionUpdate {
+            finalized: &finalized,
+            active: &active,
+            text: "Привет мир",
+            uncertain_ranges: &[],
+            inference_ms: 180,
+            window_ms: Some((0, 3_000)),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&Record::update(3_100, &update)).unwrap(),
+            concat!(
+                r#"{"event":"update","at_ms":3100,"window_ms":[0,3000],"inference_ms":180,"#,
+                r#""finalized":[{"start_ms":1200,"end_ms":2400,"text":"Привет","confidence":0.5}],"#,
+                r#""active":[{"start_ms":2400,"end_ms":3000,"text":" мир","speaker":1}],"#,
+                r#""text":"Привет мир"}"#,
+            )
+        );
+        assert_eq!(
+            serde_json::to_string(&Record::Clear { at_ms: 4_000 }).unwrap(),
+            r#"{"event":"clear","at_ms":4000}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Record::Finish { at_ms: 5_000 }).unwrap(),
+            r#"{"event":"finish","at_ms":5000}"#
+        );
+    }
+}
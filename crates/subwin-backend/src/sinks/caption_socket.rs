@@ -0,0 +1,231 @@
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Instant,
+};
+
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    sync::broadcast::{self, error::RecvError},
+};
+
+use super::{CaptionSink, CaptionUpdate, caption_log::Record};
+
+/// Number of lines kept for a client that doesn't read them fast enough.
+/// Older lines are dropped for it beyond that.
+const CLIENT_BUFFER_LINES: usize = 64;
+
+/// Streams caption changes as lines of JSON to the clients of a local
+/// socket: a Unix domain socket, or a named pipe on Windows. Meant for
+/// status bars, stream deck plugins and custom overlays.
+///
+/// Lines are the records of [`super::CaptionLogSink`], see the README for
+/// the protocol. Clients may connect and disconnect at any time, and only
+/// receive the lines sent while they're connected. The worker never waits
+/// for them: lines are handed over to the tasks serving the clients, and a
+/// client falling behind misses lines instead.
+pub struct CaptionSocketSink {
+    lines: broadcast::Sender<Arc<str>>,
+    started_at: Instant,
+}
+
+impl CaptionSocketSink {
+    /// Starts listening on the socket at `path`, replacing a socket left
+    /// there by a previous session, for the session that has started at
+    /// `started_at`. The socket is served until the sink is dropped.
+    pub fn bind(
+        context: &crate::services::AppContextHandle,
+        path: &Path,
+        started_at: Instant,
+    ) -> std::io::Result<Self> {
+        let server = Server::bind(path)?;
+        Ok(Self::serving(context, started_at, |context, lines| {
+            serve(context, server, lines)
+        }))
+    }
+
+    /// Creates a sink for the session that has started at `started_at`,
+    /// whose lines are served to the clients by the task `serve` builds. The
+    /// task should end once the lines are closed.
+    pub(super) fn serving<F>(
+        context: &crate::services::AppContextHandle,
+        started_at: Instant,
+        serve: impl FnOnce(crate::services::AppContextHandle, broadcast::Receiver<Arc<str>>) -> F,
+    ) -> Self
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let (lines, lines_rx) = broadcast::channel(CLIENT_BUFFER_LINES);
+        context.spawn(serve(context.clone(), lines_rx));
+
+        Self { lines, started_at }
+    }
+
+    fn send(&self, record: &Record) {
+        let mut line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(err) => {
+                log::warn!("Failed to serialize a caption record: {err}");
+                return;
+            }
+        };
+        line.push('\n');
+        // fails when no client is connected, which is fine
+        let _ = self.lines.send(line.into());
+    }
+
+    fn elapsed_ms(&self) -> u128 {
+        self.started_at.elapsed().as_millis()
+    }
+}
+
+impl CaptionSink for CaptionSocketSink {
+    fn emit(&mut self, update: &CaptionUpdate) {
+        self.send(&Record::update(self.elapsed_ms(), update));
+    }
+
+    fn clear(&mut self) {
+        self.send(&Record::Clear {
+            at_ms: self.elapsed_ms(),
+        });
+    }
+
+    fn finish(&mut self) {
+        self.send(&Record::Finish {
+            at_ms: self.elapsed_ms(),
+        });
+    }
+}
+
+/// Listening end of the socket.
+#[cfg(unix)]
+struct Server {
+    listener: tokio::net::UnixListener,
+    path: PathBuf,
+    /// Device and inode of the socket file, to tell it apart from a socket
+    /// bound at the same path by a later session.
+    file_id: Option<(u64, u64)>,
+}
+
+/// Returns the device and the inode of the file at `path`, without
+/// following symlinks.
+#[cfg(unix)]
+fn file_id(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    std::fs::symlink_metadata(path)
+        .ok()
+        .map(|metadata| (metadata.dev(), metadata.ino()))
+}
+
+#[cfg(unix)]
+impl Server {
+    fn bind(path: &Path) -> std::io::Result<Self> {
+        use std::os::unix::fs::FileTypeExt;
+
+        // only sockets are replaced, never a file chosen by mistake
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_socket()) {
+            std::fs::remove_file(path)?;
+        }
+        let listener = tokio::net::UnixListener::bind(path)?;
+        Ok(Self {
+            listener,
+            path: path.to_path_buf(),
+            file_id: file_id(path),
+        })
+    }
+
+    /// Waits for the next client.
+    async fn accept(&mut self) -> std::io::Result<impl AsyncWrite + Unpin + Send + 'static> {
+        let (stream, _) = self.listener.accept().await?;
+        Ok(stream)
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Server {
+    fn drop(&mut self) {
+        // a session started meanwhile may have replaced the socket already
+        if self.file_id.is_some() && file_id(&self.path) == self.file_id {
+            let _ = std::fs::remove_file(&self.path);
+        }
+    }
+}
+
+/// Listening end of the named pipe. A pipe instance serves a single client,
+/// so a new one is created for the next client once one connects.
+#[cfg(windows)]
+struct Server {
+    pipe: tokio::net::windows::named_pipe::NamedPipeServer,
+    path: PathBuf,
+}
+
+#[cfg(windows)]
+impl Server {
+    fn bind(path: &Path) -> std::io::Result<Self> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        Ok(Self {
+            pipe: ServerOptions::new()
+                .first_pipe_instance(true)
+                .create(path)?,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Waits for the next client.
+    async fn accept(&mut self) -> std::io::Result<impl AsyncWrite + Unpin + Send + 'static> {
+        use tokio::net::windows::named_pipe::ServerOptions;
+
+        self.pipe.connect().await?;
+        let next_pipe = ServerOptions::new().create(&self.path)?;
+        Ok(std::mem::replace(&mut self.pipe, next_pipe))
+    }
+}
+
+/// Accepts clients of `server` until the sink sending `lines` is dropped,
+/// serving each of them from a task of its own.
+async fn serve(
+    context: crate::services::AppContextHandle,
+    mut server: Server,
+    mut lines: broadcast::Receiver<Arc<str>>,
+) {
+    loop {
+        let client = tokio::select! {
+            client = server.accept() => client,
+            line = lines.recv() => match line {
+                Err(RecvError::Closed) => break,
+                // lines are only sent to the clients
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            },
+        };
+        match client {
+            Ok(client) => {
+                log::info!("A client has connected to the caption socket");
+                context.spawn(send_lines(client, lines.resubscribe()));
+            }
+            Err(err) => {
+                log::warn!("Failed to accept a caption socket client, closing the socket: {err}");
+                break;
+            }
+        }
+    }
+}
+
+/// Writes `lines` to `client` until it disconnects or the sink is dropped.
+async fn send_lines(mut client: impl AsyncWrite + Unpin, mut lines: broadcast::Receiver<Arc<str>>) {
+    loop {
+        match lines.recv().await {
+            Ok(line) => {
+                if client.write_all(line.as_bytes()).await.is_err() {
+                    log::info!("A client has disconnected from the caption socket");
+                    break;
+                }
+            }
+            Err(RecvError::Lagged(missed)) => {
+                log::debug!("A caption socket client has missed {missed} line(-s)");
+            }
+            Err(RecvError::Closed) => break,
+        }
+    }
+}
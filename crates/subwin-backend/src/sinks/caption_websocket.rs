@@ -0,0 +1,122 @@
+use std::{net::SocketAddr, sync::Arc, time::Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::tungstenite::Message;
+
+use super::{CaptionSink, CaptionSocketSink, CaptionUpdate};
+
+/// Streams caption changes to WebSocket clients, e.g. browser sources of
+/// streaming software or web overlays.
+///
+/// Every caption change is sent as a text message holding a record of
+/// [`super::CaptionLogSink`], like the lines of [`CaptionSocketSink`], and
+/// clients falling behind miss messages the same way.
+pub struct CaptionWebSocketSink(CaptionSocketSink);
+
+impl CaptionWebSocketSink {
+    /// Starts listening for WebSocket clients on `address` for the session
+    /// that has started at `started_at`. Clients are served until the sink
+    /// is dropped.
+    pub async fn bind(
+        context: &crate::services::AppContextHandle,
+        address: SocketAddr,
+        started_at: Instant,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(address).await?;
+        Ok(Self(CaptionSocketSink::serving(
+            context,
+            started_at,
+            |context, lines| serve(context, listener, lines),
+        )))
+    }
+}
+
+impl CaptionSink for CaptionWebSocketSink {
+    fn emit(&mut self, update: &CaptionUpdate) {
+        self.0.emit(update);
+    }
+
+    fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    fn finish(&mut self) {
+        self.0.finish();
+    }
+}
+
+/// Accepts clients of `listener` until the sink sending `lines` is dropped,
+/// serving each of them from a task of its own.
+async fn serve(
+    context: crate::services::AppContextHandle,
+    listener: TcpListener,
+    mut lines: broadcast::Receiver<Arc<str>>,
+) {
+    loop {
+        let client = tokio::select! {
+            client = listener.accept() => client,
+            line = lines.recv() => match line {
+                Err(RecvError::Closed) => break,
+                // lines are only sent to the clients
+                Ok(_) | Err(RecvError::Lagged(_)) => continue,
+            },
+        };
+        match client {
+            Ok((stream, address)) => {
+                log::info!("A client has connected to the caption WebSocket from {address}");
+                context.spawn(send_messages(stream, lines.resubscribe()));
+            }
+            Err(err) => {
+                log::warn!(
+                    "Failed to accept a caption WebSocket client, closing the WebSocket: {err}"
+                );
+                break;
+            }
+        }
+    }
+}
+
+/// Completes the WebSocket handshake with `stream`, then sends `lines` to it
+/// until it disconnects or the sink is dropped.
+async fn send_messages(stream: TcpStream, mut lines: broadcast::Receiver<Arc<str>>) {
+    let mut socket = match tokio_tungstenite::accept_async(stream).await {
+        Ok(socket) => socket,
+        Err(err) => {
+            log::info!("Failed to accept a caption WebSocket client: {err}");
+            return;
+        }
+    };
+
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Ok(line) => {
+                    let message = Message::text(line.trim_end());
+                    if socket.send(message).await.is_err() {
+                        log::info!("A client has disconnected from the caption WebSocket");
+                        break;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    log::debug!("A caption WebSocket client has missed {missed} message(-s)");
+                }
+                Err(RecvError::Closed) => {
+                    let _ = socket.close(None).await;
+                    break;
+                }
+            },
+            // reading answers pings, and notices the client closing the connection
+            message = socket.next() => match message {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => {
+                    log::info!("A client has disconnected from the caption WebSocket");
+                    break;
+                }
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
@@ -0,0 +1,341 @@
+//! Caption output stage of the transcription worker.
+//!
+//! Every caption change is passed to each active [`CaptionSink`]: the
+//! frontend bridge is always one of them, while subtitle files, per-session
+//! transcripts, a text file for OBS, a JSON log of the caption updates, a
+//! local socket and a WebSocket streaming them and standard output are
+//! enabled in the config
+//! (see [`subwin_bridge::config::CaptionSinksConfig`]). Standard output is
+//! always enabled in headless mode. Lines of plain-text transcripts and of
+//! the standard output may be prefixed with the time of their segment.
+
+use std::{
+    ops::Range,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use subwin_bridge::{
+    config::{SubtitleFormat, TRANSCRIPT_TIMESTAMP_PLACEHOLDER},
+    notification::NotificationType,
+};
+use subwin_speech::CaptionSegment;
+
+mod bridge;
+mod caption_log;
+mod caption_socket;
+mod caption_websocket;
+mod obs_text_file;
+mod stdout;
+mod subtitle_file;
+
+pub use bridge::BridgeSink;
+pub use caption_log::CaptionLogSink;
+pub use caption_socket::CaptionSocketSink;
+pub use caption_websocket::CaptionWebSocketSink;
+pub use obs_text_file::ObsTextFileSink;
+pub use stdout::StdoutSink;
+pub use subtitle_file::SubtitleFileSink;
+
+/// Name of the directory in the cache, where transcripts are saved to when
+/// no other directory has been chosen.
+const TRANSCRIPTS_DIRECTORY: &str = "transcripts";
+
+/// Name of the directory in the cache, where caption logs are saved to.
+const CAPTION_LOGS_DIRECTORY: &str = "caption-logs";
+
+/// Returns the label captions of `speaker` are prefixed with.
+pub fn speaker_label(speaker: u16) -> String {
+    format!("Спикер {}", speaker + 1)
+}
+
+/// Returns the trimmed text of `segment`, prefixed with its speaker's label
+/// if it has one.
+pub fn labeled_text(segment: &CaptionSegment) -> String {
+    let text = segment.text.trim();
+    match segment.speaker {
+        Some(speaker) if !text.is_empty() => format!("{}: {text}", speaker_label(speaker)),
+        _ => text.to_string(),
+    }
+}
+
+/// A change of the captions, produced by one transcription run.
+#[derive(Debug, Clone, Copy)]
+pub struct CaptionUpdate<'a> {
+    /// Segments that have been finalized by this run, in order. They won't
+    /// be reported again.
+    pub finalized: &'a [CaptionSegment],
+    /// Segments that are still being recognized and may change. When
+    /// channels are transcribed separately, these are the active segments
+    /// of every channel.
+    pub active: &'a [CaptionSegment],
+    /// Caption text to display: the history followed by the active segments,
+    /// on a labeled line per speaker when channels are transcribed
+    /// separately.
+    pub text: &'a str,
+    /// Byte ranges of `text` the model is unsure about, in order.
+    pub uncertain_ranges: &'a [Range<usize>],
+    /// Duration of the last inference, in milliseconds.
+    pub inference_ms: u128,
+    /// Start and end of the audio window the update has been decoded from,
+    /// in milliseconds since the start of the session; `None` if it hasn't
+    /// been produced by a decode, e.g. when push-to-talk is released.
+    pub window_ms: Option<(i64, i64)>,
+}
+
+/// Destination of the transcribed captions.
+///
+/// Sinks are driven from the blocking transcription worker, so they may
+/// block on I/O, but should keep it short to not delay the next run.
+pub trait CaptionSink: Send {
+    /// Handles a change of the captions.
+    fn emit(&mut self, update: &CaptionUpdate);
+
+    /// Called regularly by the worker, also between caption changes. Sinks
+    /// holding updates back should output them here once they're due.
+    fn poll(&mut self) {}
+
+    /// Called once the displayed captions have been cleared after silence
+    /// (see [`subwin_bridge::config::Config::clear_captions_after_silence_ms`]).
+    /// Later updates no longer contain the cleared history.
+    fn clear(&mut self) {}
+
+    /// Called once the session ends. Sinks that only output finalized
+    /// segments should output the last active ones here.
+    fn finish(&mut self) {}
+}
+
+/// Builds the file name of a transcript, or of another file kept per
+/// session, from `template` and `extension`, for a session starting now.
+fn transcript_file_name(template: &str, extension: &str) -> String {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let template = match template.trim() {
+        "" => TRANSCRIPT_TIMESTAMP_PLACEHOLDER,
+        template => template,
+    };
+    // the template must not escape the output directory
+    let name = template
+        .replace(TRANSCRIPT_TIMESTAMP_PLACEHOLDER, &timestamp.to_string())
+        .replace(['/', '\\'], "_");
+    format!("{name}.{extension}")
+}
+
+/// Creates a transcript file named `file_name` in `directory`, creating the
+/// directory if needed.
+fn create_transcript_sink(
+    directory: &Path,
+    file_name: &str,
+    format: SubtitleFormat,
+    line_prefix_template: Option<String>,
+) -> std::io::Result<SubtitleFileSink> {
+    std::fs::create_dir_all(directory)?;
+    SubtitleFileSink::create(&directory.join(file_name), format, line_prefix_template)
+}
+
+/// Opens the file the session's transcript is saved to. If the chosen
+/// directory can't be written to, the transcript is saved into the cache
+/// with a notification.
+async fn open_transcript_sink(
+    context: &crate::services::AppContextHandle,
+) -> Option<SubtitleFileSink> {
+    let (sinks_config, output_dir, cache_path) = {
+        let state = context.state.read().await;
+        (
+            state.config.caption_sinks.clone(),
+            state.config.transcript_output_dir.clone(),
+            state.cache_path.clone(),
+        )
+    };
+    let format = sinks_config.subtitle_format;
+    let line_prefix_template = sinks_config.line_prefix_template().map(str::to_string);
+    let file_name =
+        transcript_file_name(&sinks_config.transcript_file_template, format.extension());
+    let fallback_dir = cache_path.join(TRANSCRIPTS_DIRECTORY);
+
+    if let Some(output_dir) = output_dir {
+        match create_transcript_sink(
+            &output_dir,
+            &file_name,
+            format,
+            line_prefix_template.clone(),
+        ) {
+            Ok(sink) => {
+                log::info!("Saving the transcript to {:?}", output_dir.join(&file_name));
+                return Some(sink);
+            }
+            Err(err) => {
+                log::warn!(
+                    "Failed to create a transcript in {output_dir:?}, falling back to the cache: {err}"
+                );
+                context
+                    .send_notification(
+                        NotificationType::Warning,
+                        format!(
+                            "Не удалось сохранить субтитры в папку {}: {err}. Они будут сохранены в папку кэша.",
+                            output_dir.display(),
+                        ),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    match create_transcript_sink(&fallback_dir, &file_name, format, line_prefix_template) {
+        Ok(sink) => {
+            log::info!(
+                "Saving the transcript to {:?}",
+                fallback_dir.join(&file_name)
+            );
+            Some(sink)
+        }
+        Err(err) => {
+            log::error!("Failed to create a transcript in {fallback_dir:?}: {err}");
+            context
+                .send_notification(
+                    NotificationType::Warning,
+                    format!("Не удалось создать файл субтитров: {err}"),
+                )
+                .await;
+            None
+        }
+    }
+}
+
+/// Opens the caption sinks enabled in the config for the session with
+/// `session_id`, which has started at `started_at`. Sinks that fail to open
+/// are skipped with a notification, the bridge sink is always included.
+pub async fn open_caption_sinks(
+    context: &crate::services::AppContextHandle,
+    session_id: u64,
+    started_at: std::time::Instant,
+) -> Vec<Box<dyn CaptionSink>> {
+    let (sinks_config, is_headless, min_update_interval_ms, cache_path) = {
+        let state = context.state.read().await;
+        (
+            state.config.caption_sinks.clone(),
+            state.is_headless,
+            state.config.captions_config.min_update_interval_ms,
+            state.cache_path.clone(),
+        )
+    };
+
+    let line_prefix_template = sinks_config.line_prefix_template().map(str::to_string);
+    let mut sinks: Vec<Box<dyn CaptionSink>> = vec![Box::new(BridgeSink::new(
+        context.clone(),
+        session_id,
+        Duration::from_millis(min_update_interval_ms as u64),
+    ))];
+
+    if let Some(path) = sinks_config.subtitle_file {
+        match SubtitleFileSink::create(
+            &path,
+            sinks_config.subtitle_format,
+            line_prefix_template.clone(),
+        ) {
+            Ok(sink) => sinks.push(Box::new(sink)),
+            Err(err) => {
+                log::error!("Failed to create subtitle file at {path:?}: {err}");
+                context
+                    .send_notification(
+                        NotificationType::Warning,
+                        format!("Не удалось создать файл субтитров: {err}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    if let Some(path) = sinks_config.obs_caption_file {
+        match ObsTextFileSink::create(&path) {
+            Ok(sink) => sinks.push(Box::new(sink)),
+            Err(err) => {
+                log::error!("Failed to create the OBS caption file at {path:?}: {err}");
+                context
+                    .send_notification(
+                        NotificationType::Warning,
+                        format!("Не удалось создать файл субтитров для OBS: {err}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    if sinks_config.save_transcripts
+        && let Some(sink) = open_transcript_sink(context).await
+    {
+        sinks.push(Box::new(sink));
+    }
+
+    if sinks_config.log_caption_updates {
+        let directory = cache_path.join(CAPTION_LOGS_DIRECTORY);
+        let file_name = transcript_file_name(
+            &format!("captions-{TRANSCRIPT_TIMESTAMP_PLACEHOLDER}"),
+            "jsonl",
+        );
+        match std::fs::create_dir_all(&directory)
+            .and_then(|()| CaptionLogSink::create(&directory.join(&file_name), started_at))
+        {
+            Ok(sink) => {
+                log::info!(
+                    "Logging caption updates to {:?}",
+                    directory.join(&file_name)
+                );
+                sinks.push(Box::new(sink));
+            }
+            Err(err) => {
+                log::error!("Failed to create a caption log in {directory:?}: {err}");
+                context
+                    .send_notification(
+                        NotificationType::Warning,
+                        format!("Не удалось создать журнал субтитров: {err}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    if let Some(path) = sinks_config.caption_socket_path {
+        match CaptionSocketSink::bind(context, &path, started_at) {
+            Ok(sink) => {
+                log::info!("Streaming caption updates to the socket at {path:?}");
+                sinks.push(Box::new(sink));
+            }
+            Err(err) => {
+                log::error!("Failed to open the caption socket at {path:?}: {err}");
+                context
+                    .send_notification(
+                        NotificationType::Warning,
+                        format!("Не удалось открыть сокет для субтитров: {err}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    if let Some(address) = sinks_config.caption_websocket_address {
+        match CaptionWebSocketSink::bind(context, address, started_at).await {
+            Ok(sink) => {
+                log::info!("Streaming caption updates to WebSocket clients on {address}");
+                sinks.push(Box::new(sink));
+            }
+            Err(err) => {
+                log::error!("Failed to open the caption WebSocket on {address}: {err}");
+                context
+                    .send_notification(
+                        NotificationType::Warning,
+                        format!("Не удалось открыть WebSocket для субтитров на {address}: {err}"),
+                    )
+                    .await;
+            }
+        }
+    }
+
+    if sinks_config.stdout || is_headless {
+        sinks.push(Box::new(StdoutSink::new(line_prefix_template)));
+    }
+
+    sinks
+}
@@ -0,0 +1,84 @@
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use super::{CaptionSink, CaptionUpdate};
+
+/// Keeps a text file containing the current caption text, for OBS text
+/// sources reading from a file. The file is rewritten on every change, and
+/// emptied once the captions are cleared or the session ends.
+///
+/// OBS polls the file, so it's replaced atomically: the text is written
+/// into a temporary file next to it, which is then renamed over it, and OBS
+/// never reads a half-written caption.
+pub struct ObsTextFileSink {
+    path: PathBuf,
+    temporary_path: PathBuf,
+    last_written_text: String,
+    /// Whether the last write has failed, so a failing file is only
+    /// reported once until it's written again.
+    is_failing: bool,
+}
+
+impl ObsTextFileSink {
+    /// Creates a sink writing to `path`, emptying the file right away, so
+    /// captions of a previous session aren't shown.
+    pub fn create(path: &Path) -> std::io::Result<Self> {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".tmp");
+        let mut sink = Self {
+            path: path.to_path_buf(),
+            temporary_path: path.with_file_name(file_name),
+            last_written_text: String::new(),
+            is_failing: false,
+        };
+        sink.replace_file("")?;
+        Ok(sink)
+    }
+
+    /// Replaces the file's content with `text`.
+    fn replace_file(&mut self, text: &str) -> std::io::Result<()> {
+        let mut file = std::fs::File::create(&self.temporary_path)?;
+        file.write_all(text.as_bytes())?;
+        file.sync_all()?;
+        drop(file);
+        std::fs::rename(&self.temporary_path, &self.path)
+    }
+
+    fn write(&mut self, text: &str) {
+        if text == self.last_written_text {
+            return;
+        }
+
+        match self.replace_file(text) {
+            Ok(()) => {
+                self.last_written_text = text.to_string();
+                self.is_failing = false;
+            }
+            Err(err) => {
+                if !self.is_failing {
+                    log::warn!("Failed to write the captions to {:?}: {err}", self.path);
+                }
+                self.is_failing = true;
+            }
+        }
+    }
+}
+
+impl CaptionSink for ObsTextFileSink {
+    fn emit(&mut self, update: &CaptionUpdate) {
+        if !update.text.is_empty() {
+            self.write(update.text);
+        }
+    }
+
+    fn clear(&mut self) {
+        self.write("");
+    }
+
+    fn finish(&mut self) {
+        self.write("");
+        let _ = std::fs::remove_file(&self.temporary_path);
+    }
+}
@@ -0,0 +1,56 @@
+use std::io::Write;
+
+use subwin_bridge::config::format_line_prefix;
+use subwin_speech::CaptionSegment;
+
+use super::{CaptionSink, CaptionUpdate};
+
+/// Prints finalized segments to the standard output, one per line, so
+/// captions can be piped into other tools.
+pub struct StdoutSink {
+    /// Template the lines are prefixed with, see
+    /// [`subwin_bridge::config::CaptionSinksConfig::timestamp_template`].
+    line_prefix_template: Option<String>,
+    /// Active segments of the last update, printed when the session ends.
+    pending: Vec<CaptionSegment>,
+}
+
+impl StdoutSink {
+    /// Creates a sink prefixing every line with `line_prefix_template`, if
+    /// any.
+    pub fn new(line_prefix_template: Option<String>) -> Self {
+        Self {
+            line_prefix_template,
+            pending: Vec::new(),
+        }
+    }
+
+    fn print(&self, segments: &[CaptionSegment]) {
+        let mut stdout = std::io::stdout().lock();
+        for segment in segments {
+            let text = super::labeled_text(segment);
+            if text.is_empty() {
+                continue;
+            }
+            let prefix = self
+                .line_prefix_template
+                .as_deref()
+                .map(|template| format_line_prefix(template, segment.start_milliseconds))
+                .unwrap_or_default();
+            let _ = writeln!(stdout, "{prefix}{text}");
+        }
+        let _ = stdout.flush();
+    }
+}
+
+impl CaptionSink for StdoutSink {
+    fn emit(&mut self, update: &CaptionUpdate) {
+        self.print(update.finalized);
+        self.pending = update.active.to_vec();
+    }
+
+    fn finish(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        self.print(&pending);
+    }
+}
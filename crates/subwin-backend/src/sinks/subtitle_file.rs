@@ -0,0 +1,114 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use subwin_bridge::config::{SubtitleFormat, format_line_prefix};
+use subwin_speech::CaptionSegment;
+
+use super::{CaptionSink, CaptionUpdate};
+
+/// Writes finalized segments into an SRT or WebVTT subtitle file, with
+/// timestamps relative to the start of the session, or into a plain-text
+/// transcript.
+pub struct SubtitleFileSink {
+    writer: BufWriter<File>,
+    format: SubtitleFormat,
+    /// Template the lines of a plain-text transcript are prefixed with, see
+    /// [`subwin_bridge::config::CaptionSinksConfig::timestamp_template`].
+    line_prefix_template: Option<String>,
+    /// Number of the next cue, starting from 1.
+    next_cue: usize,
+    /// Active segments of the last update, written when the session ends.
+    pending: Vec<CaptionSegment>,
+}
+
+/// Formats `milliseconds` as a subtitle timestamp, using `fraction_separator`
+/// between seconds and milliseconds (`,` for SRT and `.` for WebVTT).
+fn format_timestamp(milliseconds: i64, fraction_separator: char) -> String {
+    let milliseconds = milliseconds.max(0);
+    format!(
+        "{:02}:{:02}:{:02}{fraction_separator}{:03}",
+        milliseconds / 3_600_000,
+        milliseconds / 60_000 % 60,
+        milliseconds / 1000 % 60,
+        milliseconds % 1000,
+    )
+}
+
+impl SubtitleFileSink {
+    /// Creates (or truncates) the subtitle file at `path`. Lines of a
+    /// plain-text transcript are prefixed with `line_prefix_template`, if
+    /// any.
+    pub fn create(
+        path: &Path,
+        format: SubtitleFormat,
+        line_prefix_template: Option<String>,
+    ) -> std::io::Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        if format == SubtitleFormat::Vtt {
+            writer.write_all(b"WEBVTT\n\n")?;
+        }
+
+        Ok(Self {
+            writer,
+            format,
+            line_prefix_template,
+            next_cue: 1,
+            pending: Vec::new(),
+        })
+    }
+
+    fn write_segments(&mut self, segments: &[CaptionSegment]) -> std::io::Result<()> {
+        let fraction_separator = match self.format {
+            SubtitleFormat::Srt => ',',
+            SubtitleFormat::Vtt | SubtitleFormat::Text => '.',
+        };
+
+        for segment in segments {
+            let text = super::labeled_text(segment);
+            if text.is_empty() {
+                continue;
+            }
+
+            if self.format == SubtitleFormat::Text {
+                let prefix = self
+                    .line_prefix_template
+                    .as_deref()
+                    .map(|template| format_line_prefix(template, segment.start_milliseconds))
+                    .unwrap_or_default();
+                writeln!(self.writer, "{prefix}{text}")?;
+                continue;
+            }
+
+            writeln!(
+                self.writer,
+                "{}\n{} --> {}\n{text}\n",
+                self.next_cue,
+                format_timestamp(segment.start_milliseconds, fraction_separator),
+                format_timestamp(segment.end_milliseconds, fraction_separator),
+            )?;
+            self.next_cue += 1;
+        }
+
+        // keep the file readable while the session is still running
+        self.writer.flush()
+    }
+}
+
+impl CaptionSink for SubtitleFileSink {
+    fn emit(&mut self, update: &CaptionUpdate) {
+        if let Err(err) = self.write_segments(update.finalized) {
+            log::error!("Failed to write subtitles: {err}");
+        }
+        self.pending = update.active.to_vec();
+    }
+
+    fn finish(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        if let Err(err) = self.write_segments(&pending) {
+            log::error!("Failed to write subtitles: {err}");
+        }
+    }
+}
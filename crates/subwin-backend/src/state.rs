@@ -18,8 +18,105 @@ pub struct State {
     pub active_host: std::sync::Arc<cpal::Host>,
     /// Active CPAL audio device.
     pub active_audio_device: std::sync::Arc<Option<cpal::Device>>,
-    /// Active audio stream that keeps capture alive while transcription runs.
-    pub active_stream: Option<cpal::Stream>,
+    /// Active transcription session that keeps capture alive while
+    /// transcription runs.
+    pub active_session: Option<TranscriptionSession>,
+    /// Phase of the transcription session's lifecycle, guarding against
+    /// overlapping starts and stops.
+    pub session_phase: SessionPhase,
+    /// Whether a model benchmark is running. A session isn't started
+    /// meanwhile, as both would load the model and slow each other down.
+    pub is_benchmarking: bool,
+    /// Whether the backend runs without the GUI, printing captions to the
+    /// standard output instead.
+    pub is_headless: bool,
+}
+
+/// Phase of the transcription session's lifecycle.
+///
+/// Starting and stopping a session take a while and await in between, so
+/// requests arriving meanwhile, e.g. from the hotkey or a restart after a
+/// device change, are checked against the phase: a session is only started
+/// from [`SessionPhase::Idle`] or restarted from [`SessionPhase::Running`],
+/// and a stop requested while starting is carried out once the start ends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SessionPhase {
+    /// No session is running.
+    #[default]
+    Idle,
+    /// A session is being started.
+    Starting {
+        /// Whether a stop has been requested meanwhile.
+        is_stop_queued: bool,
+    },
+    /// A session is running.
+    Running,
+    /// The session is being stopped.
+    Stopping,
+}
+
+/// Opens a new audio stream feeding the session's worker.
+pub type StreamOpener =
+    Box<dyn FnMut() -> Result<cpal::Stream, subwin_bridge::audio::AudioCaptureError> + Send + Sync>;
+
+/// The transcription worker has panicked.
+#[derive(Debug, thiserror::Error)]
+#[error("transcription worker has panicked")]
+pub struct WorkerPanicked;
+
+/// Handle of the blocking transcription worker, running either on the shared
+/// pool of blocking tasks or on a dedicated thread (see
+/// [`subwin_bridge::config::Config::dedicated_worker_thread`]).
+pub enum WorkerHandle {
+    /// Worker running on the blocking pool.
+    Pooled(tokio::task::JoinHandle<()>),
+    /// Worker running on its own thread. `finished` resolves once it's done,
+    /// or fails if it has panicked.
+    Dedicated {
+        thread: std::thread::JoinHandle<()>,
+        finished: tokio::sync::oneshot::Receiver<()>,
+    },
+}
+
+impl WorkerHandle {
+    /// Waits for the worker to finish.
+    pub async fn join(self) -> Result<(), WorkerPanicked> {
+        match self {
+            WorkerHandle::Pooled(handle) => handle.await.map_err(|_| WorkerPanicked),
+            WorkerHandle::Dedicated { thread, finished } => {
+                let result = finished.await.map_err(|_| WorkerPanicked);
+                // the thread is past its work by now, so joining is quick
+                let _ = thread.join();
+                result
+            }
+        }
+    }
+}
+
+/// Resources owned by a running transcription session.
+///
+/// Dropping the stream stops audio capture, while the stop signal tells the
+/// blocking worker to leave its decode loop.
+pub struct TranscriptionSession {
+    /// Audio stream feeding the transcription worker; `None` while the
+    /// device is released after a long silence.
+    pub stream: Option<cpal::Stream>,
+    /// Opens the audio stream again, once the released device is needed.
+    pub open_stream: StreamOpener,
+    /// Output stream playing the captured audio back, if monitoring is on.
+    pub monitor_stream: Option<cpal::Stream>,
+    /// Flag checked by the worker on every iteration; set to stop it.
+    pub stop_signal: std::sync::Arc<std::sync::atomic::AtomicBool>,
+    /// Handle of the blocking transcription worker.
+    pub worker: WorkerHandle,
+    /// Moment the session has been started.
+    pub started_at: std::time::Instant,
+    /// Timer stopping the session once its maximum duration is reached, if
+    /// one is configured.
+    pub session_timer: Option<tokio::task::AbortHandle>,
+    /// Flag set while the push-to-talk key is held, if push-to-talk is
+    /// enabled for the session.
+    pub push_to_talk: Option<std::sync::Arc<std::sync::atomic::AtomicBool>>,
 }
 
 /// Thread-safe, async-friendly shared reference to the application [`State`].
@@ -0,0 +1,60 @@
+//! Pipeline counters collected for [`subwin_bridge::stats::PipelineStats`].
+//!
+//! The counters are shared between the audio callback, the transcription
+//! worker and the realtime error forwarder. The audio callback only touches
+//! atomics, so collecting the stats stays realtime-safe.
+
+use std::sync::{
+    Mutex,
+    atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+/// Interval between two [`subwin_bridge::MessageFromBackend::PipelineStats`]
+/// updates, in milliseconds.
+pub const STATS_INTERVAL_MILLISECONDS: u32 = 1000;
+
+/// Counters of a single transcription session.
+#[derive(Debug, Default)]
+pub struct PipelineCounters {
+    /// Samples waiting in the capture resampler for a full block.
+    resampler_queue_samples: AtomicUsize,
+    /// Samples dropped because the ring buffer was full.
+    dropped_samples: AtomicU64,
+    /// Description of the latest error.
+    last_error: Mutex<Option<String>>,
+}
+
+impl PipelineCounters {
+    /// Records the capture resampler's queue depth.
+    pub fn set_resampler_queue(&self, samples: usize) {
+        self.resampler_queue_samples
+            .store(samples, Ordering::Relaxed);
+    }
+
+    /// Adds `samples` to the count of dropped samples.
+    pub fn add_dropped(&self, samples: usize) {
+        self.dropped_samples
+            .fetch_add(samples as u64, Ordering::Relaxed);
+    }
+
+    /// Remembers `error` as the latest one. Not realtime-safe.
+    pub fn set_last_error(&self, error: impl ToString) {
+        *self
+            .last_error
+            .lock()
+            .expect("failed to lock the last error") = Some(error.to_string());
+    }
+
+    /// Returns the resampler queue depth, the dropped samples count and the
+    /// latest error.
+    pub fn snapshot(&self) -> (usize, u64, Option<String>) {
+        (
+            self.resampler_queue_samples.load(Ordering::Relaxed),
+            self.dropped_samples.load(Ordering::Relaxed),
+            self.last_error
+                .lock()
+                .expect("failed to lock the last error")
+                .clone(),
+        )
+    }
+}
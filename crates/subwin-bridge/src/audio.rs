@@ -1,6 +1,72 @@
+/// Device identifier selecting the system's default input device, whichever
+/// it currently is, instead of a specific one.
+pub const SYSTEM_DEFAULT_DEVICE_ID: &str = "system_default";
+
+/// Way to pick an input device, e.g. from scripts, where the opaque device
+/// identifiers are inconvenient.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceSelector {
+    /// The device with this identifier, or [`SYSTEM_DEFAULT_DEVICE_ID`].
+    Id(String),
+    /// The device at this zero-based position in the host's enumeration
+    /// order, which is also the order devices are listed in.
+    Index(usize),
+    /// The only device whose description contains this text, ignoring case.
+    Name(String),
+}
+
 #[derive(Debug, Clone)]
 pub struct InputDevice {
     pub id: String,
     pub description: String,
     pub selected: bool,
 }
+
+/// Native format of the active input device (see
+/// [`crate::MessageFromBackend::ActiveDeviceInfo`]).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveDeviceInfo {
+    /// Human-readable device description.
+    pub description: String,
+    /// Sample rate the device captures at, in Hz.
+    pub sample_rate: u32,
+    /// Number of channels the device captures.
+    pub channels: u16,
+    /// Whether the captured audio is resampled to the rate Whisper expects,
+    /// which adds latency and may cost some quality.
+    pub is_resampled: bool,
+}
+
+/// Levels measured by a microphone test (see
+/// [`crate::MessageToBackend::TestMicrophone`]).
+#[derive(Debug, Clone)]
+pub struct MicTestResult {
+    /// Highest sample level, in dBFS.
+    pub peak_db: f32,
+    /// Average (RMS) level, in dBFS.
+    pub rms_db: f32,
+    /// Whether any samples hit full scale, i.e. the input is too loud.
+    pub clipping: bool,
+}
+
+/// Reason capturing audio from the input device has failed, telling the user
+/// what to fix (see [`crate::MessageFromBackend::AudioError`]).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum AudioCaptureError {
+    /// The device has been disconnected or is otherwise gone.
+    #[error("the input device is not available")]
+    DeviceNotAvailable,
+    /// Another application is using the device exclusively.
+    #[error("the input device is used by another application")]
+    DeviceBusy,
+    /// The device doesn't support the sample rate, format or channel layout
+    /// it has been opened with.
+    #[error("the input device doesn't support the stream configuration")]
+    UnsupportedConfig,
+    /// The system doesn't allow the application to record audio.
+    #[error("access to the input device has been denied")]
+    PermissionDenied,
+    /// Any other failure, described by the audio backend.
+    #[error("audio backend error: {0}")]
+    Backend(String),
+}
@@ -1,7 +1,9 @@
-use std::path::PathBuf;
+use std::{collections::BTreeMap, net::SocketAddr, path::PathBuf};
 
 use serde::{Deserialize, Serialize};
 
+use crate::whisper_model::{DecodingStrategy, ParamsProfile, WhisperModel, default_params_for};
+
 /// Controls the appearance of the background behind captions window.
 /// This enum determines how the area behind the caption text is rendered.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
@@ -17,9 +19,54 @@ pub enum CaptionsBackgroundAppearance {
     Blurred,
 }
 
+/// Horizontal alignment of the caption text, relative to its direction.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptionsTextAlign {
+    /// Aligned to the side lines start at: left for LTR, right for RTL text.
+    Start,
+    /// Centered within the caption box. Default value.
+    #[default]
+    Center,
+    /// Aligned to the side lines end at: right for LTR, left for RTL text.
+    End,
+}
+
+/// Effect drawn around the caption text, to keep it readable over bright
+/// backgrounds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptionsTextEffect {
+    /// Plain text. Default value.
+    #[default]
+    None,
+    /// Outline traced around every glyph.
+    Outline,
+    /// Shadow cast down and to the right of the text.
+    Shadow,
+}
+
+/// Writing direction of the caption text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CaptionsTextDirection {
+    /// Detected from the first strongly directional character of the text.
+    /// Default value.
+    #[default]
+    Auto,
+    /// Left-to-right, e.g. Latin or Cyrillic scripts.
+    Ltr,
+    /// Right-to-left, e.g. Arabic or Hebrew scripts.
+    Rtl,
+}
+
 /// Configuration for the display and styling of captions. This
 /// struct controls key visual aspects of how captions are rendered on screen.
+///
+/// Missing fields fall back to their default values, so config files written
+/// by older versions keep loading.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct CaptionsConfig {
     /// Distance in pixels from the bottom of the screen to the caption box in
     /// pixels.
@@ -30,6 +77,62 @@ pub struct CaptionsConfig {
     pub window_width: f32,
     /// Height of the caption text window in pixels.
     pub window_height: f32,
+    /// Time in milliseconds without new captions, after which the captions
+    /// fade out. Set to `0` to keep captions fully opaque.
+    pub idle_fade_delay_ms: u32,
+    /// Opacity of faded captions, from `0.0` (invisible) to `1.0` (opaque).
+    pub idle_fade_opacity: f32,
+    /// Horizontal alignment of the caption text.
+    pub text_align: CaptionsTextAlign,
+    /// Writing direction of the caption text, which decides what the start
+    /// and end alignments mean.
+    pub direction: CaptionsTextDirection,
+    /// Whether the captions window stays above other windows, including
+    /// borderless fullscreen games. Applications in exclusive fullscreen
+    /// mode usually cover it anyway; switch them to borderless (windowed
+    /// fullscreen) mode to keep the captions visible.
+    pub always_on_top: bool,
+    /// Number of finalized caption segments shown before the ones still
+    /// being recognized. Older segments are only kept by the transcript
+    /// outputs (see [`CaptionSinksConfig`]).
+    pub max_history_segments: u32,
+    /// Number of characters of a caption line shown, keeping its latest
+    /// words after an ellipsis, so very fast speech doesn't overflow the
+    /// window. Transcript outputs keep the full text. Set to `0` to show
+    /// the whole line.
+    pub max_caption_chars: u32,
+    /// Effect drawn around the caption text.
+    pub text_effect: CaptionsTextEffect,
+    /// Color of the text effect as a hex string, e.g. `"#000000"` or
+    /// `"#000000cc"` with alpha.
+    pub text_effect_color: String,
+    /// Width of the outline or offset of the shadow, in pixels.
+    pub text_effect_width: f32,
+    /// Minimum time in milliseconds between caption updates on screen.
+    /// Revisions arriving sooner are coalesced into the latest one, which
+    /// is shown once the interval passes; finalized text is shown right
+    /// away. Independent of how often the model decodes (see
+    /// [`ParamsOverrides::repeat_run_ms`]). Set to `0` to show every
+    /// revision.
+    pub min_update_interval_ms: u32,
+    /// Stable identifiers of the displays a captions window is opened on,
+    /// mirroring the captions. Disconnected displays are skipped, falling
+    /// back to the primary display if none is left. Empty opens a single
+    /// window on the display of the main window.
+    pub displays: Vec<String>,
+    /// Whether the captions window grows and shrinks to fit the caption
+    /// text, up to [`CaptionsConfig::auto_size_max_width`] by
+    /// [`CaptionsConfig::auto_size_max_height`], instead of keeping the
+    /// configured size. The window stays anchored at the bottom edge.
+    pub auto_size: bool,
+    /// Largest width of the auto-sized captions window, in pixels.
+    pub auto_size_max_width: f32,
+    /// Largest height of the auto-sized captions window, in pixels.
+    pub auto_size_max_height: f32,
+    /// Whether caption segments the model is unsure about are dimmed and
+    /// underlined, so uncertain words can be told apart, e.g. when exact
+    /// wording matters.
+    pub mark_uncertain_text: bool,
 }
 
 impl Default for CaptionsConfig {
@@ -39,17 +142,103 @@ impl Default for CaptionsConfig {
             background_appearance: CaptionsBackgroundAppearance::default(),
             window_width: 700.0,
             window_height: 80.0,
+            idle_fade_delay_ms: 4000,
+            idle_fade_opacity: 0.3,
+            text_align: CaptionsTextAlign::default(),
+            direction: CaptionsTextDirection::default(),
+            always_on_top: true,
+            max_history_segments: 6,
+            max_caption_chars: 400,
+            text_effect: CaptionsTextEffect::None,
+            text_effect_color: "#000000".to_string(),
+            text_effect_width: 2.0,
+            min_update_interval_ms: 0,
+            displays: Vec::new(),
+            auto_size: false,
+            auto_size_max_width: 1200.0,
+            auto_size_max_height: 240.0,
+            mark_uncertain_text: false,
         }
     }
 }
 
+/// Sample format audio is preferably captured in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleFormatPreference {
+    /// The device's default format.
+    #[default]
+    Auto,
+    /// 32-bit floating point samples, passed through without conversion.
+    F32,
+    /// 16-bit integer samples, cheaper for drivers producing them natively.
+    I16,
+}
+
+/// Quality of the conversion of captured audio to the transcription rate.
+/// Higher quality filters aliasing better, at the cost of CPU time and a
+/// little latency.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResamplingQuality {
+    /// Cheapest conversion, for low-end machines that can't keep up.
+    Fast,
+    /// Default value.
+    #[default]
+    Balanced,
+    /// Sharpest filtering; takes the most CPU time and adds a block of
+    /// latency, while speech recognition rarely benefits from it.
+    HighQuality,
+}
+
+/// How the channels of captured audio are mixed down to mono.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownmixMode {
+    /// Averages the channels. Suits a voice in the center of the mix, e.g.
+    /// most videos and calls. Default value.
+    #[default]
+    Average,
+    /// Keeps the loudness of channels that differ, e.g. a stereo microphone
+    /// with the speaker off to a side, where averaging makes the speech up
+    /// to 3 dB quieter. Centered voices get louder instead.
+    PowerPreserving,
+}
+
 /// Configuration for selecting specific audio devices and backends.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct AudioDeviceConfig {
     /// Identifier of the preferred audio host/backend.
     pub selected_host_id: Option<String>,
-    /// Identifier of the preferred audio input device.
+    /// Identifier of the preferred audio input device, or
+    /// [`SYSTEM_DEFAULT_DEVICE_ID`](crate::audio::SYSTEM_DEFAULT_DEVICE_ID)
+    /// to follow the system's default input device.
     pub selected_device_id: Option<String>,
+    /// Description of the preferred audio input device. Used to find the
+    /// device again when its identifier changes, e.g. after a reboot.
+    pub selected_device_description: Option<String>,
+    /// Identifier of the output device used for monitoring captured audio.
+    /// The host's default output device is used when not set.
+    pub monitor_device_id: Option<String>,
+    /// Sample format to capture audio in, if the device supports it. The
+    /// device's default format is used otherwise.
+    pub preferred_sample_format: SampleFormatPreference,
+    /// Quality of the conversion of captured audio to the transcription
+    /// rate.
+    pub resampling_quality: ResamplingQuality,
+    /// How the channels are mixed down to mono before transcription.
+    pub downmix: DownmixMode,
+    /// Whether to capture at the transcription rate of 16 kHz directly, if
+    /// the device supports it, instead of at its default rate. Skips
+    /// resampling, which saves some latency and CPU time.
+    pub capture_at_target_rate: bool,
+    /// Buffer sizes in frames forced for specific input devices, keyed by
+    /// their identifiers, e.g. `"wasapi:{…}" = 480`. For devices delivering
+    /// buffers of another size than the automatically chosen one, which are
+    /// then dropped. Sizes must be from 16 to 16 384 frames, and ones outside
+    /// of the range the device reports are ignored.
+    pub buffer_size_overrides: BTreeMap<String, u32>,
 }
 
 impl Default for AudioDeviceConfig {
@@ -57,12 +246,315 @@ impl Default for AudioDeviceConfig {
         Self {
             selected_host_id: None,
             selected_device_id: None,
+            selected_device_description: None,
+            monitor_device_id: None,
+            preferred_sample_format: SampleFormatPreference::Auto,
+            resampling_quality: ResamplingQuality::Balanced,
+            downmix: DownmixMode::Average,
+            capture_at_target_rate: true,
+            buffer_size_overrides: BTreeMap::new(),
+        }
+    }
+}
+
+/// User overrides of the transcription parameters. Unset fields fall back to
+/// the active model's [`ParamsProfile`].
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ParamsOverrides {
+    /// Strategy used to decode tokens.
+    pub strategy: Option<DecodingStrategy>,
+    /// Length of the rolling audio window, in milliseconds, from 1 000 to
+    /// 30 000.
+    pub context_length_ms: Option<u32>,
+    /// Interval between transcription runs, in milliseconds, from 100 to
+    /// 10 000.
+    pub repeat_run_ms: Option<u32>,
+}
+
+impl ParamsOverrides {
+    /// Returns `profile` with the overridden fields replaced.
+    pub fn apply(&self, profile: ParamsProfile) -> ParamsProfile {
+        ParamsProfile {
+            strategy: self.strategy.unwrap_or(profile.strategy),
+            context_length_ms: self.context_length_ms.unwrap_or(profile.context_length_ms),
+            repeat_run_ms: self.repeat_run_ms.unwrap_or(profile.repeat_run_ms),
+        }
+    }
+}
+
+/// Sampling temperature of the decoder and its fallback.
+///
+/// Decoding starts at `temperature`. When the result looks unreliable (its
+/// entropy is below `entropy_threshold`, which catches repetition loops, or
+/// its average log probability is below `logprob_threshold`), the segment is
+/// decoded again with the temperature raised by `temperature_inc`, up to 1.0.
+/// The defaults match whisper.cpp's.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TemperatureConfig {
+    /// Initial sampling temperature; `0.0` always picks the likeliest tokens.
+    pub temperature: f32,
+    /// Temperature increase per fallback attempt; `0.0` disables fallback,
+    /// which is faster, but lets hallucination loops through.
+    pub temperature_inc: f32,
+    /// Entropy below which a result is considered repetitive.
+    pub entropy_threshold: f32,
+    /// Average token log probability below which a result is considered
+    /// unreliable.
+    pub logprob_threshold: f32,
+}
+
+impl Default for TemperatureConfig {
+    fn default() -> Self {
+        Self {
+            temperature: 0.0,
+            temperature_inc: 0.2,
+            entropy_threshold: 2.4,
+            logprob_threshold: -1.0,
+        }
+    }
+}
+
+/// Settings of the stabilizer, which holds back the most recent captions
+/// while the model may still revise them.
+///
+/// A longer tail lets the model correct more of its output before it's
+/// committed to the caption history, so captions jump around less, but text
+/// is committed later. A shorter tail commits text quickly, at the risk of
+/// committing words the model would have corrected on the next run.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct StabilizerConfig {
+    /// Revisable tail of the captions, in milliseconds.
+    pub tail_ms: u32,
+    /// Whether to lengthen the tail by the measured decode latency: slow
+    /// models revise their output later, so it has to be held back longer.
+    /// Off by default, keeping the fixed [`StabilizerConfig::tail_ms`].
+    pub adaptive: bool,
+    /// Lower bound of the adapted tail, in milliseconds.
+    pub min_tail_ms: u32,
+    /// Upper bound of the adapted tail, in milliseconds.
+    pub max_tail_ms: u32,
+}
+
+impl Default for StabilizerConfig {
+    fn default() -> Self {
+        Self {
+            tail_ms: 1500,
+            adaptive: false,
+            min_tail_ms: 1000,
+            max_tail_ms: 5000,
+        }
+    }
+}
+
+/// Settings of the cleanup applied to the recognized text.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct TextNormalizationConfig {
+    /// Whether to collapse repeated whitespace and trim captions.
+    pub enabled: bool,
+    /// Whether to also capitalize the first letter of every sentence. Has no
+    /// effect on scripts without letter case.
+    pub capitalize_sentences: bool,
+}
+
+impl Default for TextNormalizationConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            capitalize_sentences: true,
         }
     }
 }
 
+/// Settings of push-to-talk captioning, where audio is only transcribed
+/// while a key is held, e.g. when speaking in a call.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PushToTalkConfig {
+    /// Whether audio is only transcribed while the key is held.
+    pub enabled: bool,
+    /// Global shortcut held to talk, e.g. `"F8"` or `"Ctrl+Alt+Space"`.
+    pub hotkey: String,
+    /// Audio still transcribed after the key is released, in milliseconds,
+    /// so the trailing words aren't cut off.
+    pub release_tail_ms: u32,
+}
+
+impl Default for PushToTalkConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            hotkey: "F8".to_string(),
+            release_tail_ms: 300,
+        }
+    }
+}
+
+/// Anchoring of the decode window relative to the latest audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DecodeWindowAnchor {
+    /// The window ends at the latest audio, which is captioned right away.
+    /// Default value.
+    #[default]
+    Trailing,
+    /// The window is centered around a point [`Config::window_lookahead_ms`]
+    /// before the latest audio, so the most recent words are decoded with
+    /// the context following them.
+    Centered,
+}
+
+/// Format of the subtitle files written while transcribing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SubtitleFormat {
+    /// SubRip subtitles (`.srt`). Default value.
+    #[default]
+    Srt,
+    /// WebVTT subtitles (`.vtt`).
+    Vtt,
+    /// Plain text (`.txt`), a line per segment, optionally prefixed with its
+    /// time (see [`CaptionSinksConfig::timestamp_lines`]).
+    Text,
+}
+
+impl SubtitleFormat {
+    /// Returns the extension of files in this format, without the dot.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            SubtitleFormat::Srt => "srt",
+            SubtitleFormat::Vtt => "vtt",
+            SubtitleFormat::Text => "txt",
+        }
+    }
+}
+
+/// Placeholder in [`CaptionSinksConfig::transcript_file_template`] replaced
+/// with the session's start time.
+pub const TRANSCRIPT_TIMESTAMP_PLACEHOLDER: &str = "{timestamp}";
+
+/// Placeholder in [`CaptionSinksConfig::timestamp_template`] replaced with
+/// the time a line's segment starts at.
+pub const LINE_TIME_PLACEHOLDER: &str = "{time}";
+
+/// Formats `milliseconds` since the start of the session as `HH:MM:SS`.
+pub fn format_session_time(milliseconds: i64) -> String {
+    let seconds = milliseconds.max(0) / 1000;
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Configuration of the additional caption outputs. Captions are always
+/// shown in the captions window.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CaptionSinksConfig {
+    /// Path to a subtitle file to write finalized captions into. The file is
+    /// overwritten on every transcription start.
+    pub subtitle_file: Option<PathBuf>,
+    /// Format of the subtitle files.
+    pub subtitle_format: SubtitleFormat,
+    /// Path to a text file kept containing the current caption text, e.g.
+    /// for an OBS text source reading from a file. The file is emptied once
+    /// the captions are cleared and when transcription stops.
+    pub obs_caption_file: Option<PathBuf>,
+    /// Whether to print finalized captions to the standard output.
+    pub stdout: bool,
+    /// Whether to log every caption update of a session, with its active
+    /// and finalized segments and the audio window it's been decoded from,
+    /// as JSON lines into the cache. Meant for replaying sessions offline,
+    /// e.g. to tune the stabilizer without running the model again.
+    pub log_caption_updates: bool,
+    /// Path of a Unix domain socket (the name of a named pipe on Windows,
+    /// e.g. `\\.\pipe\subwin-captions`) to stream the caption updates to
+    /// as JSON lines, e.g. for status bars or custom overlays. Clients may
+    /// connect at any time while transcribing.
+    pub caption_socket_path: Option<PathBuf>,
+    /// Address to accept WebSocket clients on, e.g. `"127.0.0.1:8765"`, which
+    /// receive the same records as the clients of
+    /// [`CaptionSinksConfig::caption_socket_path`] as text messages, e.g. for
+    /// browser sources. Keep it on the loopback address: clients aren't
+    /// authenticated.
+    pub caption_websocket_address: Option<SocketAddr>,
+    /// Whether to save the captions of every session into a new subtitle
+    /// file in [`Config::transcript_output_dir`].
+    pub save_transcripts: bool,
+    /// Name of the saved transcripts without the extension.
+    /// [`TRANSCRIPT_TIMESTAMP_PLACEHOLDER`] is replaced with the session's
+    /// start time, as seconds since the Unix epoch.
+    pub transcript_file_template: String,
+    /// Whether to prefix every line of plain-text transcripts, of the
+    /// standard output and of the lines copied from the transcript page with
+    /// the time its segment starts at, relative to the start of the session.
+    pub timestamp_lines: bool,
+    /// Prefix of timestamped lines. [`LINE_TIME_PLACEHOLDER`] is replaced
+    /// with the time as `HH:MM:SS`.
+    pub timestamp_template: String,
+}
+
+impl Default for CaptionSinksConfig {
+    fn default() -> Self {
+        Self {
+            subtitle_file: None,
+            subtitle_format: SubtitleFormat::default(),
+            obs_caption_file: None,
+            stdout: false,
+            log_caption_updates: false,
+            caption_socket_path: None,
+            caption_websocket_address: None,
+            save_transcripts: false,
+            transcript_file_template: format!("subwin-{TRANSCRIPT_TIMESTAMP_PLACEHOLDER}"),
+            timestamp_lines: false,
+            timestamp_template: format!("[{LINE_TIME_PLACEHOLDER}] "),
+        }
+    }
+}
+
+impl CaptionSinksConfig {
+    /// Returns the template timestamped lines are prefixed with, if lines are
+    /// timestamped.
+    pub fn line_prefix_template(&self) -> Option<&str> {
+        self.timestamp_lines
+            .then_some(self.timestamp_template.as_str())
+    }
+}
+
+/// Builds the prefix of a line whose segment starts at `milliseconds` since
+/// the start of the session, from `template` (see
+/// [`CaptionSinksConfig::timestamp_template`]).
+pub fn format_line_prefix(template: &str, milliseconds: i64) -> String {
+    template.replace(LINE_TIME_PLACEHOLDER, &format_session_time(milliseconds))
+}
+
+/// Maximum number of models kept in [`Config::recent_models`].
+pub const MAX_RECENT_MODELS: usize = 5;
+
+/// Suggested ceiling of the soft limiter (see [`Config::limiter_ceiling_db`]),
+/// in dBFS.
+pub const DEFAULT_LIMITER_CEILING_DB: f32 = -1.0;
+
+/// Suggested duration of silence, after which the captions are cleared (see
+/// [`Config::clear_captions_after_silence_ms`]), in milliseconds.
+pub const DEFAULT_CLEAR_CAPTIONS_AFTER_SILENCE_MS: u32 = 3000;
+
+/// Version of the config file's layout, stored in
+/// [`Config::config_version`]. Raised whenever fields are renamed or
+/// reshaped, so older files can be migrated.
+pub const CONFIG_VERSION: u32 = 1;
+
 /// Global application configuration.
+///
+/// Missing fields fall back to their default values, so config files written
+/// by older versions keep loading.
 #[derive(Debug, Clone, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Config {
     /// Whether to enable transcoding pipeline for input audio.
     pub enable_transcoder: bool,
@@ -70,10 +562,183 @@ pub struct Config {
     pub enable_auto_translation: bool,
     /// Configuration for the captions module of the application.
     pub captions_config: CaptionsConfig,
+    /// Whether to show the captions window while transcribing. Without it,
+    /// captions only go to the other outputs (see [`CaptionSinksConfig`]);
+    /// transcription is stopped from the main window.
+    pub show_caption_window: bool,
     /// Path to the active transcription model, if any.
     pub active_model_path: Option<PathBuf>,
     /// Configuration for audio devices for the host.
     pub audio_device_config: AudioDeviceConfig,
+    /// Whether to play the captured audio back through an output device while
+    /// transcribing. Useful for checking the device selection; beware of
+    /// feedback when monitoring a microphone through speakers.
+    pub enable_monitor: bool,
+    /// Whether the first two channels of the device are transcribed
+    /// separately instead of being mixed down, e.g. for interviews recorded
+    /// with a microphone per channel. Captions are labeled with the speaker
+    /// of their channel. Each channel runs its own transcriber, so the model
+    /// is loaded twice and decoding takes about twice the CPU/GPU time and
+    /// memory; pick a smaller model if transcription can't keep up. Devices
+    /// with a single channel are transcribed as usual.
+    pub split_channels: bool,
+    /// Base URL of a mirror to download models from instead of HuggingFace.
+    /// Model file names are appended to it as-is.
+    pub model_mirror_url: Option<String>,
+    /// Overrides of the active model's recommended transcription parameters.
+    pub params_overrides: ParamsOverrides,
+    /// Manual gain applied to the captured audio before transcription, in dB.
+    pub input_gain_db: f32,
+    /// Whether to automatically raise quiet speech to a steady loudness
+    /// before transcription. Silence below the noise gate is left as is.
+    pub enable_agc: bool,
+    /// Loudness automatic gain aims for, in dBFS (RMS), e.g. `-20.0`. Raise
+    /// it for models that do better with louder input, lower it if speech
+    /// gets distorted.
+    pub agc_target_db: f32,
+    /// Ceiling of the soft limiter applied after gain, in dBFS, e.g. `-1.0`.
+    /// Loud peaks are bent smoothly below it instead of being clipped at
+    /// full scale, which otherwise distorts speech when the gain or
+    /// automatic gain is set high. `None` clamps samples to full scale.
+    pub limiter_ceiling_db: Option<f32>,
+    /// Additional outputs the captions are written to.
+    pub caption_sinks: CaptionSinksConfig,
+    /// Directory saved transcripts are written to (see
+    /// [`CaptionSinksConfig::save_transcripts`]). `None`, or a directory
+    /// that can't be written to, saves them into the cache directory.
+    pub transcript_output_dir: Option<PathBuf>,
+    /// Duration of silence in milliseconds, after which the current utterance
+    /// is considered complete and its captions are finalized right away.
+    /// Finalized captions stay on screen, so short utterances remain
+    /// readable. Set to `0` to only finalize captions as they age out.
+    pub silence_finalize_ms: u32,
+    /// Duration of silence in milliseconds, after which the captions are
+    /// cleared, so stale text doesn't linger on screen. Text still being
+    /// recognized is never cleared. `None` keeps the last captions shown.
+    pub clear_captions_after_silence_ms: Option<u32>,
+    /// Minimum duration in milliseconds the latest caption stays on screen
+    /// before it may be cleared after silence (see
+    /// [`Config::clear_captions_after_silence_ms`]).
+    pub min_caption_display_ms: u32,
+    /// Sampling temperature and fallback settings of the decoder.
+    pub temperature: TemperatureConfig,
+    /// Whether to suppress non-speech tokens, such as laughter or music
+    /// markers, while decoding.
+    pub suppress_non_speech: bool,
+    /// Token strings kept out of the recognized text, e.g. `[BLANK_AUDIO]`,
+    /// fillers like `um`, or hallucinated phrases the model keeps producing
+    /// on silence. A string the model spells as a single token is never
+    /// decoded, while a longer one is removed where it stands as a whole
+    /// word, so words containing it are kept.
+    pub suppressed_tokens: Vec<String>,
+    /// Settings of the revisable tail of recent captions.
+    pub stabilizer: StabilizerConfig,
+    /// User dictionary fixing words the model keeps misspelling: each
+    /// phrase is replaced with its value. Phrases are matched as whole
+    /// words, ignoring case, e.g. `"pel fox" = "Pelfox"`.
+    pub word_replacements: BTreeMap<String, String>,
+    /// Cleanup of whitespace and capitalization of the recognized text,
+    /// applied to both the displayed and the exported captions.
+    pub text_normalization: TextNormalizationConfig,
+    /// Whether to start transcribing as soon as the application launches,
+    /// using the configured device and model.
+    pub auto_start_transcription: bool,
+    /// Whether the decoder produces a single segment per run instead of one
+    /// per phrase. A single line jitters less, but spans the whole context
+    /// window, so it never ages out of the stabilizer: captions are only
+    /// finalized at pauses (see [`Config::silence_finalize_ms`]).
+    pub single_segment: bool,
+    /// Maximum number of tokens the decoder produces per segment; `0` is
+    /// unlimited. Lower limits shorten decodes and caption lines, but cut
+    /// off long phrases, which get completed by later decodes or not at
+    /// all. With [`Config::single_segment`], this limits the text of the
+    /// whole window, so keep it high enough for the context length.
+    pub max_tokens: u32,
+    /// Whether the latest finalized captions are passed to the model as the
+    /// prompt of the next decode. Keeps words split between decode windows
+    /// and the spelling of names consistent, but also carries recognition
+    /// mistakes over, and a repetitive prompt may make the model repeat it
+    /// instead of transcribing the speech.
+    pub carry_prompt: bool,
+    /// Minimum length of audio a decode runs on, in milliseconds. Shorter
+    /// audio, e.g. right after the start, is padded with silence, but never
+    /// with more silence than there's audio, since too much padding relative
+    /// to the speech hurts accuracy.
+    pub min_transcription_ms: u32,
+    /// Time limit of a single decode, in milliseconds. Decodes exceeding it,
+    /// e.g. on inputs the model gets stuck on, are aborted and their window
+    /// is decoded again on the next run, so captions don't freeze. Keep it
+    /// well above the usual decode time, or captions never come through.
+    /// `None` lets decodes take as long as they need.
+    pub decode_timeout_ms: Option<u32>,
+    /// Anchoring of the decode window relative to the latest audio.
+    pub window_anchor: DecodeWindowAnchor,
+    /// Audio following a word that has to be captured before it's captioned,
+    /// in milliseconds, when the window is centered. Captions are delayed by
+    /// this much on top of the decode latency; it's capped to half of the
+    /// context window. Centering is ignored when decoding a single segment,
+    /// as it always spans the whole window.
+    pub window_lookahead_ms: u32,
+    /// Duration in minutes after which transcription stops on its own, e.g.
+    /// to free the GPU during unattended captioning. `None` never stops.
+    pub max_session_minutes: Option<u32>,
+    /// Duration of silence in minutes, after which the running session
+    /// closes its audio stream, so other applications can use the device.
+    /// The model stays loaded, and capture resumes without restarting the
+    /// session on request, once the push-to-talk key is pressed, or on
+    /// `SIGUSR1` in headless mode. Speech isn't detected while the device is
+    /// released, so without push-to-talk capture doesn't resume by itself.
+    /// `None` keeps the device open.
+    pub idle_release_minutes: Option<u32>,
+    /// Whether decoding runs on its own thread with raised priority, rather
+    /// than on the shared pool of blocking tasks. Keeps decodes on schedule
+    /// while a model download or other file I/O is busy; raising the
+    /// priority may require extra permissions and is skipped without them.
+    pub dedicated_worker_thread: bool,
+    /// Whether models are loaded on the GPU, if whisper is built with GPU
+    /// support. A model that keeps failing there is moved to the CPU for the
+    /// rest of the session either way.
+    pub use_gpu: bool,
+    /// Language of the speech as an ISO 639-1 code, e.g. `"ru"`; `None`
+    /// detects it automatically. English-only models only accept `"en"`.
+    pub language: Option<String>,
+    /// Whether to switch to a smaller downloaded model, when the active one
+    /// stays slower than real time for a while and captions fall behind.
+    /// The switch is saved as the active model.
+    pub adaptive_model: bool,
+    /// Recently used models, the most recent first. Offered for quick
+    /// switching between models; see [`Config::remember_model`].
+    pub recent_models: Vec<WhisperModel>,
+    /// Push-to-talk captioning, replacing continuous transcription when
+    /// enabled.
+    pub push_to_talk: PushToTalkConfig,
+    /// Layout version of the file the config has been loaded from, see
+    /// [`CONFIG_VERSION`]. Missing from files written before versioning,
+    /// which are version `0`.
+    #[serde(default)]
+    pub config_version: u32,
+}
+
+impl Config {
+    /// Returns the transcription parameters for the active model: its
+    /// recommended profile with the user's overrides applied.
+    pub fn active_params_profile(&self) -> ParamsProfile {
+        let profile = self
+            .active_model_path
+            .as_deref()
+            .and_then(WhisperModel::from_path)
+            .map(|model| default_params_for(&model))
+            .unwrap_or_default();
+        self.params_overrides.apply(profile)
+    }
+
+    /// Moves `model` to the front of [`Config::recent_models`], dropping its
+    /// earlier entry and the oldest models above [`MAX_RECENT_MODELS`].
+    pub fn remember_model(&mut self, model: WhisperModel) {
+        self.recent_models.retain(|recent| *recent != model);
+        self.recent_models.insert(0, model);
+        self.recent_models.truncate(MAX_RECENT_MODELS);
+    }
 }
 
 impl Default for Config {
@@ -82,8 +747,161 @@ impl Default for Config {
             enable_transcoder: true,
             enable_auto_translation: true,
             captions_config: CaptionsConfig::default(),
+            show_caption_window: true,
             active_model_path: None,
             audio_device_config: AudioDeviceConfig::default(),
+            enable_monitor: false,
+            split_channels: false,
+            model_mirror_url: None,
+            params_overrides: ParamsOverrides::default(),
+            input_gain_db: 0.0,
+            enable_agc: false,
+            agc_target_db: -20.0,
+            limiter_ceiling_db: None,
+            caption_sinks: CaptionSinksConfig::default(),
+            transcript_output_dir: None,
+            silence_finalize_ms: 800,
+            clear_captions_after_silence_ms: None,
+            min_caption_display_ms: 2000,
+            temperature: TemperatureConfig::default(),
+            suppress_non_speech: true,
+            suppressed_tokens: Vec::new(),
+            single_segment: false,
+            max_tokens: 0,
+            carry_prompt: false,
+            auto_start_transcription: false,
+            stabilizer: StabilizerConfig::default(),
+            word_replacements: BTreeMap::new(),
+            text_normalization: TextNormalizationConfig::default(),
+            min_transcription_ms: 100,
+            decode_timeout_ms: None,
+            window_anchor: DecodeWindowAnchor::Trailing,
+            window_lookahead_ms: 500,
+            max_session_minutes: None,
+            idle_release_minutes: None,
+            dedicated_worker_thread: false,
+            use_gpu: true,
+            language: None,
+            adaptive_model: false,
+            recent_models: Vec::new(),
+            push_to_talk: PushToTalkConfig::default(),
+            config_version: CONFIG_VERSION,
         }
     }
 }
+
+/// Kind of a problem found by validating the config (see [`ConfigIssue`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigIssueKind {
+    /// No model has been downloaded or selected.
+    NoActiveModel,
+    /// The active model's file doesn't exist anymore.
+    ModelMissing(PathBuf),
+    /// The active model's file size differs from the expected one, so the
+    /// file may be damaged.
+    ModelSizeMismatch(PathBuf),
+    /// The active model recognizes English speech only, while another
+    /// language is configured.
+    LanguageNotSupported(WhisperModel),
+    /// Whisper doesn't know the configured language, so it's detected
+    /// instead.
+    UnknownLanguage(String),
+    /// No input device has been selected, or it's not connected.
+    NoInputDevice,
+    /// The selected input device doesn't report its configuration, e.g.
+    /// because it's being disconnected.
+    InputDeviceUnavailable,
+    /// The captions window is too small to show a line of text, or too large
+    /// for any display.
+    CaptionsWindowSize,
+    /// An overridden window length or interval between transcription runs
+    /// (see [`ParamsOverrides`]) is out of the supported range.
+    ParamsOverride,
+    /// The buffer size overridden for the input device with the identifier
+    /// is out of the supported range (see
+    /// [`AudioDeviceConfig::buffer_size_overrides`]).
+    BufferSizeOverride(String),
+}
+
+impl ConfigIssueKind {
+    /// Returns whether the issue prevents starting a transcription session,
+    /// rather than only degrading it.
+    pub fn is_blocking(&self) -> bool {
+        !matches!(
+            self,
+            ConfigIssueKind::ModelSizeMismatch(_) | ConfigIssueKind::UnknownLanguage(_)
+        )
+    }
+}
+
+/// A problem found by validating the config (see
+/// [`crate::MessageToBackend::ValidateConfig`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub kind: ConfigIssueKind,
+    /// Description of the problem and the way to fix it, shown to the user.
+    pub message: String,
+}
+
+impl ConfigIssue {
+    /// Returns whether the issue prevents starting a transcription session
+    /// (see [`ConfigIssueKind::is_blocking`]).
+    pub fn is_blocking(&self) -> bool {
+        self.kind.is_blocking()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn remembered_model_moves_to_the_front() {
+        let mut config = Config::default();
+        config.remember_model(WhisperModel::Tiny);
+        config.remember_model(WhisperModel::Base);
+        config.remember_model(WhisperModel::Small);
+
+        config.remember_model(WhisperModel::Tiny);
+        assert_eq!(
+            config.recent_models,
+            [WhisperModel::Tiny, WhisperModel::Small, WhisperModel::Base]
+        );
+    }
+
+    #[test]
+    fn only_the_latest_models_are_remembered() {
+        let mut config = Config::default();
+        for model in WhisperModel::ALL.into_iter().take(MAX_RECENT_MODELS + 2) {
+            config.remember_model(model);
+        }
+
+        let mut latest = WhisperModel::ALL[2..MAX_RECENT_MODELS + 2].to_vec();
+        latest.reverse();
+        assert_eq!(config.recent_models, latest);
+    }
+
+    #[test]
+    fn only_issues_a_start_survives_are_not_blocking() {
+        let blocking = [
+            ConfigIssueKind::NoActiveModel,
+            ConfigIssueKind::ModelMissing(PathBuf::from("model.bin")),
+            ConfigIssueKind::LanguageNotSupported(WhisperModel::TinyEn),
+            ConfigIssueKind::NoInputDevice,
+            ConfigIssueKind::InputDeviceUnavailable,
+            ConfigIssueKind::CaptionsWindowSize,
+            ConfigIssueKind::ParamsOverride,
+            ConfigIssueKind::BufferSizeOverride("hw:0".to_string()),
+        ];
+        for kind in blocking {
+            assert!(kind.is_blocking(), "{kind:?}");
+        }
+
+        let issue = ConfigIssue {
+            kind: ConfigIssueKind::ModelSizeMismatch(PathBuf::from("model.bin")),
+            message: String::new(),
+        };
+        assert!(!issue.is_blocking());
+        assert!(!ConfigIssueKind::UnknownLanguage("xx".to_string()).is_blocking());
+    }
+}
@@ -13,13 +13,34 @@
 //! Communication happens over bounded [`tokio::sync::mpsc`] channels wrapped
 //! in [`BridgeChannels`], providing back-pressure, async compatibility, and
 //! clean separation of concerns.
+//!
+//! Backend messages travel over two channels with different semantics:
+//! - Control messages (responses, notifications, download progress) are sent
+//!   with back-pressure: when the channel is full, the sender waits until the
+//!   frontend catches up, so none of them is ever lost.
+//! - High-frequency updates (see [`MessageFromBackend::is_update`]) are sent
+//!   over a separate channel without waiting: when it's full, the update is
+//!   dropped, as the next one supersedes it anyway. This way a busy frontend
+//!   can't stall the transcription, and a backlog of updates can't delay a
+//!   notification.
+//!
+//! Input levels for level meters are published even more often, so they
+//! don't go through either of them, but through a [`tokio::sync::watch`]
+//! channel holding only the latest level. Publishing never waits and
+//! overwrites the previous level, so the frontend may skip intermediate
+//! levels, but always reads the latest one, without crowding out captions.
 
 pub mod audio;
 pub mod config;
 pub mod notification;
+pub mod stats;
+pub mod transcript;
 pub mod whisper_model;
 
-use tokio::sync::mpsc::{self, Receiver, Sender};
+use tokio::sync::{
+    mpsc::{self, Receiver, Sender},
+    watch,
+};
 
 /// Messages emitted by the backend to inform the frontend of state updates.
 ///
@@ -30,7 +51,7 @@ pub enum MessageFromBackend {
     /// Generic message for all notifications in the application.
     NotificationMessage(notification::NotificationMessage),
     /// Response to the configuration request from the frontend.
-    ConfigurationResponse(config::Config),
+    ConfigurationResponse(Box<config::Config>),
     /// Generic message for reporting the progress of a download.
     DownloadProgressUpdate {
         /// Current speed in bytes per second.
@@ -42,14 +63,128 @@ pub enum MessageFromBackend {
         /// Estimated remaining time until download completion, in seconds.
         remaining_time: f64,
     },
+    /// Progress of checking a downloaded model file against its checksum,
+    /// which follows the download.
+    ModelVerificationProgress {
+        /// Amount of bytes hashed to this point.
+        processed_bytes: u64,
+        /// Size of the model file.
+        total_bytes: u64,
+    },
     AudioDevicesListResponse(Vec<audio::InputDevice>),
-    TranscriptionStartedResponse,
+    /// Native format of the active input device, sent once the device list
+    /// is requested, a device is selected or transcription starts; `None`
+    /// if no device is selected or it can't be queried.
+    ActiveDeviceInfo(Option<audio::ActiveDeviceInfo>),
+    /// Sent once a transcription start has been accepted, while the model is
+    /// being loaded. Followed by
+    /// [`MessageFromBackend::TranscriptionStartedResponse`], or by
+    /// [`MessageFromBackend::TranscriptionStoppedResponse`] if the start is
+    /// cancelled meanwhile.
+    TranscriptionStarting,
+    /// Sent once the model has been loaded and the captions are produced.
+    TranscriptionStartedResponse {
+        /// Identifier of the session, greater than the ones of the sessions
+        /// started before it.
+        session_id: u64,
+    },
+    /// Capturing audio from the input device has failed. Sent before
+    /// [`MessageFromBackend::TranscriptionStoppedResponse`], when the failure
+    /// stops the session.
+    AudioError(audio::AudioCaptureError),
+    /// Response to [`MessageToBackend::StopTranscriptionRequest`], sent once
+    /// the audio stream and the transcription worker have been stopped.
+    TranscriptionStoppedResponse,
     TranscriptionStateUpdate {
+        /// Identifier of the session the update comes from. Updates may
+        /// arrive after the session's
+        /// [`MessageFromBackend::TranscriptionStoppedResponse`], as they're
+        /// sent over another channel, so ones of finished sessions should be
+        /// ignored.
+        session_id: u64,
         time_taken: u128,
         new_segment_text: String,
+        /// Byte ranges of `new_segment_text` the model is unsure about, in
+        /// order (see [`config::CaptionsConfig::mark_uncertain_text`]).
+        uncertain_ranges: Vec<std::ops::Range<usize>>,
+    },
+    /// Lines finalized by the running session, in order. Sent over the
+    /// control channel, so the transcript has no gaps; the last lines of a
+    /// session are sent once it ends.
+    TranscriptLines(Vec<transcript::TranscriptLine>),
+    /// Performance metrics of the latest transcription inference.
+    TranscriptionMetrics {
+        /// Elapsed time of the inference, in milliseconds.
+        inference_ms: u128,
+        /// Number of audio samples the inference has been run on.
+        samples_processed: usize,
+        /// Ratio of the inference time to the processed audio duration. Values
+        /// above `1.0` mean the model can't keep up with real time.
+        real_time_factor: f64,
     },
+    /// Response to [`MessageToBackend::TestMicrophone`] with the levels of
+    /// the recorded audio.
+    MicTestResult(audio::MicTestResult),
+    /// Periodic snapshot of the transcription pipeline's health, sent while
+    /// a session is running.
+    PipelineStats(stats::PipelineStats),
+    /// Response to [`MessageToBackend::StatusRequest`].
+    StatusResponse {
+        /// Whether a transcription session is running.
+        running: bool,
+        /// Path to the active model, if any.
+        active_model: Option<std::path::PathBuf>,
+        /// The selected input device, if it's available.
+        active_device: Option<audio::InputDevice>,
+        /// Time the running session has been transcribing for.
+        uptime: Option<std::time::Duration>,
+    },
+    /// Response to [`MessageToBackend::DownloadedModelsRequest`] with the
+    /// models present in the cache, in the order of
+    /// [`whisper_model::WhisperModel::ALL`]. Also sent after a download
+    /// completes.
+    DownloadedModelsResponse(Vec<whisper_model::WhisperModel>),
+    /// Response to [`MessageToBackend::LatencyEstimateRequest`]; `None` if
+    /// there is no input device to estimate it for.
+    LatencyEstimateResponse(Option<stats::LatencyEstimate>),
+    /// Response to [`MessageToBackend::BenchmarkModel`]; `None` if the
+    /// benchmark couldn't be run, which is reported with a notification.
+    ModelBenchmarkResult(Option<stats::ModelBenchmark>),
+    /// Response to [`MessageToBackend::ExportDiagnostics`] with the path to
+    /// the directory the diagnostics have been written to.
+    DiagnosticsExported(std::path::PathBuf),
+    /// Response to [`MessageToBackend::ValidateConfig`] with the problems
+    /// found in the config, if any. Also sent when a start fails on them.
+    ConfigValidation(Vec<config::ConfigIssue>),
+    /// Sent after the config has been written to disk. Failed saves are
+    /// reported with an error notification instead.
+    ConfigSaved,
+    /// Sent when the running session releases the input device after a long
+    /// silence (see [`config::Config::idle_release_minutes`]), with `true`,
+    /// and when it captures from the device again, with `false`.
+    CaptureReleased(bool),
 }
 
+impl MessageFromBackend {
+    /// Returns whether the message is a high-frequency update, which may be
+    /// dropped if the frontend can't keep up, because a newer one follows.
+    pub fn is_update(&self) -> bool {
+        matches!(
+            self,
+            MessageFromBackend::TranscriptionStateUpdate { .. }
+                | MessageFromBackend::TranscriptionMetrics { .. }
+                | MessageFromBackend::PipelineStats(_)
+        )
+    }
+}
+
+/// Default capacity of the control channels of [`BridgeChannels`].
+pub const DEFAULT_CONTROL_BUFFER: usize = 64;
+
+/// Default capacity of the high-frequency updates channel of
+/// [`BridgeChannels`].
+pub const DEFAULT_UPDATES_BUFFER: usize = 16;
+
 /// Commands issued by the frontend to control or query the backend.
 ///
 /// These messages drive the core functionality of the application.
@@ -57,43 +192,135 @@ pub enum MessageFromBackend {
 pub enum MessageToBackend {
     /// Request for the application configuration.
     ConfigurationRequest,
+    /// Request to replace the application configuration with the provided
+    /// one and persist it. The backend answers with a
+    /// [`MessageFromBackend::ConfigurationResponse`].
+    UpdateConfigRequest(Box<config::Config>),
     /// Request to start downloading a model.
     DownloadModelRequest(whisper_model::WhisperModel),
+    /// Request for the models that have already been downloaded. The backend
+    /// answers with a [`MessageFromBackend::DownloadedModelsResponse`].
+    DownloadedModelsRequest,
+    /// Request to make an already downloaded model the active one. A running
+    /// transcription session is restarted with the new model.
+    SetActiveModel(whisper_model::WhisperModel),
     AudioDevicesListRequest,
     SelectAudioDevice(String),
+    /// Request to select the input device picked by the selector, resolved
+    /// like [`MessageToBackend::SelectAudioDevice`] once found. Ambiguous
+    /// names are reported with an error notification listing the matches.
+    SelectAudioDeviceBy(audio::DeviceSelector),
     StartTranscriptionRequest,
+    /// Request to stop the running transcription session, if any.
+    StopTranscriptionRequest,
+    /// Request to capture from the input device again, after the running
+    /// session has released it (see [`MessageFromBackend::CaptureReleased`]).
+    ResumeCaptureRequest,
+    /// Sent when the push-to-talk key is pressed, with `true`, and released,
+    /// with `false` (see [`config::Config::push_to_talk`]). Ignored unless
+    /// push-to-talk is enabled for the running session.
+    PushToTalk(bool),
+    /// Request for the current state of the backend. The backend answers
+    /// with a [`MessageFromBackend::StatusResponse`].
+    StatusRequest,
+    /// Request to record `duration_ms` of audio from the input device with
+    /// `device_id` and measure its levels, optionally playing the recording
+    /// back. The backend answers with a [`MessageFromBackend::MicTestResult`].
+    /// Tests longer than 30 seconds are refused.
+    TestMicrophone {
+        device_id: String,
+        duration_ms: u32,
+        playback: bool,
+    },
+    /// Request for the estimated delay between speech and its captions with
+    /// the current config and input device. The backend answers with a
+    /// [`MessageFromBackend::LatencyEstimateResponse`].
+    LatencyEstimateRequest,
+    /// Request to measure how fast the active model decodes on this machine,
+    /// by transcribing a synthetic clip several times with the current
+    /// config. Refused while a session is running. The backend answers with
+    /// a [`MessageFromBackend::ModelBenchmarkResult`].
+    BenchmarkModel,
+    /// Request to write a diagnostics bundle for bug reports: the config,
+    /// the audio devices, the active model, the recent log lines and the
+    /// latest pipeline stats. The backend answers with a
+    /// [`MessageFromBackend::DiagnosticsExported`].
+    ExportDiagnostics,
+    /// Request to check the config, the active model and the input device
+    /// for the problems a start would fail on, without starting. The backend
+    /// answers with a [`MessageFromBackend::ConfigValidation`].
+    ValidateConfig,
+    /// Request to write the config into a file at the given path, to share
+    /// or back it up. The outcome is reported with a notification.
+    ExportConfigTo(std::path::PathBuf),
+    /// Request to replace the config with the one in the file at the given
+    /// path, as written by [`MessageToBackend::ExportConfigTo`], and persist
+    /// it. The backend answers with a
+    /// [`MessageFromBackend::ConfigurationResponse`] and a
+    /// [`MessageFromBackend::ConfigValidation`] of the imported config.
+    ImportConfigFrom(std::path::PathBuf),
+    /// Request for the backend to stop active work and wind down its runtime.
+    /// Sent by the frontend right before the application quits.
+    ShutdownRequest,
 }
 
 /// Paired `tokio::mpsc` channels for bidirectional communication between
-/// frontend and backend.
+/// frontend and backend. See the [crate docs](crate) for the semantics of
+/// the two backend channels.
 pub struct BridgeChannels {
     /// Receiver used by the frontend to get messages from the backend.
     pub frontend_rx: Receiver<MessageFromBackend>,
+    /// Receiver used by the frontend to get high-frequency updates from the
+    /// backend.
+    pub frontend_updates_rx: Receiver<MessageFromBackend>,
     /// Sender used by the frontend to send commands to the backend.
     pub frontend_tx: Sender<MessageToBackend>,
+    /// Receiver used by the frontend to read the latest input level.
+    /// Intermediate levels may be skipped.
+    pub frontend_levels_rx: watch::Receiver<stats::InputLevel>,
 
     /// Receiver used by the backend to get commands from the frontend.
     pub backend_rx: Receiver<MessageToBackend>,
     /// Sender used by the backend to send events/responses to the frontend.
     pub backend_tx: Sender<MessageFromBackend>,
+    /// Sender used by the backend to send high-frequency updates to the
+    /// frontend, without waiting for it.
+    pub backend_updates_tx: Sender<MessageFromBackend>,
+    /// Sender used by the backend to publish the latest input level,
+    /// overwriting the previous one.
+    pub backend_levels_tx: watch::Sender<stats::InputLevel>,
 }
 
 impl BridgeChannels {
-    /// Creates a new pair of bridged channels with the given buffer capacity.
+    /// Creates a new pair of bridged channels with `buffer` capacity for
+    /// control messages and the default capacity for updates.
     pub fn new(buffer: usize) -> Self {
-        let (to_backend_tx, to_backend_rx) = mpsc::channel(buffer);
-        let (to_frontend_tx, to_frontend_rx) = mpsc::channel(buffer);
+        Self::with_capacities(buffer, DEFAULT_UPDATES_BUFFER)
+    }
+
+    /// Creates a new pair of bridged channels with `control_buffer` capacity
+    /// for control messages in each direction and `updates_buffer` capacity
+    /// for the high-frequency updates.
+    pub fn with_capacities(control_buffer: usize, updates_buffer: usize) -> Self {
+        let (to_backend_tx, to_backend_rx) = mpsc::channel(control_buffer);
+        let (to_frontend_tx, to_frontend_rx) = mpsc::channel(control_buffer);
+        let (updates_tx, updates_rx) = mpsc::channel(updates_buffer);
+        let (levels_tx, levels_rx) = watch::channel(stats::InputLevel::SILENT);
         Self {
             frontend_tx: to_backend_tx,
             frontend_rx: to_frontend_rx,
+            frontend_updates_rx: updates_rx,
+            frontend_levels_rx: levels_rx,
             backend_rx: to_backend_rx,
             backend_tx: to_frontend_tx,
+            backend_updates_tx: updates_tx,
+            backend_levels_tx: levels_tx,
         }
     }
 }
 
 impl Default for BridgeChannels {
     fn default() -> Self {
-        Self::new(64)
+        Self::with_capacities(DEFAULT_CONTROL_BUFFER, DEFAULT_UPDATES_BUFFER)
     }
 }
@@ -0,0 +1,135 @@
+/// Snapshot of the transcription pipeline's health, sent periodically while
+/// transcribing. Meant for troubleshooting, e.g. in a debug overlay.
+#[derive(Debug, Clone, Default)]
+pub struct PipelineStats {
+    /// Average (RMS) level of the recent input after gain, in dBFS.
+    pub input_level_db: f32,
+    /// Number of samples waiting in the capture resampler for a full block.
+    pub resampler_queue_samples: usize,
+    /// Number of samples waiting in the ring buffer for the transcriber.
+    pub ring_buffer_samples: usize,
+    /// Capacity of the ring buffer, in samples.
+    pub ring_buffer_capacity: usize,
+    /// Real-time factor of the latest inference; above `1.0` the model can't
+    /// keep up.
+    pub real_time_factor: f64,
+    /// Number of captured samples dropped since the session has started,
+    /// because the ring buffer was full.
+    pub dropped_samples: u64,
+    /// Number of decodes aborted since the session has started, because
+    /// they exceeded the decode timeout.
+    pub timed_out_decodes: u64,
+    /// Number of samples exceeding the limiter's ceiling (or full scale)
+    /// after gain since the session has started.
+    pub clipped_samples: u64,
+    /// Description of the latest pipeline error, if any.
+    pub last_error: Option<String>,
+    /// Sample format the audio is captured in, e.g. `f32`.
+    pub sample_format: String,
+}
+
+/// Level of the captured audio after gain, published for level meters as
+/// often as the audio is processed (see
+/// [`crate::BridgeChannels::frontend_levels_rx`]).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputLevel {
+    /// Average (RMS) level of the latest buffer, in dBFS.
+    pub rms_db: f32,
+    /// Peak level of the latest buffer, in dBFS.
+    pub peak_db: f32,
+}
+
+impl InputLevel {
+    /// Level silence is floored at, in dBFS.
+    pub const SILENCE_DB: f32 = -100.0;
+
+    /// Level of silence, published while nothing is captured.
+    pub const SILENT: InputLevel = InputLevel {
+        rms_db: Self::SILENCE_DB,
+        peak_db: Self::SILENCE_DB,
+    };
+
+    /// Measures the level of `samples`.
+    pub fn measure(samples: &[f32]) -> Self {
+        if samples.is_empty() {
+            return Self::SILENT;
+        }
+
+        let (sum_of_squares, peak) = samples
+            .iter()
+            .fold((0.0f32, 0.0f32), |(sum, peak), sample| {
+                (sum + sample * sample, peak.max(sample.abs()))
+            });
+        let rms = (sum_of_squares / samples.len() as f32).sqrt();
+        Self {
+            rms_db: amplitude_to_db(rms),
+            peak_db: amplitude_to_db(peak),
+        }
+    }
+}
+
+impl Default for InputLevel {
+    fn default() -> Self {
+        Self::SILENT
+    }
+}
+
+/// Converts a linear amplitude to dBFS, flooring silence at
+/// [`InputLevel::SILENCE_DB`].
+fn amplitude_to_db(amplitude: f32) -> f32 {
+    (20.0 * amplitude.log10()).max(InputLevel::SILENCE_DB)
+}
+
+/// Estimate of the delay between speech and its captions with the current
+/// config, broken down into its components. Sums up to
+/// [`LatencyEstimate::estimated_latency_ms`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyEstimate {
+    /// Audio collected into a device buffer before it's handed over, in
+    /// milliseconds.
+    pub capture_buffer_ms: f64,
+    /// Delay of the resampling filter, in milliseconds.
+    pub resampler_ms: f64,
+    /// Average wait for the next decode, half of the repeat interval, in
+    /// milliseconds.
+    pub decode_wait_ms: f64,
+    /// Duration of a decode of the whole context window, in milliseconds.
+    pub decode_ms: f64,
+    /// Whether [`LatencyEstimate::decode_ms`] is based on the real-time
+    /// factor measured in the latest session, rather than guessed from the
+    /// model's size.
+    pub is_decode_measured: bool,
+    /// Audio a centered decode window waits for after a word, in
+    /// milliseconds.
+    pub lookahead_ms: f64,
+}
+
+/// Decoding speed of the active model on this machine, measured by
+/// [`crate::MessageToBackend::BenchmarkModel`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ModelBenchmark {
+    /// Number of measured decodes.
+    pub runs: u32,
+    /// Duration of the audio window every decode has been run on, in
+    /// milliseconds.
+    pub window_ms: u32,
+    /// Average duration of a decode, in milliseconds.
+    pub average_decode_ms: f64,
+    /// Duration of the slowest decode, in milliseconds.
+    pub max_decode_ms: f64,
+    /// Average real-time factor of the decodes; above `1.0` the model can't
+    /// keep up with speech.
+    pub real_time_factor: f64,
+}
+
+impl LatencyEstimate {
+    /// Returns the estimated delay between speech and its captions, in
+    /// milliseconds.
+    pub fn estimated_latency_ms(&self) -> f64 {
+        self.capture_buffer_ms
+            + self.resampler_ms
+            + self.decode_wait_ms
+            + self.decode_ms
+            + self.lookahead_ms
+    }
+}
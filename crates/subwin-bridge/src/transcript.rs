@@ -0,0 +1,14 @@
+//! Lines of the running session's transcript, kept by the frontend for
+//! review.
+
+/// A caption line that has been finalized, and won't change anymore.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscriptLine {
+    /// Start of the line, in milliseconds since the start of the session.
+    pub start_ms: i64,
+    /// End of the line, in milliseconds since the start of the session.
+    pub end_ms: i64,
+    /// Text of the line, prefixed with its speaker's label when channels
+    /// are transcribed separately.
+    pub text: String,
+}
@@ -1,26 +1,578 @@
+use serde::{Deserialize, Serialize};
+
 /// Available Whisper transcription models for download and local inference.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum WhisperModel {
     // Tiny models.
     TinyQuantized8,
     TinyQuantized5,
     Tiny,
-    // Small models.
-    SmallQuantized8,
-    SmallQuantized5,
-    Small,
+    TinyEn,
     // Base models.
     BaseQuantized8,
     BaseQuantized5,
     Base,
+    BaseEn,
+    // Small models.
+    SmallQuantized8,
+    SmallQuantized5,
+    Small,
+    SmallEn,
     // Medium models.
     MediumQuantized8,
     MediumQuantized5,
     Medium,
+    MediumEn,
     // Large models.
     LargeTurboQuantized8,
     LargeTurboQuantized5,
     LargeTurbo,
     LargeQuantized5,
     Large,
+    // Distilled models, published by the distil-whisper project. They keep
+    // the encoder of their teacher, but only two decoder layers, so they run
+    // several times faster at a small loss of accuracy.
+    DistilMediumEn,
+    DistilLargeV3,
+}
+
+/// Number of bytes in a mebibyte, used to express approximate model sizes.
+const MIB: u64 = 1024 * 1024;
+
+/// Languages Whisper has the most training data for besides English, which
+/// even base models transcribe reasonably well, as ISO 639-1 codes.
+const WELL_COVERED_LANGUAGES: [&str; 7] = ["es", "fr", "de", "it", "pt", "nl", "ca"];
+
+/// Size class of a model, from the smallest to the largest. Quantized and
+/// distilled models belong to the class of the model they're derived from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ModelTier {
+    Tiny,
+    Base,
+    Small,
+    Medium,
+    Large,
+}
+
+impl ModelTier {
+    /// Returns the human-readable name of the size class, shown in the UI.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            ModelTier::Tiny => "Мини",
+            ModelTier::Base => "Базовая",
+            ModelTier::Small => "Малая",
+            ModelTier::Medium => "Средняя",
+            ModelTier::Large => "Большая",
+        }
+    }
+
+    /// Returns the smallest size class recommended for speech in
+    /// `language`, given as an ISO 639-1 code. Smaller models make many
+    /// more mistakes on languages Whisper has less training data for.
+    pub fn recommended_for(language: &str) -> ModelTier {
+        let language = language.to_ascii_lowercase();
+        if language == "en" {
+            ModelTier::Tiny
+        } else if WELL_COVERED_LANGUAGES.contains(&language.as_str()) {
+            ModelTier::Base
+        } else {
+            ModelTier::Small
+        }
+    }
+}
+
+/// How well a model is expected to transcribe a language, see
+/// [`WhisperModel::suitability_for`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageSuitability {
+    /// The model is large enough for the language, or the language is
+    /// detected automatically.
+    Suitable,
+    /// The model recognizes the language, but a model of at least the given
+    /// size class is recommended for it.
+    TooSmall(ModelTier),
+    /// The model only recognizes English.
+    Unsupported,
+}
+
+impl WhisperModel {
+    /// All available models, from the smallest to the largest family,
+    /// followed by the distilled ones.
+    pub const ALL: [WhisperModel; 23] = [
+        WhisperModel::TinyQuantized8,
+        WhisperModel::TinyQuantized5,
+        WhisperModel::Tiny,
+        WhisperModel::TinyEn,
+        WhisperModel::BaseQuantized8,
+        WhisperModel::BaseQuantized5,
+        WhisperModel::Base,
+        WhisperModel::BaseEn,
+        WhisperModel::SmallQuantized8,
+        WhisperModel::SmallQuantized5,
+        WhisperModel::Small,
+        WhisperModel::SmallEn,
+        WhisperModel::MediumQuantized8,
+        WhisperModel::MediumQuantized5,
+        WhisperModel::Medium,
+        WhisperModel::MediumEn,
+        WhisperModel::LargeTurboQuantized8,
+        WhisperModel::LargeTurboQuantized5,
+        WhisperModel::LargeTurbo,
+        WhisperModel::LargeQuantized5,
+        WhisperModel::Large,
+        WhisperModel::DistilMediumEn,
+        WhisperModel::DistilLargeV3,
+    ];
+
+    /// Returns the HuggingFace repository the model file is published in.
+    pub fn repository(&self) -> &'static str {
+        match self {
+            WhisperModel::DistilMediumEn => "distil-whisper/distil-medium.en",
+            WhisperModel::DistilLargeV3 => "distil-whisper/distil-large-v3-ggml",
+            _ => "ggerganov/whisper.cpp",
+        }
+    }
+
+    /// Returns the name of the model file, as published in its repository
+    /// (see [`WhisperModel::repository`]).
+    pub fn file_name(&self) -> &'static str {
+        match self {
+            WhisperModel::TinyQuantized8 => "ggml-tiny-q8_0.bin",
+            WhisperModel::TinyQuantized5 => "ggml-tiny-q5_1.bin",
+            WhisperModel::Tiny => "ggml-tiny.bin",
+            WhisperModel::TinyEn => "ggml-tiny.en.bin",
+            WhisperModel::SmallQuantized8 => "ggml-small-q8_0.bin",
+            WhisperModel::SmallQuantized5 => "ggml-small-q5_1.bin",
+            WhisperModel::Small => "ggml-small.bin",
+            WhisperModel::SmallEn => "ggml-small.en.bin",
+            WhisperModel::BaseQuantized8 => "ggml-base-q8_0.bin",
+            WhisperModel::BaseQuantized5 => "ggml-base-q5_1.bin",
+            WhisperModel::Base => "ggml-base.bin",
+            WhisperModel::BaseEn => "ggml-base.en.bin",
+            WhisperModel::MediumQuantized8 => "ggml-medium-q8_0.bin",
+            WhisperModel::MediumQuantized5 => "ggml-medium-q5_0.bin",
+            WhisperModel::Medium => "ggml-medium.bin",
+            WhisperModel::MediumEn => "ggml-medium.en.bin",
+            WhisperModel::LargeTurboQuantized8 => "ggml-large-v3-turbo-q8_0.bin",
+            WhisperModel::LargeTurboQuantized5 => "ggml-large-v3-turbo-q5_0.bin",
+            WhisperModel::LargeTurbo => "ggml-large-v3-turbo.bin",
+            WhisperModel::LargeQuantized5 => "ggml-large-v3-q5_0.bin",
+            WhisperModel::Large => "ggml-large-v3.bin",
+            WhisperModel::DistilMediumEn => "ggml-medium-32-2.en.bin",
+            WhisperModel::DistilLargeV3 => "ggml-distil-large-v3.bin",
+        }
+    }
+
+    /// Returns the human-readable name of the model, shown in the UI.
+    pub fn display_name(&self) -> &'static str {
+        match self {
+            WhisperModel::TinyQuantized8 => "Мини (ускоренная, 8 бит)",
+            WhisperModel::TinyQuantized5 => "Мини (ускоренная, 5 бит)",
+            WhisperModel::Tiny => "Мини",
+            WhisperModel::TinyEn => "Мини (только английский)",
+            WhisperModel::SmallQuantized8 => "Малая (ускоренная, 8 бит)",
+            WhisperModel::SmallQuantized5 => "Малая (ускоренная, 5 бит)",
+            WhisperModel::Small => "Малая",
+            WhisperModel::SmallEn => "Малая (только английский)",
+            WhisperModel::BaseQuantized8 => "Базовая (ускоренная, 8 бит)",
+            WhisperModel::BaseQuantized5 => "Базовая (ускоренная, 5 бит)",
+            WhisperModel::Base => "Базовая",
+            WhisperModel::BaseEn => "Базовая (только английский)",
+            WhisperModel::MediumQuantized8 => "Средняя (ускоренная, 8 бит)",
+            WhisperModel::MediumQuantized5 => "Средняя (ускоренная, 5 бит)",
+            WhisperModel::Medium => "Средняя",
+            WhisperModel::MediumEn => "Средняя (только английский)",
+            WhisperModel::LargeTurboQuantized8 => "Большая турбо (ускоренная, 8 бит)",
+            WhisperModel::LargeTurboQuantized5 => "Большая турбо (ускоренная, 5 бит)",
+            WhisperModel::LargeTurbo => "Большая турбо",
+            WhisperModel::LargeQuantized5 => "Большая (ускоренная, 5 бит)",
+            WhisperModel::Large => "Большая",
+            WhisperModel::DistilMediumEn => "Средняя дистиллированная (только английский)",
+            WhisperModel::DistilLargeV3 => "Большая дистиллированная (только английский)",
+        }
+    }
+
+    /// Returns whether the model recognizes languages other than English.
+    pub fn is_multilingual(&self) -> bool {
+        !matches!(
+            self,
+            WhisperModel::TinyEn
+                | WhisperModel::BaseEn
+                | WhisperModel::SmallEn
+                | WhisperModel::MediumEn
+                | WhisperModel::DistilMediumEn
+                | WhisperModel::DistilLargeV3
+        )
+    }
+
+    /// Returns whether the model can transcribe speech in `language`, given
+    /// as an ISO 639-1 code. `None` stands for automatic detection, which
+    /// English-only models simply treat as English.
+    pub fn supports_language(&self, language: Option<&str>) -> bool {
+        self.is_multilingual()
+            || language.is_none_or(|language| language.eq_ignore_ascii_case("en"))
+    }
+
+    /// Returns the size class of the model.
+    pub fn tier(&self) -> ModelTier {
+        match self {
+            WhisperModel::TinyQuantized8
+            | WhisperModel::TinyQuantized5
+            | WhisperModel::Tiny
+            | WhisperModel::TinyEn => ModelTier::Tiny,
+            WhisperModel::BaseQuantized8
+            | WhisperModel::BaseQuantized5
+            | WhisperModel::Base
+            | WhisperModel::BaseEn => ModelTier::Base,
+            WhisperModel::SmallQuantized8
+            | WhisperModel::SmallQuantized5
+            | WhisperModel::Small
+            | WhisperModel::SmallEn => ModelTier::Small,
+            WhisperModel::MediumQuantized8
+            | WhisperModel::MediumQuantized5
+            | WhisperModel::Medium
+            | WhisperModel::MediumEn
+            | WhisperModel::DistilMediumEn => ModelTier::Medium,
+            WhisperModel::LargeTurboQuantized8
+            | WhisperModel::LargeTurboQuantized5
+            | WhisperModel::LargeTurbo
+            | WhisperModel::LargeQuantized5
+            | WhisperModel::Large
+            | WhisperModel::DistilLargeV3 => ModelTier::Large,
+        }
+    }
+
+    /// Returns how well the model is expected to transcribe speech in
+    /// `language`, given as an ISO 639-1 code; `None` stands for automatic
+    /// detection, which any model can do.
+    pub fn suitability_for(&self, language: Option<&str>) -> LanguageSuitability {
+        if !self.supports_language(language) {
+            return LanguageSuitability::Unsupported;
+        }
+        match language.map(ModelTier::recommended_for) {
+            Some(recommended) if self.tier() < recommended => {
+                LanguageSuitability::TooSmall(recommended)
+            }
+            _ => LanguageSuitability::Suitable,
+        }
+    }
+
+    /// Returns the approximate number of the model's parameters. Quantized
+    /// models have as many parameters as their full-precision counterparts.
+    pub fn parameter_count(&self) -> u64 {
+        const MILLION: u64 = 1_000_000;
+        let count_millions = match self {
+            WhisperModel::TinyQuantized8
+            | WhisperModel::TinyQuantized5
+            | WhisperModel::Tiny
+            | WhisperModel::TinyEn => 39,
+            WhisperModel::BaseQuantized8
+            | WhisperModel::BaseQuantized5
+            | WhisperModel::Base
+            | WhisperModel::BaseEn => 74,
+            WhisperModel::SmallQuantized8
+            | WhisperModel::SmallQuantized5
+            | WhisperModel::Small
+            | WhisperModel::SmallEn => 244,
+            WhisperModel::MediumQuantized8
+            | WhisperModel::MediumQuantized5
+            | WhisperModel::Medium
+            | WhisperModel::MediumEn => 769,
+            WhisperModel::LargeTurboQuantized8
+            | WhisperModel::LargeTurboQuantized5
+            | WhisperModel::LargeTurbo => 809,
+            WhisperModel::LargeQuantized5 | WhisperModel::Large => 1550,
+            WhisperModel::DistilMediumEn => 394,
+            WhisperModel::DistilLargeV3 => 756,
+        };
+        count_millions * MILLION
+    }
+
+    /// Returns the models with fewer parameters than this one, so faster to
+    /// run, from the largest to the smallest. Used to fall back to a faster
+    /// model when this one can't keep up with real time.
+    pub fn smaller_models(&self) -> Vec<WhisperModel> {
+        let mut models: Vec<WhisperModel> = WhisperModel::ALL
+            .into_iter()
+            .filter(|model| model.parameter_count() < self.parameter_count())
+            .collect();
+        models.sort_by_key(|model| {
+            std::cmp::Reverse((model.parameter_count(), model.approx_size_bytes()))
+        });
+        models
+    }
+
+    /// Finds the model a file has been downloaded as, by its file name.
+    pub fn from_file_name(file_name: &str) -> Option<WhisperModel> {
+        WhisperModel::ALL
+            .into_iter()
+            .find(|model| model.file_name() == file_name)
+    }
+
+    /// Finds the model stored at `path`, judging by its file name. Returns
+    /// `None` for custom models.
+    pub fn from_path(path: &std::path::Path) -> Option<WhisperModel> {
+        path.file_name()
+            .and_then(|file_name| file_name.to_str())
+            .and_then(WhisperModel::from_file_name)
+    }
+
+    /// Returns whether the model has been downloaded into `cache_path`.
+    /// Interrupted downloads are stored under a different name, so they
+    /// don't count.
+    pub fn is_downloaded(&self, cache_path: &std::path::Path) -> bool {
+        cache_path.join(self.file_name()).is_file()
+    }
+
+    /// Returns the approximate size of the model file, in bytes.
+    ///
+    /// Sizes are rounded to whole mebibytes and are meant for display and
+    /// rough disk space estimates, not for verifying downloaded files.
+    pub fn approx_size_bytes(&self) -> u64 {
+        let size_mib = match self {
+            WhisperModel::TinyQuantized8 => 42,
+            WhisperModel::TinyQuantized5 => 31,
+            WhisperModel::Tiny => 75,
+            WhisperModel::TinyEn => 75,
+            WhisperModel::SmallQuantized8 => 252,
+            WhisperModel::SmallQuantized5 => 181,
+            WhisperModel::Small => 466,
+            WhisperModel::SmallEn => 466,
+            WhisperModel::BaseQuantized8 => 78,
+            WhisperModel::BaseQuantized5 => 57,
+            WhisperModel::Base => 142,
+            WhisperModel::BaseEn => 142,
+            WhisperModel::MediumQuantized8 => 785,
+            WhisperModel::MediumQuantized5 => 514,
+            WhisperModel::Medium => 1463,
+            WhisperModel::MediumEn => 1463,
+            WhisperModel::LargeTurboQuantized8 => 834,
+            WhisperModel::LargeTurboQuantized5 => 547,
+            WhisperModel::LargeTurbo => 1549,
+            WhisperModel::LargeQuantized5 => 1031,
+            WhisperModel::Large => 2951,
+            WhisperModel::DistilMediumEn => 757,
+            WhisperModel::DistilLargeV3 => 1449,
+        };
+        size_mib * MIB
+    }
+}
+
+/// Decoding strategy used by Whisper to pick tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", tag = "type")]
+pub enum DecodingStrategy {
+    /// Picks the most likely token at every step. Fast, and good enough for
+    /// larger models.
+    Greedy,
+    /// Keeps `beam_size` candidate sequences. Slower, but helps smaller
+    /// models stay coherent.
+    BeamSearch { beam_size: u32 },
+}
+
+/// Transcription parameters recommended for a specific model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParamsProfile {
+    /// Strategy used to decode tokens.
+    pub strategy: DecodingStrategy,
+    /// Length of the rolling audio window, in milliseconds.
+    pub context_length_ms: u32,
+    /// Interval between transcription runs, in milliseconds.
+    pub repeat_run_ms: u32,
+}
+
+impl Default for ParamsProfile {
+    /// Profile for models of unknown size, e.g. custom ones.
+    fn default() -> Self {
+        Self {
+            strategy: DecodingStrategy::Greedy,
+            context_length_ms: 3000,
+            repeat_run_ms: 500,
+        }
+    }
+}
+
+/// Returns the recommended transcription parameters for `model`.
+///
+/// Tiny and base models lose coherence on long windows and benefit from beam
+/// search, while medium and large ones do fine with greedy decoding, but are
+/// too slow to run as often. Distilled models decode fast enough to keep the
+/// usual interval.
+pub fn default_params_for(model: &WhisperModel) -> ParamsProfile {
+    match model {
+        WhisperModel::TinyQuantized8
+        | WhisperModel::TinyQuantized5
+        | WhisperModel::Tiny
+        | WhisperModel::TinyEn => ParamsProfile {
+            strategy: DecodingStrategy::BeamSearch { beam_size: 5 },
+            context_length_ms: 2000,
+            repeat_run_ms: 500,
+        },
+        WhisperModel::BaseQuantized8
+        | WhisperModel::BaseQuantized5
+        | WhisperModel::Base
+        | WhisperModel::BaseEn => ParamsProfile {
+            strategy: DecodingStrategy::BeamSearch { beam_size: 3 },
+            context_length_ms: 2500,
+            repeat_run_ms: 500,
+        },
+        WhisperModel::SmallQuantized8
+        | WhisperModel::SmallQuantized5
+        | WhisperModel::Small
+        | WhisperModel::SmallEn => ParamsProfile::default(),
+        WhisperModel::MediumQuantized8
+        | WhisperModel::MediumQuantized5
+        | WhisperModel::Medium
+        | WhisperModel::MediumEn => ParamsProfile {
+            strategy: DecodingStrategy::Greedy,
+            context_length_ms: 3000,
+            repeat_run_ms: 750,
+        },
+        WhisperModel::LargeTurboQuantized8
+        | WhisperModel::LargeTurboQuantized5
+        | WhisperModel::LargeTurbo
+        | WhisperModel::LargeQuantized5
+        | WhisperModel::Large => ParamsProfile {
+            strategy: DecodingStrategy::Greedy,
+            context_length_ms: 4000,
+            repeat_run_ms: 1000,
+        },
+        WhisperModel::DistilMediumEn | WhisperModel::DistilLargeV3 => ParamsProfile {
+            strategy: DecodingStrategy::Greedy,
+            context_length_ms: 3000,
+            repeat_run_ms: 500,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn models_are_listed_from_the_smallest_family() {
+        let (distilled, families): (Vec<_>, Vec<_>) = WhisperModel::ALL
+            .into_iter()
+            .partition(|model| model.repository() != WhisperModel::Tiny.repository());
+        assert!(families.is_sorted_by_key(WhisperModel::tier));
+        assert_eq!(
+            distilled,
+            [WhisperModel::DistilMediumEn, WhisperModel::DistilLargeV3]
+        );
+        assert!(WhisperModel::ALL.ends_with(&distilled));
+    }
+
+    #[test]
+    fn smaller_size_classes_are_recommended_for_better_covered_languages() {
+        assert_eq!(ModelTier::recommended_for("en"), ModelTier::Tiny);
+        assert_eq!(ModelTier::recommended_for("EN"), ModelTier::Tiny);
+        assert_eq!(ModelTier::recommended_for("de"), ModelTier::Base);
+        assert_eq!(ModelTier::recommended_for("Fr"), ModelTier::Base);
+        assert_eq!(ModelTier::recommended_for("ru"), ModelTier::Small);
+        assert_eq!(ModelTier::recommended_for("uk"), ModelTier::Small);
+    }
+
+    #[test]
+    fn models_of_the_recommended_size_class_are_suitable() {
+        let cases = [
+            // the smallest suitable class and the one right below it
+            (WhisperModel::Small, "ru", LanguageSuitability::Suitable),
+            (
+                WhisperModel::BaseQuantized5,
+                "ru",
+                LanguageSuitability::TooSmall(ModelTier::Small),
+            ),
+            (WhisperModel::Base, "de", LanguageSuitability::Suitable),
+            (
+                WhisperModel::Tiny,
+                "de",
+                LanguageSuitability::TooSmall(ModelTier::Base),
+            ),
+            (
+                WhisperModel::TinyQuantized5,
+                "en",
+                LanguageSuitability::Suitable,
+            ),
+            // larger classes stay suitable
+            (
+                WhisperModel::LargeTurbo,
+                "ru",
+                LanguageSuitability::Suitable,
+            ),
+        ];
+        for (model, language, suitability) in cases {
+            assert_eq!(
+                model.suitability_for(Some(language)),
+                suitability,
+                "{model:?} for {language:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn english_only_models_suit_no_other_language() {
+        for model in [
+            WhisperModel::TinyEn,
+            WhisperModel::MediumEn,
+            WhisperModel::DistilMediumEn,
+            WhisperModel::DistilLargeV3,
+        ] {
+            assert_eq!(
+                model.suitability_for(Some("ru")),
+                LanguageSuitability::Unsupported
+            );
+            assert_eq!(
+                model.suitability_for(Some("en")),
+                LanguageSuitability::Suitable
+            );
+        }
+    }
+
+    #[test]
+    fn any_model_suits_automatic_detection() {
+        for model in WhisperModel::ALL {
+            assert_eq!(model.suitability_for(None), LanguageSuitability::Suitable);
+        }
+    }
+
+    #[test]
+    fn smaller_models_go_from_the_largest_to_the_smallest() {
+        let smaller = WhisperModel::Small.smaller_models();
+        assert_eq!(
+            smaller.iter().map(WhisperModel::tier).collect::<Vec<_>>(),
+            [ModelTier::Base; 4]
+                .into_iter()
+                .chain([ModelTier::Tiny; 4])
+                .collect::<Vec<_>>()
+        );
+        // among equally large models, the ones with larger files come first
+        assert_eq!(smaller[0], WhisperModel::Base);
+        assert_eq!(smaller[3], WhisperModel::BaseQuantized5);
+        assert_eq!(smaller[7], WhisperModel::TinyQuantized5);
+
+        let smaller = WhisperModel::Large.smaller_models();
+        assert_eq!(smaller[0], WhisperModel::LargeTurbo);
+        assert!(smaller.contains(&WhisperModel::DistilLargeV3));
+        assert!(
+            smaller
+                .windows(2)
+                .all(|pair| pair[0].parameter_count() >= pair[1].parameter_count())
+        );
+    }
+
+    #[test]
+    fn smaller_models_have_fewer_parameters_only() {
+        for model in [WhisperModel::Medium, WhisperModel::MediumQuantized5] {
+            assert!(!model.smaller_models().contains(&WhisperModel::MediumEn));
+        }
+        for model in [
+            WhisperModel::TinyQuantized8,
+            WhisperModel::TinyQuantized5,
+            WhisperModel::Tiny,
+            WhisperModel::TinyEn,
+        ] {
+            assert_eq!(model.smaller_models(), []);
+        }
+    }
 }
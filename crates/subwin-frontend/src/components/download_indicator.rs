@@ -1,28 +1,53 @@
 use gpui::{ParentElement, Styled, div, prelude::FluentBuilder};
 use gpui_component::ActiveTheme;
 
-use crate::formatting::{format_bytes, format_eta, format_speed};
+use crate::{
+    entities::download_entity::{DownloadProgressEvent, VerificationProgressEvent},
+    formatting::{format_bytes, format_eta, format_speed},
+};
+
+/// Phase of a model download, which is in progress.
+#[derive(Debug, Clone, Copy)]
+enum DownloadPhase {
+    Downloading(DownloadProgressEvent),
+    /// The downloaded file is being checked against its checksum.
+    Verifying(VerificationProgressEvent),
+}
 
 #[derive(Debug, Clone)]
 pub struct DownloadIndicator {
-    download_progress_event: Option<crate::entities::download_entity::DownloadProgressEvent>,
+    phase: Option<DownloadPhase>,
 }
 
 impl DownloadIndicator {
     pub fn new(data: &crate::entities::DataEntities, cx: &mut gpui::Context<Self>) -> Self {
-        cx.subscribe(&data.download, |this, _, event, cx| {
-            this.download_progress_event = if event.downloaded_bytes != event.total_bytes {
-                Some(*event)
-            } else {
-                None
-            };
+        cx.subscribe(
+            &data.download,
+            |this, _, event: &DownloadProgressEvent, cx| {
+                this.phase = if event.downloaded_bytes != event.total_bytes {
+                    Some(DownloadPhase::Downloading(*event))
+                } else {
+                    None
+                };
 
-            cx.notify();
-        })
+                cx.notify();
+            },
+        )
         .detach();
-        Self {
-            download_progress_event: None,
-        }
+        cx.subscribe(
+            &data.download,
+            |this, _, event: &VerificationProgressEvent, cx| {
+                this.phase = if event.processed_bytes != event.total_bytes {
+                    Some(DownloadPhase::Verifying(*event))
+                } else {
+                    None
+                };
+
+                cx.notify();
+            },
+        )
+        .detach();
+        Self { phase: None }
     }
 }
 
@@ -35,15 +60,19 @@ impl gpui::Render for DownloadIndicator {
         div()
             .text_sm()
             .text_color(cx.theme().muted_foreground)
-            .when(self.download_progress_event.is_some(), |this| {
-                let progress = self.download_progress_event.unwrap();
-                this.child(format!(
+            .when_some(self.phase, |this, phase| match phase {
+                DownloadPhase::Downloading(progress) => this.child(format!(
                     "Скачано {} из {} ({}). Осталось {}.",
                     format_bytes(progress.downloaded_bytes),
                     format_bytes(progress.total_bytes),
                     format_speed(progress.speed),
                     format_eta(progress.remaining_time),
-                ))
+                )),
+                DownloadPhase::Verifying(progress) => this.child(format!(
+                    "Проверка... {} из {}.",
+                    format_bytes(progress.processed_bytes),
+                    format_bytes(progress.total_bytes),
+                )),
             })
     }
 }
@@ -1,4 +1,6 @@
 #[derive(Debug, Clone, Default)]
 pub struct AudioDevicesEntity {
     pub audio_devices: Vec<subwin_bridge::audio::InputDevice>,
+    /// Native format of the active input device, if one is selected.
+    pub active_device_info: Option<subwin_bridge::audio::ActiveDeviceInfo>,
 }
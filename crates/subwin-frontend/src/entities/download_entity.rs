@@ -6,18 +6,29 @@ pub struct DownloadProgressEvent {
     pub remaining_time: f64,
 }
 
+/// Progress of checking a downloaded model file, which follows the download.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VerificationProgressEvent {
+    pub processed_bytes: u64,
+    pub total_bytes: u64,
+}
+
 #[derive(Debug, Clone)]
 pub struct DownloadEntity {
     pub progress: DownloadProgressEvent,
+    /// Models present in the cache, as last reported by the backend.
+    pub downloaded_models: Vec<subwin_bridge::whisper_model::WhisperModel>,
 }
 
 impl DownloadEntity {
     pub fn new(_: &mut gpui::Context<Self>) -> Self {
         Self {
             progress: DownloadProgressEvent::default(),
+            downloaded_models: Vec::new(),
         }
     }
 }
 
 impl gpui::EventEmitter<DownloadProgressEvent> for DownloadEntity {}
+impl gpui::EventEmitter<VerificationProgressEvent> for DownloadEntity {}
 impl gpui::Global for DownloadEntity {}
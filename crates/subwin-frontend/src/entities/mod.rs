@@ -4,10 +4,58 @@ pub mod audio_devices_entity;
 pub mod download_entity;
 pub mod settings_entity;
 
+/// Number of transcript lines kept; the oldest ones are dropped beyond it.
+const MAX_TRANSCRIPT_LINES: usize = 5000;
+
 #[derive(Debug, Clone, Default)]
 pub struct CaptionsEntity {
+    /// Whether the backend reports a running transcription session.
+    pub is_running: bool,
+    /// Whether the backend is starting a session, loading the model.
+    pub is_starting: bool,
+    /// Identifier of the running session, if any.
+    pub session_id: Option<u64>,
+    /// Identifier of the latest finished session. Caption updates of it and
+    /// of earlier sessions are ignored.
+    pub finished_session_id: u64,
+    /// Whether the running session has released the input device after a
+    /// long silence.
+    pub is_capture_released: bool,
     pub last_run_duration: u128,
     pub last_run_content: String,
+    /// Byte ranges of [`CaptionsEntity::last_run_content`] the model is
+    /// unsure about.
+    pub last_run_uncertain_ranges: Vec<std::ops::Range<usize>>,
+    /// Moment the last caption update has been received, if any.
+    pub last_update_at: Option<std::time::Instant>,
+    /// Real-time factor of the latest inference (see
+    /// [`subwin_bridge::MessageFromBackend::TranscriptionMetrics`]).
+    pub real_time_factor: f64,
+    /// Latest pipeline stats of the running session, if any.
+    pub pipeline_stats: Option<subwin_bridge::stats::PipelineStats>,
+    /// Whether the backend is benchmarking the active model.
+    pub is_benchmarking: bool,
+    /// Estimated caption delay with the current config, if known.
+    pub latency_estimate: Option<subwin_bridge::stats::LatencyEstimate>,
+    /// Problems found in the config by the latest validation.
+    pub config_issues: Vec<subwin_bridge::config::ConfigIssue>,
+    /// Finalized caption lines, in order, kept across sessions until
+    /// they're cleared.
+    pub transcript: Vec<subwin_bridge::transcript::TranscriptLine>,
+    /// Number of lines ever appended to the transcript. Unlike its length,
+    /// it keeps growing once the oldest lines get dropped.
+    pub transcript_appended_lines: u64,
+}
+
+impl CaptionsEntity {
+    /// Appends `lines` to the transcript, dropping the oldest lines beyond
+    /// [`MAX_TRANSCRIPT_LINES`].
+    pub fn append_transcript(&mut self, lines: Vec<subwin_bridge::transcript::TranscriptLine>) {
+        self.transcript_appended_lines += lines.len() as u64;
+        self.transcript.extend(lines);
+        let excess = self.transcript.len().saturating_sub(MAX_TRANSCRIPT_LINES);
+        self.transcript.drain(..excess);
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -16,4 +64,7 @@ pub struct DataEntities {
     pub download: Entity<download_entity::DownloadEntity>,
     pub audio_devices: Entity<audio_devices_entity::AudioDevicesEntity>,
     pub captions: Entity<CaptionsEntity>,
+    /// Latest input level of the running session, updated separately from
+    /// the captions, as it changes much more often.
+    pub input_level: Entity<subwin_bridge::stats::InputLevel>,
 }
@@ -1,9 +1,23 @@
 use gpui::{AppContext, Entity};
 use subwin_bridge::config::Config;
 
+/// Whether the changes made in the settings have reached the disk.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConfigSaveStatus {
+    /// Nothing has been changed yet.
+    #[default]
+    Unchanged,
+    /// Changes have been sent, but their save hasn't been confirmed, or it
+    /// has failed.
+    Pending,
+    /// The latest changes have been saved.
+    Saved,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct SettingsEntity {
     pub config: Config,
+    pub save_status: ConfigSaveStatus,
 }
 
 impl SettingsEntity {
@@ -13,4 +27,15 @@ impl SettingsEntity {
             cx.notify();
         });
     }
+
+    pub fn set_save_status<C: AppContext>(
+        entity: &Entity<Self>,
+        save_status: ConfigSaveStatus,
+        cx: &mut C,
+    ) {
+        entity.update(cx, |this, cx| {
+            this.save_status = save_status;
+            cx.notify();
+        });
+    }
 }
@@ -57,3 +57,39 @@ pub fn format_eta(seconds: f64) -> String {
 
     out
 }
+
+/// Checks whether `c` belongs to a right-to-left script block: Hebrew,
+/// Arabic, Syriac, Thaana and related scripts, including their presentation
+/// forms.
+fn is_rtl_char(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0590}'..='\u{08FF}'
+            | '\u{FB1D}'..='\u{FDFF}'
+            | '\u{FE70}'..='\u{FEFF}'
+            | '\u{10800}'..='\u{10FFF}'
+            | '\u{1E800}'..='\u{1EFFF}'
+    )
+}
+
+/// Detects whether `text` should be rendered right-to-left, judging by its
+/// first strongly directional (alphabetic) character. Text without any such
+/// character is considered left-to-right.
+pub fn is_rtl_text(text: &str) -> bool {
+    text.chars()
+        .find(|c| c.is_alphabetic())
+        .is_some_and(is_rtl_char)
+}
+
+/// Escapes the punctuation of `text` that markdown could interpret, e.g.
+/// `*` around a word, so a markdown text view shows it as is.
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c.is_ascii_punctuation() {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
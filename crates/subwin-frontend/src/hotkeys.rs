@@ -0,0 +1,116 @@
+//! System-wide shortcuts, working while another application is focused.
+//!
+//! Users keep their game or call focused while the captions run, so the
+//! quick actions are registered with the OS instead of a gpui key context.
+//! Global shortcuts aren't available everywhere (e.g. on Wayland), so each
+//! of them also has an in-window binding (see [`crate::views::bind_keys`]),
+//! except for push-to-talk, which has to be held while speaking elsewhere.
+
+use std::time::Duration;
+
+use global_hotkey::{
+    GlobalHotKeyEvent, GlobalHotKeyManager, HotKeyState,
+    hotkey::{CMD_OR_CTRL, Code, HotKey, Modifiers},
+};
+use gpui::{App, Global};
+use subwin_bridge::config::PushToTalkConfig;
+
+use crate::{BackendBridge, entities::DataEntities, views::copy_last_caption};
+
+/// Interval, in which the pressed global shortcuts are picked up.
+const HOTKEY_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Keeps the registered shortcuts alive, they are unregistered once the
+/// manager is dropped.
+struct GlobalHotkeys {
+    manager: GlobalHotKeyManager,
+    /// The registered push-to-talk shortcut, if push-to-talk is enabled.
+    push_to_talk: Option<HotKey>,
+}
+
+impl Global for GlobalHotkeys {}
+
+/// Registers the global shortcuts and handles them until the application
+/// quits. Failing to register them isn't fatal, the in-window bindings
+/// still work.
+pub fn register_global_hotkeys(data: &DataEntities, cx: &mut App) {
+    let manager = match GlobalHotKeyManager::new() {
+        Ok(manager) => manager,
+        Err(err) => {
+            log::warn!("Global shortcuts are not available: {err}");
+            return;
+        }
+    };
+
+    let copy_last_caption_hotkey = HotKey::new(Some(CMD_OR_CTRL | Modifiers::ALT), Code::KeyC);
+    if let Err(err) = manager.register(copy_last_caption_hotkey) {
+        log::warn!("Failed to register the copy last caption shortcut: {err}");
+        return;
+    }
+    cx.set_global(GlobalHotkeys {
+        manager,
+        push_to_talk: None,
+    });
+
+    // the push-to-talk shortcut follows the config, which arrives later
+    cx.observe(&data.settings, |settings, cx| {
+        let push_to_talk = settings.read(cx).config.push_to_talk.clone();
+        update_push_to_talk_hotkey(&push_to_talk, cx);
+    })
+    .detach();
+
+    let captions = data.captions.clone();
+    cx.spawn(async move |cx| {
+        loop {
+            cx.background_executor().timer(HOTKEY_POLL_INTERVAL).await;
+            while let Ok(event) = GlobalHotKeyEvent::receiver().try_recv() {
+                let _ = cx.update(|cx| {
+                    let push_to_talk = cx.global::<GlobalHotkeys>().push_to_talk;
+                    if push_to_talk.is_some_and(|hotkey| hotkey.id() == event.id()) {
+                        let bridge = cx.global::<BackendBridge>().clone();
+                        let is_held = event.state() == HotKeyState::Pressed;
+                        cx.spawn(async move |_| bridge.push_to_talk(is_held).await)
+                            .detach();
+                    } else if event.state() == HotKeyState::Pressed
+                        && event.id() == copy_last_caption_hotkey.id()
+                    {
+                        copy_last_caption(&captions, cx);
+                    }
+                });
+            }
+        }
+    })
+    .detach();
+}
+
+/// Registers the push-to-talk shortcut of `config` in place of the previous
+/// one, or unregisters it if push-to-talk is disabled.
+fn update_push_to_talk_hotkey(config: &PushToTalkConfig, cx: &mut App) {
+    let hotkey = if config.enabled {
+        match config.hotkey.parse::<HotKey>() {
+            Ok(hotkey) => Some(hotkey),
+            Err(err) => {
+                log::warn!("Invalid push-to-talk shortcut {:?}: {err}", config.hotkey);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    let hotkeys = cx.global_mut::<GlobalHotkeys>();
+    if hotkeys.push_to_talk == hotkey {
+        return;
+    }
+    if let Some(previous) = hotkeys.push_to_talk.take()
+        && let Err(err) = hotkeys.manager.unregister(previous)
+    {
+        log::warn!("Failed to unregister the push-to-talk shortcut: {err}");
+    }
+    if let Some(hotkey) = hotkey {
+        match hotkeys.manager.register(hotkey) {
+            Ok(()) => hotkeys.push_to_talk = Some(hotkey),
+            Err(err) => log::warn!("Failed to register the push-to-talk shortcut: {err}"),
+        }
+    }
+}
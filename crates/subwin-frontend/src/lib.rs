@@ -1,23 +1,128 @@
-use gpui::{AppContext, Application, Global, WindowOptions};
+use std::time::{Duration, Instant};
+
+use gpui::{AnyWindowHandle, AppContext, Application, AsyncApp, Global, WindowOptions};
 use gpui_component::{
     Root, WindowExt,
     notification::{Notification, NotificationType},
 };
-use subwin_bridge::MessageFromBackend;
-use tokio::sync::mpsc;
+use subwin_bridge::{MessageFromBackend, audio::AudioCaptureError, stats::InputLevel};
+use tokio::sync::{mpsc, watch};
 
 use crate::entities::{
     CaptionsEntity,
     audio_devices_entity::AudioDevicesEntity,
-    download_entity::{DownloadEntity, DownloadProgressEvent},
-    settings_entity::SettingsEntity,
+    download_entity::{DownloadEntity, DownloadProgressEvent, VerificationProgressEvent},
+    settings_entity::{ConfigSaveStatus, SettingsEntity},
 };
 
 pub mod components;
 pub mod entities;
 pub mod formatting;
+mod hotkeys;
+mod platform;
 mod views;
 
+/// Average level in dBFS below which a tested microphone is reported as too
+/// quiet.
+const QUIET_MIC_THRESHOLD_DB: f32 = -45.0;
+
+/// Interval, in which repeats of the last shown notification are collapsed
+/// into it instead of being shown again.
+const NOTIFICATION_REPEAT_WINDOW: Duration = Duration::from_secs(5);
+
+/// Remembers the last shown notification, so the errors repeating in the
+/// realtime pipeline don't bury the UI in identical notifications.
+#[derive(Default)]
+struct NotificationThrottle {
+    /// Text of the last shown notification, along with when it was shown.
+    last_shown: Option<(String, Instant)>,
+    /// Number of the repeats of the last notification suppressed since.
+    suppressed: usize,
+}
+
+impl Global for NotificationThrottle {}
+
+impl NotificationThrottle {
+    /// Returns the text to show for `message`, or `None` if it repeats the
+    /// last notification, shown less than [`NOTIFICATION_REPEAT_WINDOW`] ago.
+    /// Once shown again, the text mentions the number of suppressed repeats.
+    fn admit(&mut self, message: &str, now: Instant) -> Option<String> {
+        let is_repeat = self
+            .last_shown
+            .as_ref()
+            .is_some_and(|(last_message, _)| last_message == message);
+        if !is_repeat {
+            self.suppressed = 0;
+            self.last_shown = Some((message.to_string(), now));
+            return Some(message.to_string());
+        }
+
+        if let Some((_, shown_at)) = &mut self.last_shown {
+            if now.duration_since(*shown_at) < NOTIFICATION_REPEAT_WINDOW {
+                self.suppressed += 1;
+                return None;
+            }
+            *shown_at = now;
+        }
+        let suppressed = std::mem::take(&mut self.suppressed);
+        Some(if suppressed > 0 {
+            format!("{message} (ещё повторов: {suppressed})")
+        } else {
+            message.to_string()
+        })
+    }
+}
+
+/// Shows a notification in the window, unless it has just been shown.
+fn push_notification(
+    window_handle: AnyWindowHandle,
+    message: String,
+    notification_type: NotificationType,
+    cx: &mut AsyncApp,
+) {
+    let _ = window_handle.update(cx, |_, window, cx| {
+        let throttle = cx.global_mut::<NotificationThrottle>();
+        let Some(message) = throttle.admit(&message, Instant::now()) else {
+            log::debug!("Suppressed a repeated notification: {message}");
+            return;
+        };
+        let notification = Notification::new()
+            .message(message)
+            .with_type(notification_type);
+        window.push_notification(notification, cx);
+    });
+}
+
+/// Describes a failure to capture audio to the user, along with the way to
+/// fix it.
+fn audio_error_message(error: &AudioCaptureError) -> String {
+    match error {
+        AudioCaptureError::DeviceNotAvailable => {
+            "Устройство записи отключено или недоступно. Подключите его или выберите другое."
+                .to_string()
+        }
+        AudioCaptureError::DeviceBusy => {
+            "Устройство записи занято другим приложением. Закройте его или выберите другое устройство."
+                .to_string()
+        }
+        AudioCaptureError::UnsupportedConfig => {
+            "Устройство записи не поддерживает нужный формат звука. Выберите другое устройство или другой формат сэмплов в настройках."
+                .to_string()
+        }
+        AudioCaptureError::PermissionDenied if cfg!(target_os = "macos") => {
+            "Нет доступа к микрофону. Разрешите его в «Системные настройки» → «Конфиденциальность и безопасность» → «Микрофон» и перезапустите приложение."
+                .to_string()
+        }
+        AudioCaptureError::PermissionDenied => {
+            "Нет доступа к устройству записи. Разрешите приложениям доступ к микрофону в настройках системы."
+                .to_string()
+        }
+        AudioCaptureError::Backend(description) => {
+            format!("Не удалось захватить звук: {description}")
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct BackendBridge {
     pub to_backend: mpsc::Sender<subwin_bridge::MessageToBackend>,
@@ -31,6 +136,15 @@ impl BackendBridge {
             .expect("failed to request config");
     }
 
+    pub async fn update_config(&self, config: subwin_bridge::config::Config) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::UpdateConfigRequest(
+                Box::new(config),
+            ))
+            .await
+            .expect("failed to update config");
+    }
+
     pub async fn download_model(&self, model: subwin_bridge::whisper_model::WhisperModel) {
         self.to_backend
             .send(subwin_bridge::MessageToBackend::DownloadModelRequest(model))
@@ -38,6 +152,69 @@ impl BackendBridge {
             .expect("failed to request model download");
     }
 
+    pub async fn request_downloaded_models(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::DownloadedModelsRequest)
+            .await
+            .expect("failed to request downloaded models");
+    }
+
+    pub async fn set_active_model(&self, model: subwin_bridge::whisper_model::WhisperModel) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::SetActiveModel(model))
+            .await
+            .expect("failed to switch the active model");
+    }
+
+    pub async fn request_status(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::StatusRequest)
+            .await
+            .expect("failed to request backend status");
+    }
+
+    pub async fn request_latency_estimate(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::LatencyEstimateRequest)
+            .await
+            .expect("failed to request a latency estimate");
+    }
+
+    pub async fn benchmark_model(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::BenchmarkModel)
+            .await
+            .expect("failed to request a model benchmark");
+    }
+
+    pub async fn validate_config(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::ValidateConfig)
+            .await
+            .expect("failed to request a config validation");
+    }
+
+    pub async fn export_config(&self, path: std::path::PathBuf) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::ExportConfigTo(path))
+            .await
+            .expect("failed to request a config export");
+    }
+
+    pub async fn import_config(&self, path: std::path::PathBuf) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::ImportConfigFrom(path))
+            .await
+            .expect("failed to request a config import");
+    }
+
+    pub async fn export_diagnostics(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::ExportDiagnostics)
+            .await
+            .expect("failed to request a diagnostics export");
+    }
+
     pub async fn request_audio_devices_list(&self) {
         self.to_backend
             .send(subwin_bridge::MessageToBackend::AudioDevicesListRequest)
@@ -60,36 +237,320 @@ impl BackendBridge {
             .await
             .expect("failed to select the audio device");
     }
+
+    pub async fn test_microphone(&self, device_id: String, duration_ms: u32, playback: bool) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::TestMicrophone {
+                device_id,
+                duration_ms,
+                playback,
+            })
+            .await
+            .expect("failed to request a microphone test");
+    }
+
+    pub async fn resume_capture_request(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::ResumeCaptureRequest)
+            .await
+            .expect("failed to request capture resume");
+    }
+
+    pub async fn push_to_talk(&self, is_held: bool) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::PushToTalk(is_held))
+            .await
+            .expect("failed to send the push-to-talk state");
+    }
+
+    pub async fn stop_transcription_request(&self) {
+        self.to_backend
+            .send(subwin_bridge::MessageToBackend::StopTranscriptionRequest)
+            .await
+            .expect("failed to request transcription stop");
+    }
+
+    pub async fn request_shutdown(&self) {
+        // the backend may already be gone by the time the app quits
+        let _ = self
+            .to_backend
+            .send(subwin_bridge::MessageToBackend::ShutdownRequest)
+            .await;
+    }
 }
 
 impl Global for BackendBridge {}
 
+/// Applies a message received from the backend to the UI state.
+fn handle_backend_message(
+    message: MessageFromBackend,
+    listener_data: &entities::DataEntities,
+    window_handle: AnyWindowHandle,
+    cx: &mut AsyncApp,
+) {
+    match message {
+        MessageFromBackend::ConfigurationResponse(config) => {
+            SettingsEntity::update(&listener_data.settings, *config, cx)
+        }
+        MessageFromBackend::NotificationMessage(notification) => {
+            let notification_type = match notification.notification_type {
+                subwin_bridge::notification::NotificationType::Info => NotificationType::Info,
+                subwin_bridge::notification::NotificationType::Success => NotificationType::Success,
+                subwin_bridge::notification::NotificationType::Warning => NotificationType::Warning,
+                subwin_bridge::notification::NotificationType::Error => NotificationType::Error,
+            };
+            push_notification(window_handle, notification.message, notification_type, cx);
+        }
+        MessageFromBackend::DownloadProgressUpdate {
+            downloaded_bytes,
+            total_bytes,
+            speed,
+            remaining_time,
+        } => {
+            // TODO: rewrite this to be like `SettingsEntity`?
+            let _ = listener_data.download.update(cx, |model, cx| {
+                let event = DownloadProgressEvent {
+                    downloaded_bytes,
+                    total_bytes,
+                    speed,
+                    remaining_time,
+                };
+                model.progress = event;
+                cx.emit(event);
+                cx.notify();
+            });
+        }
+        MessageFromBackend::ModelVerificationProgress {
+            processed_bytes,
+            total_bytes,
+        } => {
+            let _ = listener_data.download.update(cx, |_, cx| {
+                cx.emit(VerificationProgressEvent {
+                    processed_bytes,
+                    total_bytes,
+                });
+                cx.notify();
+            });
+        }
+        MessageFromBackend::DownloadedModelsResponse(downloaded_models) => {
+            let _ = listener_data.download.update(cx, |model, cx| {
+                model.downloaded_models = downloaded_models;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::AudioDevicesListResponse(audio_devices) => {
+            let _ = listener_data.audio_devices.update(cx, |model, cx| {
+                model.audio_devices = audio_devices;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::ActiveDeviceInfo(info) => {
+            let _ = listener_data.audio_devices.update(cx, |model, cx| {
+                model.active_device_info = info;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::TranscriptionStarting => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.is_starting = true;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::TranscriptionStartedResponse { session_id } => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.session_id = Some(session_id);
+                model.is_running = true;
+                model.is_starting = false;
+                model.is_capture_released = false;
+                cx.notify();
+            });
+            // the backend may start on its own, e.g. on launch
+            let _ = window_handle.update(cx, |_, window, cx| {
+                views::open_captions_window(listener_data, window, cx);
+            });
+        }
+        MessageFromBackend::AudioError(error) => {
+            let message = audio_error_message(&error);
+            push_notification(window_handle, message, NotificationType::Error, cx);
+        }
+        MessageFromBackend::MicTestResult(result) => {
+            let (message, notification_type) = if result.clipping {
+                (
+                    "Звук перегружен, уменьшите громкость микрофона.",
+                    NotificationType::Warning,
+                )
+            } else if result.rms_db < QUIET_MIC_THRESHOLD_DB {
+                (
+                    "Звук очень тихий, увеличьте громкость микрофона.",
+                    NotificationType::Warning,
+                )
+            } else {
+                ("Уровень звука в норме.", NotificationType::Success)
+            };
+            let message = format!(
+                "{message} Пик: {:.1} дБ, средний уровень: {:.1} дБ.",
+                result.peak_db, result.rms_db,
+            );
+            push_notification(window_handle, message, notification_type, cx);
+        }
+        MessageFromBackend::StatusResponse { running, .. } => {
+            // the model and device are shown from the config
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.is_running = running;
+                cx.notify();
+            });
+            if running {
+                let _ = window_handle.update(cx, |_, window, cx| {
+                    views::open_captions_window(listener_data, window, cx);
+                });
+            }
+        }
+        MessageFromBackend::LatencyEstimateResponse(estimate) => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.latency_estimate = estimate;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::ModelBenchmarkResult(result) => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.is_benchmarking = false;
+                cx.notify();
+            });
+            // failures have been reported with a notification
+            if let Some(result) = result {
+                let (message, notification_type) = if result.real_time_factor > 1.0 {
+                    (
+                        "Модель не успевает за речью, выберите модель поменьше.",
+                        NotificationType::Warning,
+                    )
+                } else {
+                    ("Модель успевает за речью.", NotificationType::Success)
+                };
+                let message = format!(
+                    "{message} Распознавание {:.1} с звука: в среднем {:.0} мс, до {:.0} мс (RTF: {:.2}).",
+                    result.window_ms as f64 / 1000.0,
+                    result.average_decode_ms,
+                    result.max_decode_ms,
+                    result.real_time_factor,
+                );
+                push_notification(window_handle, message, notification_type, cx);
+            }
+        }
+        MessageFromBackend::ConfigValidation(issues) => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.config_issues = issues;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::DiagnosticsExported(path) => {
+            let message = format!("Диагностика сохранена в {}", path.display());
+            push_notification(window_handle, message, NotificationType::Success, cx);
+        }
+        MessageFromBackend::CaptureReleased(is_released) => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.is_capture_released = is_released;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::ConfigSaved => {
+            SettingsEntity::set_save_status(&listener_data.settings, ConfigSaveStatus::Saved, cx)
+        }
+        MessageFromBackend::PipelineStats(stats) => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.pipeline_stats = Some(stats);
+                cx.notify();
+            });
+        }
+        MessageFromBackend::TranscriptionStoppedResponse => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                if let Some(session_id) = model.session_id.take() {
+                    model.finished_session_id = session_id;
+                }
+                model.is_running = false;
+                model.is_starting = false;
+                model.is_capture_released = false;
+                model.pipeline_stats = None;
+                cx.notify();
+            });
+        }
+        MessageFromBackend::TranscriptionStateUpdate {
+            session_id,
+            time_taken,
+            new_segment_text,
+            uncertain_ranges,
+        } => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                // sent over the update channel, so it may arrive late
+                if session_id <= model.finished_session_id {
+                    return;
+                }
+                model.last_run_content = new_segment_text;
+                model.last_run_uncertain_ranges = uncertain_ranges;
+                model.last_run_duration = time_taken;
+                model.last_update_at = Some(std::time::Instant::now());
+                cx.notify();
+            });
+        }
+        MessageFromBackend::TranscriptLines(lines) => {
+            let _ = listener_data.captions.update(cx, |model, cx| {
+                model.append_transcript(lines);
+                cx.notify();
+            });
+        }
+        MessageFromBackend::TranscriptionMetrics {
+            inference_ms,
+            real_time_factor,
+            ..
+        } => {
+            let _ = listener_data.captions.update(cx, |model, _| {
+                // the view is refreshed with the next caption
+                model.last_run_duration = inference_ms;
+                model.real_time_factor = real_time_factor;
+            });
+        }
+    }
+}
+
 pub fn run(
     mut rx: mpsc::Receiver<subwin_bridge::MessageFromBackend>,
+    mut updates_rx: mpsc::Receiver<subwin_bridge::MessageFromBackend>,
+    mut levels_rx: watch::Receiver<InputLevel>,
     tx: mpsc::Sender<subwin_bridge::MessageToBackend>,
 ) -> anyhow::Result<()> {
     let app = Application::new().with_assets(gpui_component_assets::Assets);
 
     app.run(move |cx| {
         gpui_component::init(cx);
+        views::bind_keys(cx);
 
         let download = cx.new(DownloadEntity::new);
         let settings = cx.new(|_| SettingsEntity::default());
         let audio_devices = cx.new(|_| AudioDevicesEntity::default());
         let captions = cx.new(|_| CaptionsEntity::default());
+        let input_level = cx.new(|_| InputLevel::SILENT);
 
         let data = entities::DataEntities {
             settings,
             download,
             audio_devices,
             captions,
+            input_level,
         };
         let listener_data = data.clone();
+        hotkeys::register_global_hotkeys(&data, cx);
 
         let bridge = BackendBridge {
             to_backend: tx.clone(),
         };
         cx.set_global(bridge.clone());
+        cx.set_global(NotificationThrottle::default());
+
+        let shutdown_bridge = bridge.clone();
+        cx.on_app_quit(move |_| {
+            let bridge = shutdown_bridge.clone();
+            async move { bridge.request_shutdown().await }
+        })
+        .detach();
 
         cx.spawn(async move |cx| {
             cx.open_window(WindowOptions::default(), |window, cx| {
@@ -97,73 +558,31 @@ pub fn run(
                 let window_handle = window.window_handle();
                 cx.spawn(async move |cx| {
                     while let Some(message) = rx.recv().await {
-                        match message {
-                            MessageFromBackend::ConfigurationResponse(config) => {
-                                SettingsEntity::update(&listener_data.settings, config, cx)
-                            }
-                            MessageFromBackend::NotificationMessage(notification) => {
-                                let _notification_type = match notification.notification_type {
-                                    subwin_bridge::notification::NotificationType::Info => {
-                                        NotificationType::Info
-                                    }
-                                    subwin_bridge::notification::NotificationType::Success => {
-                                        NotificationType::Success
-                                    }
-                                    subwin_bridge::notification::NotificationType::Warning => {
-                                        NotificationType::Warning
-                                    }
-                                    subwin_bridge::notification::NotificationType::Error => {
-                                        NotificationType::Error
-                                    }
-                                };
-                                window_handle
-                                    .update(cx, |_, window, cx| {
-                                        let _notification = Notification::new()
-                                            .message(notification.message)
-                                            .with_type(_notification_type);
-                                        window.push_notification(_notification, cx);
-                                    })
-                                    .expect("failed to push a new notification");
-                            }
-                            MessageFromBackend::DownloadProgressUpdate {
-                                downloaded_bytes,
-                                total_bytes,
-                                speed,
-                                remaining_time,
-                            } => {
-                                // TODO: rewrite this to be like `SettingsEntity`?
-                                let _ = listener_data.download.update(cx, |model, cx| {
-                                    let event = DownloadProgressEvent {
-                                        downloaded_bytes,
-                                        total_bytes,
-                                        speed,
-                                        remaining_time,
-                                    };
-                                    model.progress = event;
-                                    cx.emit(event);
-                                    cx.notify();
-                                });
-                            }
-                            MessageFromBackend::AudioDevicesListResponse(audio_devices) => {
-                                let _ = listener_data.audio_devices.update(cx, |model, cx| {
-                                    model.audio_devices = audio_devices;
-                                    cx.notify();
-                                });
-                            }
-                            MessageFromBackend::TranscriptionStartedResponse => {
-                                println!("Backend says that the transcription has been started.");
-                            }
-                            MessageFromBackend::TranscriptionStateUpdate {
-                                time_taken,
-                                new_segment_text,
-                            } => {
-                                let _ = listener_data.captions.update(cx, |model, cx| {
-                                    model.last_run_content = new_segment_text;
-                                    model.last_run_duration = time_taken;
-                                    cx.notify();
-                                });
-                            }
-                        }
+                        handle_backend_message(message, &listener_data, window_handle, cx);
+                    }
+                })
+                .detach();
+
+                // updates are received separately, so a backlog of them
+                // doesn't delay the other messages
+                let updates_data = listener_data.clone();
+                cx.spawn(async move |cx| {
+                    while let Some(message) = updates_rx.recv().await {
+                        handle_backend_message(message, &updates_data, window_handle, cx);
+                    }
+                })
+                .detach();
+
+                // only the latest level is kept, the ones published while
+                // the UI was busy are skipped
+                let input_level = listener_data.input_level.clone();
+                cx.spawn(async move |cx| {
+                    while levels_rx.changed().await.is_ok() {
+                        let level = *levels_rx.borrow_and_update();
+                        let _ = input_level.update(cx, |model, cx| {
+                            *model = level;
+                            cx.notify();
+                        });
                     }
                 })
                 .detach();
@@ -171,6 +590,8 @@ pub fn run(
                 // TODO: maybe move this into another place?
                 cx.spawn(async move |_| {
                     bridge.request_config().await;
+                    bridge.request_status().await;
+                    bridge.request_downloaded_models().await;
                     bridge.request_audio_devices_list().await;
                 })
                 .detach();
@@ -186,3 +607,67 @@ pub fn run(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_notifications_are_shown() {
+        let mut throttle = NotificationThrottle::default();
+        let now = Instant::now();
+
+        assert_eq!(throttle.admit("a", now), Some("a".to_string()));
+        assert_eq!(throttle.admit("b", now), Some("b".to_string()));
+        // only the last notification is remembered
+        assert_eq!(throttle.admit("a", now), Some("a".to_string()));
+    }
+
+    #[test]
+    fn repeats_within_the_window_are_suppressed() {
+        let mut throttle = NotificationThrottle::default();
+        let now = Instant::now();
+
+        assert!(throttle.admit("a", now).is_some());
+        assert_eq!(throttle.admit("a", now), None);
+        let almost_expired = now + NOTIFICATION_REPEAT_WINDOW - Duration::from_millis(1);
+        assert_eq!(throttle.admit("a", almost_expired), None);
+        assert_eq!(throttle.suppressed, 2);
+    }
+
+    #[test]
+    fn repeats_after_the_window_mention_the_suppressed_ones() {
+        let mut throttle = NotificationThrottle::default();
+        let now = Instant::now();
+
+        assert!(throttle.admit("a", now).is_some());
+        assert_eq!(throttle.admit("a", now), None);
+        assert_eq!(throttle.admit("a", now), None);
+        let expired = now + NOTIFICATION_REPEAT_WINDOW;
+        assert_eq!(
+            throttle.admit("a", expired),
+            Some("a (ещё повторов: 2)".to_string())
+        );
+
+        // the window starts over from the repeat that has been shown
+        assert_eq!(throttle.admit("a", expired + Duration::from_secs(1)), None);
+        let expired_again = expired + NOTIFICATION_REPEAT_WINDOW;
+        assert_eq!(
+            throttle.admit("a", expired_again),
+            Some("a (ещё повторов: 1)".to_string())
+        );
+        let quiet_since = expired_again + NOTIFICATION_REPEAT_WINDOW * 2;
+        assert_eq!(throttle.admit("a", quiet_since), Some("a".to_string()));
+    }
+
+    #[test]
+    fn another_notification_drops_the_suppressed_count() {
+        let mut throttle = NotificationThrottle::default();
+        let now = Instant::now();
+
+        assert!(throttle.admit("a", now).is_some());
+        assert_eq!(throttle.admit("a", now), None);
+        assert_eq!(throttle.admit("b", now), Some("b".to_string()));
+        assert_eq!(throttle.suppressed, 0);
+    }
+}
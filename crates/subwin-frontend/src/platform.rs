@@ -0,0 +1,83 @@
+//! Platform-specific window tweaks that gpui doesn't expose.
+
+use gpui::{Pixels, Size, Window};
+
+/// Keeps `window` above all other, non-topmost windows.
+///
+/// gpui already places pop-up windows on top on macOS and X11, but on
+/// Windows they are plain tool windows, which fall behind whatever gets
+/// focused, so the window is made topmost explicitly.
+#[cfg(target_os = "windows")]
+pub fn keep_window_on_top(window: &Window) {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{
+        HWND_TOPMOST, SWP_NOACTIVATE, SWP_NOMOVE, SWP_NOSIZE, SetWindowPos,
+    };
+
+    // without a native handle there's nothing to adjust, and the window
+    // still works, only without staying on top
+    let Ok(handle) = window.window_handle() else {
+        return;
+    };
+    if let RawWindowHandle::Win32(handle) = handle.as_raw() {
+        // SAFETY: the handle belongs to a live window owned by this process
+        unsafe {
+            SetWindowPos(
+                handle.hwnd.get() as _,
+                HWND_TOPMOST,
+                0,
+                0,
+                0,
+                0,
+                SWP_NOMOVE | SWP_NOSIZE | SWP_NOACTIVATE,
+            );
+        }
+    }
+}
+
+/// Keeps `window` above all other windows. Pop-up windows are already kept
+/// on top by gpui on this platform.
+#[cfg(not(target_os = "windows"))]
+pub fn keep_window_on_top(_window: &Window) {}
+
+/// Resizes `window` to `size`, keeping the center of its bottom edge in
+/// place, so captions stay anchored above the bottom of the screen.
+#[cfg(target_os = "windows")]
+pub fn resize_window_anchored(window: &mut Window, size: Size<Pixels>) {
+    use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+    use windows_sys::Win32::UI::WindowsAndMessaging::{SWP_NOACTIVATE, SWP_NOZORDER, SetWindowPos};
+
+    let Ok(handle) = window.window_handle() else {
+        window.resize(size);
+        return;
+    };
+    let RawWindowHandle::Win32(handle) = handle.as_raw() else {
+        window.resize(size);
+        return;
+    };
+
+    let bounds = window.bounds();
+    let scale_factor = window.scale_factor();
+    let x = bounds.center().x - size.width / 2.0;
+    let y = bounds.bottom() - size.height;
+    // SAFETY: the handle belongs to a live window owned by this process
+    unsafe {
+        SetWindowPos(
+            handle.hwnd.get() as _,
+            std::ptr::null_mut(),
+            (f32::from(x) * scale_factor).round() as i32,
+            (f32::from(y) * scale_factor).round() as i32,
+            (f32::from(size.width) * scale_factor).round() as i32,
+            (f32::from(size.height) * scale_factor).round() as i32,
+            SWP_NOZORDER | SWP_NOACTIVATE,
+        );
+    }
+}
+
+/// Resizes `window` to `size`. gpui can't move windows here, so resizing
+/// keeps the bottom-left corner in place on macOS and the top-left one on
+/// Linux.
+#[cfg(not(target_os = "windows"))]
+pub fn resize_window_anchored(window: &mut Window, size: Size<Pixels>) {
+    window.resize(size);
+}
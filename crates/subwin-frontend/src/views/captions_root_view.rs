@@ -1,57 +1,584 @@
-use gpui::{Entity, ParentElement, Styled, div, hsla, px};
+use std::{ops::Range, rc::Rc, time::Duration};
+
+use gpui::{
+    Animation, AnimationExt, AnyWindowHandle, App, AppContext, Bounds, ClipboardItem, Div, Entity,
+    FontWeight, Global, HighlightStyle, Hsla, IntoElement, ParentElement, Pixels, PlatformDisplay,
+    Point, Rgba, Size, Styled, StyledText, Subscription, Task, TextAlign, UnderlineStyle, Window,
+    WindowBounds, WindowOptions, div, hsla, prelude::FluentBuilder, px, rems, size,
+};
 use gpui_component::{ActiveTheme, IconName, Sizable, StyledExt, button::Button};
+use subwin_bridge::config::{
+    CaptionsBackgroundAppearance, CaptionsTextAlign, CaptionsTextDirection, CaptionsTextEffect,
+};
+
+use crate::{
+    BackendBridge,
+    entities::{CaptionsEntity, DataEntities, settings_entity::SettingsEntity},
+    formatting::is_rtl_text,
+    platform,
+};
+
+/// Directions the text copies of an outline are offset in, scaled by its
+/// width. Eight copies around the text approximate a stroke.
+const OUTLINE_OFFSETS: [(f32, f32); 8] = [
+    (-1.0, -1.0),
+    (0.0, -1.0),
+    (1.0, -1.0),
+    (-1.0, 0.0),
+    (1.0, 0.0),
+    (-1.0, 1.0),
+    (0.0, 1.0),
+    (1.0, 1.0),
+];
+
+/// How much caption text the model is unsure about is faded out (see
+/// [`subwin_bridge::config::CaptionsConfig::mark_uncertain_text`]).
+const UNCERTAIN_TEXT_FADE_OUT: f32 = 0.4;
+
+/// Duration of the transition between the full and faded caption opacity.
+const FADE_ANIMATION_DURATION: Duration = Duration::from_millis(600);
+
+/// Delay after the last resize event before the new window size is persisted.
+const RESIZE_SAVE_DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// Height of a single caption line, in pixels.
+const CAPTION_LINE_HEIGHT: f32 = 30.0;
+
+/// Window width below which captions are rendered with a smaller font.
+const NARROW_WINDOW_WIDTH: f32 = 360.0;
+
+/// Font size of the captions in windows that aren't narrow, in rems.
+const CAPTION_FONT_SIZE_REMS: f32 = 1.5;
+
+/// Share of the window's width taken by the caption box.
+const CAPTION_BOX_WIDTH_SHARE: f32 = 5.0 / 6.0;
+
+/// Horizontal and vertical padding around the caption text, including the
+/// border, in pixels.
+const CAPTION_BOX_PADDING: (f32, f32) = (34.0, 18.0);
+
+/// Delay before an auto-sized window grows to fit longer captions. Short, so
+/// text is clamped only briefly, but still coalescing word-by-word updates.
+const AUTO_SIZE_GROW_DELAY: Duration = Duration::from_millis(150);
 
-use crate::entities::{CaptionsEntity, DataEntities};
+/// Delay before an auto-sized window shrinks to fit shorter captions. Long,
+/// so the window doesn't jitter while captions are revised.
+const AUTO_SIZE_SHRINK_DELAY: Duration = Duration::from_millis(1500);
+
+/// Size changes of an auto-sized window smaller than this are ignored, in
+/// pixels.
+const AUTO_SIZE_TOLERANCE: f32 = 16.0;
+
+/// Minimum size the captions window can be shrunk to.
+pub fn captions_window_min_size() -> Size<Pixels> {
+    size(px(160.), px(48.))
+}
+
+/// Handles of the currently open captions windows, one per display.
+#[derive(Default)]
+struct CaptionsWindows(Vec<AnyWindowHandle>);
+
+impl Global for CaptionsWindows {}
+
+/// Opens the captions windows (unless they're already open) at the
+/// configured position on their displays, and asks the backend to start
+/// transcribing. Does nothing while a start is already underway.
+pub fn start_captions(data: &DataEntities, window: &Window, cx: &mut App) {
+    if data.captions.read(cx).is_starting {
+        return;
+    }
+    open_captions_window(data, window, cx);
+
+    let bridge = cx.global::<BackendBridge>().clone();
+    cx.spawn(async move |_| {
+        bridge.start_transcription_request().await;
+    })
+    .detach();
+}
+
+/// Returns the displays a captions window is opened on: the configured ones
+/// that are still connected, or the primary display if none of them is.
+/// Without configured displays, the display of `window` is used.
+fn resolve_captions_displays(
+    displays: &[String],
+    window: &Window,
+    cx: &App,
+) -> Vec<Rc<dyn PlatformDisplay>> {
+    if displays.is_empty() {
+        return window.display(cx).into_iter().collect();
+    }
+
+    let connected: Vec<_> = cx
+        .displays()
+        .into_iter()
+        .filter(|display| {
+            display
+                .uuid()
+                .is_ok_and(|uuid| displays.contains(&uuid.to_string()))
+        })
+        .collect();
+    if connected.is_empty() {
+        log::warn!("None of the configured caption displays is connected, using the primary one");
+        return cx.primary_display().into_iter().collect();
+    }
+    connected
+}
+
+/// Opens a captions window at the configured position on every configured
+/// display, unless they're already open or disabled in the config. Used
+/// directly when the backend has started transcribing on its own, e.g. on
+/// launch.
+pub fn open_captions_window(data: &DataEntities, window: &Window, cx: &mut App) {
+    // captions still go to the other outputs
+    if !data.settings.read(cx).config.show_caption_window {
+        return;
+    }
+
+    let open_windows = cx.windows();
+    let is_open = cx
+        .default_global::<CaptionsWindows>()
+        .0
+        .iter()
+        .any(|handle| open_windows.contains(handle));
+    if is_open {
+        return;
+    }
+
+    let captions_config = data.settings.read(cx).config.captions_config.clone();
+    let (window_width, window_height) = (
+        px(captions_config.window_width),
+        px(captions_config.window_height),
+    );
+    let window_background = match captions_config.background_appearance {
+        CaptionsBackgroundAppearance::Opaque => gpui::WindowBackgroundAppearance::Opaque,
+        CaptionsBackgroundAppearance::Transparent => gpui::WindowBackgroundAppearance::Transparent,
+        CaptionsBackgroundAppearance::Blurred => gpui::WindowBackgroundAppearance::Blurred,
+    };
+
+    let displays = resolve_captions_displays(&captions_config.displays, window, cx);
+    if displays.is_empty() {
+        log::error!("No display is available for the captions window");
+        return;
+    }
+
+    let mut handles = Vec::with_capacity(displays.len());
+    for display in displays {
+        let display_bounds = display.bounds();
+        let origin = Point::new(
+            display_bounds.origin.x + (display_bounds.size.width - window_width) / 2.0,
+            display_bounds.origin.y
+                + Pixels::from(
+                    display_bounds.size.height.to_f64()
+                        - captions_config.padding_from_bottom as f64,
+                ),
+        );
+
+        let caption_window_bounds = Bounds::new(origin, size(window_width, window_height));
+
+        let captions_window_options: WindowOptions = WindowOptions {
+            window_bounds: Some(WindowBounds::Windowed(caption_window_bounds)),
+            titlebar: None,
+            focus: false,
+            show: true,
+            kind: if captions_config.always_on_top {
+                gpui::WindowKind::PopUp
+            } else {
+                gpui::WindowKind::Normal
+            },
+            is_movable: true,
+            is_resizable: true,
+            is_minimizable: false,
+            display_id: Some(display.id()),
+            window_background,
+            app_id: Some("subwin".to_owned()),
+            window_min_size: Some(captions_window_min_size()),
+            window_decorations: None,
+            tabbing_identifier: Some("subwin".to_owned()),
+        };
+
+        let handle = cx
+            .open_window(captions_window_options, |window, cx| {
+                if captions_config.always_on_top {
+                    platform::keep_window_on_top(window);
+                }
+                cx.new(|cx| CaptionsRootView::new(data, window, cx))
+            })
+            .expect("failed to open captions window");
+        handles.push(handle.into());
+    }
+    cx.set_global(CaptionsWindows(handles));
+}
+
+/// Closes the captions windows, if they're open, and asks the backend to
+/// stop transcribing.
+pub fn stop_captions(cx: &mut App) {
+    let handles = std::mem::take(&mut cx.default_global::<CaptionsWindows>().0);
+    for handle in handles {
+        // fails when called from within a captions window itself, which
+        // then removes itself
+        let _ = handle.update(cx, |_, window, _| window.remove_window());
+    }
+
+    let bridge = cx.global::<BackendBridge>().clone();
+    cx.spawn(async move |_| {
+        bridge.stop_transcription_request().await;
+    })
+    .detach();
+}
+
+/// Copies the last caption to the clipboard. Nothing is copied while there's
+/// no caption yet.
+pub fn copy_last_caption(captions: &Entity<CaptionsEntity>, cx: &mut App) {
+    let caption = captions.read(cx).last_run_content.trim().to_string();
+    if !caption.is_empty() {
+        cx.write_to_clipboard(ClipboardItem::new_string(caption));
+    }
+}
+
+/// Stops the captions if the backend reports them running or starting,
+/// starts them otherwise.
+pub fn toggle_captions(data: &DataEntities, window: &Window, cx: &mut App) {
+    let captions = data.captions.read(cx);
+    if captions.is_running || captions.is_starting {
+        stop_captions(cx);
+    } else {
+        start_captions(data, window, cx);
+    }
+}
 
 pub struct CaptionsRootView {
     pub captions_entity: Entity<CaptionsEntity>,
+    settings: Entity<SettingsEntity>,
+    /// Whether captions have been idle long enough to be faded out.
+    is_faded: bool,
+    /// Incremented on every fade, so that the fade animation restarts.
+    fade_generation: usize,
+    /// Pending timer that fades the captions out; replaced (and thus
+    /// cancelled) on every caption update.
+    fade_task: Option<Task<()>>,
+    /// Observer of the captions window's bounds.
+    _bounds_subscription: Subscription,
+    /// Pending task persisting the window size after a resize.
+    resize_task: Option<Task<()>>,
+    /// Size an auto-sized window is about to be resized to, if any.
+    auto_size_target: Option<Size<Pixels>>,
+    /// Pending task resizing an auto-sized window to fit the captions.
+    auto_size_task: Option<Task<()>>,
 }
 
 impl CaptionsRootView {
-    pub fn new(data: &DataEntities) -> Self {
+    pub fn new(data: &DataEntities, window: &mut Window, cx: &mut gpui::Context<Self>) -> Self {
+        cx.observe(&data.captions, |this, _, cx| this.schedule_fade(cx))
+            .detach();
+
+        // persist the new window size once the user stops resizing
+        let bounds_subscription = cx.observe_window_bounds(window, |this, window, cx| {
+            let window_size = window.bounds().size;
+            this.resize_task = Some(cx.spawn(async move |this, cx| {
+                cx.background_executor().timer(RESIZE_SAVE_DEBOUNCE).await;
+                let _ = this.update(cx, |this, cx| this.save_window_size(window_size, cx));
+            }));
+        });
+
         Self {
             captions_entity: data.captions.clone(),
+            settings: data.settings.clone(),
+            is_faded: false,
+            fade_generation: 0,
+            fade_task: None,
+            _bounds_subscription: bounds_subscription,
+            resize_task: None,
+            auto_size_target: None,
+            auto_size_task: None,
+        }
+    }
+
+    /// Returns the window size fitting `text` on as few lines as possible,
+    /// within the minimum size and the configured maximum one.
+    fn fit_window_size(&self, text: &str, window: &Window, cx: &App) -> Size<Pixels> {
+        let captions_config = &self.settings.read(cx).config.captions_config;
+        let min_size = captions_window_min_size();
+        // never narrow enough to switch to the smaller font measured against
+        let min_width = f32::from(min_size.width).max(NARROW_WINDOW_WIDTH);
+        let max_width = captions_config.auto_size_max_width.max(min_width);
+        let min_height = f32::from(min_size.height);
+        let max_height = captions_config.auto_size_max_height.max(min_height);
+
+        let (padding_x, padding_y) = CAPTION_BOX_PADDING;
+        let max_text_width = max_width * CAPTION_BOX_WIDTH_SHARE - padding_x;
+        let font_size = rems(CAPTION_FONT_SIZE_REMS).to_pixels(window.rem_size());
+        let mut text_style = window.text_style();
+        text_style.font_weight = FontWeight::SEMIBOLD;
+        let run = text_style.to_run(text.len());
+
+        let (mut text_width, mut line_count) = (0.0f32, 0usize);
+        if let Ok(lines) = window.text_system().shape_text(
+            text.to_string().into(),
+            font_size,
+            &[run],
+            Some(px(max_text_width)),
+            None,
+        ) {
+            for line in lines.iter() {
+                let line_size = line.size(px(CAPTION_LINE_HEIGHT));
+                text_width = text_width.max(f32::from(line_size.width));
+                line_count += (f32::from(line_size.height) / CAPTION_LINE_HEIGHT).round() as usize;
+            }
+        }
+
+        // a line's worth of height is left to the toolbar above the text
+        let width = (text_width + padding_x) / CAPTION_BOX_WIDTH_SHARE;
+        let height = (line_count.max(1) + 1) as f32 * CAPTION_LINE_HEIGHT + padding_y;
+        size(
+            px(width.clamp(min_width, max_width)),
+            px(height.clamp(min_height, max_height)),
+        )
+    }
+
+    /// Resizes an auto-sized window to fit `text`, once it has stayed long
+    /// enough for the window not to jitter with every caption update.
+    fn schedule_auto_size(&mut self, text: &str, window: &Window, cx: &mut gpui::Context<Self>) {
+        // cleared captions keep the window's size until the next ones
+        if text.trim().is_empty() {
+            return;
+        }
+
+        let target = self.fit_window_size(text, window, cx);
+        let current = self
+            .auto_size_target
+            .unwrap_or_else(|| window.viewport_size());
+        let is_close = |a: Pixels, b: Pixels| f32::from(a - b).abs() < AUTO_SIZE_TOLERANCE;
+        if is_close(target.width, current.width) && is_close(target.height, current.height) {
+            return;
+        }
+
+        let viewport_size = window.viewport_size();
+        let delay = if target.width > viewport_size.width || target.height > viewport_size.height {
+            AUTO_SIZE_GROW_DELAY
+        } else {
+            AUTO_SIZE_SHRINK_DELAY
+        };
+        self.auto_size_target = Some(target);
+        self.auto_size_task = Some(cx.spawn_in(window, async move |this, cx| {
+            cx.background_executor().timer(delay).await;
+            let _ = this.update_in(cx, |this, window, _| {
+                this.auto_size_target = None;
+                platform::resize_window_anchored(window, target);
+            });
+        }));
+    }
+
+    /// Sends the updated captions window size to the backend, if it differs
+    /// from the configured one.
+    fn save_window_size(&mut self, window_size: Size<Pixels>, cx: &mut gpui::Context<Self>) {
+        let mut config = self.settings.read(cx).config.clone();
+        // an auto-sized window follows the captions, not the user
+        if config.captions_config.auto_size {
+            return;
+        }
+        let (width, height) = (f32::from(window_size.width), f32::from(window_size.height));
+        if config.captions_config.window_width == width
+            && config.captions_config.window_height == height
+        {
+            return;
+        }
+
+        config.captions_config.window_width = width;
+        config.captions_config.window_height = height;
+        let bridge = cx.global::<BackendBridge>().clone();
+        cx.spawn(async move |_, _| {
+            bridge.update_config(config).await;
+        })
+        .detach();
+    }
+
+    /// Snaps captions back to full opacity and restarts the idle timer, which
+    /// fades them out once no new captions have arrived for the configured
+    /// delay.
+    fn schedule_fade(&mut self, cx: &mut gpui::Context<Self>) {
+        self.is_faded = false;
+        self.fade_task = None;
+
+        let fade_delay_ms = self
+            .settings
+            .read(cx)
+            .config
+            .captions_config
+            .idle_fade_delay_ms;
+        let last_update_at = self.captions_entity.read(cx).last_update_at;
+        let Some(last_update_at) = last_update_at else {
+            return;
+        };
+        if fade_delay_ms == 0 {
+            return;
         }
+
+        let remaining =
+            Duration::from_millis(fade_delay_ms as u64).saturating_sub(last_update_at.elapsed());
+        self.fade_task = Some(cx.spawn(async move |this, cx| {
+            cx.background_executor().timer(remaining).await;
+            let _ = this.update(cx, |this, cx| {
+                this.is_faded = true;
+                this.fade_generation += 1;
+                cx.notify();
+            });
+        }));
     }
 }
 
+/// Returns the highlights marking the `uncertain_ranges` of `text`. Ranges
+/// not matching the text, e.g. of an older caption, are skipped.
+fn uncertain_highlights(
+    text: &str,
+    uncertain_ranges: &[Range<usize>],
+) -> Vec<(Range<usize>, HighlightStyle)> {
+    let style = HighlightStyle {
+        fade_out: Some(UNCERTAIN_TEXT_FADE_OUT),
+        underline: Some(UnderlineStyle {
+            thickness: px(1.0),
+            color: None,
+            wavy: false,
+        }),
+        ..Default::default()
+    };
+    let mut last_end = 0;
+    uncertain_ranges
+        .iter()
+        .filter(|range| {
+            let is_valid = range.start >= last_end
+                && range.start < range.end
+                && text.is_char_boundary(range.start)
+                && text.is_char_boundary(range.end);
+            if is_valid {
+                last_end = range.end;
+            }
+            is_valid
+        })
+        .map(|range| (range.clone(), style))
+        .collect()
+}
+
 impl gpui::Render for CaptionsRootView {
     fn render(
         &mut self,
-        _: &mut gpui::Window,
+        window: &mut gpui::Window,
         cx: &mut gpui::Context<Self>,
     ) -> impl gpui::IntoElement {
+        // reflow captions to the current window size: show as many lines as
+        // fit vertically, and shrink the font for narrow windows
+        let viewport_size = window.viewport_size();
+        let is_narrow = f32::from(viewport_size.width) < NARROW_WINDOW_WIDTH;
+        let max_lines = ((f32::from(viewport_size.height) / CAPTION_LINE_HEIGHT) as usize)
+            .saturating_sub(1)
+            .max(1);
+
+        let caption_text = self.captions_entity.read(cx).last_run_content.clone();
+        if self.settings.read(cx).config.captions_config.auto_size {
+            self.schedule_auto_size(&caption_text, window, cx);
+        }
+
         let state = self.captions_entity.read(cx);
-        let caption_text = state.last_run_content.clone();
+        let captions_config = &self.settings.read(cx).config.captions_config;
+        let faded_opacity = captions_config.idle_fade_opacity.clamp(0.0, 1.0);
+
+        // text shaping handles the glyph order of RTL runs, but which side
+        // the lines stick to depends on the direction
+        let is_rtl = match captions_config.direction {
+            CaptionsTextDirection::Auto => is_rtl_text(&caption_text),
+            CaptionsTextDirection::Ltr => false,
+            CaptionsTextDirection::Rtl => true,
+        };
+        let text_align = match (captions_config.text_align, is_rtl) {
+            (CaptionsTextAlign::Center, _) => TextAlign::Center,
+            (CaptionsTextAlign::Start, false) | (CaptionsTextAlign::End, true) => TextAlign::Left,
+            (CaptionsTextAlign::Start, true) | (CaptionsTextAlign::End, false) => TextAlign::Right,
+        };
+
+        // gpui can't stroke text, so effects are drawn as offset copies of
+        // the text behind it
+        let effect_color = Rgba::try_from(captions_config.text_effect_color.as_str())
+            .map(Hsla::from)
+            .unwrap_or_else(|_| gpui::black());
+        let effect_width = captions_config.text_effect_width.max(0.0);
+        let effect_offsets: Vec<(f32, f32)> = match captions_config.text_effect {
+            CaptionsTextEffect::None => Vec::new(),
+            CaptionsTextEffect::Outline => OUTLINE_OFFSETS.to_vec(),
+            CaptionsTextEffect::Shadow => vec![(1.0, 1.0)],
+        };
+        // uncertain text is only marked on the text itself, effects are
+        // drawn the same, so they keep lining up with it
+        let uncertain_highlights: Vec<(Range<usize>, HighlightStyle)> =
+            if captions_config.mark_uncertain_text {
+                uncertain_highlights(&caption_text, &state.last_run_uncertain_ranges)
+            } else {
+                Vec::new()
+            };
+        let text_layer = |color: Hsla, highlights: &[(Range<usize>, HighlightStyle)]| -> Div {
+            div()
+                .when_else(is_narrow, |this| this.text_lg(), |this| this.text_2xl())
+                .font_semibold()
+                .text_align(text_align)
+                .text_color(color)
+                .line_height(px(CAPTION_LINE_HEIGHT))
+                .line_clamp(max_lines)
+                .overflow_hidden()
+                .map(|this| {
+                    if highlights.is_empty() {
+                        this.child(caption_text.clone())
+                    } else {
+                        this.child(
+                            StyledText::new(caption_text.clone())
+                                .with_highlights(highlights.iter().cloned()),
+                        )
+                    }
+                })
+        };
+        let effect_layers = effect_offsets.into_iter().map(|(dx, dy)| {
+            text_layer(effect_color, &[])
+                .absolute()
+                .top(px(dy * effect_width))
+                .left(px(dx * effect_width))
+                .w_full()
+        });
+
+        let caption_box = div()
+            .w_full()
+            .max_w_5_6()
+            .px_4()
+            .py_2()
+            .rounded_xl()
+            .bg(hsla(0., 0., 0., 0.65))
+            .border_1()
+            .border_color(hsla(0., 0., 1., 0.18))
+            .shadow_lg()
+            .min_w_0()
+            .child(
+                div()
+                    .relative()
+                    .children(effect_layers)
+                    .child(text_layer(hsla(0., 0., 1., 0.95), &uncertain_highlights).relative()),
+            );
+
+        // fade out smoothly once idle, but snap back as soon as speech resumes
+        let caption_box = if self.is_faded {
+            caption_box
+                .with_animation(
+                    ("caption_fade", self.fade_generation),
+                    Animation::new(FADE_ANIMATION_DURATION),
+                    move |this, delta| this.opacity(1.0 - (1.0 - faded_opacity) * delta),
+                )
+                .into_any_element()
+        } else {
+            caption_box.into_any_element()
+        };
+
         div()
             .size_full()
             .flex()
             .items_center()
             .justify_center()
             .text_color(cx.theme().foreground)
-            .child(
-                div()
-                    .w_full()
-                    .max_w_5_6()
-                    .px_4()
-                    .py_2()
-                    .rounded_xl()
-                    .bg(hsla(0., 0., 0., 0.65))
-                    .border_1()
-                    .border_color(hsla(0., 0., 1., 0.18))
-                    .shadow_lg()
-                    .child(
-                        div()
-                            .text_2xl()
-                            .font_semibold()
-                            .text_center()
-                            .text_color(hsla(0., 0., 1., 0.95))
-                            .line_height(px(30.))
-                            .line_clamp(2)
-                            .overflow_hidden()
-                            .child(caption_text),
-                    ),
-            )
+            .child(caption_box)
             .child(
                 div()
                     .absolute()
@@ -63,16 +590,38 @@ impl gpui::Render for CaptionsRootView {
                     .items_start()
                     .justify_between()
                     .child(
-                        Button::new("stop_transcribing")
-                            .icon(IconName::Close)
-                            .outline()
-                            .small(),
+                        div()
+                            .flex()
+                            .gap_1()
+                            .child(
+                                Button::new("stop_transcribing")
+                                    .icon(IconName::Close)
+                                    .outline()
+                                    .small()
+                                    .on_click(|_, window, cx| {
+                                        window.remove_window();
+                                        stop_captions(cx);
+                                    }),
+                            )
+                            .child(
+                                Button::new("copy_last_caption")
+                                    .icon(IconName::Copy)
+                                    .outline()
+                                    .small()
+                                    .tooltip("Скопировать последнюю фразу")
+                                    .on_click(cx.listener(|this, _, _, cx| {
+                                        copy_last_caption(&this.captions_entity, cx);
+                                    })),
+                            ),
                     )
                     .child(
                         div()
                             .text_sm()
                             .text_color(cx.theme().muted_foreground)
-                            .child(state.last_run_duration.to_string()),
+                            .child(format!(
+                                "{} мс · RTF {:.2}",
+                                state.last_run_duration, state.real_time_factor
+                            )),
                     ),
             )
     }
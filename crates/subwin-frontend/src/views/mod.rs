@@ -1,40 +1,106 @@
 mod captions_root_view;
 mod overview_page;
 mod settings_page;
+mod transcript_page;
 
 mod model;
 
-use gpui::{AnyView, AppContext, Context, IntoElement, ParentElement, Render, Styled, Window, div};
+use gpui::{
+    AnyView, App, AppContext, Context, FocusHandle, InteractiveElement, IntoElement, KeyBinding,
+    ParentElement, Render, Styled, Window, actions, div, prelude::FluentBuilder,
+};
 use gpui_component::{
     IconName, Root, Side,
     sidebar::{Sidebar, SidebarGroup, SidebarHeader, SidebarMenu, SidebarMenuItem},
 };
+use subwin_bridge::stats::PipelineStats;
 
 use crate::{
     entities::DataEntities,
-    views::{model::ModelPage, overview_page::OverviewPage, settings_page::SettingsPage},
+    views::{
+        captions_root_view::toggle_captions, model::ModelPage, overview_page::OverviewPage,
+        settings_page::SettingsPage, transcript_page::TranscriptPage,
+    },
 };
 
+pub use captions_root_view::{copy_last_caption, open_captions_window};
+
+/// Key context of the main window, scoping its shortcuts.
+const KEY_CONTEXT: &str = "FrontendUi";
+
+actions!(
+    subwin,
+    [
+        /// Switches to the overview page.
+        ShowOverview,
+        /// Switches to the application settings page.
+        ShowSettings,
+        /// Switches to the model settings page.
+        ShowModelSettings,
+        /// Switches to the transcript page.
+        ShowTranscript,
+        /// Starts or stops the captions.
+        ToggleCaptions,
+        /// Shows or hides the pipeline stats overlay.
+        ToggleDebugOverlay,
+        /// Copies the last caption to the clipboard.
+        CopyLastCaption,
+    ]
+);
+
+/// Registers the main window's keyboard shortcuts. `secondary` is Cmd on
+/// macOS and Ctrl elsewhere.
+pub fn bind_keys(cx: &mut App) {
+    cx.bind_keys([
+        KeyBinding::new("secondary-1", ShowOverview, Some(KEY_CONTEXT)),
+        KeyBinding::new("secondary-2", ShowSettings, Some(KEY_CONTEXT)),
+        KeyBinding::new("secondary-3", ShowModelSettings, Some(KEY_CONTEXT)),
+        KeyBinding::new("secondary-4", ShowTranscript, Some(KEY_CONTEXT)),
+        KeyBinding::new("secondary-enter", ToggleCaptions, Some(KEY_CONTEXT)),
+        KeyBinding::new("secondary-shift-d", ToggleDebugOverlay, Some(KEY_CONTEXT)),
+        KeyBinding::new("secondary-alt-c", CopyLastCaption, Some(KEY_CONTEXT)),
+    ]);
+}
+
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum PageUi {
     Overview,
     Settings,
     ModelSettings,
+    Transcript,
 }
 
 pub struct FrontendUi {
     data: DataEntities,
     active_page: PageUi,
     active_page_view: AnyView,
+    focus_handle: FocusHandle,
+    /// Whether the pipeline stats overlay is shown.
+    show_debug_overlay: bool,
 }
 
 impl FrontendUi {
     pub fn new(data: &DataEntities, window: &mut Window, cx: &mut Context<Self>) -> Self {
         let initial_view = cx.new(|cx| OverviewPage::new(data, window, cx)).into();
+
+        // focus the root, so shortcuts work before anything has been clicked
+        let focus_handle = cx.focus_handle();
+        window.focus(&focus_handle);
+
+        // keep the debug overlay's stats fresh
+        cx.observe(&data.captions, |this: &mut Self, _, cx| {
+            if this.show_debug_overlay {
+                cx.notify();
+            }
+        })
+        .detach();
+
         Self {
             data: data.clone(),
             active_page: PageUi::Overview,
             active_page_view: initial_view,
+            focus_handle,
+            show_debug_overlay: false,
         }
     }
 
@@ -45,9 +111,12 @@ impl FrontendUi {
                 .into(),
             PageUi::Settings => cx.new(|cx| SettingsPage::new(&self.data, cx)).into(),
             PageUi::ModelSettings => cx.new(|cx| ModelPage::new(&self.data, window, cx)).into(),
+            PageUi::Transcript => cx.new(|cx| TranscriptPage::new(&self.data, cx)).into(),
         };
         self.active_page = page;
         self.active_page_view = new_page;
+        // the focused element may have belonged to the replaced page
+        window.focus(&self.focus_handle);
         cx.notify();
     }
 }
@@ -62,6 +131,30 @@ impl Render for FrontendUi {
         };
 
         div()
+            .track_focus(&self.focus_handle)
+            .key_context(KEY_CONTEXT)
+            .on_action(cx.listener(|this, _: &ShowOverview, window, cx| {
+                this.change_page(PageUi::Overview, window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ShowSettings, window, cx| {
+                this.change_page(PageUi::Settings, window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ShowModelSettings, window, cx| {
+                this.change_page(PageUi::ModelSettings, window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ShowTranscript, window, cx| {
+                this.change_page(PageUi::Transcript, window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ToggleCaptions, window, cx| {
+                toggle_captions(&this.data, window, cx);
+            }))
+            .on_action(cx.listener(|this, _: &ToggleDebugOverlay, _, cx| {
+                this.show_debug_overlay = !this.show_debug_overlay;
+                cx.notify();
+            }))
+            .on_action(cx.listener(|this, _: &CopyLastCaption, _, cx| {
+                copy_last_caption(&this.data.captions, cx);
+            }))
             .flex()
             .size_full()
             .child(
@@ -87,11 +180,57 @@ impl Render for FrontendUi {
                                         .active(self.active_page == PageUi::ModelSettings)
                                         .icon(IconName::Bot)
                                         .on_click(on_page_change(PageUi::ModelSettings)),
+                                )
+                                .child(
+                                    SidebarMenuItem::new("Расшифровка")
+                                        .active(self.active_page == PageUi::Transcript)
+                                        .icon(IconName::BookOpen)
+                                        .on_click(on_page_change(PageUi::Transcript)),
                                 ),
                         ),
                     ),
             )
             .child(div().p_5().size_full().child(self.active_page_view.clone()))
+            .when(self.show_debug_overlay, |this| {
+                this.child(debug_overlay(
+                    self.data.captions.read(cx).pipeline_stats.as_ref(),
+                ))
+            })
             .children(notification_layer)
     }
 }
+
+/// Renders the pipeline stats overlay in the main window's corner.
+fn debug_overlay(stats: Option<&PipelineStats>) -> impl IntoElement {
+    let lines = match stats {
+        Some(stats) => vec![
+            format!("Уровень входа: {:.1} дБ", stats.input_level_db),
+            format!("Формат захвата: {}", stats.sample_format),
+            format!("Очередь ресемплера: {}", stats.resampler_queue_samples),
+            format!(
+                "Буфер: {} / {}",
+                stats.ring_buffer_samples, stats.ring_buffer_capacity
+            ),
+            format!("RTF: {:.2}", stats.real_time_factor),
+            format!("Потеряно сэмплов: {}", stats.dropped_samples),
+            format!("Прервано распознаваний: {}", stats.timed_out_decodes),
+            format!("Перегрузка (сэмплов): {}", stats.clipped_samples),
+            format!(
+                "Последняя ошибка: {}",
+                stats.last_error.as_deref().unwrap_or("нет")
+            ),
+        ],
+        None => vec!["Распознавание не запущено".to_string()],
+    };
+
+    div()
+        .absolute()
+        .bottom_2()
+        .right_2()
+        .p_2()
+        .rounded_md()
+        .bg(gpui::black().opacity(0.75))
+        .text_color(gpui::white())
+        .text_xs()
+        .children(lines.into_iter().map(|line| div().child(line)))
+}
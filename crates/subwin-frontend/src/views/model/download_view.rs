@@ -1,12 +1,23 @@
-use gpui::{AppContext, Context, Entity, ParentElement, SharedString, Styled, Window, div};
+use gpui::{
+    AppContext, Context, Entity, ParentElement, SharedString, Styled, Window, div,
+    prelude::FluentBuilder,
+};
 use gpui_component::{
-    IconName, IndexPath, StyledExt,
+    ActiveTheme, IconName, IndexPath, StyledExt,
     button::{Button, ButtonVariants},
-    select::{Select, SelectItem, SelectState},
+    select::{Select, SelectEvent, SelectItem, SelectState},
+};
+use subwin_bridge::whisper_model::{LanguageSuitability, WhisperModel};
+
+use crate::{
+    components::download_indicator::DownloadIndicator,
+    entities::{download_entity::DownloadEntity, settings_entity::SettingsEntity},
+    formatting::format_bytes,
 };
-use subwin_bridge::whisper_model::WhisperModel;
 
-use crate::components::download_indicator::DownloadIndicator;
+/// Models larger than this require an explicit confirmation before the
+/// download starts.
+const CONFIRM_DOWNLOAD_THRESHOLD_BYTES: u64 = 1024 * 1024 * 1024;
 
 #[derive(Debug, Clone)]
 struct Model {
@@ -15,9 +26,9 @@ struct Model {
 }
 
 impl Model {
-    pub fn new(display_name: &'static str, value: WhisperModel) -> Self {
+    pub fn new(value: WhisperModel) -> Self {
         Self {
-            display_name: display_name.into(),
+            display_name: value.display_name().into(),
             value,
         }
     }
@@ -38,7 +49,11 @@ impl SelectItem for Model {
 #[derive(Debug, Clone)]
 pub struct DownloadModelView {
     is_loading: bool,
+    /// Whether the user has been asked to confirm a large download.
+    is_awaiting_confirmation: bool,
     indicator: Entity<DownloadIndicator>,
+    settings: Entity<SettingsEntity>,
+    download: Entity<DownloadEntity>,
     model_selector: Entity<SelectState<Vec<Model>>>,
 }
 
@@ -49,55 +64,98 @@ impl DownloadModelView {
         cx: &mut Context<Self>,
     ) -> Self {
         let model_selector = cx.new(|cx| {
-            let models: Vec<Model> = vec![
-                // tiny
-                Model::new("Мини (ускоренная, 8 бит)", WhisperModel::TinyQuantized8),
-                Model::new("Мини (ускоренная, 5 бит)", WhisperModel::TinyQuantized5),
-                Model::new("Мини", WhisperModel::Tiny),
-                // small
-                Model::new("Малая (ускоренная, 8 бит)", WhisperModel::SmallQuantized8),
-                Model::new("Малая (ускоренная, 5 бит)", WhisperModel::SmallQuantized5),
-                Model::new("Малая", WhisperModel::Small),
-                // base
-                Model::new("Базовая (ускоренная, 8 бит)", WhisperModel::BaseQuantized8),
-                Model::new("Базовая (ускоренная, 5 бит)", WhisperModel::BaseQuantized5),
-                Model::new("Базовая", WhisperModel::Base),
-                // medium
-                Model::new(
-                    "Средняя (ускоренная, 8 бит)",
-                    WhisperModel::MediumQuantized8,
-                ),
-                Model::new(
-                    "Средняя (ускоренная, 5 бит)",
-                    WhisperModel::MediumQuantized5,
-                ),
-                Model::new("Средняя", WhisperModel::Medium),
-                // large
-                Model::new(
-                    "Большая турбо (ускоренная, 8 бит)",
-                    WhisperModel::LargeTurboQuantized8,
-                ),
-                Model::new(
-                    "Большая турбо (ускоренная, 5 бит)",
-                    WhisperModel::LargeTurboQuantized5,
-                ),
-                Model::new("Большая турбо", WhisperModel::LargeTurbo),
-                Model::new("Большая (ускоренная, 5 бит)", WhisperModel::LargeQuantized5),
-                Model::new("Большая", WhisperModel::Large),
-            ];
+            let models: Vec<Model> = WhisperModel::ALL.into_iter().map(Model::new).collect();
             SelectState::new(models, Some(IndexPath::default()), window, cx)
         });
+        cx.subscribe_in(
+            &model_selector,
+            window,
+            |this, _, event, _, cx| match event {
+                SelectEvent::Confirm(_) => {
+                    // a different model needs its own confirmation
+                    this.is_awaiting_confirmation = false;
+                    cx.notify();
+                }
+            },
+        )
+        .detach();
+        // downloaded models are offered for use instead of a download
+        cx.observe(&data.download, |_, _, cx| cx.notify()).detach();
+
         let indicator = cx.new(|cx| DownloadIndicator::new(data, cx));
         Self {
             is_loading: false,
+            is_awaiting_confirmation: false,
             indicator,
+            settings: data.settings.clone(),
+            download: data.download.clone(),
             model_selector,
         }
     }
+
+    /// Starts downloading the selected model, asking for a confirmation first
+    /// if the model is large.
+    fn start_download(&mut self, confirmed: bool, cx: &mut Context<Self>) {
+        let bridge = cx.global::<crate::BackendBridge>().clone();
+        let selected_model = self.model_selector.read(cx).selected_value().cloned();
+
+        // TODO: should we display a notification error, if nothing has been selected?
+        let Some(model) = selected_model else {
+            return;
+        };
+
+        if !confirmed && model.approx_size_bytes() > CONFIRM_DOWNLOAD_THRESHOLD_BYTES {
+            self.is_awaiting_confirmation = true;
+            cx.notify();
+            return;
+        }
+
+        self.is_loading = true;
+        self.is_awaiting_confirmation = false;
+        cx.notify();
+        cx.spawn(async move |_, _| {
+            bridge.download_model(model).await;
+        })
+        .detach();
+    }
+
+    /// Makes the selected, already downloaded model the active one.
+    fn use_model(&mut self, cx: &mut Context<Self>) {
+        let bridge = cx.global::<crate::BackendBridge>().clone();
+        let Some(model) = self.model_selector.read(cx).selected_value().cloned() else {
+            return;
+        };
+
+        cx.spawn(async move |_, _| {
+            bridge.set_active_model(model).await;
+        })
+        .detach();
+    }
 }
 
 impl gpui::Render for DownloadModelView {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl gpui::IntoElement {
+        let selected_model = self.model_selector.read(cx).selected_value().cloned();
+        let language = self.settings.read(cx).config.language.clone();
+        let language_hint = match selected_model
+            .as_ref()
+            .map(|model| model.suitability_for(language.as_deref()))
+        {
+            Some(LanguageSuitability::Unsupported) => Some(
+                "Модель распознаёт только английскую речь, а в настройках выбран другой язык."
+                    .to_string(),
+            ),
+            Some(LanguageSuitability::TooSmall(recommended)) => Some(format!(
+                "Для языка {} рекомендуется модель «{}» или больше, меньшие модели часто ошибаются.",
+                language.as_deref().unwrap_or_default().to_uppercase(),
+                recommended.display_name(),
+            )),
+            Some(LanguageSuitability::Suitable) | None => None,
+        };
+        let is_downloaded = selected_model
+            .as_ref()
+            .is_some_and(|model| self.download.read(cx).downloaded_models.contains(model));
+
         div()
             .size_full()
             .flex()
@@ -118,27 +176,107 @@ impl gpui::Render for DownloadModelView {
                             .min_w_72()
                             .placeholder("Выберите модель..."),
                     )
-                    .child(
-                        Button::new("download_model")
-                            .primary()
-                            .icon(IconName::ArrowDown)
-                            .loading(self.is_loading)
-                            .label("Начать загрузку")
-                            .on_click(cx.listener(move |this, _, _, cx| {
-                                let bridge = cx.global::<crate::BackendBridge>().clone();
-                                let selected_model =
-                                    this.model_selector.read(cx).selected_value().cloned();
-
-                                // TODO: should we display a notification error, if nothing has been selected?
-                                if let Some(model) = selected_model {
-                                    this.is_loading = true;
-                                    cx.notify();
-                                    cx.spawn(async move |_, _| {
-                                        bridge.download_model(model).await;
-                                    })
-                                    .detach();
-                                }
-                            })),
+                    .when_some(selected_model, |this, model| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(cx.theme().muted_foreground)
+                                .child(format!(
+                                    "Размер файла: ~{}, параметров: ~{} млн, {}",
+                                    format_bytes(model.approx_size_bytes()),
+                                    model.parameter_count() / 1_000_000,
+                                    if model.is_multilingual() {
+                                        "многоязычная"
+                                    } else {
+                                        "только английский"
+                                    },
+                                )),
+                        )
+                    })
+                    .when(is_downloaded, |this| {
+                        this.child(
+                            div()
+                                .text_sm()
+                                .text_color(cx.theme().success)
+                                .child("✓ Модель уже скачана."),
+                        )
+                    })
+                    .when_some(language_hint, |this, hint| {
+                        this.child(div().text_sm().text_color(cx.theme().warning).child(hint))
+                    })
+                    .when_else(
+                        self.is_awaiting_confirmation,
+                        |this| {
+                            this.child(
+                                div()
+                                    .text_sm()
+                                    .child("Модель занимает больше гигабайта. Начать загрузку?"),
+                            )
+                            .child(
+                                div()
+                                    .flex()
+                                    .gap_2()
+                                    .child(
+                                        Button::new("confirm_download_model")
+                                            .primary()
+                                            .icon(IconName::ArrowDown)
+                                            .label("Подтвердить")
+                                            .on_click(cx.listener(|this, _, _, cx| {
+                                                this.start_download(true, cx);
+                                            })),
+                                    )
+                                    .child(
+                                        Button::new("cancel_download_model")
+                                            .label("Отмена")
+                                            .on_click(cx.listener(|this, _, _, cx| {
+                                                this.is_awaiting_confirmation = false;
+                                                cx.notify();
+                                            })),
+                                    ),
+                            )
+                        },
+                        |this| {
+                            this.when_else(
+                                is_downloaded,
+                                |this| {
+                                    this.child(
+                                        div()
+                                            .flex()
+                                            .gap_2()
+                                            .child(
+                                                Button::new("use_model")
+                                                    .primary()
+                                                    .icon(IconName::Check)
+                                                    .label("Использовать")
+                                                    .on_click(cx.listener(|this, _, _, cx| {
+                                                        this.use_model(cx);
+                                                    })),
+                                            )
+                                            .child(
+                                                Button::new("redownload_model")
+                                                    .icon(IconName::ArrowDown)
+                                                    .loading(self.is_loading)
+                                                    .label("Скачать заново")
+                                                    .on_click(cx.listener(|this, _, _, cx| {
+                                                        this.start_download(false, cx);
+                                                    })),
+                                            ),
+                                    )
+                                },
+                                |this| {
+                                    this.child(
+                                        Button::new("download_model")
+                                            .primary()
+                                            .icon(IconName::ArrowDown)
+                                            .loading(self.is_loading)
+                                            .label("Начать загрузку")
+                                            .on_click(cx.listener(|this, _, _, cx| {
+                                                this.start_download(false, cx);
+                                            })),
+                                    )
+                                },
+                            )
+                        },
                     ),
             )
             .child(self.indicator.clone())
@@ -3,13 +3,18 @@ use gpui::{
     prelude::FluentBuilder,
 };
 use gpui_component::{
-    StyledExt,
+    Disableable, StyledExt,
+    button::Button,
     group_box::{GroupBox, GroupBoxVariants},
 };
 
+use subwin_bridge::whisper_model::{DecodingStrategy, WhisperModel};
+
 use crate::{
+    BackendBridge,
     components::settings_item::SettingsItem,
-    entities::{DataEntities, settings_entity::SettingsEntity},
+    entities::{DataEntities, download_entity::DownloadEntity, settings_entity::SettingsEntity},
+    formatting::format_bytes,
     views::model::download_view::DownloadModelView,
 };
 
@@ -18,13 +23,18 @@ mod download_view;
 #[derive(Debug, Clone)]
 pub struct ModelPage {
     settings: Entity<SettingsEntity>,
+    download: Entity<DownloadEntity>,
     download_view: Entity<DownloadModelView>,
 }
 
 impl ModelPage {
     pub fn new(data: &DataEntities, window: &mut Window, cx: &mut Context<Self>) -> Self {
+        // the active model's file status follows the downloaded models
+        cx.observe(&data.download, |_, _, cx| cx.notify()).detach();
+
         Self {
             settings: data.settings.clone(),
+            download: data.download.clone(),
             download_view: cx.new(|cx| DownloadModelView::new(data, window, cx)),
         }
     }
@@ -38,13 +48,34 @@ impl gpui::Render for ModelPage {
             settings.config.active_model_path.is_none(),
             |this| this.child(self.download_view.clone()),
             |this| {
-                let active_model_path = settings
-                    .config
-                    .active_model_path
-                    .as_ref()
-                    .unwrap()
-                    .display()
-                    .to_string();
+                let active_model_path = settings.config.active_model_path.as_deref().unwrap();
+                let active_model = WhisperModel::from_path(active_model_path);
+                let active_model_label = match &active_model {
+                    Some(model) => format!(
+                        "{} ({})",
+                        model.display_name(),
+                        format_bytes(model.approx_size_bytes())
+                    ),
+                    // custom models are only known by their path
+                    None => active_model_path.display().to_string(),
+                };
+                // the backend only lists the known models it has found
+                let active_model_status = match &active_model {
+                    Some(model) if self.download.read(cx).downloaded_models.contains(model) => {
+                        "Файл модели на месте."
+                    }
+                    Some(_) => "Файл модели не найден, скачайте модель заново.",
+                    None => "Собственная модель, файл не проверяется.",
+                };
+                let recent_models = settings.config.recent_models.clone();
+                let params_profile = settings.config.active_params_profile();
+                let strategy = match params_profile.strategy {
+                    DecodingStrategy::Greedy => "жадный поиск".to_string(),
+                    DecodingStrategy::BeamSearch { beam_size } => {
+                        format!("лучевой поиск ({beam_size})")
+                    }
+                };
+
                 this.child(
                     div()
                         .flex()
@@ -52,11 +83,39 @@ impl gpui::Render for ModelPage {
                         .gap_3()
                         .child(div().child("Настройки модели").text_2xl().font_bold())
                         .child(
-                            GroupBox::new().outline().child(
-                                SettingsItem::new()
-                                    .label("Активная модель")
-                                    .child(active_model_path),
-                            ),
+                            GroupBox::new()
+                                .outline()
+                                .child(
+                                    SettingsItem::new().label("Активная модель").child(
+                                        div()
+                                            .flex()
+                                            .flex_col()
+                                            .child(active_model_label)
+                                            .child(div().text_sm().child(active_model_status)),
+                                    ),
+                                )
+                                .child(SettingsItem::new().label("Параметры распознавания").child(
+                                    format!(
+                                        "{strategy}, окно {} мс, запуск каждые {} мс",
+                                        params_profile.context_length_ms,
+                                        params_profile.repeat_run_ms,
+                                    ),
+                                ))
+                                .when(!recent_models.is_empty(), |this| {
+                                    this.child(SettingsItem::new().label("Недавние модели").child(
+                                        div().flex().gap_2().children(
+                                            recent_models.into_iter().enumerate().map(
+                                                |(index, model)| {
+                                                    recent_model_button(
+                                                        index,
+                                                        model.clone(),
+                                                        active_model.as_ref() == Some(&model),
+                                                    )
+                                                },
+                                            ),
+                                        ),
+                                    ))
+                                }),
                         ),
                 )
                 // TODO: add other fields
@@ -64,3 +123,18 @@ impl gpui::Render for ModelPage {
         )
     }
 }
+
+/// Quick-switch button that makes a recently used `model` active.
+fn recent_model_button(index: usize, model: WhisperModel, is_active: bool) -> Button {
+    Button::new(("recent_model", index))
+        .label(model.display_name())
+        .disabled(is_active)
+        .on_click(move |_, _, cx| {
+            let bridge = cx.global::<BackendBridge>().clone();
+            let model = model.clone();
+            cx.spawn(async move |_| {
+                bridge.set_active_model(model).await;
+            })
+            .detach();
+        })
+}
@@ -1,20 +1,28 @@
 use gpui::{
-    AppContext, Bounds, Context, Entity, IntoElement, ParentElement, Pixels, Point, Render,
-    SharedString, Styled, Window, WindowBounds, WindowOptions, div, px, size,
+    AppContext, Context, Entity, IntoElement, ParentElement, Render, SharedString, Styled, Window,
+    div, prelude::FluentBuilder, relative,
 };
 use gpui_component::{
-    Disableable, IndexPath, StyledExt,
+    ActiveTheme, Disableable, IndexPath, StyledExt,
     button::Button,
     select::{Select, SelectEvent, SelectItem, SelectState},
 };
-use subwin_bridge::config::CaptionsBackgroundAppearance;
 
 use crate::{
     BackendBridge,
-    entities::{DataEntities, settings_entity::SettingsEntity},
-    views::captions_root_view::CaptionsRootView,
+    entities::DataEntities,
+    views::captions_root_view::{start_captions, stop_captions},
 };
 
+/// Duration of the microphone test recording, in milliseconds.
+const MIC_TEST_DURATION_MS: u32 = 3000;
+
+/// Input level shown as an empty level meter, in dBFS.
+const METER_FLOOR_DB: f32 = -60.0;
+
+/// Peak level above which the level meter warns of clipping, in dBFS.
+const METER_CLIP_DB: f32 = -1.0;
+
 #[derive(Debug, Clone)]
 struct AudioDevice {
     id: SharedString,
@@ -34,10 +42,8 @@ impl SelectItem for AudioDevice {
 }
 
 pub struct OverviewPage {
-    is_active: bool,
+    data: DataEntities,
     active_audio_device: Entity<SelectState<Vec<AudioDevice>>>,
-    captions_window_view: Entity<CaptionsRootView>,
-    settings: Entity<SettingsEntity>,
 }
 
 impl OverviewPage {
@@ -95,6 +101,7 @@ impl OverviewPage {
                         state.set_selected_index(Some(IndexPath::new(selected_index)), window, cx);
                     });
                 }
+                cx.notify();
             },
         )
         .detach();
@@ -123,17 +130,111 @@ impl OverviewPage {
         )
         .detach();
 
-        Self {
-            is_active: false,
+        // re-render when transcription starts or stops
+        cx.observe(&data.captions, |_, _, cx| cx.notify()).detach();
+        cx.observe(&data.input_level, |_, _, cx| cx.notify())
+            .detach();
+
+        // the latency estimate and the config's validity depend on the
+        // config and the input device
+        cx.observe(&data.settings, |this, _, cx| {
+            this.request_latency_estimate(cx);
+            this.request_validation(cx);
+        })
+        .detach();
+        cx.observe(&data.audio_devices, |this, _, cx| {
+            this.request_latency_estimate(cx);
+            this.request_validation(cx);
+        })
+        .detach();
+        // a downloaded model may resolve the issues
+        cx.observe(&data.download, |this, _, cx| this.request_validation(cx))
+            .detach();
+
+        let page = Self {
+            data: data.clone(),
             active_audio_device,
-            captions_window_view: cx.new(|_| CaptionsRootView::new(data)),
-            settings: data.settings.clone(),
-        }
+        };
+        page.request_validation(cx);
+        page
+    }
+
+    /// Asks the backend to check the config for problems a start would fail
+    /// on.
+    fn request_validation(&self, cx: &mut Context<Self>) {
+        let bridge = cx.global::<BackendBridge>().clone();
+        cx.spawn(async move |_, _| {
+            bridge.validate_config().await;
+        })
+        .detach();
+    }
+
+    /// Asks the backend to measure how fast the active model decodes.
+    fn benchmark_model(&self, cx: &mut Context<Self>) {
+        self.data.captions.update(cx, |captions, cx| {
+            captions.is_benchmarking = true;
+            cx.notify();
+        });
+        let bridge = cx.global::<BackendBridge>().clone();
+        cx.spawn(async move |_, _| {
+            bridge.benchmark_model().await;
+        })
+        .detach();
+    }
+
+    /// Asks the backend for the latency estimate of the current config.
+    fn request_latency_estimate(&self, cx: &mut Context<Self>) {
+        let bridge = cx.global::<BackendBridge>().clone();
+        cx.spawn(async move |_, _| {
+            bridge.request_latency_estimate().await;
+        })
+        .detach();
     }
 }
 
 impl Render for OverviewPage {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let (
+            is_running,
+            is_starting,
+            is_capture_released,
+            is_benchmarking,
+            latency_estimate,
+            config_issues,
+        ) = {
+            let captions = self.data.captions.read(cx);
+            (
+                captions.is_running,
+                captions.is_starting,
+                captions.is_capture_released,
+                captions.is_benchmarking,
+                captions.latency_estimate,
+                captions.config_issues.clone(),
+            )
+        };
+        let is_start_blocked = config_issues.iter().any(|issue| issue.is_blocking());
+        let input_level = *self.data.input_level.read(cx);
+        let audio_devices = self.data.audio_devices.read(cx);
+        let selected_device_id = audio_devices
+            .audio_devices
+            .iter()
+            .find(|device| device.selected)
+            .map(|device| device.id.clone());
+        let device_info = match &audio_devices.active_device_info {
+            Some(info) => format!(
+                "{}: {} Гц, каналов: {}{}",
+                info.description,
+                info.sample_rate,
+                info.channels,
+                if info.is_resampled {
+                    ", с передискретизацией в 16 кГц"
+                } else {
+                    ""
+                },
+            ),
+            None => "Источник звука не выбран или недоступен.".to_string(),
+        };
+
         div()
             .flex()
             .flex_col()
@@ -141,76 +242,124 @@ impl Render for OverviewPage {
             .child(div().child("Главная").text_2xl().font_bold())
             .child(Select::new(&self.active_audio_device).placeholder("Выберите источник звука..."))
             .child(
-                div().flex().gap_3().child(
-                    Button::new("start_transcribing")
-                        .disabled(self.is_active)
-                        .label("Включить субтитры")
-                        .on_click(cx.listener(|this, _, window, cx| {
-                            let captions_config = {
-                                let settings = this.settings.read(cx);
-                                settings.config.captions_config.clone()
-                            };
-                            let (window_width, window_height) = (
-                                px(captions_config.window_width),
-                                px(captions_config.window_height),
-                            );
-                            let window_background = match captions_config.background_appearance {
-                                CaptionsBackgroundAppearance::Opaque => {
-                                    gpui::WindowBackgroundAppearance::Opaque
-                                }
-                                CaptionsBackgroundAppearance::Transparent => {
-                                    gpui::WindowBackgroundAppearance::Transparent
-                                }
-                                CaptionsBackgroundAppearance::Blurred => {
-                                    gpui::WindowBackgroundAppearance::Blurred
-                                }
-                            };
-
-                            let display = window
-                                .display(cx)
-                                .expect("failed to get current window's display");
-
-                            let display_size = display.bounds().size;
-                            let origin = Point::new(
-                                (display_size.width - window_width) / 2.0,
-                                Pixels::from(
-                                    display_size.height.to_f64()
-                                        - captions_config.padding_from_bottom as f64,
-                                ),
-                            );
-
-                            let caption_window_bounds =
-                                Bounds::new(origin, size(window_width, window_height));
-
-                            let captions_window_options: WindowOptions = WindowOptions {
-                                window_bounds: Some(WindowBounds::Windowed(caption_window_bounds)),
-                                titlebar: None,
-                                focus: false,
-                                show: true,
-                                kind: gpui::WindowKind::PopUp,
-                                is_movable: true,
-                                is_resizable: false,
-                                is_minimizable: false,
-                                display_id: None,
-                                window_background,
-                                app_id: Some("subwin".to_owned()),
-                                window_min_size: None,
-                                window_decorations: None,
-                                tabbing_identifier: Some("subwin".to_owned()),
-                            };
-
-                            cx.open_window(captions_window_options, |_, _| {
-                                this.captions_window_view.clone()
-                            })
-                            .expect("failed to open captions window");
-
-                            let bridge = cx.global::<BackendBridge>().clone();
-                            cx.spawn(async move |_, _| {
-                                bridge.start_transcription_request().await;
-                            })
-                            .detach();
-                        })),
-                ),
+                div()
+                    .text_sm()
+                    .text_color(cx.theme().muted_foreground)
+                    .child(device_info),
+            )
+            .child(
+                div()
+                    .flex()
+                    .gap_3()
+                    .child(
+                        // the benchmark has the model loaded until it ends
+                        Button::new("start_transcribing")
+                            .disabled(
+                                is_running || is_starting || is_benchmarking || is_start_blocked,
+                            )
+                            .loading(is_starting)
+                            .label("Включить субтитры")
+                            .on_click(cx.listener(|this, _, window, cx| {
+                                start_captions(&this.data, window, cx);
+                            })),
+                    )
+                    .child(
+                        // stopping also cancels a start that's underway
+                        Button::new("stop_transcribing")
+                            .disabled(!is_running && !is_starting)
+                            .label("Выключить субтитры")
+                            .on_click(|_, _, cx| stop_captions(cx)),
+                    )
+                    .when(is_running && is_capture_released, |this| {
+                        this.child(Button::new("resume_capture").label("Продолжить").on_click(
+                            |_, _, cx| {
+                                let bridge = cx.global::<BackendBridge>().clone();
+                                cx.spawn(async move |_| {
+                                    bridge.resume_capture_request().await;
+                                })
+                                .detach();
+                            },
+                        ))
+                    })
+                    .child(
+                        Button::new("test_microphone")
+                            .disabled(selected_device_id.is_none())
+                            .label("Проверить микрофон")
+                            .on_click(move |_, _, cx| {
+                                let Some(device_id) = selected_device_id.clone() else {
+                                    return;
+                                };
+                                let bridge = cx.global::<BackendBridge>().clone();
+                                cx.spawn(async move |_| {
+                                    bridge
+                                        .test_microphone(device_id, MIC_TEST_DURATION_MS, true)
+                                        .await;
+                                })
+                                .detach();
+                            }),
+                    )
+                    .child(
+                        // a session and a benchmark would slow each other down
+                        Button::new("benchmark_model")
+                            .disabled(is_running || is_starting || is_benchmarking)
+                            .loading(is_benchmarking)
+                            .label("Проверить скорость модели")
+                            .on_click(cx.listener(|this, _, _, cx| {
+                                this.benchmark_model(cx);
+                            })),
+                    ),
+            )
+            .when(!is_running && !config_issues.is_empty(), |this| {
+                this.child(div().flex().flex_col().gap_1().text_sm().children(
+                    config_issues.into_iter().map(|issue| {
+                        let color = if issue.is_blocking() {
+                            cx.theme().danger
+                        } else {
+                            cx.theme().warning
+                        };
+                        div().text_color(color).child(issue.message)
+                    }),
+                ))
+            })
+            .when(is_running, |this| {
+                let fill = ((input_level.rms_db - METER_FLOOR_DB) / -METER_FLOOR_DB).clamp(0.0, 1.0);
+                let color = if input_level.peak_db > METER_CLIP_DB {
+                    cx.theme().warning
+                } else {
+                    cx.theme().success
+                };
+                this.child(
+                    div()
+                        .w_full()
+                        .h_2()
+                        .rounded_md()
+                        .bg(cx.theme().muted_foreground.opacity(0.2))
+                        .child(div().h_full().w(relative(fill)).rounded_md().bg(color)),
+                )
+            })
+            .when_some(
+                latency_estimate.filter(|_| !is_running),
+                |this, estimate| {
+                    this.child(
+                        div()
+                            .text_sm()
+                            .text_color(cx.theme().muted_foreground)
+                            .child(format!(
+                                "Ожидаемая задержка субтитров: ~{:.0} мс (буфер {:.0}, преобразование {:.0}, ожидание {:.0}, распознавание {:.0}{}, упреждение {:.0}).",
+                                estimate.estimated_latency_ms(),
+                                estimate.capture_buffer_ms,
+                                estimate.resampler_ms,
+                                estimate.decode_wait_ms,
+                                estimate.decode_ms,
+                                if estimate.is_decode_measured {
+                                    ""
+                                } else {
+                                    ", примерно"
+                                },
+                                estimate.lookahead_ms,
+                            )),
+                    )
+                },
             )
     }
 }
@@ -1,12 +1,30 @@
-use gpui::{AppContext, Context, Entity, IntoElement, ParentElement, Render, Styled, Window, div};
+use gpui::{
+    AppContext, Context, Entity, IntoElement, ParentElement, PathPromptOptions, Render, Styled,
+    Window, div, prelude::FluentBuilder,
+};
 use gpui_component::{
     StyledExt,
+    button::Button,
     group_box::{GroupBox, GroupBoxVariants},
     slider::{Slider, SliderEvent, SliderState},
     switch::Switch,
 };
 
-use crate::{components::settings_item::SettingsItem, entities::DataEntities};
+use subwin_bridge::config::{
+    Config, DEFAULT_CLEAR_CAPTIONS_AFTER_SILENCE_MS, DEFAULT_LIMITER_CEILING_DB, SubtitleFormat,
+};
+
+use crate::{
+    BackendBridge,
+    components::settings_item::SettingsItem,
+    entities::{
+        DataEntities,
+        settings_entity::{ConfigSaveStatus, SettingsEntity},
+    },
+};
+
+/// File name suggested when exporting the config.
+const EXPORTED_CONFIG_FILE_NAME: &str = "subwin-config.toml";
 
 pub struct SettingsPage {
     data: DataEntities,
@@ -41,20 +59,144 @@ impl SettingsPage {
             padding_from_button_state,
         }
     }
+
+    /// Applies `update` to a copy of the current config and sends it to the
+    /// backend to be persisted.
+    fn update_config(&self, cx: &mut Context<Self>, update: impl FnOnce(&mut Config)) {
+        let mut config = self.data.settings.read(cx).config.clone();
+        update(&mut config);
+        SettingsEntity::set_save_status(&self.data.settings, ConfigSaveStatus::Pending, cx);
+
+        let bridge = cx.global::<BackendBridge>().clone();
+        cx.spawn(async move |_, _| {
+            bridge.update_config(config).await;
+        })
+        .detach();
+    }
+
+    /// Builds a switch per connected display, choosing whether a captions
+    /// window is opened on it. `selected` are the identifiers of the chosen
+    /// displays.
+    fn display_switches(&self, selected: &[String], cx: &mut Context<Self>) -> Vec<Switch> {
+        let primary_display_id = cx.primary_display().map(|display| display.id());
+        cx.displays()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, display)| {
+                // displays without a stable identifier can't be remembered
+                let uuid = display.uuid().ok()?.to_string();
+                let display_size = display.bounds().size;
+                let label = format!(
+                    "Монитор {} ({}×{}{})",
+                    index + 1,
+                    f32::from(display_size.width),
+                    f32::from(display_size.height),
+                    if primary_display_id == Some(display.id()) {
+                        ", основной"
+                    } else {
+                        ""
+                    },
+                );
+                let switch = Switch::new(("caption_display", index))
+                    .label(label)
+                    .checked(selected.contains(&uuid))
+                    .on_click(cx.listener(move |this, checked: &bool, _, cx| {
+                        let checked = *checked;
+                        let uuid = uuid.clone();
+                        this.update_config(cx, |config| {
+                            let displays = &mut config.captions_config.displays;
+                            displays.retain(|display| *display != uuid);
+                            if checked {
+                                displays.push(uuid);
+                            }
+                        });
+                    }));
+                Some(switch)
+            })
+            .collect()
+    }
+
+    /// Asks the user for the directory saved transcripts are written to.
+    fn choose_transcript_output_dir(&self, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: false,
+            directories: true,
+            multiple: false,
+            prompt: Some("Выбрать папку".into()),
+        });
+        cx.spawn(async move |this, cx| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(directory) = paths.into_iter().next() else {
+                return;
+            };
+            let _ = this.update(cx, |this, cx| {
+                this.update_config(cx, |config| {
+                    config.transcript_output_dir = Some(directory);
+                });
+            });
+        })
+        .detach();
+    }
+
+    /// Asks the user for the file to export the config into.
+    fn export_config(&self, cx: &mut Context<Self>) {
+        let directory = std::env::current_dir().unwrap_or_default();
+        let path = cx.prompt_for_new_path(&directory, Some(EXPORTED_CONFIG_FILE_NAME));
+        let bridge = cx.global::<BackendBridge>().clone();
+        cx.spawn(async move |_, _| {
+            let Ok(Ok(Some(path))) = path.await else {
+                return;
+            };
+            bridge.export_config(path).await;
+        })
+        .detach();
+    }
+
+    /// Asks the user for a previously exported config file to import.
+    fn import_config(&self, cx: &mut Context<Self>) {
+        let paths = cx.prompt_for_paths(PathPromptOptions {
+            files: true,
+            directories: false,
+            multiple: false,
+            prompt: Some("Импортировать".into()),
+        });
+        let bridge = cx.global::<BackendBridge>().clone();
+        cx.spawn(async move |_, _| {
+            let Ok(Ok(Some(paths))) = paths.await else {
+                return;
+            };
+            let Some(path) = paths.into_iter().next() else {
+                return;
+            };
+            bridge.import_config(path).await;
+        })
+        .detach();
+    }
 }
 
 impl Render for SettingsPage {
     fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
-        let config = {
+        let (config, save_status) = {
             let settings_state = self.data.settings.read(cx);
-            &settings_state.config.clone()
+            (&settings_state.config.clone(), settings_state.save_status)
+        };
+        let save_status = match save_status {
+            ConfigSaveStatus::Unchanged => None,
+            ConfigSaveStatus::Pending => Some("Изменения ещё не сохранены."),
+            ConfigSaveStatus::Saved => Some("✓ Изменения сохранены."),
         };
+        let display_switches = self.display_switches(&config.captions_config.displays, cx);
 
         div()
             .size_full()
             .flex()
             .flex_col()
             .gap_6()
+            .when_some(save_status, |this, save_status| {
+                this.child(div().text_sm().child(save_status))
+            })
             .child(
                 GroupBox::new()
                     .outline()
@@ -72,17 +214,380 @@ impl Render for SettingsPage {
                                 Switch::new("enable_auto_translation")
                                     .checked(config.enable_auto_translation),
                             ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Автоматически усиливать тихий звук?")
+                            .child(
+                                Switch::new("enable_agc")
+                                    .checked(config.enable_agc)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.enable_agc = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Смягчать перегрузку громкого звука?")
+                            .child(
+                                Switch::new("enable_limiter")
+                                    .checked(config.limiter_ceiling_db.is_some())
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.limiter_ceiling_db =
+                                                checked.then_some(DEFAULT_LIMITER_CEILING_DB);
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Прослушивать захватываемый звук?")
+                            .child(
+                                Switch::new("enable_monitor")
+                                    .checked(config.enable_monitor)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.enable_monitor = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Скрывать пометки вроде смеха и музыки?")
+                            .child(
+                                Switch::new("suppress_non_speech")
+                                    .checked(config.suppress_non_speech)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.suppress_non_speech = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Распознавать речь одной строкой?")
+                            .child(
+                                Switch::new("single_segment")
+                                    .checked(config.single_segment)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.single_segment = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Учитывать уже распознанный текст (может повторять ошибки)?")
+                            .child(
+                                Switch::new("carry_prompt")
+                                    .checked(config.carry_prompt)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.carry_prompt = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Переходить на модель поменьше, если распознавание отстаёт?")
+                            .child(
+                                Switch::new("adaptive_model")
+                                    .checked(config.adaptive_model)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.adaptive_model = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Распознавать речь на видеокарте (если поддерживается)?")
+                            .child(Switch::new("use_gpu").checked(config.use_gpu).on_click(
+                                cx.listener(|this, checked: &bool, _, cx| {
+                                    let checked = *checked;
+                                    this.update_config(cx, |config| {
+                                        config.use_gpu = checked;
+                                    });
+                                }),
+                            )),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Исправлять пробелы и заглавные буквы?")
+                            .child(
+                                Switch::new("text_normalization")
+                                    .checked(config.text_normalization.enabled)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.text_normalization.enabled = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Включать субтитры при запуске?")
+                            .child(
+                                Switch::new("auto_start_transcription")
+                                    .checked(config.auto_start_transcription)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.auto_start_transcription = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Распознавать речь, только пока зажата клавиша?")
+                            .child(
+                                Switch::new("push_to_talk")
+                                    .label(config.push_to_talk.hotkey.clone())
+                                    .checked(config.push_to_talk.enabled)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.push_to_talk.enabled = checked;
+                                        });
+                                    })),
+                            ),
                     ),
             )
             .child(
                 GroupBox::new()
                     .outline()
                     .child(div().child("Внешний вид").text_xl().font_bold())
+                    .child(
+                        SettingsItem::new()
+                            .label("Показывать окно субтитров?")
+                            .child(
+                                Switch::new("show_caption_window")
+                                    .checked(config.show_caption_window)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.show_caption_window = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Показывать субтитры поверх всех окон?")
+                            .child(
+                                Switch::new("captions_always_on_top")
+                                    .checked(config.captions_config.always_on_top)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.captions_config.always_on_top = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Подгонять размер окна субтитров под текст?")
+                            .child(
+                                Switch::new("captions_auto_size")
+                                    .checked(config.captions_config.auto_size)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.captions_config.auto_size = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Выделять неуверенно распознанный текст?")
+                            .child(
+                                Switch::new("mark_uncertain_text")
+                                    .checked(config.captions_config.mark_uncertain_text)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.captions_config.mark_uncertain_text = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Мониторы для субтитров (по умолчанию — текущий)")
+                            .child(div().flex().flex_col().gap_1().children(display_switches)),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Убирать субтитры после паузы в речи?")
+                            .child(
+                                Switch::new("clear_captions_after_silence")
+                                    .checked(config.clear_captions_after_silence_ms.is_some())
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.clear_captions_after_silence_ms = checked
+                                                .then_some(DEFAULT_CLEAR_CAPTIONS_AFTER_SILENCE_MS);
+                                        });
+                                    })),
+                            ),
+                    )
                     .child(
                         SettingsItem::new()
                             .label("Отступ от низа экрана")
                             .child(Slider::new(&self.padding_from_button_state).max_w_1_4()),
                     ),
             )
+            .child(
+                GroupBox::new()
+                    .outline()
+                    .child(div().child("Сохранение субтитров").text_xl().font_bold())
+                    .child(
+                        SettingsItem::new()
+                            .label("Сохранять субтитры каждого сеанса в файл?")
+                            .child(
+                                Switch::new("save_transcripts")
+                                    .checked(config.caption_sinks.save_transcripts)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.caption_sinks.save_transcripts = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Сохранять простым текстом вместо субтитров?")
+                            .child(
+                                Switch::new("plain_text_transcripts")
+                                    .checked(
+                                        config.caption_sinks.subtitle_format
+                                            == SubtitleFormat::Text,
+                                    )
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.caption_sinks.subtitle_format = if checked {
+                                                SubtitleFormat::Text
+                                            } else {
+                                                SubtitleFormat::default()
+                                            };
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new()
+                            .label("Добавлять время к строкам текста?")
+                            .child(
+                                Switch::new("timestamp_lines")
+                                    .checked(config.caption_sinks.timestamp_lines)
+                                    .on_click(cx.listener(|this, checked: &bool, _, cx| {
+                                        let checked = *checked;
+                                        this.update_config(cx, |config| {
+                                            config.caption_sinks.timestamp_lines = checked;
+                                        });
+                                    })),
+                            ),
+                    )
+                    .child(
+                        SettingsItem::new().label("Папка для субтитров").child(
+                            div()
+                                .flex()
+                                .items_center()
+                                .gap_2()
+                                .child(match &config.transcript_output_dir {
+                                    Some(directory) => directory.display().to_string(),
+                                    None => "Папка кэша".to_string(),
+                                })
+                                .when(config.transcript_output_dir.is_some(), |this| {
+                                    this.child(
+                                        Button::new("reset_transcript_output_dir")
+                                            .label("Сбросить")
+                                            .on_click(cx.listener(|this, _, _, cx| {
+                                                this.update_config(cx, |config| {
+                                                    config.transcript_output_dir = None;
+                                                });
+                                            })),
+                                    )
+                                })
+                                .child(
+                                    Button::new("choose_transcript_output_dir")
+                                        .label("Выбрать…")
+                                        .on_click(cx.listener(|this, _, _, cx| {
+                                            this.choose_transcript_output_dir(cx);
+                                        })),
+                                ),
+                        ),
+                    ),
+            )
+            .child(
+                GroupBox::new()
+                    .outline()
+                    .child(div().child("Файл настроек").text_xl().font_bold())
+                    .child(
+                        SettingsItem::new()
+                            .label("Поделиться настройками или восстановить их")
+                            .child(
+                                div()
+                                    .flex()
+                                    .items_center()
+                                    .gap_2()
+                                    .child(
+                                        Button::new("import_config")
+                                            .label("Импортировать…")
+                                            .on_click(cx.listener(|this, _, _, cx| {
+                                                this.import_config(cx);
+                                            })),
+                                    )
+                                    .child(
+                                        Button::new("export_config")
+                                            .label("Экспортировать…")
+                                            .on_click(cx.listener(|this, _, _, cx| {
+                                                this.export_config(cx);
+                                            })),
+                                    ),
+                            ),
+                    ),
+            )
+            .child(
+                GroupBox::new()
+                    .outline()
+                    .child(div().child("Диагностика").text_xl().font_bold())
+                    .child(
+                        SettingsItem::new()
+                            .label("Данные для отчёта об ошибке")
+                            .child(
+                                Button::new("export_diagnostics")
+                                    .label("Экспортировать диагностику")
+                                    .on_click(|_, _, cx| {
+                                        let bridge = cx.global::<BackendBridge>().clone();
+                                        cx.spawn(async move |_| {
+                                            bridge.export_diagnostics().await;
+                                        })
+                                        .detach();
+                                    }),
+                            ),
+                    ),
+            )
     }
 }
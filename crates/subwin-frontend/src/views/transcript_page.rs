@@ -0,0 +1,209 @@
+use gpui::{
+    AnyElement, ClipboardItem, Context, InteractiveElement, IntoElement, ListAlignment, ListOffset,
+    ListState, ParentElement, Render, StatefulInteractiveElement, Styled, Window, div, list,
+    prelude::FluentBuilder, px,
+};
+use gpui_component::{ActiveTheme, Disableable, StyledExt, button::Button, text::TextView};
+use subwin_bridge::config::{format_line_prefix, format_session_time};
+
+use crate::{entities::DataEntities, formatting::escape_markdown};
+
+/// Height of the transcript rendered beyond its visible part, so lines
+/// don't pop in while scrolling.
+const LIST_OVERDRAW: f32 = 200.0;
+
+/// Finalized captions of the sessions, for reviewing and copying them. New
+/// lines are followed, unless the transcript has been scrolled up.
+///
+/// Only the visible lines are laid out, as the transcript may grow to
+/// thousands of them.
+pub struct TranscriptPage {
+    data: DataEntities,
+    /// Bottom-aligned, so it sticks to the latest line unless scrolled up.
+    list_state: ListState,
+    /// Index of the line copied last, marked until another one is copied.
+    copied_line: Option<usize>,
+}
+
+impl TranscriptPage {
+    pub fn new(data: &DataEntities, cx: &mut Context<Self>) -> Self {
+        let captions = data.captions.read(cx);
+        let list_state = ListState::new(
+            captions.transcript.len(),
+            ListAlignment::Bottom,
+            px(LIST_OVERDRAW),
+        );
+
+        let mut appended_lines = captions.transcript_appended_lines;
+        cx.observe(&data.captions, move |this: &mut Self, captions, cx| {
+            let captions = captions.read(cx);
+            let new_lines = (captions.transcript_appended_lines - appended_lines) as usize;
+            let item_count = this.list_state.item_count();
+            if new_lines == 0 && captions.transcript.len() == item_count {
+                return;
+            }
+            appended_lines = captions.transcript_appended_lines;
+
+            // the oldest lines get dropped beyond the limit, or all on clear
+            let dropped_lines = (item_count + new_lines).saturating_sub(captions.transcript.len());
+            this.list_state.splice(item_count..item_count, new_lines);
+            this.list_state.splice(0..dropped_lines, 0);
+            this.copied_line = None;
+            cx.notify();
+        })
+        .detach();
+
+        Self {
+            data: data.clone(),
+            list_state,
+            copied_line: None,
+        }
+    }
+
+    /// Returns whether the transcript is scrolled to the latest line.
+    fn is_at_bottom(&self) -> bool {
+        self.list_state.logical_scroll_top().item_ix >= self.list_state.item_count()
+    }
+
+    /// Renders the transcript line at `index`. Its text can be selected and
+    /// copied, and a click on its time copies the whole line, prefixed with
+    /// its timestamp if lines are timestamped (see
+    /// [`subwin_bridge::config::CaptionSinksConfig::timestamp_lines`]).
+    fn render_line(
+        &mut self,
+        index: usize,
+        window: &mut Window,
+        cx: &mut Context<Self>,
+    ) -> AnyElement {
+        let captions = self.data.captions.read(cx);
+        let Some(line) = captions.transcript.get(index).cloned() else {
+            return div().into_any_element();
+        };
+        // stays the same for a line as the older ones get dropped
+        let line_number =
+            captions.transcript_appended_lines as usize - captions.transcript.len() + index;
+        let is_copied = self.copied_line == Some(index);
+        let copied_text = match self
+            .data
+            .settings
+            .read(cx)
+            .config
+            .caption_sinks
+            .line_prefix_template()
+        {
+            Some(template) => format_line_prefix(template, line.start_ms) + &line.text,
+            None => line.text.clone(),
+        };
+
+        div()
+            .flex()
+            .gap_3()
+            .px_2()
+            .py_1()
+            .child(
+                div()
+                    .id(("transcript_time", line_number))
+                    .flex_none()
+                    .px_1()
+                    .rounded_md()
+                    .cursor_pointer()
+                    .text_color(cx.theme().muted_foreground)
+                    .hover(|style| style.bg(cx.theme().muted_foreground.opacity(0.1)))
+                    .child(format_session_time(line.start_ms))
+                    .on_click(cx.listener(move |this, _, _, cx| {
+                        cx.write_to_clipboard(ClipboardItem::new_string(copied_text.clone()));
+                        this.copied_line = Some(index);
+                        cx.notify();
+                    })),
+            )
+            .child(
+                div().flex_1().child(
+                    TextView::markdown(
+                        ("transcript_line", line_number),
+                        escape_markdown(&line.text),
+                        window,
+                        cx,
+                    )
+                    .selectable(true),
+                ),
+            )
+            .when(is_copied, |this| {
+                this.child(
+                    div()
+                        .flex_none()
+                        .text_sm()
+                        .text_color(cx.theme().success)
+                        .child("Скопировано"),
+                )
+            })
+            .into_any_element()
+    }
+}
+
+impl Render for TranscriptPage {
+    fn render(&mut self, _: &mut Window, cx: &mut Context<Self>) -> impl IntoElement {
+        let is_empty = self.data.captions.read(cx).transcript.is_empty();
+        let is_scrolled_up = !self.is_at_bottom();
+
+        div()
+            .flex()
+            .flex_col()
+            .gap_3()
+            .size_full()
+            .child(
+                div()
+                    .flex()
+                    .items_center()
+                    .justify_between()
+                    .child(div().child("Расшифровка").text_2xl().font_bold())
+                    .child(
+                        Button::new("clear_transcript")
+                            .disabled(is_empty)
+                            .label("Очистить")
+                            .on_click(cx.listener(|this, _, _, cx| {
+                                this.data.captions.update(cx, |captions, cx| {
+                                    captions.transcript.clear();
+                                    cx.notify();
+                                });
+                            })),
+                    ),
+            )
+            .child(
+                div()
+                    .text_sm()
+                    .text_color(cx.theme().muted_foreground)
+                    .child("Выделите текст, чтобы скопировать его, или нажмите на время строки, чтобы скопировать её целиком."),
+            )
+            .when(is_empty, |this| {
+                this.child(
+                    div()
+                        .text_color(cx.theme().muted_foreground)
+                        .child("Здесь появятся распознанные субтитры."),
+                )
+            })
+            .child(
+                list(
+                    self.list_state.clone(),
+                    cx.processor(|this, index, window, cx| this.render_line(index, window, cx)),
+                )
+                .flex_1()
+                .min_h_0(),
+            )
+            .when(is_scrolled_up, |this| {
+                this.child(
+                    div().flex().justify_center().child(
+                        Button::new("jump_to_latest")
+                            .label("К последним строкам")
+                            .on_click(cx.listener(|this, _, _, cx| {
+                                let item_count = this.list_state.item_count();
+                                this.list_state.scroll_to(ListOffset {
+                                    item_ix: item_count,
+                                    offset_in_item: px(0.),
+                                });
+                                cx.notify();
+                            })),
+                    ),
+                )
+            })
+    }
+}
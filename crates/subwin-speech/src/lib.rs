@@ -6,6 +6,8 @@
 //! low-latency, incremental captioning by repeatedly processing recent audio
 //! context.
 
+pub mod normalize;
+pub mod replacements;
 pub mod stabilizer;
 pub mod whisper;
 
@@ -27,15 +29,57 @@ pub const REPEAT_RUN_MILLISECONDS: u32 = 500;
 
 /// Converts a duration in milliseconds to the equivalent number of audio samples
 /// at the given sample rate.
-pub(crate) fn milliseconds_to_samples(milliseconds: u32, sample_rate: u32) -> usize {
+pub fn milliseconds_to_samples(milliseconds: u32, sample_rate: u32) -> usize {
     ((sample_rate as u64 * milliseconds as u64) / 1000) as usize
 }
 
+/// Performance metrics of a single transcription inference.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TranscriptionMetrics {
+    /// Elapsed time of the inference, in milliseconds.
+    pub inference_ms: u128,
+    /// Number of audio samples the inference has been run on.
+    pub samples_processed: usize,
+    /// Ratio of the inference time to the duration of the processed audio.
+    /// Values above `1.0` mean the transcriber is slower than real time and
+    /// can't keep up with the incoming audio.
+    pub real_time_factor: f64,
+    /// Whether the inference has been aborted for exceeding its time limit,
+    /// without producing any segments.
+    pub timed_out: bool,
+}
+
+impl TranscriptionMetrics {
+    /// Builds metrics for an inference over `samples_processed` samples at
+    /// `sample_rate`, which took `inference_ms` milliseconds.
+    pub fn new(inference_ms: u128, samples_processed: usize, sample_rate: u32) -> Self {
+        let audio_ms = samples_processed as f64 * 1000.0 / sample_rate as f64;
+        let real_time_factor = if audio_ms > 0.0 {
+            inference_ms as f64 / audio_ms
+        } else {
+            0.0
+        };
+
+        Self {
+            inference_ms,
+            samples_processed,
+            real_time_factor,
+            timed_out: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct CaptionSegment {
     pub start_milliseconds: i64,
     pub end_milliseconds: i64,
     pub text: String,
+    /// Speaker the segment has been recognized for, when channels are
+    /// transcribed separately; `None` for mixed audio.
+    pub speaker: Option<u16>,
+    /// Average probability of the segment's text tokens, from `0.0` to
+    /// `1.0`; `None` if the transcriber doesn't report it.
+    pub confidence: Option<f32>,
 }
 
 /// Trait for real-time audio transcribers that process mono `f32` samples and
@@ -47,6 +91,9 @@ pub struct CaptionSegment {
 ///   (defined by [`CONTEXT_LENGTH_MILLISECONDS`]).
 /// - Emit incremental or refined transcription segments.
 pub trait Transcriber<P> {
+    /// Error returned when an inference fails.
+    type Error;
+
     /// Returns the minimum number of buffered samples required before a
     /// transcription attempt can be performed.
     fn min_transcription_samples(sample_rate: u32) -> usize;
@@ -68,12 +115,30 @@ pub trait Transcriber<P> {
     ///
     /// A tuple containing:
     ///
-    /// * `Option<String>` - The concatenated transcribed text from the current
-    ///   audio segment, if any text was produced. Returns `None` if no speech
-    ///   was detected, no text was generated, or if processing failed.
-    /// * `u128` - The elapsed time for the transcription inference in
-    ///   milliseconds.
-    fn try_transcribe(&mut self, params: P) -> (Vec<CaptionSegment>, u128);
+    /// * `Vec<CaptionSegment>` - The transcribed segments of the current audio
+    ///   window. Empty if no speech was detected or no text was generated.
+    /// * `Option<TranscriptionMetrics>` - Metrics of the inference, or `None`
+    ///   if no inference has been run.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the inference itself has failed. The buffered audio
+    /// is kept, so the caller may retry on the next scheduled run, or rebuild
+    /// the transcriber if failures persist.
+    fn try_transcribe(
+        &mut self,
+        params: P,
+    ) -> Result<(Vec<CaptionSegment>, Option<TranscriptionMetrics>), Self::Error>;
+}
+
+/// RMS level in dBFS at or below which audio is considered silence.
+pub const SILENCE_THRESHOLD_DB: f64 = -60.0;
+
+/// Returns whether `samples` are quiet enough to be considered silence (see
+/// [`SILENCE_THRESHOLD_DB`]).
+pub fn is_silence(samples: &[f32]) -> bool {
+    let rms = calculate_samples_rms(samples);
+    rms == 0.0 || (20.0 * rms.log10()) <= SILENCE_THRESHOLD_DB
 }
 
 pub(crate) fn calculate_samples_rms<T>(samples_data: &[T]) -> f64
@@ -0,0 +1,65 @@
+//! Whitespace and capitalization cleanup of the recognized text.
+//!
+//! Overlapping decodes don't always agree on spacing and capitalization, so
+//! joined captions may contain doubled spaces or sentences starting in
+//! lowercase. Normalization is idempotent, so already normalized segments
+//! can be passed through it again.
+
+use crate::CaptionSegment;
+
+/// Characters that end a sentence, including the CJK full-width ones.
+const SENTENCE_ENDINGS: &[char] = &['.', '!', '?', '…', '。', '！', '？'];
+
+/// Collapses runs of whitespace into single spaces and trims `text`. When
+/// `capitalize_sentences` is set, uppercases the first letter of every
+/// sentence, and of the text itself if `starts_sentence` is set. Letters of
+/// scripts without case are left as is.
+pub fn normalize_text(text: &str, starts_sentence: bool, capitalize_sentences: bool) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut capitalize_next = starts_sentence && capitalize_sentences;
+    for word in text.split_whitespace() {
+        if !result.is_empty() {
+            result.push(' ');
+        }
+
+        for char in word.chars() {
+            if capitalize_next && char.is_alphabetic() {
+                result.extend(char.to_uppercase());
+                capitalize_next = false;
+            } else {
+                result.push(char);
+            }
+        }
+
+        if capitalize_sentences && word.ends_with(SENTENCE_ENDINGS) {
+            capitalize_next = true;
+        }
+    }
+    result
+}
+
+/// Returns whether the text following `previous_text` starts a new sentence.
+/// With no previous text, the following text is the first sentence.
+pub fn ends_sentence(previous_text: Option<&str>) -> bool {
+    previous_text.is_none_or(|text| {
+        let text = text.trim_end();
+        text.is_empty() || text.ends_with(SENTENCE_ENDINGS)
+    })
+}
+
+/// Normalizes consecutive `segments` in place (see [`normalize_text`]).
+/// `previous_text` is the text preceding the first segment, used to tell
+/// whether it starts a new sentence.
+pub fn normalize_segments<'a>(
+    previous_text: Option<&str>,
+    segments: impl IntoIterator<Item = &'a mut CaptionSegment>,
+    capitalize_sentences: bool,
+) {
+    let mut starts_sentence = ends_sentence(previous_text);
+    for segment in segments {
+        segment.text = normalize_text(&segment.text, starts_sentence, capitalize_sentences);
+        if !segment.text.is_empty() {
+            starts_sentence = ends_sentence(Some(&segment.text));
+        }
+    }
+}
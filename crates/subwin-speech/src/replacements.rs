@@ -0,0 +1,156 @@
+//! User dictionary applied to the recognized text.
+//!
+//! Whisper tends to consistently misspell names and jargon it hasn't seen
+//! much of. A [`WordReplacer`] fixes such words with user-provided
+//! replacements, matching them case-insensitively and only as whole words.
+
+/// Replaces whole words and phrases of the recognized text.
+#[derive(Debug, Clone, Default)]
+pub struct WordReplacer {
+    /// Lowercased phrases to find and their replacements, the longest phrases
+    /// first, so they win over the shorter ones they overlap with.
+    rules: Vec<(Vec<char>, String)>,
+}
+
+impl WordReplacer {
+    /// Builds a replacer from `(phrase, replacement)` pairs. Blank phrases are
+    /// ignored.
+    pub fn new<'a>(replacements: impl IntoIterator<Item = (&'a String, &'a String)>) -> Self {
+        let mut rules: Vec<(Vec<char>, String)> = replacements
+            .into_iter()
+            .filter(|(phrase, _)| !phrase.trim().is_empty())
+            .map(|(phrase, replacement)| {
+                let phrase = phrase.trim().chars().map(fold_case).collect();
+                (phrase, replacement.clone())
+            })
+            .collect();
+        rules.sort_by_key(|(phrase, _)| std::cmp::Reverse(phrase.len()));
+        Self { rules }
+    }
+
+    /// Returns whether there is nothing to replace.
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Returns `text` with every whole-word occurrence of a phrase replaced.
+    /// Replaced text is not matched again.
+    pub fn apply(&self, text: &str) -> String {
+        if self.is_empty() {
+            return text.to_string();
+        }
+
+        let chars: Vec<char> = text.chars().collect();
+        let mut result = String::with_capacity(text.len());
+        let mut position = 0;
+        while position < chars.len() {
+            let at_word_start = position == 0 || !is_word_char(chars[position - 1]);
+            let matched = at_word_start
+                .then(|| {
+                    self.rules
+                        .iter()
+                        .find(|(phrase, _)| matches_at(&chars, position, phrase))
+                })
+                .flatten();
+
+            match matched {
+                Some((phrase, replacement)) => {
+                    result.push_str(replacement);
+                    position += phrase.len();
+                }
+                None => {
+                    result.push(chars[position]);
+                    position += 1;
+                }
+            }
+        }
+        result
+    }
+}
+
+/// Returns whether the lowercased `phrase` occurs in `chars` at `start` and
+/// ends at a word boundary.
+fn matches_at(chars: &[char], start: usize, phrase: &[char]) -> bool {
+    let end = start + phrase.len();
+    end <= chars.len()
+        && chars[start..end]
+            .iter()
+            .zip(phrase)
+            .all(|(char, phrase_char)| fold_case(*char) == *phrase_char)
+        && (end == chars.len() || !is_word_char(chars[end]))
+}
+
+/// Lowercases a single character for case-insensitive matching.
+fn fold_case(char: char) -> char {
+    char.to_lowercase().next().unwrap_or(char)
+}
+
+/// Returns whether `char` is a part of a word, rather than a separator.
+fn is_word_char(char: char) -> bool {
+    char.is_alphanumeric() || char == '\'' || char == '_'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn replacer(rules: &[(&str, &str)]) -> WordReplacer {
+        let rules: std::collections::BTreeMap<String, String> = rules
+            .iter()
+            .map(|(phrase, replacement)| (phrase.to_string(), replacement.to_string()))
+            .collect();
+        WordReplacer::new(&rules)
+    }
+
+    #[test]
+    fn multi_word_phrases_are_replaced_case_insensitively() {
+        let replacer = replacer(&[("open ai", "OpenAI"), ("Джи Пи Ти", "GPT")]);
+        assert_eq!(
+            replacer.apply("Open AI trained джи пи ти models."),
+            "OpenAI trained GPT models."
+        );
+    }
+
+    #[test]
+    fn only_whole_words_are_replaced() {
+        let replacer = replacer(&[("cat", "dog"), ("new york", "New York")]);
+        assert_eq!(
+            replacer.apply("cat, category, bobcat, cat's"),
+            "dog, category, bobcat, cat's"
+        );
+        assert_eq!(replacer.apply("new yorker"), "new yorker");
+    }
+
+    #[test]
+    fn longest_overlapping_phrase_wins() {
+        let replacer = replacer(&[
+            ("new york", "New York"),
+            ("new york city", "NYC"),
+            ("york", "Йорк"),
+        ]);
+        assert_eq!(
+            replacer.apply("new york city is not new york"),
+            "NYC is not New York"
+        );
+    }
+
+    #[test]
+    fn earlier_overlapping_phrase_wins() {
+        let replacer = replacer(&[("foo bar", "X"), ("bar baz", "Y")]);
+        assert_eq!(replacer.apply("foo bar baz"), "X baz");
+        assert_eq!(replacer.apply("bar baz foo bar"), "Y X");
+    }
+
+    #[test]
+    fn replaced_text_is_not_matched_again() {
+        let replacer = replacer(&[("gpt", "GPT chat"), ("chat", "talk")]);
+        assert_eq!(replacer.apply("gpt and chat"), "GPT chat and talk");
+    }
+
+    #[test]
+    fn blank_phrases_are_ignored() {
+        let replacer = replacer(&[(" ", "nothing"), ("", "nothing")]);
+        assert!(replacer.is_empty());
+        assert_eq!(replacer.apply("some text"), "some text");
+    }
+}
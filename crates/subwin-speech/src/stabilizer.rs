@@ -22,6 +22,18 @@ impl CaptionsStabilizer {
         }
     }
 
+    /// Returns how much of the recent audio, in milliseconds, may still be
+    /// revised before its captions are finalized.
+    pub fn tail_ms(&self) -> i64 {
+        self.tail_ms
+    }
+
+    /// Changes the revisable tail, e.g. to adapt it to the decode latency.
+    /// Already finalized captions are not affected.
+    pub fn set_tail_ms(&mut self, tail_ms: i64) {
+        self.tail_ms = tail_ms;
+    }
+
     pub fn push(
         &mut self,
         now_milliseconds: i64,
@@ -37,12 +49,14 @@ impl CaptionsStabilizer {
                 continue;
             }
 
+            // Overlap / duplicate of an already finalized segment, either aged
+            // out or flushed at an utterance boundary
+            if segment.end_milliseconds <= self.last_final_end_ms + self.dedupe_fuzz_ms {
+                continue;
+            }
+
             if segment.end_milliseconds <= cutoff_ms {
                 // Candidate for finalization
-                if segment.end_milliseconds <= self.last_final_end_ms + self.dedupe_fuzz_ms {
-                    continue; // overlap / duplicate
-                }
-
                 self.last_final_end_ms = self.last_final_end_ms.max(segment.end_milliseconds);
                 update.history.push(segment);
             } else {
@@ -53,4 +67,102 @@ impl CaptionsStabilizer {
 
         update
     }
+
+    /// Finalizes all `segments` regardless of how recent they are, e.g. when
+    /// the speaker has paused and the utterance is known to be complete.
+    ///
+    /// Segments that have already been finalized are skipped, so they won't
+    /// be finalized again once the transcriber reports them on later runs.
+    pub fn flush(&mut self, mut segments: Vec<CaptionSegment>) -> CaptionUpdate {
+        segments.sort_by_key(|segment| (segment.start_milliseconds, segment.end_milliseconds));
+
+        let mut update = CaptionUpdate::default();
+        for segment in segments {
+            if segment.end_milliseconds <= self.last_final_end_ms + self.dedupe_fuzz_ms {
+                continue;
+            }
+
+            self.last_final_end_ms = self.last_final_end_ms.max(segment.end_milliseconds);
+            update.history.push(segment);
+        }
+
+        update
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn segment(start_milliseconds: i64, end_milliseconds: i64, text: &str) -> CaptionSegment {
+        CaptionSegment {
+            start_milliseconds,
+            end_milliseconds,
+            text: text.to_string(),
+            speaker: None,
+            confidence: None,
+        }
+    }
+
+    fn texts(segments: &[CaptionSegment]) -> Vec<&str> {
+        segments
+            .iter()
+            .map(|segment| segment.text.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn speech_around_a_silence_flush_is_finalized_once() {
+        let mut stabilizer = CaptionsStabilizer::new(1_000);
+        let mut finalized = Vec::new();
+
+        // speech: the older word ages out of the tail, the newer one may
+        // still change
+        let update = stabilizer.push(
+            2_000,
+            vec![segment(0, 800, "hello"), segment(800, 1_600, "world")],
+        );
+        assert_eq!(texts(&update.active), ["world"]);
+        finalized.extend(update.history);
+
+        // silence: the same words are decoded again, and the pause flushes
+        // the ones still active
+        let mut update = stabilizer.push(
+            2_200,
+            vec![segment(0, 800, "hello"), segment(800, 1_600, "world")],
+        );
+        let utterance = std::mem::take(&mut update.active);
+        finalized.extend(update.history);
+        finalized.extend(stabilizer.flush(utterance).history);
+
+        // speech again: the window still holds the flushed words, with
+        // slightly shifted timestamps, followed by the new ones
+        let update = stabilizer.push(
+            4_500,
+            vec![
+                segment(0, 820, "hello"),
+                segment(820, 1_650, "world"),
+                segment(2_500, 3_200, "again"),
+                segment(3_200, 3_900, "friend"),
+            ],
+        );
+        assert_eq!(texts(&update.active), ["friend"]);
+        finalized.extend(update.history);
+        finalized.extend(stabilizer.flush(update.active).history);
+
+        assert_eq!(texts(&finalized), ["hello", "world", "again", "friend"]);
+    }
+
+    #[test]
+    fn flushing_the_same_utterance_twice_finalizes_it_once() {
+        let mut stabilizer = CaptionsStabilizer::new(1_000);
+        let utterance = vec![segment(0, 800, "hello"), segment(800, 1_600, "world")];
+
+        let first = stabilizer.flush(utterance.clone());
+        assert_eq!(texts(&first.history), ["hello", "world"]);
+        assert!(stabilizer.flush(utterance).history.is_empty());
+
+        let update = stabilizer.push(5_000, vec![segment(2_000, 2_600, "next")]);
+        assert_eq!(texts(&update.history), ["next"]);
+    }
 }
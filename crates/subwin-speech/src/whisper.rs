@@ -1,10 +1,103 @@
-use std::{collections::VecDeque, time::Instant};
+use std::{
+    collections::VecDeque,
+    ffi::c_int,
+    time::{Duration, Instant},
+};
 
 use whisper_rs::{
-    FullParams, WhisperContext, WhisperContextParameters, WhisperError, WhisperState,
+    FullParams, WhisperContext, WhisperContextParameters, WhisperError, WhisperSegment,
+    WhisperState, WhisperSysContext, WhisperSysState, WhisperTokenData, WhisperTokenId,
 };
 
-use crate::{CaptionSegment, Transcriber, milliseconds_to_samples};
+pub use whisper_rs::SamplingStrategy;
+
+/// Sampling temperature and its fallback settings, see
+/// [`FullParams::set_temperature_inc`].
+#[derive(Debug, Clone, Copy)]
+pub struct TemperatureOptions {
+    /// Initial sampling temperature.
+    pub temperature: f32,
+    /// Temperature increase per fallback attempt; `0.0` disables fallback.
+    pub temperature_inc: f32,
+    /// Entropy below which a result is decoded again.
+    pub entropy_threshold: f32,
+    /// Average token log probability below which a result is decoded again.
+    pub logprob_threshold: f32,
+}
+
+/// Anchoring of the decode window relative to the latest audio.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WindowAnchor {
+    /// The window ends at the latest audio, which is reported right away.
+    /// The most recent words are decoded without any context following them.
+    #[default]
+    Trailing,
+    /// The window is centered around the point `lookahead_ms` before the
+    /// latest audio: segments ending past it are held back until that much
+    /// audio follows them, so they're decoded with context on both sides.
+    /// Captions are delayed by the lookahead, which is capped to half of
+    /// the window.
+    Centered { lookahead_ms: u32 },
+}
+
+use crate::{CaptionSegment, Transcriber, TranscriptionMetrics, milliseconds_to_samples};
+
+/// Abort callback of a decode, whose user data points to the deadline of the
+/// decode (see [`WhisperTranscriber::set_decode_timeout`]).
+unsafe extern "C" fn abort_past_deadline(user_data: *mut std::ffi::c_void) -> bool {
+    // SAFETY: the user data is the transcriber's boxed deadline, which
+    // outlives the decode it's set for
+    let deadline = unsafe { &*(user_data as *const Option<Instant>) };
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Logits filter of a decode, whose user data points to the ids of the
+/// suppressed tokens (see [`WhisperTranscriber::set_suppressed_tokens`]).
+unsafe extern "C" fn suppress_token_ids(
+    _context: *mut WhisperSysContext,
+    _state: *mut WhisperSysState,
+    _tokens: *const WhisperTokenData,
+    _n_tokens: c_int,
+    logits: *mut f32,
+    user_data: *mut std::ffi::c_void,
+) {
+    // SAFETY: the user data is the transcriber's token ids, which outlive
+    // the decode they're set for, and the logits span the whole vocabulary
+    // the ids have been tokenized with
+    let token_ids = unsafe { &*(user_data as *const Vec<WhisperTokenId>) };
+    for &token_id in token_ids {
+        unsafe { *logits.add(token_id as usize) = f32::NEG_INFINITY };
+    }
+}
+
+/// Removes every occurrence of the `suppressed` strings standing on its own
+/// from `text`, leaving the ones that are a part of a longer word. Like a
+/// regex `\b`, a boundary is only needed next to the string's letters and
+/// digits, so e.g. `[BLANK_AUDIO]` is removed even when glued to a word.
+fn strip_suppressed(text: &str, suppressed: &[String]) -> String {
+    let is_word_char = |c: char| c.is_alphanumeric();
+    let mut text = text.to_string();
+    for token in suppressed {
+        let starts_with_word = token.chars().next().is_some_and(is_word_char);
+        let ends_with_word = token.chars().next_back().is_some_and(is_word_char);
+        let mut search_from = 0;
+        while let Some(offset) = text[search_from..].find(token.as_str()) {
+            let start = search_from + offset;
+            let end = start + token.len();
+            let is_glued_before =
+                starts_with_word && text[..start].chars().next_back().is_some_and(is_word_char);
+            let is_glued_after =
+                ends_with_word && text[end..].chars().next().is_some_and(is_word_char);
+            if !is_glued_before && !is_glued_after {
+                text.replace_range(start..end, "");
+                search_from = start;
+            } else {
+                search_from = end;
+            }
+        }
+    }
+    text
+}
 
 /// Real-time Whisper-based audio transcriber.
 ///
@@ -17,55 +110,224 @@ pub struct WhisperTranscriber {
     scratch_buffer: Vec<f32>,
     /// Rolling window of recent audio samples.
     segment_window: VecDeque<f32>,
+    /// Loaded model, kept for tokenizing the prompt.
+    whisper_context: WhisperContext,
     /// Internal Whisper inference state.
     whisper_state: WhisperState,
+    /// Tokens of the text preceding the window, passed to every decode (see
+    /// [`WhisperTranscriber::set_prompt`]).
+    prompt_tokens: Vec<c_int>,
     /// Target rolling window length, in samples.
     length_samples: usize,
     /// Decode scheduling interval, in samples.
     repeat_run_samples: usize,
     /// Minimum number of samples required for a decode attempt.
     min_transcode_samples: usize,
+    /// Audio at the end of the window, whose segments are held back (see
+    /// [`WindowAnchor::Centered`]), in samples.
+    lookahead_samples: usize,
+    /// Path to the loaded model, kept for reloading it.
+    model_path: String,
+    /// Token strings removed from the decoded text.
+    suppressed_tokens: Vec<String>,
+    /// Ids of the suppressed token strings spelled as a single token, which
+    /// the decoder never produces.
+    suppressed_token_ids: Vec<WhisperTokenId>,
+    /// Time limit of a single decode, if any.
+    decode_timeout: Option<Duration>,
+    /// Moment the running decode is aborted at. Boxed, so its address stays
+    /// valid for the abort callback while the transcriber moves.
+    decode_deadline: Box<Option<Instant>>,
     target_rate: u32,
     total_samples_seen: i64,
 }
 
 impl WhisperTranscriber {
+    /// Loads the model at `path` and prepares a transcriber that keeps a
+    /// rolling window of `context_length_ms` and runs inference every
+    /// `repeat_run_ms` (see [`crate::CONTEXT_LENGTH_MILLISECONDS`] and
+    /// [`crate::REPEAT_RUN_MILLISECONDS`] for the defaults).
     pub fn new(
         target_rate: u32,
         path: &str,
         context_params: WhisperContextParameters,
+        context_length_ms: u32,
+        repeat_run_ms: u32,
     ) -> Result<Self, WhisperError> {
         let min_transcode_samples = WhisperTranscriber::min_transcription_samples(target_rate);
-        let length_samples =
-            milliseconds_to_samples(crate::CONTEXT_LENGTH_MILLISECONDS, target_rate);
-        let repeat_run_samples =
-            milliseconds_to_samples(crate::REPEAT_RUN_MILLISECONDS, target_rate);
+        let length_samples = milliseconds_to_samples(context_length_ms, target_rate);
+        let repeat_run_samples = milliseconds_to_samples(repeat_run_ms, target_rate);
 
         let transcoder_context = WhisperContext::new_with_params(path, context_params)?;
         let whisper_state = transcoder_context.create_state()?;
         whisper_rs::install_logging_hooks();
 
         Ok(Self {
+            model_path: path.to_string(),
+            suppressed_tokens: Vec::new(),
+            suppressed_token_ids: Vec::new(),
+            decode_timeout: None,
+            decode_deadline: Box::new(None),
             total_samples_seen: 0,
             target_rate,
             since_last_decode: 0,
             segment_window: VecDeque::with_capacity(length_samples),
             scratch_buffer: Vec::with_capacity(min_transcode_samples),
+            whisper_context: transcoder_context,
             whisper_state,
+            prompt_tokens: Vec::new(),
             length_samples,
             repeat_run_samples,
             min_transcode_samples,
+            lookahead_samples: 0,
         })
     }
 
-    pub fn build_context_params() -> WhisperContextParameters<'static> {
+    /// Reloads the model with `context_params` and replaces the inference
+    /// state, keeping the buffered audio. Used to recover from persistent
+    /// inference failures, e.g. by falling back from the GPU to the CPU.
+    pub fn reload(&mut self, context_params: WhisperContextParameters) -> Result<(), WhisperError> {
+        let transcoder_context = WhisperContext::new_with_params(&self.model_path, context_params)?;
+        self.whisper_state = transcoder_context.create_state()?;
+        self.whisper_context = transcoder_context;
+        Ok(())
+    }
+
+    /// Sets the text preceding the window, e.g. the latest finalized
+    /// captions, as the prompt of the following decodes. An empty `text`
+    /// clears it.
+    ///
+    /// Whisper continues the prompt, so words split by the window's start
+    /// and names spelled earlier come out consistent across windows. On the
+    /// other hand, a misrecognized prompt makes the same mistake likelier,
+    /// and a repetitive one may make the model repeat it instead of
+    /// transcribing the audio. Only the prompt's last tokens are kept, up to
+    /// the half of the text context Whisper reserves for it.
+    pub fn set_prompt(&mut self, text: &str) -> Result<(), WhisperError> {
+        self.prompt_tokens.clear();
+        let text = text.trim();
+        if text.is_empty() {
+            return Ok(());
+        }
+
+        // a token never spans less than a byte
+        let mut tokens = self.whisper_context.tokenize(text, text.len() + 1)?;
+        let max_tokens = (self.whisper_context.n_text_ctx() / 2) as usize;
+        let excess_tokens = tokens.len().saturating_sub(max_tokens);
+        tokens.drain(..excess_tokens);
+        self.prompt_tokens.extend(tokens);
+        Ok(())
+    }
+
+    /// Sets the minimum length of audio a decode runs on, in milliseconds.
+    /// Defaults to [`Transcriber::min_transcription_samples`].
+    ///
+    /// Decodes still only run every `repeat_run_ms`; when less audio than
+    /// the minimum is buffered by then, e.g. right after the start or a
+    /// [`WhisperTranscriber::clear_window`], it's padded with silence. While
+    /// warming up, the padded window grows with the buffered audio: it's at
+    /// most twice as long as the audio, so silence never outweighs speech,
+    /// and at least [`Transcriber::min_transcription_samples`] long, which
+    /// Whisper needs to decode at all. Once enough audio is buffered, the
+    /// window is never padded. Values above the window length are capped to
+    /// it.
+    pub fn set_min_transcription_ms(&mut self, min_transcription_ms: u32) {
+        self.min_transcode_samples =
+            milliseconds_to_samples(min_transcription_ms, self.target_rate)
+                .min(self.length_samples);
+        self.scratch_buffer.reserve(self.min_transcode_samples);
+    }
+
+    /// Sets the anchoring of the decode window. Defaults to
+    /// [`WindowAnchor::Trailing`].
+    pub fn set_window_anchor(&mut self, anchor: WindowAnchor) {
+        self.lookahead_samples = match anchor {
+            WindowAnchor::Trailing => 0,
+            WindowAnchor::Centered { lookahead_ms } => {
+                centered_lookahead_samples(lookahead_ms, self.target_rate, self.length_samples)
+            }
+        };
+    }
+
+    /// Sets the time limit of a single decode; `None`, the default, lets
+    /// decodes run as long as they take. A decode exceeding it is aborted
+    /// and reported with [`TranscriptionMetrics::timed_out`], so a
+    /// pathologically slow input doesn't stall the captions. The window is
+    /// decoded again on the next run.
+    pub fn set_decode_timeout(&mut self, timeout: Option<Duration>) {
+        self.decode_timeout = timeout;
+    }
+
+    /// Sets token strings, e.g. `[BLANK_AUDIO]` or `um`, that are kept out
+    /// of the decoded text. A string the model spells as a single token,
+    /// with or without a leading space, is suppressed while decoding, so
+    /// the decoder picks the next likeliest token instead. Longer ones are
+    /// removed from the decoded text where they stand as whole words, and
+    /// segments left empty are dropped.
+    pub fn set_suppressed_tokens(&mut self, tokens: Vec<String>) {
+        self.suppressed_tokens = tokens
+            .into_iter()
+            .filter(|token| !token.trim().is_empty())
+            .collect();
+        self.suppressed_token_ids.clear();
+        for token in &self.suppressed_tokens {
+            let token = token.trim();
+            for spelling in [token.to_string(), format!(" {token}")] {
+                // a string that can't be tokenized is still removed from the
+                // text; a token never spans less than a byte
+                if let Ok(ids) = self.whisper_context.tokenize(&spelling, spelling.len() + 1)
+                    && ids.len() == 1
+                {
+                    self.suppressed_token_ids.extend(ids);
+                }
+            }
+        }
+        self.suppressed_token_ids.sort_unstable();
+        self.suppressed_token_ids.dedup();
+    }
+
+    /// Returns the start and the end of the buffered audio window, in
+    /// milliseconds since the first accepted sample.
+    pub fn window_bounds_ms(&self) -> (i64, i64) {
+        let sample_rate = self.target_rate as i64;
+        let window_start = self.total_samples_seen - self.segment_window.len() as i64;
+        (
+            window_start * 1000 / sample_rate,
+            self.total_samples_seen * 1000 / sample_rate,
+        )
+    }
+
+    /// Drops the buffered audio, so the next decode only covers samples
+    /// accepted afterwards. Timestamps keep counting from the start.
+    pub fn clear_window(&mut self) {
+        self.segment_window.clear();
+        self.since_last_decode = 0;
+    }
+
+    pub fn build_context_params(use_gpu: bool) -> WhisperContextParameters<'static> {
         let mut context_params = WhisperContextParameters::default();
-        context_params.use_gpu(true);
+        context_params.use_gpu(use_gpu);
         context_params
     }
 
-    pub fn build_request_params() -> FullParams<'static, 'static> {
-        let mut params = FullParams::new(whisper_rs::SamplingStrategy::Greedy { best_of: 1 });
+    /// Returns the language code known to Whisper for `code`, e.g. `"en"`
+    /// for `"EN"`, or `None` if Whisper doesn't support the language.
+    pub fn resolve_language(code: &str) -> Option<&'static str> {
+        if code.contains('\0') {
+            return None;
+        }
+        whisper_rs::get_lang_id(&code.to_ascii_lowercase()).and_then(whisper_rs::get_lang_str)
+    }
+
+    pub fn build_request_params(
+        strategy: SamplingStrategy,
+        temperature: TemperatureOptions,
+        suppress_non_speech: bool,
+        single_segment: bool,
+        max_tokens: u32,
+        language: Option<&'static str>,
+    ) -> FullParams<'static, 'static> {
+        let mut params = FullParams::new(strategy);
         // disable some not usable shit
         params.set_print_progress(false);
         params.set_print_realtime(false);
@@ -77,17 +339,112 @@ impl WhisperTranscriber {
         // TODO: re-enable this: params.set_n_threads(num_cpus::get_physical() as i32);
         params.set_no_timestamps(false);
         params.set_token_timestamps(false);
-        params.set_single_segment(false);
-        // params.set_max_tokens(96);
-        params.set_language(None); // TODO: request from end-calling user
+        // a single segment spans the whole window, so it only ends with it
+        params.set_single_segment(single_segment);
+        // `0` doesn't limit the segments
+        params.set_max_tokens(max_tokens.min(i32::MAX as u32) as i32);
+        // `None` detects the language from the audio
+        params.set_language(language);
+
+        // temperature fallback
+        params.set_temperature(temperature.temperature);
+        params.set_temperature_inc(temperature.temperature_inc);
+        params.set_entropy_thold(temperature.entropy_threshold);
+        params.set_logprob_thold(temperature.logprob_threshold);
+
+        // non-speech tokens are annotations like laughter or music markers
+        params.set_suppress_nst(suppress_non_speech);
 
         params
     }
 }
 
+/// Returns the lookahead of a [`WindowAnchor::Centered`] window of
+/// `length_samples`, capped to half of the window.
+fn centered_lookahead_samples(lookahead_ms: u32, sample_rate: u32, length_samples: usize) -> usize {
+    milliseconds_to_samples(lookahead_ms, sample_rate).min(length_samples / 2)
+}
+
+/// Returns the length a window of `buffered_samples` is padded to while less
+/// audio than `min_transcode_samples` is buffered, in samples (see
+/// [`WhisperTranscriber::set_min_transcription_ms`]).
+fn warmup_window_samples(
+    buffered_samples: usize,
+    min_transcode_samples: usize,
+    sample_rate: u32,
+) -> usize {
+    let hard_min_samples = WhisperTranscriber::min_transcription_samples(sample_rate);
+    (buffered_samples * 2).clamp(
+        hard_min_samples,
+        min_transcode_samples.max(hard_min_samples),
+    )
+}
+
+/// Places the segments of a decode window in the stream.
+#[derive(Debug, Clone, Copy)]
+struct WindowTiming {
+    /// Stream time of the window's first sample.
+    start_ms: i64,
+    /// Segments ending past it are held back, `None` if none are.
+    report_until_ms: Option<i64>,
+}
+
+impl WindowTiming {
+    /// Timing of a window holding the last `buffered_samples` of the
+    /// `total_samples_seen`, the last `lookahead_samples` of which are only
+    /// context for the segments before them.
+    fn new(
+        total_samples_seen: i64,
+        buffered_samples: i64,
+        lookahead_samples: usize,
+        sample_rate: u32,
+    ) -> Self {
+        let sample_rate = sample_rate as i64;
+        let start_ms = (total_samples_seen - buffered_samples) * 1000 / sample_rate;
+        // measured from the last accepted sample, not the padded window's end
+        let report_until_ms = (lookahead_samples > 0)
+            .then(|| (total_samples_seen - lookahead_samples as i64) * 1000 / sample_rate);
+        Self {
+            start_ms,
+            report_until_ms,
+        }
+    }
+
+    /// Returns the stream times of a segment spanning `start_timestamp` to
+    /// `end_timestamp`, in centiseconds of the window, or `None` if it ends
+    /// in the lookahead. Such segments are reported by a later decode, once
+    /// the audio following them is in the window.
+    fn place(&self, start_timestamp: i64, end_timestamp: i64) -> Option<(i64, i64)> {
+        let start_ms = self.start_ms + start_timestamp * 10;
+        let end_ms = self.start_ms + end_timestamp * 10;
+        if self
+            .report_until_ms
+            .is_some_and(|report_until_ms| end_ms > report_until_ms)
+        {
+            return None;
+        }
+        Some((start_ms, end_ms))
+    }
+}
+
+/// Returns the average probability of the text tokens of `segment`, which
+/// are the ones before `text_token_end`; special and timestamp tokens follow
+/// it. Returns `None` if the segment has no text tokens.
+fn segment_confidence(segment: &WhisperSegment, text_token_end: WhisperTokenId) -> Option<f32> {
+    let (sum, count) = (0..segment.n_tokens())
+        .filter_map(|index| segment.get_token(index))
+        .filter(|token| token.token_id() < text_token_end)
+        .fold((0.0f32, 0u32), |(sum, count), token| {
+            (sum + token.token_probability(), count + 1)
+        });
+    (count > 0).then(|| sum / count as f32)
+}
+
 impl Transcriber<FullParams<'static, 'static>> for WhisperTranscriber {
+    type Error = WhisperError;
+
     fn min_transcription_samples(sample_rate: u32) -> usize {
-        (sample_rate as usize) / 10 // expect minimum a second of submitted audio
+        (sample_rate as usize) / 10 // expect minimum a tenth of a second of submitted audio
     }
 
     fn accept_samples(&mut self, samples: &[f32]) {
@@ -105,65 +462,262 @@ impl Transcriber<FullParams<'static, 'static>> for WhisperTranscriber {
     fn try_transcribe(
         &mut self,
         mut params: FullParams<'static, 'static>,
-    ) -> (Vec<CaptionSegment>, u128) {
+    ) -> Result<(Vec<CaptionSegment>, Option<TranscriptionMetrics>), WhisperError> {
         // fail fast, if there's not enough data to process yet
         if self.since_last_decode < self.repeat_run_samples {
-            return (Vec::new(), 0);
+            return Ok((Vec::new(), None));
         }
 
         let start = Instant::now();
+        // zero padding is appended after the audio, so it doesn't shift the
+        // window's start
+        let buffered_samples = self.segment_window.len() as i64;
 
         // get transcode audio, if there's more enough data for transcode.
-        // otherwise, pad with zero-value for the provided type
+        // otherwise, pad with zero-value for the provided type, growing the
+        // padded window along with the audio while warming up
         let transcode_audio: &[f32] = if self.segment_window.len() >= self.min_transcode_samples {
             self.segment_window.make_contiguous()
         } else {
+            let padded_samples = warmup_window_samples(
+                self.segment_window.len(),
+                self.min_transcode_samples,
+                self.target_rate,
+            );
             self.scratch_buffer.clear();
             self.scratch_buffer
                 .extend(self.segment_window.iter().copied());
-            self.scratch_buffer.resize(self.min_transcode_samples, 0.0);
+            self.scratch_buffer.resize(padded_samples, 0.0);
             &self.scratch_buffer
         };
 
         // TODO: make the threshold configurable.
-        let rms = super::calculate_samples_rms(transcode_audio);
-        if rms == 0.0 || (20.0 * rms.log10()) <= -60.0 {
+        if super::is_silence(transcode_audio) {
             self.since_last_decode = 0;
-            return (Vec::new(), 0);
+            return Ok((Vec::new(), None));
         }
 
-        // reset the current model offset and remove unwanted junk
+        // reset the current model offset
         params.set_offset_ms(0);
-        params.set_suppress_nst(true);
+        // the prompt is borrowed from `self` for the decode only
+        let mut params: FullParams<'static, '_> = params;
+        if !self.prompt_tokens.is_empty() {
+            params.set_tokens(&self.prompt_tokens);
+        }
 
-        let sample_rate = self.target_rate as i64;
         let window_samples = transcode_audio.len() as i64;
-        let window_start_ms = (self.total_samples_seen - window_samples) * 1000 / sample_rate;
+        let timing = WindowTiming::new(
+            self.total_samples_seen,
+            buffered_samples,
+            self.lookahead_samples,
+            self.target_rate,
+        );
+
+        *self.decode_deadline = self.decode_timeout.map(|timeout| start + timeout);
+        if self.decode_deadline.is_some() {
+            // SAFETY: the callback only reads the deadline, which is boxed
+            // and kept alive by `self` for the whole decode
+            unsafe {
+                params.set_abort_callback(Some(abort_past_deadline));
+                params.set_abort_callback_user_data(
+                    &*self.decode_deadline as *const Option<Instant> as *mut std::ffi::c_void,
+                );
+            }
+        }
+
+        if !self.suppressed_token_ids.is_empty() {
+            // SAFETY: the callback only reads the ids, which are kept alive
+            // and unchanged by `self` for the whole decode
+            unsafe {
+                params.set_filter_logits_callback(Some(suppress_token_ids));
+                params.set_filter_logits_callback_user_data(
+                    &self.suppressed_token_ids as *const Vec<WhisperTokenId>
+                        as *mut std::ffi::c_void,
+                );
+            }
+        }
 
-        if let Err(e) = self.whisper_state.full(params, transcode_audio) {
-            eprintln!("Failed to transcode audio: {e}");
-            return (Vec::new(), 0);
+        let result = self.whisper_state.full(params, transcode_audio);
+        let is_timed_out = self
+            .decode_deadline
+            .take()
+            .is_some_and(|deadline| Instant::now() >= deadline);
+        if let Err(e) = result {
+            // retry on the next scheduled run rather than on every buffer
+            self.since_last_decode = 0;
+            if is_timed_out {
+                let mut metrics = TranscriptionMetrics::new(
+                    start.elapsed().as_millis(),
+                    window_samples as usize,
+                    self.target_rate,
+                );
+                metrics.timed_out = true;
+                return Ok((Vec::new(), Some(metrics)));
+            }
+            return Err(e);
         }
 
+        let text_token_end = self.whisper_context.token_eot();
         let mut segments = Vec::new();
         for segment in self.whisper_state.as_iter() {
-            let text = segment.to_str_lossy().unwrap_or_default();
+            let text = strip_suppressed(
+                &segment.to_str_lossy().unwrap_or_default(),
+                &self.suppressed_tokens,
+            );
             if text.trim().is_empty() {
                 continue;
             }
 
-            let start_milliseconds = window_start_ms + (segment.start_timestamp() * 10);
-            let end_milliseconds = window_start_ms + (segment.end_timestamp() * 10);
+            let Some((start_milliseconds, end_milliseconds)) =
+                timing.place(segment.start_timestamp(), segment.end_timestamp())
+            else {
+                continue;
+            };
             segments.push(CaptionSegment {
                 start_milliseconds,
                 end_milliseconds,
-                text: text.to_string(),
+                text,
+                speaker: None,
+                confidence: segment_confidence(&segment, text_token_end),
             });
         }
 
-        let duration = start.elapsed().as_millis();
+        let metrics = TranscriptionMetrics::new(
+            start.elapsed().as_millis(),
+            window_samples as usize,
+            self.target_rate,
+        );
         self.since_last_decode = 0;
 
-        (segments, duration)
+        Ok((segments, Some(metrics)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE_RATE: u32 = 16_000;
+
+    #[test]
+    fn centered_window_segments_are_offset_by_the_window_start() {
+        // a 10 s window after 25 s of audio, holding back the last 2 s
+        let timing = WindowTiming::new(
+            25 * SAMPLE_RATE as i64,
+            10 * SAMPLE_RATE as i64,
+            2 * SAMPLE_RATE as usize,
+            SAMPLE_RATE,
+        );
+
+        assert_eq!(timing.place(0, 250), Some((15_000, 17_500)));
+        assert_eq!(timing.place(420, 735), Some((19_200, 22_350)));
+        // ending right at the lookahead is still reported
+        assert_eq!(timing.place(600, 800), Some((21_000, 23_000)));
+        // ending in the lookahead is held back, even if starting before it
+        assert_eq!(timing.place(700, 801), None);
+        assert_eq!(timing.place(820, 990), None);
+    }
+
+    #[test]
+    fn centered_window_offsets_a_partly_filled_window() {
+        // 3 s of audio after the window has been cleared at 40 s
+        let timing = WindowTiming::new(
+            43 * SAMPLE_RATE as i64,
+            3 * SAMPLE_RATE as i64,
+            SAMPLE_RATE as usize / 2,
+            SAMPLE_RATE,
+        );
+
+        assert_eq!(timing.place(50, 200), Some((40_500, 42_000)));
+        assert_eq!(timing.place(200, 260), None);
+    }
+
+    #[test]
+    fn trailing_window_reports_every_segment() {
+        let timing = WindowTiming::new(
+            12 * SAMPLE_RATE as i64,
+            10 * SAMPLE_RATE as i64,
+            0,
+            SAMPLE_RATE,
+        );
+
+        assert_eq!(timing.place(0, 100), Some((2_000, 3_000)));
+        // segments may end in the padding past the latest audio
+        assert_eq!(timing.place(900, 1_050), Some((11_000, 12_500)));
+    }
+
+    #[test]
+    fn centered_lookahead_is_capped_to_half_of_the_window() {
+        let length_samples = 10 * SAMPLE_RATE as usize;
+
+        assert_eq!(
+            centered_lookahead_samples(1_500, SAMPLE_RATE, length_samples),
+            24_000
+        );
+        assert_eq!(
+            centered_lookahead_samples(60_000, SAMPLE_RATE, length_samples),
+            length_samples / 2
+        );
+    }
+
+    #[test]
+    fn suppressed_strings_are_stripped_as_whole_words_only() {
+        let suppressed = ["um".to_string(), "[BLANK_AUDIO]".to_string()];
+
+        assert_eq!(
+            strip_suppressed(" Um, the drum is under the umbrella", &suppressed),
+            " Um, the drum is under the umbrella"
+        );
+        assert_eq!(
+            strip_suppressed(" um, I think um", &suppressed),
+            " , I think "
+        );
+        assert_eq!(strip_suppressed("[BLANK_AUDIO]", &suppressed), "");
+        assert_eq!(
+            strip_suppressed(" Hello[BLANK_AUDIO] world", &suppressed),
+            " Hello world"
+        );
+    }
+
+    #[test]
+    fn warmup_window_grows_with_the_buffered_audio() {
+        let hard_min = WhisperTranscriber::min_transcription_samples(SAMPLE_RATE);
+        let min_transcode = SAMPLE_RATE as usize;
+
+        assert_eq!(
+            warmup_window_samples(500, min_transcode, SAMPLE_RATE),
+            hard_min
+        );
+        assert_eq!(
+            warmup_window_samples(4_000, min_transcode, SAMPLE_RATE),
+            8_000
+        );
+        assert_eq!(
+            warmup_window_samples(12_000, min_transcode, SAMPLE_RATE),
+            min_transcode
+        );
+        // a minimum below the hard one doesn't shorten the window
+        assert_eq!(
+            warmup_window_samples(500, hard_min / 2, SAMPLE_RATE),
+            hard_min
+        );
+    }
+
+    #[test]
+    fn early_windows_start_at_their_first_sample() {
+        // the first 0.25 s, padded to 0.5 s after the audio
+        let padded = WindowTiming::new(4_000, 4_000, 0, SAMPLE_RATE);
+        assert_eq!(padded.start_ms, 0);
+        assert_eq!(padded.place(5, 20), Some((50, 200)));
+
+        // 0.25 s buffered since the window has been cleared at 10 s
+        let cleared = WindowTiming::new(164_000, 4_000, 0, SAMPLE_RATE);
+        assert_eq!(cleared.start_ms, 10_000);
+        assert_eq!(cleared.place(5, 20), Some((10_050, 10_200)));
+
+        // the first 2 s, long enough to be decoded unpadded
+        let unpadded = WindowTiming::new(32_000, 32_000, 0, SAMPLE_RATE);
+        assert_eq!(unpadded.start_ms, 0);
+        assert_eq!(unpadded.place(100, 150), Some((1_000, 1_500)));
     }
 }
@@ -1,13 +1,159 @@
+use subwin_bridge::{MessageFromBackend, audio::DeviceSelector, notification::NotificationType};
+
+/// Usage shown when the command line arguments can't be parsed.
+const USAGE: &str = "usage: subwin [--bridge-buffer <messages>] [--updates-buffer <messages>] \
+     [--headless [--device <device id> | --device-index <index> | --device-name <name>] \
+     [--model <model path>]]";
+
+/// Parsed command line arguments.
+struct Args {
+    /// Headless options if the app should run without the GUI.
+    headless: Option<subwin_backend::HeadlessOptions>,
+    /// Capacity of the bridge's control channels.
+    control_buffer: usize,
+    /// Capacity of the bridge's high-frequency updates channel.
+    updates_buffer: usize,
+}
+
+/// Parses the capacity of a bridge channel given after `flag`.
+fn parse_buffer(flag: &str, value: Option<String>) -> usize {
+    match value.as_deref().map(str::parse::<usize>) {
+        Some(Ok(buffer)) if buffer > 0 => buffer,
+        _ => {
+            eprintln!("{flag} expects a positive number of messages\n{USAGE}");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Parses the device index given after `--device-index`.
+fn parse_device_index(value: Option<String>) -> usize {
+    match value.as_deref().map(str::parse::<usize>) {
+        Some(Ok(index)) => index,
+        _ => {
+            eprintln!("--device-index expects a device number, starting from 0\n{USAGE}");
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Parses the command line arguments.
+fn parse_args() -> Args {
+    let mut is_headless = false;
+    let mut options = subwin_backend::HeadlessOptions::default();
+    let mut control_buffer = subwin_bridge::DEFAULT_CONTROL_BUFFER;
+    let mut updates_buffer = subwin_bridge::DEFAULT_UPDATES_BUFFER;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--headless" => is_headless = true,
+            "--device" => options.device = args.next().map(DeviceSelector::Id),
+            "--device-index" => {
+                options.device = Some(DeviceSelector::Index(parse_device_index(args.next())))
+            }
+            "--device-name" => options.device = args.next().map(DeviceSelector::Name),
+            "--model" => options.model_path = args.next().map(Into::into),
+            "--bridge-buffer" => control_buffer = parse_buffer(&arg, args.next()),
+            "--updates-buffer" => updates_buffer = parse_buffer(&arg, args.next()),
+            "--help" | "-h" => {
+                println!("{USAGE}");
+                std::process::exit(0);
+            }
+            _ => {
+                eprintln!("unknown argument: {arg}\n{USAGE}");
+                std::process::exit(2);
+            }
+        }
+    }
+
+    if !is_headless && (options.device.is_some() || options.model_path.is_some()) {
+        eprintln!("device and model options are only supported with --headless\n{USAGE}");
+        std::process::exit(2);
+    }
+    Args {
+        headless: is_headless.then_some(options),
+        control_buffer,
+        updates_buffer,
+    }
+}
+
+/// Runs the backend without the GUI, logging its notifications until it
+/// shuts down.
+fn run_headless(channels: subwin_bridge::BridgeChannels, options: subwin_backend::HeadlessOptions) {
+    let subwin_bridge::BridgeChannels {
+        frontend_tx,
+        mut frontend_rx,
+        backend_tx,
+        backend_rx,
+        backend_updates_tx,
+        backend_levels_tx,
+        // captions are printed by the backend, so updates are left unread
+        frontend_updates_rx: _,
+        frontend_levels_rx: _,
+    } = channels;
+
+    let backend = subwin_backend::run_headless(
+        backend_rx,
+        backend_tx,
+        backend_updates_tx,
+        backend_levels_tx,
+        options,
+    );
+    while let Some(message) = frontend_rx.blocking_recv() {
+        if let MessageFromBackend::NotificationMessage(notification) = message {
+            match notification.notification_type {
+                NotificationType::Info | NotificationType::Success => {
+                    log::info!("{}", notification.message)
+                }
+                NotificationType::Warning => log::warn!("{}", notification.message),
+                NotificationType::Error => log::error!("{}", notification.message),
+            }
+        }
+    }
+
+    // keep the backend's inbound channel open until it's done
+    drop(frontend_tx);
+    backend.join().expect("backend thread has panicked");
+}
+
 fn main() {
-    simple_logger::SimpleLogger::new()
+    let args = parse_args();
+
+    let logger = simple_logger::SimpleLogger::new()
         .with_colors(true)
         .with_threads(true)
-        .with_local_timestamps()
-        .init()
+        .with_local_timestamps();
+    // recent lines are kept for diagnostics exports
+    let max_level = logger.max_level();
+    subwin_backend::install_log_recorder(logger, max_level)
         .expect("failed to build logger instance");
 
-    let channels = subwin_bridge::BridgeChannels::default();
-    subwin_backend::run(channels.backend_rx, channels.backend_tx);
-    subwin_frontend::run(channels.frontend_rx, channels.frontend_tx)
-        .expect("failed to run frontend");
+    let channels =
+        subwin_bridge::BridgeChannels::with_capacities(args.control_buffer, args.updates_buffer);
+    if let Some(options) = args.headless {
+        run_headless(channels, options);
+        return;
+    }
+
+    let shutdown_tx = channels.frontend_tx.clone();
+
+    let backend = subwin_backend::run(
+        channels.backend_rx,
+        channels.backend_tx,
+        channels.backend_updates_tx,
+        channels.backend_levels_tx,
+    );
+    subwin_frontend::run(
+        channels.frontend_rx,
+        channels.frontend_updates_rx,
+        channels.frontend_levels_rx,
+        channels.frontend_tx,
+    )
+    .expect("failed to run frontend");
+
+    // the frontend has already requested a shutdown if it quit normally, but
+    // make sure the backend stops before waiting for it
+    let _ = shutdown_tx.blocking_send(subwin_bridge::MessageToBackend::ShutdownRequest);
+    backend.join().expect("backend thread has panicked");
 }